@@ -0,0 +1,38 @@
+//! Evaluates the expression on every line of stdin and prints its value
+//!
+//! `_` holds the value of the previous line, so results can be built up one
+//! step at a time:
+//!
+//! ```text
+//! $ printf '2 * 21\n_ / 4\nmax(_, 11)\n' | cargo run --example calculator
+//! 42
+//! 10.5
+//! 11
+//! ```
+use std::io::{self, BufRead};
+
+use lox::{CompiledExpression, Environment};
+
+fn main() -> io::Result<()> {
+    let mut env = Environment::new();
+    lox::stdlib::install(&mut env);
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let value = CompiledExpression::compile(line.trim())
+            .map_err(|e| e.to_string())
+            .and_then(|expr| expr.eval_mut(&mut env).map_err(|e| e.to_string()));
+        match value {
+            Ok(value) => {
+                println!("{}", String::from(value.clone()));
+                env.define("_".into(), value);
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//! Hands values of the host program to a script and reads back the
+//! variables the script declared
+//!
+//! The script prices an order, the host provides the order and picks the
+//! results out of the global variables once the script is done:
+//!
+//! ```text
+//! $ cargo run --example embed_host_vars
+//! ```
+use lox::{Interpreter, Literal};
+
+const SCRIPT: &str = r#"
+let subtotal = price * quantity;
+let discount = 0;
+// Members get 10% off, there are no `if` statements yet
+attempt {
+    assert member;
+    discount = subtotal / 10;
+} handle (notMember) {
+    discount = 0;
+}
+let total = subtotal - discount;
+print "${quantity} x ${item} for ${total}";
+"#;
+
+fn price(item: &str, price: f32, quantity: i64, member: bool) -> Result<(), String> {
+    let mut interpreter = Interpreter::new(SCRIPT.into());
+    let env = interpreter.environment_mut();
+    env.define("item".into(), Literal::String(item.into()));
+    env.define("price".into(), Literal::Number(price));
+    env.define("quantity".into(), Literal::Integer(quantity));
+    env.define("member".into(), Literal::Boolean(member));
+
+    interpreter.interpret(true).map_err(|e| e.to_string())?;
+
+    let env = interpreter.environment();
+    for name in ["subtotal", "discount", "total"] {
+        let value = env.get(name).cloned().unwrap_or(Literal::Nil);
+        println!("  {} = {}", name, String::from(value));
+    }
+    Ok(())
+}
+
+fn main() {
+    let orders = [("pencil", 0.5, 12, false), ("notebook", 4.0, 5, true)];
+    for (item, unit_price, quantity, member) in orders {
+        if let Err(e) = price(item, unit_price, quantity, member) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
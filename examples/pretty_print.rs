@@ -0,0 +1,60 @@
+//! Parses a program and prints the tree of every statement, one statement per
+//! line with the statements of blocks indented below them
+//!
+//! The program is read from the file given as argument, or from stdin:
+//!
+//! ```text
+//! $ echo 'let a = 1 + 2 * 3; { print a; }' | cargo run --example pretty_print
+//! ```
+use std::io::{self, Read};
+use std::{env, fs, process};
+
+use lox::{Parser, Scanner, Statement};
+
+fn print_tree(statement: &Statement, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match statement {
+        Statement::Block(_, statements) => {
+            println!("{}block", indent);
+            for statement in statements {
+                print_tree(statement, depth + 1);
+            }
+        }
+        Statement::Attempt(body, name, handler) => {
+            println!("{}attempt", indent);
+            print_tree(body, depth + 1);
+            println!("{}handle {}", indent, name.lexeme);
+            print_tree(handler, depth + 1);
+        }
+        statement => println!("{}{}", indent, String::from(statement.clone())),
+    }
+}
+
+fn main() {
+    let source = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {}", path, e)),
+        None => {
+            let mut source = String::new();
+            io::stdin()
+                .read_to_string(&mut source)
+                .map(|_| source)
+                .map_err(|e| format!("cannot read stdin: {}", e))
+        }
+    };
+
+    let statements = source.and_then(|source| {
+        let tokens = Scanner::new(&source).map_err(|e| e.to_string())?.tokens;
+        Parser::new(tokens, true).parse().map_err(|e| e.to_string())
+    });
+    match statements {
+        Ok(statements) => {
+            for statement in &statements {
+                print_tree(statement, 0);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Runs an untrusted snippet read from stdin within limits and reports why it
+//! stopped
+//!
+//! The snippet may execute at most 1000 statements, declare 100 variables
+//! and build strings of 10000 characters, and is interrupted after a
+//! second. Files and environment variables are out of reach, which they are
+//! unless enabled.
+//!
+//! ```text
+//! $ echo 'let a = "ab"; a = "${a}${a}${a}${a}${a}${a}";' | cargo run --example sandbox
+//! ```
+use std::io::{self, Read};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use lox::errors::InterpreterErrorKind;
+use lox::Interpreter;
+
+const MAX_STATEMENTS: usize = 1000;
+const MAX_VARIABLES: usize = 100;
+const MAX_STRING_LEN: usize = 10_000;
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+fn main() -> io::Result<()> {
+    let mut snippet = String::new();
+    io::stdin().read_to_string(&mut snippet)?;
+
+    let mut interpreter = Interpreter::with_output(snippet, Vec::new());
+    interpreter.set_max_statements(Some(MAX_STATEMENTS));
+    interpreter.set_max_variables(Some(MAX_VARIABLES));
+    interpreter.set_max_string_len(Some(MAX_STRING_LEN));
+
+    // Interrupts the snippet unless it's done before the timeout
+    let handle = interpreter.interrupt_handle();
+    let (done, finished) = mpsc::channel::<()>();
+    let watchdog = thread::spawn(move || {
+        if finished.recv_timeout(TIMEOUT).is_err() {
+            handle.interrupt();
+        }
+    });
+    let result = interpreter.interpret(true);
+    let _ = done.send(());
+    watchdog.join().unwrap();
+
+    print!("{}", String::from_utf8_lossy(interpreter.output()));
+    let statements = interpreter.stats().statements;
+    match result {
+        Ok(()) => match interpreter.exit_code() {
+            Some(code) => println!("exited with code {} after {} statements", code, statements),
+            None => println!("finished after {} statements", statements),
+        },
+        Err(e) if e.kind == InterpreterErrorKind::Interrupted => {
+            println!("timed out after {:?}", TIMEOUT)
+        }
+        Err(e) if e.kind == InterpreterErrorKind::Evaluation => {
+            println!("stopped after {} statements: {}", statements, e)
+        }
+        Err(e) => println!("rejected: {}", e),
+    }
+    Ok(())
+}
@@ -0,0 +1,294 @@
+use std::fmt;
+
+use crate::types::{Expression, Span, Statement, Token, TokenType};
+
+/// Point where two programs stop being structurally equal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// Diverging construct in the first program, `None` if it's missing
+    pub left: Option<Span>,
+    /// Diverging construct in the second program, `None` if it's missing
+    pub right: Option<Span>,
+    pub description: String,
+}
+
+impl Difference {
+    fn new(description: &str, left: Option<&Token>, right: Option<&Token>) -> Self {
+        let location = match left.or(right) {
+            Some(token) => format!(" at {}", token.location()),
+            None => String::new(),
+        };
+        Self {
+            left: left.map(Token::span),
+            right: right.map(Token::span),
+            description: format!("{}{}", description, location),
+        }
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+/// Compares two parsed programs and reports the first point they diverge
+///
+/// Programs are compared the same way `PartialEq` compares [Statement]s:
+/// locations, whitespace, comments and the spelling of numbers are ignored
+/// while groupings are significant, `(a + b)` differs from `a + b`. The
+/// returned list is empty when both programs are equal.
+pub fn ast_diff(a: &[Statement], b: &[Statement]) -> Vec<Difference> {
+    diff_statements(a, b).into_iter().collect()
+}
+
+fn diff_statements(a: &[Statement], b: &[Statement]) -> Option<Difference> {
+    if let Some(difference) = a
+        .iter()
+        .zip(b)
+        .find_map(|(left, right)| diff_statement(left, right))
+    {
+        return Some(difference);
+    }
+
+    match (a.get(b.len()), b.get(a.len())) {
+        (Some(extra), _) => Some(Difference::new(
+            &format!("{} vs nothing", statement_kind(extra)),
+            first_token(extra),
+            None,
+        )),
+        (_, Some(extra)) => Some(Difference::new(
+            &format!("nothing vs {}", statement_kind(extra)),
+            None,
+            first_token(extra),
+        )),
+        _ => None,
+    }
+}
+
+fn diff_statement(left: &Statement, right: &Statement) -> Option<Difference> {
+    match (left, right) {
+        (Statement::Expression(expr), Statement::Expression(other_expr))
+        | (Statement::Variable(expr), Statement::Variable(other_expr))
+        | (Statement::Exit(_, expr), Statement::Exit(_, other_expr)) => {
+            diff_expression(expr, other_expr)
+        }
+        (Statement::Assign(name, expr), Statement::Assign(other_name, other_expr)) => {
+            diff_token("variable", name, other_name).or_else(|| diff_expression(expr, other_expr))
+        }
+        (Statement::Block(_, stmts), Statement::Block(_, other_stmts)) => {
+            diff_statements(stmts, other_stmts)
+        }
+        (
+            Statement::Attempt(body, name, handler),
+            Statement::Attempt(other_body, other_name, other_handler),
+        ) => diff_statement(body, other_body)
+            .or_else(|| diff_token("variable", name, other_name))
+            .or_else(|| diff_statement(handler, other_handler)),
+        (Statement::Print(keyword, expr), Statement::Print(other_keyword, other_expr))
+            if keyword._type == other_keyword._type =>
+        {
+            diff_expression(expr, other_expr)
+        }
+        (
+            Statement::Assert(keyword, condition, message),
+            Statement::Assert(other_keyword, other_condition, other_message),
+        ) => {
+            diff_expression(condition, other_condition).or_else(|| match (message, other_message) {
+                (Some(message), Some(other_message)) => diff_expression(message, other_message),
+                (None, None) => None,
+                _ => Some(Difference::new(
+                    "assertion message differs",
+                    Some(keyword),
+                    Some(other_keyword),
+                )),
+            })
+        }
+        _ => Some(Difference::new(
+            &format!("{} vs {}", statement_kind(left), statement_kind(right)),
+            first_token(left),
+            first_token(right),
+        )),
+    }
+}
+
+fn diff_expression(left: &Expression, right: &Expression) -> Option<Difference> {
+    match (left, right) {
+        (Expression::Unary(op, expr), Expression::Unary(other_op, other_expr)) => {
+            diff_token("operator", op, other_op).or_else(|| diff_expression(expr, other_expr))
+        }
+        (
+            Expression::Binary(left, op, right),
+            Expression::Binary(other_left, other_op, other_right),
+        ) => diff_expression(left, other_left)
+            .or_else(|| diff_token("operator", op, other_op))
+            .or_else(|| diff_expression(right, other_right)),
+        (Expression::Grouping(expr), Expression::Grouping(other_expr)) => {
+            diff_expression(expr, other_expr)
+        }
+        (Expression::Literal(token), Expression::Literal(other_token)) => {
+            diff_token("literal", token, other_token)
+        }
+        (Expression::Variable(token), Expression::Variable(other_token)) => {
+            diff_token("variable", token, other_token)
+        }
+        (Expression::Assignment(name, expr), Expression::Assignment(other_name, other_expr)) => {
+            diff_token("variable", name, other_name).or_else(|| diff_expression(expr, other_expr))
+        }
+        (Expression::Interpolation(parts), Expression::Interpolation(other_parts))
+            if parts.len() == other_parts.len() =>
+        {
+            parts
+                .iter()
+                .zip(other_parts)
+                .find_map(|(part, other_part)| diff_expression(part, other_part))
+        }
+        (
+            Expression::Call(callee, _, arguments),
+            Expression::Call(other_callee, paren, other_arguments),
+        ) => diff_expression(callee, other_callee).or_else(|| {
+            if arguments.len() == other_arguments.len() {
+                arguments
+                    .iter()
+                    .zip(other_arguments)
+                    .find_map(|(argument, other_argument)| {
+                        diff_expression(argument, other_argument)
+                    })
+            } else {
+                Some(Difference::new(
+                    &format!("{} arguments vs {}", arguments.len(), other_arguments.len()),
+                    Some(left.leftmost_token()),
+                    Some(paren),
+                ))
+            }
+        }),
+        _ => Some(Difference::new(
+            &format!("{} vs {}", expression_kind(left), expression_kind(right)),
+            Some(left.leftmost_token()),
+            Some(right.leftmost_token()),
+        )),
+    }
+}
+
+fn diff_token(kind: &str, left: &Token, right: &Token) -> Option<Difference> {
+    if left.is_equivalent(right) {
+        None
+    } else {
+        Some(Difference::new(
+            &format!("{} '{}' vs '{}'", kind, left.lexeme, right.lexeme),
+            Some(left),
+            Some(right),
+        ))
+    }
+}
+
+fn first_token(stmt: &Statement) -> Option<&Token> {
+    match stmt {
+        Statement::Expression(expr) | Statement::Variable(expr) => Some(expr.leftmost_token()),
+        Statement::Assign(token, _)
+        | Statement::Exit(token, _)
+        | Statement::Assert(token, _, _)
+        | Statement::Print(token, _)
+        | Statement::Block(token, _)
+        | Statement::Empty(token) => Some(token),
+        Statement::Attempt(body, _, _) => first_token(body),
+    }
+}
+
+fn statement_kind(stmt: &Statement) -> &'static str {
+    match stmt {
+        Statement::Expression(_) => "expression statement",
+        Statement::Variable(_) => "variable statement",
+        Statement::Assign(_, _) => "let statement",
+        Statement::Block(_, _) => "block",
+        Statement::Exit(_, _) => "exit statement",
+        Statement::Assert(_, _, _) => "assert statement",
+        Statement::Print(keyword, _) if keyword._type == TokenType::Write => "write statement",
+        Statement::Print(_, _) => "print statement",
+        Statement::Attempt(_, _, _) => "attempt statement",
+        Statement::Empty(_) => "empty statement",
+    }
+}
+
+fn expression_kind(expr: &Expression) -> &'static str {
+    match expr {
+        Expression::Unary(_, _) => "unary expression",
+        Expression::Binary(_, _, _) => "binary expression",
+        Expression::Grouping(_) => "grouping",
+        Expression::Literal(_) => "literal",
+        Expression::Variable(_) => "variable",
+        Expression::Assignment(_, _) => "assignment",
+        Expression::Interpolation(_) => "interpolated string",
+        Expression::Call(_, _, _) => "call",
+        Expression::This(_) => "this",
+        Expression::Super(_, _) => "superclass method",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::{Parser, Scanner};
+
+    fn parse(source: &str) -> Vec<Statement> {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        Parser::new(tokens, true).parse().unwrap()
+    }
+
+    fn diff(a: &str, b: &str) -> Vec<String> {
+        ast_diff(&parse(a), &parse(b))
+            .iter()
+            .map(Difference::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn ignores_layout_comments_and_number_spelling() {
+        let a = "let a = 1 + 2;\n{ a * 3.0; }";
+        let b = "let a =\n  1+2; // sum\n{\n  a * 3; }";
+
+        assert_eq!(diff(a, b), Vec::<String>::new());
+        assert_eq!(parse(a), parse(b));
+    }
+
+    #[test]
+    fn reports_the_first_divergence() {
+        let a = "let a = 1;\n{ a * 2 - 1; }";
+        let b = "let a = 1;\n{ a + 2 - 4; }";
+
+        let differences = ast_diff(&parse(a), &parse(b));
+        assert_eq!(differences.len(), 1);
+        assert_eq!(differences[0].to_string(), "operator '*' vs '+' at 2:5");
+        assert_eq!(differences[0].left, Some(Span { offset: 15, len: 1 }));
+        assert_ne!(parse(a), parse(b));
+    }
+
+    #[test]
+    fn groupings_are_significant() {
+        assert_eq!(
+            diff("(a + b);", "((a + b));"),
+            vec!["binary expression vs grouping at 1:2"]
+        );
+        assert_eq!(
+            diff("(a + b);", "a + b;"),
+            vec!["grouping vs binary expression at 1:2"]
+        );
+        assert_eq!(diff("(a) + (b);", "(a) + (b);"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_missing_statements() {
+        assert_eq!(
+            diff("a; 1;", "a;"),
+            vec!["expression statement vs nothing at 1:4"]
+        );
+        assert_eq!(
+            diff("a;", "a; let b = 1;"),
+            vec!["nothing vs let statement at 1:8"]
+        );
+        assert_eq!(
+            diff("{ a; }", "exit 1;"),
+            vec!["block vs exit statement at 1:1"]
+        );
+    }
+}
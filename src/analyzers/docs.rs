@@ -0,0 +1,84 @@
+use crate::{
+    analyzers::{scanner::ScannerResult, Scanner},
+    types::{Token, TokenType},
+};
+
+/// Extracts documentation comments from the source
+///
+/// A documentation comment is a run of line comments on consecutive lines
+/// immediately preceding a declaration. Each run is returned with the
+/// identifier token of the declaration it documents; runs that aren't
+/// followed by a declaration are ignored.
+///
+/// ## Examples
+/// ```rust
+/// use lox::extract_docs;
+///
+/// let docs = extract_docs("// The answer\nlet answer = 42;").unwrap();
+/// assert_eq!(docs[0].0, "The answer");
+/// assert_eq!(docs[0].1.lexeme, "answer");
+/// ```
+pub fn extract_docs(source: &str) -> ScannerResult<Vec<(String, Token)>> {
+    let tokens = Scanner::with_comments(source, true)?.tokens;
+    let mut docs: Vec<(String, Token)> = Vec::new();
+    let mut comments: Vec<&Token> = Vec::new();
+    let mut previous_line = 0;
+
+    for (idx, token) in tokens.iter().enumerate() {
+        let follows_comments = comments
+            .last()
+            .is_some_and(|comment| comment.line + 1 == token.line);
+
+        match token._type {
+            TokenType::Comment if token.line != previous_line => {
+                if !follows_comments {
+                    comments.clear();
+                }
+                comments.push(token);
+            }
+            TokenType::Let if follows_comments && token.line != previous_line => {
+                if let Some(name) = tokens.get(idx + 1) {
+                    let text: Vec<&str> = comments
+                        .iter()
+                        .map(|comment| comment.lexeme.strip_prefix(' ').unwrap_or(&comment.lexeme))
+                        .collect();
+                    docs.push((text.join("\n"), name.clone()));
+                }
+                comments.clear();
+            }
+            _ => comments.clear(),
+        }
+        previous_line = token.line;
+    }
+
+    Ok(docs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_comment_runs_with_declarations() {
+        let source = "// The first\n// number\nlet one = 1;\n\n// Detached\n\nlet two = 2;\nlet three = 3; // trailing\n// Last\nlet four = 4;";
+        let docs = extract_docs(source).unwrap();
+
+        let docs: Vec<(String, String, usize)> = docs
+            .into_iter()
+            .map(|(text, token)| (text, token.lexeme, token.line))
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                ("The first\nnumber".to_string(), "one".to_string(), 3),
+                ("Last".to_string(), "four".to_string(), 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_comments_inside_strings_and_at_end_of_file() {
+        let source = "let s = \"// not docs\";\n// dangling";
+        assert!(extract_docs(source).unwrap().is_empty());
+    }
+}
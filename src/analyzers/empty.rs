@@ -0,0 +1,130 @@
+use crate::errors::{codes, Warning};
+use crate::types::Statement;
+
+/// Finds blocks without statements, such as the body of an attempt left as
+/// `{}`
+///
+/// Handlers are not reported when they are empty as that's the way to
+/// ignore an error on purpose, blocks nested in them are.
+pub fn empty_blocks(stmts: &[Statement]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for stmt in stmts {
+        statement(stmt, &mut warnings);
+    }
+    warnings
+}
+
+/// Finds lone `;`, such as a second `;` ending a statement or one following
+/// a block
+pub fn empty_statements(stmts: &[Statement]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for stmt in stmts {
+        stray_semicolons(stmt, &mut warnings);
+    }
+    warnings
+}
+
+fn stray_semicolons(stmt: &Statement, warnings: &mut Vec<Warning>) {
+    match stmt {
+        Statement::Empty(semicolon) => warnings.push(Warning::new(
+            codes::EMPTY_STATEMENT,
+            &[],
+            semicolon.line,
+            semicolon.column,
+        )),
+        Statement::Block(_, stmts) => {
+            for stmt in stmts {
+                stray_semicolons(stmt, warnings);
+            }
+        }
+        Statement::Attempt(body, _, handler) => {
+            stray_semicolons(body, warnings);
+            stray_semicolons(handler, warnings);
+        }
+        _ => {}
+    }
+}
+
+fn statement(stmt: &Statement, warnings: &mut Vec<Warning>) {
+    match stmt {
+        Statement::Block(brace, stmts) if stmts.is_empty() => {
+            warnings.push(Warning::new(
+                codes::EMPTY_BLOCK,
+                &[],
+                brace.line,
+                brace.column,
+            ));
+        }
+        Statement::Block(_, stmts) => {
+            for stmt in stmts {
+                statement(stmt, warnings);
+            }
+        }
+        Statement::Attempt(body, _, handler) => {
+            statement(body, warnings);
+            if let Statement::Block(_, stmts) = handler.as_ref() {
+                for stmt in stmts {
+                    statement(stmt, warnings);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::{Parser, Scanner};
+
+    fn empty(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        let statements = Parser::new(tokens, true).parse().unwrap();
+        empty_blocks(&statements)
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn empty_blocks_are_reported_at_their_brace() {
+        assert_eq!(
+            empty("{}\n{ { } }\nattempt {} handle (e) { {} }"),
+            vec![
+                "warning[W006]: empty block at line 1 column 1",
+                "warning[W006]: empty block at line 2 column 3",
+                "warning[W006]: empty block at line 3 column 9",
+                "warning[W006]: empty block at line 3 column 25",
+            ]
+        );
+    }
+
+    #[test]
+    fn blocks_with_statements_and_empty_handlers_are_not_reported() {
+        assert!(empty("{ 1; }\nattempt { 1 & true; } handle (e) {}").is_empty());
+    }
+
+    fn stray(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        let statements = Parser::new(tokens, true).parse().unwrap();
+        empty_statements(&statements)
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lone_semicolons_are_reported() {
+        assert_eq!(
+            stray(";\n1;;\n{ ; 2; };\nattempt { ; } handle (e) { }"),
+            vec![
+                "warning[W008]: empty statement at line 1 column 1",
+                "warning[W008]: empty statement at line 2 column 3",
+                "warning[W008]: empty statement at line 3 column 3",
+                "warning[W008]: empty statement at line 3 column 9",
+                "warning[W008]: empty statement at line 4 column 11",
+            ]
+        );
+        assert!(stray("1; { 2; }\nlet a = 3;").is_empty());
+    }
+}
@@ -0,0 +1,341 @@
+//! Formatter printing programs back as source in a canonical layout
+//!
+//! Every statement goes on its own line, blocks are indented by four spaces
+//! and statements end with a `;`. Comments stay where they were written,
+//! above the statement they precede or after the one they follow on the same
+//! line, and runs of blank lines between statements are kept as a single
+//! blank line. Parentheses are kept as written unless
+//! [strip_parens](FormatOptions::strip_parens) is set.
+use crate::analyzers::parser::{LineComment, Trivia};
+use crate::analyzers::{is_redundant_group, Parser, Scanner};
+use crate::errors::InterpreterError;
+use crate::types::{Expression, Statement, TokenType};
+
+const INDENT: &str = "    ";
+
+/// Formats the source, failing if it doesn't scan or parse
+///
+/// ## Examples
+/// ```rust
+/// use lox::format_source;
+///
+/// let source = "// total\nlet a=1;  let b = a+2; // sum\n\n\n{ print b; }";
+/// assert_eq!(
+///     format_source(source).unwrap(),
+///     "// total\nlet a = 1;\nlet b = a + 2; // sum\n\n{\n    print b;\n}\n"
+/// );
+/// ```
+pub fn format_source(source: &str) -> Result<String, InterpreterError> {
+    format_source_with(source, &FormatOptions::default())
+}
+
+/// Choices [format_source_with] leaves to the caller
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Drops the parentheses that don't change how the expression parses,
+    /// see [is_redundant_group]
+    pub strip_parens: bool,
+}
+
+/// Formats the source like [format_source] with the options
+///
+/// ## Examples
+/// ```rust
+/// use lox::{format_source_with, FormatOptions};
+///
+/// let options = FormatOptions { strip_parens: true };
+/// assert_eq!(
+///     format_source_with("let a = ((1) + 2) * (3);", &options).unwrap(),
+///     "let a = (1 + 2) * 3;\n"
+/// );
+/// ```
+pub fn format_source_with(
+    source: &str,
+    options: &FormatOptions,
+) -> Result<String, InterpreterError> {
+    let scanner = Scanner::with_comments(source, true)?;
+    let mut parser = Parser::with_trivia(scanner.tokens, scanner.blank_lines, false);
+    let (statements, closing) = parser.parse_with_trivia()?;
+
+    let mut formatter = Formatter {
+        strip_parens: options.strip_parens,
+        ..Formatter::default()
+    };
+    for (index, (statement, trivia)) in statements.iter().enumerate() {
+        let mut nested = trivia.nested.iter();
+        formatter.statement(statement, trivia, &mut nested, index == 0);
+    }
+    formatter.comments(&closing, statements.is_empty());
+    Ok(formatter.output)
+}
+
+#[derive(Default)]
+struct Formatter {
+    output: String,
+    depth: usize,
+    strip_parens: bool,
+}
+
+impl Formatter {
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    /// Separates what follows from what precedes it by a blank line if
+    /// there was one, except at the start of a block or of the program
+    fn blank_line(&mut self, blank_lines: usize, first: bool) {
+        if blank_lines > 0 && !first {
+            self.output.push('\n');
+        }
+    }
+
+    fn comments(&mut self, comments: &[LineComment], mut first: bool) {
+        for comment in comments {
+            self.blank_line(comment.blank_lines, first);
+            self.indent();
+            self.output.push_str("//");
+            self.output.push_str(&comment.token.lexeme);
+            self.output.push('\n');
+            first = false;
+        }
+    }
+
+    /// Prints the statement on its own lines, `nested` holds the trivia of
+    /// the statements within its blocks
+    fn statement<'a>(
+        &mut self,
+        statement: &Statement,
+        trivia: &Trivia,
+        nested: &mut impl Iterator<Item = &'a Trivia>,
+        first: bool,
+    ) {
+        self.comments(&trivia.leading, first);
+        self.blank_line(trivia.blank_lines, first && trivia.leading.is_empty());
+        self.indent();
+        match statement {
+            Statement::Expression(expr) | Statement::Variable(expr) => {
+                let expr = self.expression(expr);
+                self.output.push_str(&expr);
+                self.output.push(';');
+            }
+            Statement::Assign(name, expr) => {
+                let expr = self.expression(expr);
+                self.output
+                    .push_str(&format!("let {} = {};", name.lexeme, expr));
+            }
+            Statement::Print(keyword, expr) | Statement::Exit(keyword, expr) => {
+                let expr = self.expression(expr);
+                self.output
+                    .push_str(&format!("{} {};", keyword.lexeme, expr));
+            }
+            Statement::Assert(keyword, condition, message) => {
+                self.output.push_str(&keyword.lexeme);
+                self.output.push(' ');
+                let condition = self.expression(condition);
+                self.output.push_str(&condition);
+                if let Some(message) = message {
+                    let message = self.expression(message);
+                    self.output.push_str(", ");
+                    self.output.push_str(&message);
+                }
+                self.output.push(';');
+            }
+            Statement::Empty(_) => self.output.push(';'),
+            Statement::Block(_, statements) => self.block(statements, &trivia.closing, nested),
+            Statement::Attempt(body, name, handler) => {
+                self.output.push_str("attempt ");
+                self.attempt_block(body, nested);
+                self.output.push_str(&format!(" handle ({}) ", name.lexeme));
+                self.attempt_block(handler, nested);
+            }
+        }
+        if let Some(comment) = &trivia.trailing {
+            self.output.push_str(" //");
+            self.output.push_str(&comment.lexeme);
+        }
+        self.output.push('\n');
+    }
+
+    /// Prints the block from its opening to its closing brace
+    fn block<'a>(
+        &mut self,
+        statements: &[Statement],
+        closing: &[LineComment],
+        nested: &mut impl Iterator<Item = &'a Trivia>,
+    ) {
+        if statements.is_empty() && closing.is_empty() {
+            self.output.push_str("{}");
+            return;
+        }
+        self.output.push_str("{\n");
+        self.depth += 1;
+        for (index, statement) in statements.iter().enumerate() {
+            let trivia = nested.next().cloned().unwrap_or_default();
+            self.statement(statement, &trivia, nested, index == 0);
+        }
+        self.comments(closing, statements.is_empty());
+        self.depth -= 1;
+        self.indent();
+        self.output.push('}');
+    }
+
+    fn attempt_block<'a>(
+        &mut self,
+        block: &Statement,
+        nested: &mut impl Iterator<Item = &'a Trivia>,
+    ) {
+        let closing = nested
+            .next()
+            .map(|trivia| trivia.closing.clone())
+            .unwrap_or_default();
+        if let Statement::Block(_, statements) = block {
+            self.block(statements, &closing, nested);
+        }
+    }
+}
+
+impl Formatter {
+    /// Source of the expression
+    fn expression(&self, expr: &Expression) -> String {
+        self.subexpression(expr, None)
+    }
+
+    /// Source of the expression directly contained in `parent`
+    fn subexpression(&self, expr: &Expression, parent: Option<&Expression>) -> String {
+        let nested = |child: &Expression| self.subexpression(child, Some(expr));
+        match expr {
+            Expression::Grouping(inner)
+                if self.strip_parens && is_redundant_group(parent, expr) =>
+            {
+                self.subexpression(inner, parent)
+            }
+            Expression::Grouping(inner) => format!("({})", nested(inner)),
+            Expression::Literal(token) if token._type == TokenType::String => {
+                let quote = quote(&[&token.lexeme]);
+                format!("{}{}{}", quote, token.lexeme, quote)
+            }
+            Expression::Literal(token) | Expression::Variable(token) | Expression::This(token) => {
+                token.lexeme.clone()
+            }
+            Expression::Super(keyword, method) => format!("{}.{}", keyword.lexeme, method.lexeme),
+            Expression::Unary(operator, operand) => {
+                format!("{}{}", operator.lexeme, nested(operand))
+            }
+            Expression::Binary(left, operator, right) => {
+                format!("{} {} {}", nested(left), operator.lexeme, nested(right))
+            }
+            Expression::Assignment(name, value) => format!("{} = {}", name.lexeme, nested(value)),
+            Expression::Call(callee, _, arguments) => {
+                let arguments: Vec<String> = arguments.iter().map(nested).collect();
+                format!("{}({})", nested(callee), arguments.join(", "))
+            }
+            Expression::Interpolation(parts) => {
+                let segments: Vec<&str> = parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        Expression::Literal(token) if is_segment(&token._type) => {
+                            Some(token.lexeme.as_str())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let quote = quote(&segments);
+                let mut text = quote.to_string();
+                for part in parts {
+                    match part {
+                        Expression::Literal(token) if is_segment(&token._type) => {
+                            text.push_str(&token.lexeme)
+                        }
+                        part => text.push_str(&format!("${{{}}}", nested(part))),
+                    }
+                }
+                text.push_str(quote);
+                text
+            }
+        }
+    }
+}
+
+fn is_segment(token_type: &TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::StringHead | TokenType::StringMiddle | TokenType::StringTail
+    )
+}
+
+/// Quotes able to hold the text of the string, double quotes unless the
+/// text contains them or spans several lines
+pub(super) fn quote(segments: &[&str]) -> &'static str {
+    let contains = |c: char| segments.iter().any(|segment| segment.contains(c));
+    if contains('\n') || (contains('"') && contains('\'')) {
+        "\"\"\""
+    } else if contains('"') {
+        "'"
+    } else {
+        "\""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pipeline;
+
+    #[test]
+    fn formatted_sources_are_left_as_they_are() {
+        let sources = [
+            "let a = 1;\nprint a;\n",
+            "// The answer\n// to everything\nlet answer = 42;\n\n// Halved\nlet half = answer / 2; // rounded down\n",
+            "let a = 1;\n\nlet b = a * (2 + 3);\n\nprint \"a is ${a} and b is ${b}\";\n",
+            "{\n    // inside\n    let a = \"it's\";\n\n    {\n        print a; // nested\n    }\n    // before the brace\n}\n",
+            "attempt {\n    write 'say \"hi\"';\n} handle (e) {\n    // ignored\n} // after\n",
+            "assert clock() >= 0, \"\"\"time\nflows\"\"\";\n;\n{}\n// the end\n",
+        ];
+        for source in sources {
+            assert_eq!(format_source(source).unwrap(), source);
+        }
+    }
+
+    #[test]
+    fn layout_is_normalized() {
+        let source =
+            "\n\n// first\n\n\n// second\nlet a=-1 ;{a=a+1;}// grown\n\n\n\nprint a\n// last";
+        assert_eq!(
+            format_source(source).unwrap(),
+            "// first\n\n// second\nlet a = -1;\n{\n    a = a + 1;\n} // grown\n\nprint a;\n// last\n"
+        );
+    }
+
+    #[test]
+    fn comments_within_statements_move_above_them() {
+        let source = "let a = 1 + // one\n    2;\nprint a;";
+        assert_eq!(
+            format_source(source).unwrap(),
+            "// one\nlet a = 1 + 2;\nprint a;\n"
+        );
+    }
+
+    #[test]
+    fn trivia_doesnt_change_the_parsed_program() {
+        let source = "// a\nlet a = 1; // b\n\n{\n    // c\n    a = a + 1;\n\n    attempt { a; } handle (e) {\n        // d\n    }\n    // e\n}\n// f";
+        let pipeline = Pipeline::default();
+        let statements = pipeline.parse(pipeline.scan(source).unwrap()).unwrap();
+
+        let scanner = Scanner::with_comments(source, true).unwrap();
+        let mut parser = Parser::with_trivia(scanner.tokens, scanner.blank_lines, false);
+        let (with_trivia, closing) = parser.parse_with_trivia().unwrap();
+        let with_trivia: Vec<Statement> = with_trivia
+            .into_iter()
+            .map(|(statement, _)| statement)
+            .collect();
+        assert_eq!(with_trivia, statements);
+        assert_eq!(closing[0].token.lexeme, " f");
+
+        let formatted = format_source(source).unwrap();
+        let reformatted = pipeline.parse(pipeline.scan(&formatted).unwrap()).unwrap();
+        assert_eq!(reformatted, statements);
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::types::{Expression, TokenType};
+
+/// Binding strength of unary operators, calls and primaries, tighter than
+/// any binary operator
+const UNARY: u8 = u8::MAX;
+
+/// Returns true if the parentheses of `group` can be dropped without
+/// changing how the expression parses, `parent` being the expression
+/// directly containing the group or None if nothing does
+///
+/// `(1 + 2) * 3` needs its group while `(1 * 2) + 3` and `((a))` don't, and
+/// neither do groups that are a whole statement, argument or interpolated
+/// expression. Groups of negations under the same negation, as in `-(-a)`,
+/// are kept so they don't read as `--a`. Expressions other than
+/// [Grouping](Expression::Grouping) are never redundant.
+pub fn is_redundant_group(parent: Option<&Expression>, group: &Expression) -> bool {
+    let Expression::Grouping(inner) = group else {
+        return false;
+    };
+
+    let inner_precedence = match inner.as_ref() {
+        Expression::Binary(_, operator, _) => operator._type.precedence().unwrap_or(UNARY),
+        Expression::Assignment(_, _) => TokenType::Equal.precedence().unwrap(),
+        _ => UNARY,
+    };
+    match parent {
+        None
+        | Some(
+            Expression::Grouping(_) | Expression::Assignment(_, _) | Expression::Interpolation(_),
+        ) => true,
+        Some(Expression::Call(callee, _, _)) => {
+            !std::ptr::eq(callee.as_ref(), group) || inner_precedence == UNARY
+        }
+        Some(Expression::Unary(operator, _)) => match inner.as_ref() {
+            Expression::Unary(inner_operator, _) => !matches!(
+                (&operator._type, &inner_operator._type),
+                (TokenType::Minus, TokenType::Minus) | (TokenType::Not, TokenType::Not)
+            ),
+            _ => inner_precedence == UNARY,
+        },
+        Some(Expression::Binary(left, operator, _)) => {
+            let precedence = operator._type.precedence().unwrap_or(UNARY);
+            // Operators are left associative, `(a - b) - c` is `a - b - c`
+            inner_precedence > precedence
+                || (inner_precedence == precedence && std::ptr::eq(left.as_ref(), group))
+        }
+        Some(
+            Expression::Literal(_)
+            | Expression::Variable(_)
+            | Expression::This(_)
+            | Expression::Super(_, _),
+        ) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::{format_source_with, FormatOptions, Parser, Scanner};
+    use crate::{Environment, Literal, Statement};
+
+    fn parse(source: &str) -> Expression {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        match Parser::new(tokens, true).parse().unwrap().remove(0) {
+            Statement::Expression(expr) | Statement::Variable(expr) => expr,
+            statement => panic!("expected an expression, got {:?}", statement),
+        }
+    }
+
+    /// Source of the expression statement formatted with only the necessary
+    /// groups
+    fn stripped(source: &str) -> String {
+        let options = FormatOptions { strip_parens: true };
+        let formatted = format_source_with(source, &options).unwrap();
+        formatted.trim_end_matches(";\n").to_string()
+    }
+
+    #[test]
+    fn only_necessary_groups_are_kept() {
+        let scenarios = [
+            ("(1 + 2) * 3;", "(1 + 2) * 3"),
+            ("(1) + (2);", "1 + 2"),
+            ("(1 * 2) + 3;", "1 * 2 + 3"),
+            ("(a - b) - c;", "a - b - c"),
+            ("a - (b - c);", "a - (b - c)"),
+            ("((a));", "a"),
+            ("-(a + 1);", "-(a + 1)"),
+            ("-(abs(a));", "-abs(a)"),
+            ("-(-a);", "-(-a)"),
+            ("!(-a);", "!-a"),
+            ("(a = 1) + 2;", "(a = 1) + 2"),
+            ("a = (b = 1);", "a = b = 1"),
+            ("max((a + 1), (b));", "max(a + 1, b)"),
+            ("(max)(1, 2);", "max(1, 2)"),
+            ("(1 < 2) == (3 << 4);", "1 < 2 == 3 << 4"),
+            ("1 << (2 < 3);", "1 << 2 < 3"),
+            ("(1 << 2) < 3;", "(1 << 2) < 3"),
+            ("\"a ${(b)} ${(1 + 2) * 3}\";", "\"a ${b} ${(1 + 2) * 3}\""),
+            ("('a ${(\"b\")}') + 'c';", "\"a ${\"b\"}\" + \"c\""),
+        ];
+
+        for (source, expected) in scenarios {
+            assert_eq!(stripped(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn precedence_matches_the_parser() {
+        assert_eq!(TokenType::Star.precedence(), Some(10));
+        assert!(TokenType::NilCoalesce.precedence() < TokenType::Or.precedence());
+        assert!(TokenType::Plus.precedence() > TokenType::ShiftLeft.precedence());
+        assert!(TokenType::And.precedence() == TokenType::EqualEqual.precedence());
+        assert_eq!(TokenType::Not.precedence(), None);
+        assert_eq!(TokenType::Identifier.precedence(), None);
+    }
+
+    /// Small xorshift generator so the expressions are random but reproducible
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    const OPERATORS: [&str; 17] = [
+        "+", "-", "*", "/", "==", "!=", "<", "<=", ">", ">=", "&", "|", "^", "<<", "&&", "||", "??",
+    ];
+
+    /// Expression with a group around every operation
+    fn generate(random: &mut Random, depth: usize) -> String {
+        match random.below(if depth == 0 { 2 } else { 5 }) {
+            0 => ["a", "b", "c"][random.below(3)].to_string(),
+            1 => ["0", "1", "2", "3", "4", "nil"][random.below(6)].to_string(),
+            2 => format!(
+                "({}{})",
+                ["-", "~", "!"][random.below(3)],
+                generate(random, depth - 1)
+            ),
+            _ => format!(
+                "({} {} {})",
+                generate(random, depth - 1),
+                OPERATORS[random.below(OPERATORS.len())],
+                generate(random, depth - 1)
+            ),
+        }
+    }
+
+    fn evaluate(source: &str, bindings: &[i64; 3]) -> Option<String> {
+        let mut env = Environment::new();
+        for (name, value) in ["a", "b", "c"].iter().zip(bindings) {
+            env.define(name.to_string(), Literal::Integer(*value));
+        }
+        let value = parse(&format!("{};", source)).evaluate(&mut env).ok()?;
+        Some(value.into())
+    }
+
+    #[test]
+    fn stripping_groups_keeps_the_meaning() {
+        let mut random = Random(0x9e37_79b9_7f4a_7c15);
+        let mut evaluated = 0;
+
+        for _ in 0..500 {
+            let source = generate(&mut random, 4);
+            let formatted = stripped(&format!("{};", source));
+            for _ in 0..5 {
+                let bindings = [
+                    random.below(7) as i64 - 3,
+                    random.below(7) as i64 - 3,
+                    random.below(7) as i64 - 3,
+                ];
+                let value = evaluate(&source, &bindings);
+                evaluated += value.is_some() as usize;
+                assert_eq!(
+                    value,
+                    evaluate(&formatted, &bindings),
+                    "{} formatted as {} with {:?}",
+                    source,
+                    formatted,
+                    bindings
+                );
+            }
+        }
+        // Most operands mix types and fail, make sure enough of them don't
+        assert!(evaluated > 250, "only {} evaluated", evaluated);
+    }
+}
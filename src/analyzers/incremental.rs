@@ -0,0 +1,375 @@
+use std::ops::Range;
+
+use crate::analyzers::{Parser, Scanner};
+use crate::errors::InterpreterError;
+use crate::types::{Expression, Statement, Token, TokenType};
+
+/// Parser that keeps the statements of a source up to date as it's edited
+///
+/// Edits only re-scan and re-parse the top-level declarations they touch.
+/// Re-parsing starts at the closest declaration before the edit that follows
+/// a `;` or `}`, and stops at the first declaration after the edit that starts
+/// right after one; declarations past that point are reused with their
+/// locations shifted. The statements are always the same as parsing the whole
+/// source in strict mode would produce.
+///
+/// ## Examples
+/// ```rust
+/// use lox::IncrementalParser;
+///
+/// let mut parser = IncrementalParser::new("let a = 1;\nlet b = 2;").unwrap();
+/// parser.edit(8..9, "10").unwrap();
+///
+/// assert_eq!(parser.source(), "let a = 10;\nlet b = 2;");
+/// assert_eq!(parser.statements().len(), 2);
+/// ```
+pub struct IncrementalParser {
+    source: String,
+    statements: Vec<Statement>,
+    /// First and last token of every statement
+    bounds: Vec<(Token, Token)>,
+    /// Set when the source failed to parse, the next edit parses it whole
+    stale: bool,
+}
+
+impl IncrementalParser {
+    pub fn new(source: &str) -> Result<Self, InterpreterError> {
+        let mut parser = Self {
+            source: source.into(),
+            statements: Vec::new(),
+            bounds: Vec::new(),
+            stale: true,
+        };
+        parser.parse_all()?;
+        Ok(parser)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Statements of the source, empty if the last edit failed to parse
+    pub fn statements(&self) -> &[Statement] {
+        &self.statements
+    }
+
+    /// Replaces the byte `range` of the source with `replacement` and updates
+    /// the statements
+    ///
+    /// The edit is kept even if the new source fails to scan or parse, in
+    /// which case the statements are cleared until a later edit fixes it.
+    ///
+    /// # Panics
+    /// If the range is out of bounds or not on char boundaries.
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) -> Result<(), InterpreterError> {
+        if self.stale {
+            self.source.replace_range(range, replacement);
+            return self.parse_all();
+        }
+
+        // Start from a declaration the edit can't merge into the one before
+        let mut first = self
+            .bounds
+            .partition_point(|(start, _)| start.offset < range.start)
+            .saturating_sub(1);
+        while first > 0 && !ends_statement(&self.bounds[first - 1].1) {
+            first -= 1;
+        }
+        let (offset, line, column) = match self.bounds.get(first) {
+            Some((start, _)) if start.offset < range.start => {
+                (start.offset, start.line, start.column)
+            }
+            _ => (0, 1, 1),
+        };
+
+        let edit_end = range.start + replacement.len();
+        let delta = edit_end as isize - range.end as isize;
+        let shifted = |token: &Token| (token.offset as isize + delta) as usize;
+        self.source.replace_range(range.clone(), replacement);
+
+        // Scan until the cursor reaches an untouched declaration that can be
+        // reused as is
+        let mut scanner = Scanner::resuming(&self.source[offset..], offset, line, column);
+        let mut reused = self
+            .bounds
+            .partition_point(|(start, _)| start.offset < range.end);
+        let mut resync = None;
+        while scanner.has_next() {
+            if scanner.scan_token().is_err() {
+                return self.parse_all();
+            }
+
+            while reused < self.bounds.len() && shifted(&self.bounds[reused].0) < scanner.offset() {
+                reused += 1;
+            }
+            if reused < self.bounds.len()
+                && shifted(&self.bounds[reused].0) == scanner.offset()
+                && !scanner.in_interpolation()
+                && scanner.tokens.last().is_none_or(ends_statement)
+            {
+                resync = Some(reused);
+                break;
+            }
+        }
+        if scanner.in_interpolation() {
+            return self.parse_all();
+        }
+
+        let (new_line, new_column) = scanner.line_column();
+        let declarations = match Parser::new(scanner.tokens, true).parse_declarations() {
+            Ok(declarations) => declarations,
+            Err(_) => return self.parse_all(),
+        };
+
+        let mut statements = Vec::new();
+        let mut bounds = Vec::new();
+        if let Some(reused) = resync {
+            let start = &self.bounds[reused].0;
+            let old_line = start.line;
+            let line_delta = new_line as isize - start.line as isize;
+            let column_delta = new_column as isize - start.column as isize;
+            let mut shift = |token: &mut Token| {
+                if token.is_synthetic() {
+                    return;
+                }
+                if token.line == old_line {
+                    token.column = (token.column as isize + column_delta) as usize;
+                }
+                token.line = (token.line as isize + line_delta) as usize;
+                token.offset = (token.offset as isize + delta) as usize;
+            };
+
+            statements = self.statements.split_off(reused);
+            bounds = self.bounds.split_off(reused);
+            for statement in statements.iter_mut() {
+                visit_statement_tokens(statement, &mut shift);
+            }
+            for (start, end) in bounds.iter_mut() {
+                shift(start);
+                shift(end);
+            }
+        }
+
+        self.statements.truncate(first);
+        self.bounds.truncate(first);
+        for (statement, start, end) in declarations {
+            self.statements.push(statement);
+            self.bounds.push((start, end));
+        }
+        self.statements.append(&mut statements);
+        self.bounds.append(&mut bounds);
+        Ok(())
+    }
+
+    fn parse_all(&mut self) -> Result<(), InterpreterError> {
+        self.stale = true;
+        self.statements.clear();
+        self.bounds.clear();
+
+        let scanner = Scanner::new(&self.source)?;
+        for (statement, start, end) in Parser::new(scanner.tokens, true).parse_declarations()? {
+            self.statements.push(statement);
+            self.bounds.push((start, end));
+        }
+
+        self.stale = false;
+        Ok(())
+    }
+}
+
+/// Returns true if a declaration ending with the token can't be continued
+/// by the tokens that follow it
+fn ends_statement(token: &Token) -> bool {
+    matches!(token._type, TokenType::SemiColon | TokenType::RightBrace)
+}
+
+fn visit_statement_tokens(statement: &mut Statement, visit: &mut impl FnMut(&mut Token)) {
+    match statement {
+        Statement::Expression(expr) | Statement::Variable(expr) => {
+            visit_expression_tokens(expr, visit)
+        }
+        Statement::Assign(token, expr)
+        | Statement::Exit(token, expr)
+        | Statement::Print(token, expr) => {
+            visit(token);
+            visit_expression_tokens(expr, visit);
+        }
+        Statement::Assert(keyword, condition, message) => {
+            visit(keyword);
+            visit_expression_tokens(condition, visit);
+            if let Some(message) = message {
+                visit_expression_tokens(message, visit);
+            }
+        }
+        Statement::Block(brace, statements) => {
+            visit(brace);
+            for statement in statements {
+                visit_statement_tokens(statement, visit);
+            }
+        }
+        Statement::Attempt(body, name, handler) => {
+            visit_statement_tokens(body, visit);
+            visit(name);
+            visit_statement_tokens(handler, visit);
+        }
+        Statement::Empty(semicolon) => visit(semicolon),
+    }
+}
+
+fn visit_expression_tokens(expr: &mut Expression, visit: &mut impl FnMut(&mut Token)) {
+    match expr {
+        Expression::Literal(token) | Expression::Variable(token) | Expression::This(token) => {
+            visit(token)
+        }
+        Expression::Super(keyword, method) => {
+            visit(keyword);
+            visit(method);
+        }
+        Expression::Unary(token, expr) | Expression::Assignment(token, expr) => {
+            visit(token);
+            visit_expression_tokens(expr, visit);
+        }
+        Expression::Binary(left, token, right) => {
+            visit_expression_tokens(left, visit);
+            visit(token);
+            visit_expression_tokens(right, visit);
+        }
+        Expression::Grouping(expr) => visit_expression_tokens(expr, visit),
+        Expression::Call(callee, paren, arguments) => {
+            visit_expression_tokens(callee, visit);
+            visit(paren);
+            for argument in arguments {
+                visit_expression_tokens(argument, visit);
+            }
+        }
+        Expression::Interpolation(parts) => {
+            for part in parts {
+                visit_expression_tokens(part, visit);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAMS: [&str; 3] = [
+        "let a = 1;\nlet b = a + 2;\n{ let c = \"${a} and ${b}\"; c; }\na = b = 3;\nb;\nexit 0;",
+        "// totals\nlet total = 0;\n{\n  total = total + 1; // one\n  { total = total * 2; }\n}\ntotal;\n{ total; } { }",
+        "let s = \"\"\"multi\nline\"\"\";\nlet t = \"${s} ${\"${1 + 2}\"}\";\n(t);\n-1 & ~2; let u = 3; u = 4;",
+    ];
+
+    /// Snippets spliced over random ranges, these mostly break the program
+    const FRAGMENTS: [&str; 12] = [
+        "",
+        ";",
+        "}",
+        "{",
+        " + 2",
+        "\n",
+        "// c\n",
+        "\"s ${a}\"",
+        "a",
+        "\"",
+        "${",
+        "=",
+    ];
+
+    /// Snippets inserted after a `;` or `}`, these mostly keep it valid
+    const STATEMENTS: [&str; 6] = [
+        "let x = 3;",
+        "\n{ x; }\n",
+        " exit 1;",
+        "\"${x}\";",
+        "// note\n",
+        "a = 1 + 2;\n",
+    ];
+
+    /// Small xorshift generator so the edits are random but reproducible
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    fn parse(source: &str) -> Option<Vec<Statement>> {
+        let scanner = Scanner::new(source).ok()?;
+        Parser::new(scanner.tokens, true).parse().ok()
+    }
+
+    fn assert_matches_full_parse(parser: &IncrementalParser, edit: &str) {
+        match parse(parser.source()) {
+            // Debug output includes every location so spans are compared too
+            Some(expected) => assert_eq!(
+                format!("{:?}", parser.statements()),
+                format!("{:?}", expected),
+                "{} gave\n{}",
+                edit,
+                parser.source()
+            ),
+            None => assert!(parser.statements().is_empty(), "{}", edit),
+        }
+    }
+
+    #[test]
+    fn reuses_statements_after_the_edit() {
+        let mut parser = IncrementalParser::new("let a = 1;\n{ a; }\nlet b = 2; b;").unwrap();
+        parser.edit(8..9, "1 +\n 41").unwrap();
+
+        assert_eq!(parser.source(), "let a = 1 +\n 41;\n{ a; }\nlet b = 2; b;");
+        assert_eq!(parser.statements().len(), 4);
+        assert_matches_full_parse(&parser, "8..9");
+    }
+
+    #[test]
+    fn recovers_after_edits_that_fail_to_parse() {
+        let mut parser = IncrementalParser::new("let a = 1;\nlet b = 2;").unwrap();
+
+        assert!(parser.edit(9..10, "").is_err());
+        assert!(parser.statements().is_empty());
+
+        parser.edit(9..9, "; a;").unwrap();
+        assert_eq!(parser.statements().len(), 3);
+        assert_matches_full_parse(&parser, "9..9");
+    }
+
+    #[test]
+    fn random_edits_match_a_full_parse() {
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+
+        for program in PROGRAMS {
+            let mut parser = IncrementalParser::new(program).unwrap();
+            for _ in 0..300 {
+                let source = parser.source().to_string();
+                let (start, end, replacement) = if random.below(2) == 0 {
+                    let start = random.below(source.len() + 1);
+                    let end = start + random.below((source.len() - start).min(8) + 1);
+                    (start, end, FRAGMENTS[random.below(FRAGMENTS.len())])
+                } else {
+                    let ends: Vec<usize> = source
+                        .match_indices([';', '}'])
+                        .map(|(i, _)| i + 1)
+                        .collect();
+                    let start = ends[random.below(ends.len())];
+                    (start, start, STATEMENTS[random.below(STATEMENTS.len())])
+                };
+
+                let edit = format!("{:?} replacing {:?}", start..end, replacement);
+                let _ = parser.edit(start..end, replacement);
+                assert_matches_full_parse(&parser, &edit);
+
+                // Undo edits that break the program so later edits stay incremental
+                if parse(parser.source()).is_none() {
+                    let _ = parser.edit(start..start + replacement.len(), &source[start..end]);
+                    assert_matches_full_parse(&parser, &format!("undo of {}", edit));
+                }
+            }
+        }
+    }
+}
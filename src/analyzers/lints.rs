@@ -0,0 +1,304 @@
+//! Lints run by static analysis and the level each of them is reported at
+//!
+//! Every lint has a name, such as `shadowing`, and a default
+//! [LintLevel]. A [LintConfig] overrides the levels, from `--allow`, `--warn`
+//! and `--deny` on the command line and from a comment at the start of the
+//! script such as `// lox-lint: deny(shadowing), allow(empty-block)`, which
+//! takes precedence.
+//!
+//! ## Examples
+//! ```rust
+//! use lox::lints::{self, LintConfig, LintLevel};
+//! use lox::Pipeline;
+//!
+//! let source = "// lox-lint: allow(unused-variable)\nlet a = 1;\n{ let a = 2; }";
+//! let mut config = LintConfig::default();
+//! config.set("shadowing", LintLevel::Deny).unwrap();
+//! config.apply_comment(source).unwrap();
+//!
+//! let pipeline = Pipeline::default();
+//! let statements = pipeline.parse(pipeline.scan(source).unwrap()).unwrap();
+//! let warnings = lints::run(&statements, &config);
+//! assert_eq!(warnings.len(), 1);
+//! assert_eq!(warnings[0].level, LintLevel::Deny);
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{
+    double_negations, empty_blocks, empty_statements, shadowed_variables, unused_results,
+    unused_variables,
+};
+use crate::errors::codes::{self, ErrorCode};
+use crate::errors::Warning;
+use crate::Statement;
+
+/// What is done with the warnings of a lint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintLevel {
+    /// The lint doesn't run
+    Allow,
+    /// Warnings are reported and the program still runs
+    #[default]
+    Warn,
+    /// Warnings are reported and the program doesn't run
+    Deny,
+}
+
+impl FromStr for LintLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(LintLevel::Allow),
+            "warn" => Ok(LintLevel::Warn),
+            "deny" => Ok(LintLevel::Deny),
+            _ => Err(format!(
+                "unknown lint level `{}`, expected allow, warn or deny",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LintLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warn",
+            LintLevel::Deny => "deny",
+        })
+    }
+}
+
+/// Analysis pass reporting one kind of problem
+#[derive(Debug, Clone, Copy)]
+pub struct Lint {
+    pub name: &'static str,
+    /// Codes of the warnings the lint reports
+    pub codes: &'static [ErrorCode],
+    pub default: LintLevel,
+    pub check: fn(&[Statement]) -> Vec<Warning>,
+}
+
+/// Every lint, in the order of their codes
+pub const LINTS: &[Lint] = &[
+    Lint {
+        name: "unused-variable",
+        codes: &[codes::UNUSED_VARIABLE],
+        default: LintLevel::Warn,
+        check: unused_variables,
+    },
+    Lint {
+        name: "unused-result",
+        codes: &[codes::UNUSED_COMPARISON, codes::UNUSED_RESULT],
+        default: LintLevel::Warn,
+        check: unused_results,
+    },
+    Lint {
+        name: "double-negation",
+        codes: &[
+            codes::DOUBLE_NOT,
+            codes::DOUBLE_MINUS,
+            codes::DOUBLE_MINUS_VALUE,
+        ],
+        default: LintLevel::Warn,
+        check: double_negations,
+    },
+    Lint {
+        name: "empty-block",
+        codes: &[codes::EMPTY_BLOCK],
+        default: LintLevel::Warn,
+        check: empty_blocks,
+    },
+    Lint {
+        name: "shadowing",
+        codes: &[codes::SHADOWED_VARIABLE],
+        default: LintLevel::Warn,
+        check: shadowed_variables,
+    },
+    Lint {
+        name: "empty-statement",
+        codes: &[codes::EMPTY_STATEMENT],
+        default: LintLevel::Warn,
+        check: empty_statements,
+    },
+];
+
+/// The lint with the name
+pub fn find(name: &str) -> Option<&'static Lint> {
+    LINTS.iter().find(|lint| lint.name == name)
+}
+
+/// The lint reporting warnings with the code
+pub fn reporting(code: ErrorCode) -> Option<&'static Lint> {
+    LINTS.iter().find(|lint| lint.codes.contains(&code))
+}
+
+/// Levels of the lints that don't use their default
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    levels: HashMap<&'static str, LintLevel>,
+}
+
+impl LintConfig {
+    pub fn level(&self, lint: &Lint) -> LintLevel {
+        self.levels.get(lint.name).copied().unwrap_or(lint.default)
+    }
+
+    /// Sets the level of the lint, failing for unknown lints
+    pub fn set(&mut self, name: &str, level: LintLevel) -> Result<(), String> {
+        let lint = find(name).ok_or_else(|| format!("unknown lint `{}`", name))?;
+        self.levels.insert(lint.name, level);
+        Ok(())
+    }
+
+    /// Reads a flag such as `--deny=shadowing,empty-block`, returning false
+    /// if it isn't a lint flag
+    pub fn parse_flag(&mut self, flag: &str) -> Result<bool, String> {
+        let Some((level, names)) = flag
+            .strip_prefix("--")
+            .and_then(|flag| flag.split_once('='))
+        else {
+            return Ok(false);
+        };
+        let level = match level {
+            "allow" | "warn" | "deny" => level.parse()?,
+            _ => return Ok(false),
+        };
+        for name in names.split(',') {
+            self.set(name, level)?;
+        }
+        Ok(true)
+    }
+
+    /// Applies the `// lox-lint:` comments preceding the first line of code,
+    /// each listing levels such as `deny(shadowing, empty-block)`
+    pub fn apply_comment(&mut self, source: &str) -> Result<(), String> {
+        let comments = source
+            .lines()
+            .map(str::trim)
+            .take_while(|line| line.is_empty() || line.starts_with("//"));
+        for comment in comments {
+            let Some(levels) = comment
+                .strip_prefix("//")
+                .and_then(|comment| comment.trim_start().strip_prefix("lox-lint:"))
+            else {
+                continue;
+            };
+            let mut levels = levels.trim();
+            while !levels.is_empty() {
+                let invalid = || format!("invalid `lox-lint` comment `{}`", comment);
+                let (level, rest) = levels.split_once('(').ok_or_else(invalid)?;
+                let (names, rest) = rest.split_once(')').ok_or_else(invalid)?;
+                let level = level.trim().parse()?;
+                for name in names.split(',') {
+                    self.set(name.trim(), level)?;
+                }
+                levels = rest.trim_start_matches([',', ' ']);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs the lints that aren't allowed, returning their warnings in source
+/// order with the level of their lint
+pub fn run(statements: &[Statement], config: &LintConfig) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for lint in LINTS {
+        let level = config.level(lint);
+        if level == LintLevel::Allow {
+            continue;
+        }
+        warnings.extend((lint.check)(statements).into_iter().map(|mut warning| {
+            warning.level = level;
+            warning
+        }));
+    }
+    warnings.sort_by_key(|warning| (warning.line, warning.column));
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pipeline;
+
+    fn lint(source: &str, config: &LintConfig) -> Vec<String> {
+        let pipeline = Pipeline::default();
+        let statements = pipeline.parse(pipeline.scan(source).unwrap()).unwrap();
+        run(&statements, config)
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn every_warning_code_belongs_to_one_lint() {
+        for code in codes::ALL {
+            let lints = LINTS.iter().filter(|lint| lint.codes.contains(code));
+            let expected = usize::from(code.severity == codes::Severity::Warning);
+            assert_eq!(lints.count(), expected, "{}", code);
+        }
+    }
+
+    #[test]
+    fn flags_override_the_default_levels() {
+        let mut config = LintConfig::default();
+        assert!(config.parse_flag("--allow=empty-block").unwrap());
+        assert!(config
+            .parse_flag("--deny=shadowing,unused-variable")
+            .unwrap());
+        assert!(!config.parse_flag("--max-vars=3").unwrap());
+        assert_eq!(
+            config.parse_flag("--warn=shadow"),
+            Err("unknown lint `shadow`".into())
+        );
+
+        assert_eq!(
+            lint("let a = 1;\n{ {} let a = 2; }", &config),
+            vec![
+                "error[W001]: unused variable `a` at line 2 column 10",
+                "error[W007]: `a` shadows the variable declared at line 1 column 5 at line 2 column 10",
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_override_the_flags() {
+        let mut config = LintConfig::default();
+        config.parse_flag("--deny=empty-block").unwrap();
+        let source = "// lox-lint: allow(empty-block), deny(shadowing)\n\
+                      //lox-lint: warn(unused-variable)\n\
+                      let a = 1;\n{ {} let a = 2; }";
+        config.apply_comment(source).unwrap();
+
+        assert_eq!(
+            lint(source, &config),
+            vec![
+                "warning[W001]: unused variable `a` at line 4 column 10",
+                "error[W007]: `a` shadows the variable declared at line 3 column 5 at line 4 column 10",
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_after_code_are_ignored_and_invalid_ones_fail() {
+        let mut config = LintConfig::default();
+        config
+            .apply_comment("let a = 1;\n// lox-lint: deny(shadowing)")
+            .unwrap();
+        assert_eq!(config, LintConfig::default());
+
+        assert_eq!(
+            config.apply_comment("// lox-lint: deny(shadowing"),
+            Err("invalid `lox-lint` comment `// lox-lint: deny(shadowing`".into())
+        );
+        assert_eq!(
+            config.apply_comment("// lox-lint: forbid(shadowing)"),
+            Err("unknown lint level `forbid`, expected allow, warn or deny".into())
+        );
+    }
+}
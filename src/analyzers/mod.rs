@@ -6,8 +6,33 @@
 //! ```rust
 //!
 //! ```
+pub mod diff;
+pub mod docs;
+pub mod empty;
+pub mod formatter;
+pub mod groups;
+pub mod incremental;
+pub mod lints;
+pub mod negations;
 pub mod parser;
+pub mod reconstruct;
+pub mod references;
 pub mod scanner;
+pub mod shadowing;
+pub mod symbols;
+pub mod unused;
 
-pub use parser::Parser;
+pub use diff::{ast_diff, Difference};
+pub use docs::extract_docs;
+pub use empty::{empty_blocks, empty_statements};
+pub use formatter::{format_source, format_source_with, FormatOptions};
+pub use groups::is_redundant_group;
+pub use incremental::IncrementalParser;
+pub use negations::double_negations;
+pub use parser::{LineComment, Parser, Trivia};
+pub use reconstruct::{canonical_number, reconstruct_source};
+pub use references::{find_reference, Reference};
 pub use scanner::Scanner;
+pub use shadowing::shadowed_variables;
+pub use symbols::{index_symbols, Symbol, SymbolIndex, Usage};
+pub use unused::{unused_results, unused_variables};
@@ -0,0 +1,140 @@
+use crate::errors::{codes, Warning};
+use crate::types::{Expression, Statement, TokenType};
+
+/// Finds `!` and `-` applied twice in a row, such as `!!done` or `--count`,
+/// which give back the operand unchanged
+///
+/// `--count` is most likely meant to decrement `count` as it does in C, but
+/// it negates it twice instead. Negations separated by a grouping, as in
+/// `-(-count)`, are taken as intended and not reported.
+pub fn double_negations(stmts: &[Statement]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for stmt in stmts {
+        statement(stmt, &mut warnings);
+    }
+    warnings
+}
+
+fn statement(stmt: &Statement, warnings: &mut Vec<Warning>) {
+    match stmt {
+        Statement::Expression(expr)
+        | Statement::Variable(expr)
+        | Statement::Assign(_, expr)
+        | Statement::Exit(_, expr)
+        | Statement::Print(_, expr) => expression(expr, warnings),
+        Statement::Assert(_, condition, message) => {
+            expression(condition, warnings);
+            if let Some(message) = message {
+                expression(message, warnings);
+            }
+        }
+        Statement::Block(_, stmts) => {
+            for stmt in stmts {
+                statement(stmt, warnings);
+            }
+        }
+        Statement::Attempt(body, _, handler) => {
+            statement(body, warnings);
+            statement(handler, warnings);
+        }
+        Statement::Empty(_) => {}
+    }
+}
+
+fn expression(expr: &Expression, warnings: &mut Vec<Warning>) {
+    match expr {
+        Expression::Unary(operator, operand) => {
+            let code = match (&operator._type, &**operand) {
+                (TokenType::Not, Expression::Unary(inner, _)) if inner._type == TokenType::Not => {
+                    Some(codes::DOUBLE_NOT)
+                }
+                // Only a variable could have been meant to be decremented
+                (TokenType::Minus, Expression::Unary(inner, negated))
+                    if inner._type == TokenType::Minus =>
+                {
+                    Some(match &**negated {
+                        Expression::Variable(name) if name._type == TokenType::Identifier => {
+                            codes::DOUBLE_MINUS
+                        }
+                        _ => codes::DOUBLE_MINUS_VALUE,
+                    })
+                }
+                // `--5` negates the number -5
+                (TokenType::Minus, Expression::Literal(number))
+                    if number._type == TokenType::Number && number.lexeme.starts_with('-') =>
+                {
+                    Some(codes::DOUBLE_MINUS_VALUE)
+                }
+                _ => None,
+            };
+            if let Some(code) = code {
+                warnings.push(Warning::new(code, &[], operator.line, operator.column));
+            }
+            expression(operand, warnings);
+        }
+        Expression::Assignment(_, expr) | Expression::Grouping(expr) => expression(expr, warnings),
+        Expression::Binary(left, _, right) => {
+            expression(left, warnings);
+            expression(right, warnings);
+        }
+        Expression::Interpolation(parts) => {
+            for part in parts {
+                expression(part, warnings);
+            }
+        }
+        Expression::Call(callee, _, arguments) => {
+            expression(callee, warnings);
+            for argument in arguments {
+                expression(argument, warnings);
+            }
+        }
+        Expression::Literal(_)
+        | Expression::Variable(_)
+        | Expression::This(_)
+        | Expression::Super(_, _) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::{Parser, Scanner};
+
+    fn negations(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        let statements = Parser::new(tokens, true).parse().unwrap();
+        double_negations(&statements)
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn double_negations_are_reported_at_the_outer_operator() {
+        assert_eq!(
+            negations("let a = 1;\nlet b = --a;\n{ !!(a < 2); }"),
+            vec![
+                "warning[W005]: `--` negates twice and has no effect, write `a = a - 1` to decrement at line 2 column 9",
+                "warning[W004]: `!!` negates twice and has no effect, use the operand as is at line 3 column 3",
+            ]
+        );
+        assert_eq!(negations("---1;").len(), 2);
+    }
+
+    #[test]
+    fn only_variables_are_told_to_decrement() {
+        assert_eq!(
+            negations("--5;\n--(a);\n--abs(a);"),
+            vec![
+                "warning[W009]: `--` negates twice and has no effect, use the operand as is at line 1 column 1",
+                "warning[W009]: `--` negates twice and has no effect, use the operand as is at line 2 column 1",
+                "warning[W009]: `--` negates twice and has no effect, use the operand as is at line 3 column 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn other_nested_unaries_are_not_reported() {
+        assert!(negations("-(-1); !(!true); -~1; ~-1; !(1 < -2);").is_empty());
+    }
+}
@@ -15,9 +15,18 @@
 ///
 ///   declaration -> varDcl | statement;
 ///
-///   declaration -> "var" IDENTIFIER ( "=" expression )? ";" ;
+///   varDcl -> "let" IDENTIFIER ( "=" expression )? ";" ;
 ///
-///   statement -> exprStmt | block;
+///   statement -> exprStmt | exitStmt | assertStmt | printStmt | attemptStmt
+///                | block;
+///
+///   exitStmt -> "exit" expression ";" ;
+///
+///   assertStmt -> "assert" expression ( "," expression )? ";" ;
+///
+///   printStmt -> ( "print" | "write" ) expression ";" ;
+///
+///   attemptStmt -> "attempt" block "handle" "(" IDENTIFIER ")" block ;
 ///
 ///   block -> "{" declaration "}";
 ///
@@ -25,9 +34,19 @@
 ///
 ///   expression -> assignment ;
 ///
-///   assignment -> IDENTIFIER "=" assignment | equality;
+///   assignment -> IDENTIFIER "=" assignment | coalesce;
+///
+///   coalesce -> equality ( "??" equality )* ;
 ///   
-///   equality -> comparison ( ( "!=", "==" ) comparison) ;
+///   equality -> bitOr ( ( "!=", "==", "&&", "||" ) bitOr )* ;
+///
+///   bitOr -> bitXor ( "|" bitXor )* ;
+///
+///   bitXor -> bitAnd ( "^" bitAnd )* ;
+///
+///   bitAnd -> shift ( "&" shift )* ;
+///
+///   shift -> comparison ( ( "<<", ">>" ) comparison )* ;
 ///
 ///   comparison -> term ( ( ">", "<", ">=", "<=" ) factor )* ;
 ///
@@ -35,162 +54,700 @@
 ///
 ///   factor -> unary ( ( "/", "*" ) unary)* ;
 ///
-///   unary -> ( "!", "-" ) unary | primary;
+///   unary -> "-" NUMBER | ( "!", "-", "~" ) unary | call;
+///
+///   call -> primary ( "(" arguments? ")" )* ;
+///
+///   A `.` after a call or primary is reported as property access, which
+///   isn't supported yet.
+///
+///   arguments -> expression ( "," expression )* ;
 ///
-///   primary -> NUMBER | STRING | "true" | "false" | "(" expression ")"
-///              | IDENTIFIER ;
+///   primary -> NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")"
+///              | IDENTIFIER | interpolation | "this" | "super" "." IDENTIFIER ;
+///
+///   interpolation -> STRING_HEAD expression ( STRING_MIDDLE expression )*
+///                    STRING_TAIL ;
 use crate::{
-    errors::{ExceptionType, ParserError},
-    types::{Expression, Statement, Token, TokenType},
+    errors::{codes, ParserError},
+    types::{Expression, Statement, Token, TokenStream, TokenType},
+    LanguageVersion,
 };
 
 pub type ParserResult<T> = Result<T, ParserError>;
 
+/// Statements paired with their [Trivia], followed by the comments after the
+/// last statement
+pub type Annotated = (Vec<(Statement, Trivia)>, Vec<LineComment>);
+
+/// Line comment kept by [Parser::parse_with_trivia]
+#[derive(Debug, Clone)]
+pub struct LineComment {
+    /// Blank lines between the comment and whatever precedes it
+    pub blank_lines: usize,
+    /// Comment token, its lexeme is the text following the `//`
+    pub token: Token,
+}
+
+/// Comments and blank lines around a statement, which the parser otherwise
+/// discards
+///
+/// Comments within an expression spanning several lines are moved to the
+/// leading comments of their statement.
+#[derive(Debug, Clone, Default)]
+pub struct Trivia {
+    /// Comments on the lines before the statement
+    pub leading: Vec<LineComment>,
+    /// Blank lines between the statement and its last leading comment, or
+    /// whatever precedes the statement if there is none
+    pub blank_lines: usize,
+    /// Comment on the line the statement ends on
+    pub trailing: Option<Token>,
+    /// Comments before the closing brace of a block
+    pub closing: Vec<LineComment>,
+    /// Trivia of every statement within the blocks of the statement, in
+    /// source order, the blocks of an `attempt` included
+    pub nested: Vec<Trivia>,
+}
+
+/// Comments and blank lines set aside by a parser keeping trivia
+#[derive(Default)]
+struct TriviaState {
+    /// Comments in source order
+    comments: Vec<Token>,
+    /// First comment not attached to a statement yet
+    next: usize,
+    blank_lines: Vec<usize>,
+    /// Line of the last comment attached
+    line: usize,
+    /// Trivia of the statements parsed so far, in source order
+    parsed: Vec<Trivia>,
+    /// Statements being parsed, innermost last, as indexes in `parsed`
+    open: Vec<usize>,
+}
+
+impl TriviaState {
+    /// Blank lines strictly between the lines
+    fn blank_between(&self, after: usize, before: usize) -> usize {
+        let first = self.blank_lines.partition_point(|&line| line <= after);
+        let last = self.blank_lines.partition_point(|&line| line < before);
+        last.saturating_sub(first)
+    }
+
+    /// Takes the comments preceding the offset, with the blank lines before
+    /// each of them counted from `line`
+    fn take_comments(&mut self, offset: usize, mut line: usize) -> Vec<LineComment> {
+        let mut comments = Vec::new();
+        while let Some(token) = self
+            .comments
+            .get(self.next)
+            .filter(|comment| comment.offset < offset)
+        {
+            comments.push(LineComment {
+                blank_lines: self.blank_between(line, token.line),
+                token: token.clone(),
+            });
+            line = line.max(token.line);
+            self.next += 1;
+        }
+        self.line = line;
+        comments
+    }
+}
+
+/// Line the token ends on, multiline strings end past the line they start on
+fn end_line(token: &Token) -> usize {
+    token.line + token.lexeme.matches('\n').count()
+}
+
+/// Valid left hand side of an assignment expression
+enum AssignTarget {
+    Variable(Token),
+}
+
 /// AST Parser for the Lox language
 pub struct Parser {
-    current: usize,
-    source: Vec<Token>,
+    tokens: TokenStream,
     strict_mode: bool,
+    /// Errors of statements skipped while recovering inside blocks
+    errors: Vec<ParserError>,
+    /// Set after an error until synchronizing finds where the next statement
+    /// starts, errors found meanwhile are follow-ups of the first one
+    panic_mode: bool,
+    version: LanguageVersion,
+    /// Whether the tokens are within a method, where `this` can be used,
+    /// always false until classes can be declared
+    in_method: bool,
+    /// Whether the tokens are within a method of a subclass, where `super`
+    /// can be used
+    in_subclass: bool,
+    /// Comments and blank lines kept for [Parser::parse_with_trivia], none
+    /// unless the parser was created [with_trivia](Parser::with_trivia)
+    trivia: Option<TriviaState>,
 }
 
 impl Parser {
+    /// Creates a parser over the tokens, transparently skipping any
+    /// [TokenType::Comment] tokens kept by the scanner
+    ///
+    /// The tokens don't need to end with a [TokenType::Eof], input ends at
+    /// the first one or after the last token, whichever comes first. See
+    /// [Parser::parse] for tokens found after an Eof.
     pub fn new(source: Vec<Token>, strict_mode: bool) -> Self {
+        Self::with_version(source, strict_mode, LanguageVersion::default())
+    }
+
+    /// Creates a parser that rejects the syntax the language version doesn't
+    /// have
+    pub fn with_version(source: Vec<Token>, strict_mode: bool, version: LanguageVersion) -> Self {
         Self {
-            source,
-            current: 0,
+            tokens: TokenStream::new(source),
             strict_mode,
+            errors: Vec::new(),
+            panic_mode: false,
+            version,
+            in_method: false,
+            in_subclass: false,
+            trivia: None,
         }
     }
 
+    /// Creates a parser keeping the comments among the tokens and the blank
+    /// lines of the source aside for [Parser::parse_with_trivia], such as
+    /// those of [Scanner::with_comments](crate::Scanner::with_comments)
+    pub fn with_trivia(source: Vec<Token>, blank_lines: Vec<usize>, strict_mode: bool) -> Self {
+        Self::with_trivia_and_version(source, blank_lines, strict_mode, LanguageVersion::default())
+    }
+
+    /// Creates a parser keeping the trivia like [Parser::with_trivia] that
+    /// rejects the syntax the language version doesn't have
+    pub(crate) fn with_trivia_and_version(
+        source: Vec<Token>,
+        blank_lines: Vec<usize>,
+        strict_mode: bool,
+        version: LanguageVersion,
+    ) -> Self {
+        let comments = source
+            .iter()
+            .filter(|token| token._type == TokenType::Comment)
+            .cloned()
+            .collect();
+        Self {
+            trivia: Some(TriviaState {
+                comments,
+                blank_lines,
+                ..TriviaState::default()
+            }),
+            ..Self::with_version(source, strict_mode, version)
+        }
+    }
+
+    /// Parses the program, failing with the first error in the source
+    ///
+    /// Input ends at the first [TokenType::Eof], or after the last token if
+    /// there is none. Tokens after an Eof are an error reported at the first
+    /// of them as "unexpected tokens after end of input", the scanner never
+    /// produces them but token vectors built by hand or spliced together
+    /// might.
     pub fn parse(&mut self) -> ParserResult<Vec<Statement>> {
+        let (statements, errors) = self.parse_recovering();
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(statements),
+        }
+    }
+
+    /// Parses the program skipping statements that fail to parse, returning
+    /// the statements that did parse along with every error in source order
+    ///
+    /// Statements are only fit to be executed when there are no errors, the
+    /// recovered statements are meant for analysis of broken programs.
+    pub fn parse_recovering(&mut self) -> (Vec<Statement>, Vec<ParserError>) {
         let mut statements: Vec<Statement> = Vec::new();
-        while self.current < self.source.len() {
+        while !self.tokens.is_at_end() {
+            if let Some(statement) = self.parse_or_synchronize() {
+                statements.push(statement);
+            }
+        }
+        self.check_trailing();
+
+        (statements, std::mem::take(&mut self.errors))
+    }
+
+    /// Parses the program like [Parser::parse], pairing every statement with
+    /// the comments and blank lines around it so a formatter can print them
+    /// back in place
+    ///
+    /// Comments following the last statement are returned along with the
+    /// statements. Parsers not created [with_trivia](Parser::with_trivia)
+    /// have no comments or blank lines to attach.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use lox::{Parser, Scanner};
+    ///
+    /// let scanner = Scanner::with_comments("// one\nlet a = 1;\n\nlet b = 2; // two", true).unwrap();
+    /// let mut parser = Parser::with_trivia(scanner.tokens, scanner.blank_lines, false);
+    /// let (statements, closing) = parser.parse_with_trivia().unwrap();
+    ///
+    /// assert_eq!(statements[0].1.leading[0].token.lexeme, " one");
+    /// assert_eq!(statements[1].1.blank_lines, 1);
+    /// assert_eq!(statements[1].1.trailing.as_ref().unwrap().lexeme, " two");
+    /// assert!(closing.is_empty());
+    /// ```
+    pub fn parse_with_trivia(&mut self) -> ParserResult<Annotated> {
+        self.trivia.get_or_insert_with(TriviaState::default);
+        let mut statements = Vec::new();
+        let mut starts = Vec::new();
+        while !self.tokens.is_at_end() {
+            starts.push(self.trivia.as_ref().map_or(0, |trivia| trivia.parsed.len()));
             statements.push(self.parse_declaration()?);
         }
+        self.check_trailing();
+        if let Some(error) = self.errors.first() {
+            return Err(error.clone());
+        }
+
+        let line = end_line(self.tokens.previous());
+        let trivia = self.trivia.as_mut().unwrap();
+        let closing = trivia.take_comments(usize::MAX, line.max(trivia.line));
+        let mut parsed = std::mem::take(&mut trivia.parsed);
+        let mut paired = Vec::with_capacity(statements.len());
+        for (statement, start) in statements.into_iter().zip(starts).rev() {
+            let mut nested = parsed.split_off(start);
+            let mut trivia = nested.remove(0);
+            trivia.nested = nested;
+            paired.push((statement, trivia));
+        }
+        paired.reverse();
+        Ok((paired, closing))
+    }
+
+    /// Starts the trivia of the statement under the cursor with the
+    /// comments and blank lines preceding it
+    fn begin_trivia(&mut self) {
+        let Some(trivia) = &mut self.trivia else {
+            return;
+        };
+        let start = self.tokens.peek();
+        let after = match self.tokens.position() {
+            0 => 0,
+            _ => end_line(self.tokens.previous()),
+        };
+        let leading = trivia.take_comments(start.offset, after.max(trivia.line));
+        let blank_lines = trivia.blank_between(trivia.line, start.line);
+        trivia.open.push(trivia.parsed.len());
+        trivia.parsed.push(Trivia {
+            leading,
+            blank_lines,
+            ..Trivia::default()
+        });
+    }
+
+    /// Ends the trivia of the statement just parsed, attaching the comment
+    /// on its last line if `trailing` is set
+    fn end_trivia(&mut self, trailing: bool) {
+        let Some(trivia) = &mut self.trivia else {
+            return;
+        };
+        let Some(index) = trivia.open.pop() else {
+            return;
+        };
+        let last = self.tokens.previous();
+        let line = end_line(last);
+        let inner = trivia.take_comments(last.offset, line);
+        trivia.parsed[index].leading.extend(inner);
+        if !trailing {
+            return;
+        }
+        // Code between the statement and the comment takes it instead
+        let next = (!self.tokens.is_at_end()).then(|| self.tokens.peek().offset);
+        if let Some(comment) = trivia
+            .comments
+            .get(trivia.next)
+            .filter(|comment| comment.line == line && next.is_none_or(|next| comment.offset < next))
+        {
+            trivia.parsed[index].trailing = Some(comment.clone());
+            trivia.next += 1;
+        }
+    }
 
-        Ok(statements)
+    /// Attaches the comments before the closing brace under the cursor to
+    /// the innermost statement, the block being parsed
+    fn close_trivia(&mut self) {
+        let Some(trivia) = &mut self.trivia else {
+            return;
+        };
+        let Some(&index) = trivia.open.last() else {
+            return;
+        };
+        let line = end_line(self.tokens.previous()).max(trivia.line);
+        let closing = trivia.take_comments(self.tokens.peek().offset, line);
+        trivia.parsed[index].closing = closing;
+    }
+
+    /// Records an error if tokens follow the end of input
+    fn check_trailing(&mut self) {
+        if let Some(token) = self.tokens.trailing() {
+            self.errors
+                .push(ParserError::new(codes::TRAILING_TOKENS, &[], token));
+        }
+    }
+
+    /// Parses a single expression without a trailing `;`, such as `a * 2`,
+    /// failing if anything follows it
+    pub(crate) fn parse_standalone_expression(&mut self) -> ParserResult<Expression> {
+        let expr = self.parse_expression()?;
+        if !self.tokens.is_at_end() {
+            return Err(ParserError::new(
+                codes::EXPECTED_END_OF_EXPRESSION,
+                &[],
+                self.tokens.peek(),
+            ));
+        }
+        self.check_trailing();
+
+        match self.errors.first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(expr),
+        }
+    }
+
+    /// Parses the program returning every declaration along with its first
+    /// and last token
+    pub(super) fn parse_declarations(&mut self) -> ParserResult<Vec<(Statement, Token, Token)>> {
+        let mut declarations = Vec::new();
+        while !self.tokens.is_at_end() {
+            let first = self.tokens.peek().clone();
+            let statement = self.parse_declaration()?;
+            declarations.push((statement, first, self.tokens.previous().clone()));
+        }
+        self.check_trailing();
+
+        match self.errors.first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(declarations),
+        }
+    }
+
+    /// Parses a declaration, on failure the error is recorded and the rest of
+    /// the statement is skipped
+    fn parse_or_synchronize(&mut self) -> Option<Statement> {
+        let start = self.tokens.position();
+        match self.parse_declaration() {
+            Ok(statement) => Some(statement),
+            Err(error) => {
+                self.record(error);
+                self.synchronize();
+                // Always make progress, i.e past a stray `}` at the top level
+                if self.tokens.position() == start {
+                    self.tokens.advance();
+                }
+                None
+            }
+        }
+    }
+
+    /// Records the error unless it follows from one already recorded, so a
+    /// single mistake is reported once
+    fn record(&mut self, error: ParserError) {
+        if self.panic_mode || self.errors.last() == Some(&error) {
+            return;
+        }
+        self.errors.push(error);
+        self.panic_mode = true;
+    }
+
+    /// Skips tokens up to and including the next `;`, or up to the next
+    /// token that starts a statement or closes a block
+    ///
+    /// Running out of input leaves the parser in panic mode, so the blocks
+    /// left unclosed by the broken statement aren't reported again.
+    fn synchronize(&mut self) {
+        while !self.tokens.is_at_end() {
+            match self.tokens.peek()._type {
+                TokenType::SemiColon => {
+                    self.tokens.advance();
+                    self.panic_mode = false;
+                    return;
+                }
+                TokenType::Let
+                | TokenType::Exit
+                | TokenType::Assert
+                | TokenType::Attempt
+                | TokenType::Print
+                | TokenType::Write
+                | TokenType::LeftBrace
+                | TokenType::RightBrace => {
+                    self.panic_mode = false;
+                    return;
+                }
+                _ => {
+                    self.tokens.advance();
+                }
+            }
+        }
+    }
+
+    /// Fails unless the language version has the extension starting with
+    /// the token
+    fn require_extensions(&self, token: &Token) -> ParserResult<()> {
+        if self.version.has_extensions() {
+            return Ok(());
+        }
+        Err(ParserError::new(
+            codes::UNAVAILABLE_IN_VERSION,
+            &[&token.lexeme, &self.version],
+            token,
+        ))
     }
 
     fn parse_declaration(&mut self) -> ParserResult<Statement> {
-        if self.advance_if_match(vec![TokenType::Let]) {
+        self.begin_trivia();
+        let statement = if self.tokens.match_any(&[TokenType::Let]).is_some() {
             self.parse_variable()
         } else {
             self.parse_statement()
-        }
+        };
+        self.end_trivia(true);
+        statement
     }
 
     fn parse_variable(&mut self) -> ParserResult<Statement> {
-        if !self.matches(vec![TokenType::Identifier]) {
-            Err(ParserError::new(
-                "expected an identifier",
-                &self.peek(),
-                ExceptionType::RuntimeException,
-            ))
+        if self.tokens.peek()._type.is_keyword() {
+            return Err(Self::reserved_word(
+                self.tokens.peek(),
+                "used as a variable name",
+            ));
+        }
+        let name = self
+            .tokens
+            .expect(TokenType::Identifier, codes::EXPECTED_IDENTIFIER, &[])?;
+        self.check_and_consume(TokenType::Equal)?;
+        let initializer = self.parse_expression()?;
+        self.end_statement()?;
+        Ok(Statement::Assign(name, initializer))
+    }
+
+    /// Consumes the semicolon ending a statement, which is optional outside
+    /// of strict mode
+    fn end_statement(&mut self) -> ParserResult<()> {
+        if self.strict_mode {
+            self.check_and_consume(TokenType::SemiColon)?;
         } else {
-            let name = self.consume();
-            self.check_and_consume(TokenType::Equal)?;
-            let initializer = self.parse_expression()?;
-            if self.strict_mode {
-                self.check_and_consume(TokenType::SemiColon)?;
-            } else if self.matches(vec![TokenType::SemiColon]) {
-                self.consume();
-            }
-            Ok(Statement::Assign(name, initializer))
+            self.tokens.match_any(&[TokenType::SemiColon]);
         }
+        Ok(())
     }
 
     fn parse_statement(&mut self) -> ParserResult<Statement> {
-        if self.matches(vec![TokenType::LeftBrace]) {
+        if self.tokens.check(&TokenType::LeftBrace) {
             self.parse_block()
+        } else if let Some(semicolon) = self.tokens.match_any(&[TokenType::SemiColon]) {
+            Ok(Statement::Empty(semicolon))
+        } else if let Some(keyword) = self.tokens.match_any(&[TokenType::Exit]) {
+            self.require_extensions(&keyword)?;
+            let code = self.parse_expression()?;
+            self.end_statement()?;
+            Ok(Statement::Exit(keyword, code))
+        } else if let Some(keyword) = self.tokens.match_any(&[TokenType::Assert]) {
+            self.require_extensions(&keyword)?;
+            let condition = self.parse_expression()?;
+            let message = match self.tokens.match_any(&[TokenType::Comma]) {
+                Some(_) => Some(self.parse_expression()?),
+                None => None,
+            };
+            self.end_statement()?;
+            Ok(Statement::Assert(keyword, condition, message))
+        } else if let Some(keyword) = self.tokens.match_any(&[TokenType::Attempt]) {
+            let body = self.parse_attempt_block()?;
+            self.check_and_consume(TokenType::Handle)?;
+            self.check_and_consume(TokenType::LeftParen)?;
+            if self.tokens.peek()._type.is_keyword() {
+                return Err(Self::reserved_word(
+                    self.tokens.peek(),
+                    "used as a variable name",
+                ));
+            }
+            let name =
+                self.tokens
+                    .expect(TokenType::Identifier, codes::EXPECTED_IDENTIFIER, &[])?;
+            self.check_and_consume(TokenType::RightParen)?;
+            let handler = self.parse_attempt_block()?;
+            // Checked once the blocks are parsed so the `handle` part isn't
+            // reported as well
+            self.require_extensions(&keyword)?;
+            Ok(Statement::Attempt(Box::new(body), name, Box::new(handler)))
+        } else if matches!(self.tokens.peek()._type, TokenType::Print | TokenType::Write)
+            // `print = 1;` is reported as assigning to a reserved word
+            && self.tokens.peek_n(1)._type != TokenType::Equal
+        {
+            let keyword = self.tokens.advance();
+            if keyword._type == TokenType::Write {
+                self.require_extensions(&keyword)?;
+            }
+            let value = self.parse_expression()?;
+            self.end_statement()?;
+            Ok(Statement::Print(keyword, value))
         } else {
             let expr = self.parse_expression()?;
-            if self.strict_mode {
-                self.check_and_consume(TokenType::SemiColon)?;
-            } else if self.matches(vec![TokenType::SemiColon]) {
-                self.consume();
-            }
+            self.end_statement()?;
             match expr {
-                Expression::Variable(token) => Ok(Statement::Variable(Expression::Variable(token))),
+                Expression::Variable(_) => Ok(Statement::Variable(expr)),
                 _ => Ok(Statement::Expression(expr)),
             }
         }
     }
 
-    fn is_at_end(&self) -> bool {
-        self.current >= self.source.len()
-    }
-
     fn parse_block(&mut self) -> ParserResult<Statement> {
         let mut statements: Vec<Statement> = Vec::new();
+        let brace = self.check_and_consume(TokenType::LeftBrace)?;
 
-        while !self.matches(vec![TokenType::LeftBrace]) && !self.is_at_end() {
-            statements.push(self.parse_declaration()?);
+        // Errors are recorded so one bad statement doesn't hide the rest of
+        // the block
+        while !self.tokens.check(&TokenType::RightBrace) && !self.tokens.is_at_end() {
+            if let Some(statement) = self.parse_or_synchronize() {
+                statements.push(statement);
+            }
         }
 
+        self.close_trivia();
         self.check_and_consume(TokenType::RightBrace)?;
-        Ok(Statement::Block(statements))
+        Ok(Statement::Block(brace, statements))
+    }
+
+    /// Parses a block of an `attempt`, which has trivia of its own
+    fn parse_attempt_block(&mut self) -> ParserResult<Statement> {
+        self.begin_trivia();
+        let block = self.parse_block();
+        // A comment after the brace belongs to what follows it
+        self.end_trivia(false);
+        block
     }
 
     fn parse_assignment(&mut self) -> ParserResult<Expression> {
-        let expr = self.parse_equality()?;
+        let start = self.tokens.position();
+        let expr = self.parse_coalesce()?;
 
-        if self.matches(vec![TokenType::Equal]) {
-            let name = self.previous();
-            self.consume();
-            let rexpr = self.parse_expression()?;
+        if self.tokens.match_any(&[TokenType::Equal]).is_some() {
+            let value = self.parse_assignment()?;
 
-            Ok(Expression::Assignment(name, Box::new(rexpr)))
+            match self.to_assignment_target(expr, start)? {
+                AssignTarget::Variable(name) => Ok(Expression::Assignment(name, Box::new(value))),
+            }
         } else {
             Ok(expr)
         }
     }
 
+    /// Validates the left hand side of an assignment, which started at the
+    /// `start` position of the tokens
+    fn to_assignment_target(&self, expr: Expression, start: usize) -> ParserResult<AssignTarget> {
+        let construct = match &expr {
+            Expression::Variable(token) if token._type == TokenType::Identifier => {
+                return Ok(AssignTarget::Variable(token.clone()));
+            }
+            Expression::Variable(_) | Expression::Literal(_) => "a literal",
+            Expression::Grouping(inner) if matches!(inner.as_ref(), Expression::Variable(_)) => {
+                "a grouped expression, remove the parentheses"
+            }
+            Expression::Grouping(_) => "a grouped expression",
+            Expression::Unary(_, _) => "a unary expression",
+            Expression::Binary(_, _, _) => "a binary expression",
+            Expression::Assignment(_, _) => "an assignment",
+            Expression::Interpolation(_) => "an interpolated string",
+            Expression::Call(_, _, _) => "a call",
+            Expression::This(_) => "`this`",
+            Expression::Super(_, _) => "a superclass method",
+        };
+
+        Err(ParserError::new(
+            codes::INVALID_ASSIGNMENT_TARGET,
+            &[&construct],
+            // The opening parenthesis of a group, which the expression
+            // doesn't keep
+            self.tokens.at(start),
+        ))
+    }
+
     fn parse_expression(&mut self) -> ParserResult<Expression> {
         self.parse_assignment()
     }
 
-    fn peek(&self) -> Token {
-        if self.current < self.source.len() {
-            self.source[self.current].clone()
-        } else {
-            self.previous()
+    fn parse_coalesce(&mut self) -> ParserResult<Expression> {
+        let mut expr = self.parse_equality()?;
+
+        while let Some(operator) = self.tokens.match_any(&[TokenType::NilCoalesce]) {
+            self.require_extensions(&operator)?;
+            let rexpr = self.parse_equality()?;
+            expr = Expression::Binary(Box::new(expr), operator, Box::new(rexpr));
         }
+
+        Ok(expr)
     }
 
-    fn previous(&self) -> Token {
-        self.source[self.current - 1].clone()
+    fn parse_equality(&mut self) -> ParserResult<Expression> {
+        let mut expr = self.parse_bit_or()?;
+
+        while let Some(operator) = self.tokens.match_any(&[
+            TokenType::NotEqual,
+            TokenType::EqualEqual,
+            TokenType::Or,
+            TokenType::And,
+        ]) {
+            let r_expr = self.parse_bit_or()?;
+            expr = Expression::Binary(Box::new(expr), operator, Box::new(r_expr));
+        }
+
+        Ok(expr)
     }
 
-    fn matches(&self, options: Vec<TokenType>) -> bool {
-        options.iter().any(|option| option == &self.peek()._type)
+    fn parse_bit_or(&mut self) -> ParserResult<Expression> {
+        let mut expr = self.parse_bit_xor()?;
+
+        while let Some(operator) = self.tokens.match_any(&[TokenType::BitOr]) {
+            self.require_extensions(&operator)?;
+            let rexpr = self.parse_bit_xor()?;
+            expr = Expression::Binary(Box::new(expr), operator, Box::new(rexpr));
+        }
+
+        Ok(expr)
     }
 
-    fn consume(&mut self) -> Token {
-        let token = self.peek();
-        self.current += 1;
-        token
+    fn parse_bit_xor(&mut self) -> ParserResult<Expression> {
+        let mut expr = self.parse_bit_and()?;
+
+        while let Some(operator) = self.tokens.match_any(&[TokenType::BitXor]) {
+            self.require_extensions(&operator)?;
+            let rexpr = self.parse_bit_and()?;
+            expr = Expression::Binary(Box::new(expr), operator, Box::new(rexpr));
+        }
+
+        Ok(expr)
     }
 
-    fn advance_if_match(&mut self, options: Vec<TokenType>) -> bool {
-        let result = self.matches(options);
-        if result {
-            self.consume();
+    fn parse_bit_and(&mut self) -> ParserResult<Expression> {
+        let mut expr = self.parse_shift()?;
+
+        while let Some(operator) = self.tokens.match_any(&[TokenType::BitAnd]) {
+            self.require_extensions(&operator)?;
+            let rexpr = self.parse_shift()?;
+            expr = Expression::Binary(Box::new(expr), operator, Box::new(rexpr));
         }
-        result
+
+        Ok(expr)
     }
 
-    fn parse_equality(&mut self) -> ParserResult<Expression> {
+    fn parse_shift(&mut self) -> ParserResult<Expression> {
         let mut expr = self.parse_comparison()?;
 
-        while self.advance_if_match(vec![
-            TokenType::NotEqual,
-            TokenType::EqualEqual,
-            TokenType::Or,
-            TokenType::And,
-        ]) {
-            let operator = self.previous();
-            let r_expr = self.parse_comparison()?;
-            expr = Expression::Binary(Box::new(expr), operator, Box::new(r_expr));
+        while let Some(operator) = self
+            .tokens
+            .match_any(&[TokenType::ShiftLeft, TokenType::ShiftRight])
+        {
+            self.require_extensions(&operator)?;
+            let rexpr = self.parse_comparison()?;
+            expr = Expression::Binary(Box::new(expr), operator, Box::new(rexpr));
         }
 
         Ok(expr)
@@ -199,14 +756,12 @@ impl Parser {
     fn parse_comparison(&mut self) -> ParserResult<Expression> {
         let mut expr = self.parse_term()?;
 
-        while self.advance_if_match(vec![
+        while let Some(operator) = self.tokens.match_any(&[
             TokenType::Greater,
             TokenType::GreaterEqual,
             TokenType::Less,
             TokenType::LessEqual,
-            TokenType::EqualEqual,
         ]) {
-            let operator = self.previous();
             let rexpr = self.parse_term()?;
             expr = Expression::Binary(Box::new(expr), operator, Box::new(rexpr));
         }
@@ -217,8 +772,7 @@ impl Parser {
     fn parse_term(&mut self) -> ParserResult<Expression> {
         let mut expr = self.parse_factor()?;
 
-        while self.advance_if_match(vec![TokenType::Minus, TokenType::Plus]) {
-            let operator = self.previous();
+        while let Some(operator) = self.tokens.match_any(&[TokenType::Minus, TokenType::Plus]) {
             let rexpr = self.parse_factor()?;
             expr = Expression::Binary(Box::new(expr), operator, Box::new(rexpr));
         }
@@ -229,8 +783,7 @@ impl Parser {
     fn parse_factor(&mut self) -> ParserResult<Expression> {
         let mut expr = self.parse_unary()?;
 
-        while self.advance_if_match(vec![TokenType::Slash, TokenType::Star, TokenType::Equal]) {
-            let operator = self.previous();
+        while let Some(operator) = self.tokens.match_any(&[TokenType::Slash, TokenType::Star]) {
             let rexpr = self.parse_unary()?;
             expr = Expression::Binary(Box::new(expr), operator, Box::new(rexpr));
         }
@@ -239,45 +792,192 @@ impl Parser {
     }
 
     fn parse_unary(&mut self) -> ParserResult<Expression> {
-        if self.advance_if_match(vec![TokenType::Not, TokenType::Minus]) {
-            let operator = self.previous();
+        if let Some(operator) =
+            self.tokens
+                .match_any(&[TokenType::Not, TokenType::Minus, TokenType::BitNot])
+        {
+            if operator._type == TokenType::BitNot {
+                self.require_extensions(&operator)?;
+            }
+            // `-5` is a number of its own, located at the minus, rather than
+            // the negation of 5. With anything between them the number
+            // wouldn't span the source it was read from, so `- 5` is
+            // negated.
+            if operator._type == TokenType::Minus
+                && self.tokens.check(&TokenType::Number)
+                && self.tokens.peek().offset == operator.offset + 1
+            {
+                let number = self.tokens.advance();
+                self.reject_trailing_dot(&number)?;
+                return Ok(Expression::Literal(Token {
+                    lexeme: format!("-{}", number.lexeme),
+                    _type: TokenType::Number,
+                    ..operator
+                }));
+            }
             let rexpr = self.parse_unary()?;
             Ok(Expression::Unary(operator, Box::new(rexpr)))
         } else {
-            self.parse_primary()
+            self.parse_call()
+        }
+    }
+
+    fn parse_call(&mut self) -> ParserResult<Expression> {
+        let mut expr = self.parse_primary()?;
+
+        loop {
+            if self.tokens.check(&TokenType::Dot) {
+                return Err(Self::property_access(self.tokens.peek()));
+            }
+            if self.tokens.match_any(&[TokenType::LeftParen]).is_none() {
+                break;
+            }
+            let mut arguments = Vec::new();
+            if !self.tokens.check(&TokenType::RightParen) {
+                arguments.push(self.parse_expression()?);
+                while self.tokens.match_any(&[TokenType::Comma]).is_some() {
+                    arguments.push(self.parse_expression()?);
+                }
+            }
+            let paren = self.check_and_consume(TokenType::RightParen)?;
+            expr = Expression::Call(Box::new(expr), paren, arguments);
         }
+
+        Ok(expr)
     }
 
     fn parse_primary(&mut self) -> ParserResult<Expression> {
-        if self.matches(vec![
+        if let Some(literal) = self.tokens.match_any(&[
             TokenType::False,
             TokenType::True,
+            TokenType::Nil,
             TokenType::Number,
             TokenType::String,
         ]) {
-            Ok(Expression::Literal(self.consume()))
-        } else if self.advance_if_match(vec![TokenType::LeftParen]) {
+            self.reject_trailing_dot(&literal)?;
+            Ok(Expression::Literal(literal))
+        } else if self.tokens.match_any(&[TokenType::LeftParen]).is_some() {
             let expr = self.parse_expression()?;
             self.check_and_consume(TokenType::RightParen)?;
             Ok(Expression::Grouping(Box::new(expr)))
+        } else if self.tokens.check(&TokenType::StringHead) {
+            self.parse_interpolation()
+        } else if let Some(keyword) = self.tokens.match_any(&[TokenType::This]) {
+            if !self.in_method {
+                return Err(ParserError::new(codes::THIS_OUTSIDE_CLASS, &[], &keyword));
+            }
+            Ok(Expression::This(keyword))
+        } else if let Some(keyword) = self.tokens.match_any(&[TokenType::Super]) {
+            if !self.in_subclass {
+                return Err(ParserError::new(
+                    codes::SUPER_OUTSIDE_SUBCLASS,
+                    &[],
+                    &keyword,
+                ));
+            }
+            self.check_and_consume(TokenType::Dot)?;
+            let method =
+                self.tokens
+                    .expect(TokenType::Identifier, codes::EXPECTED_IDENTIFIER, &[])?;
+            Ok(Expression::Super(keyword, method))
+        } else if self.tokens.peek()._type.is_keyword() {
+            let usage = if self.tokens.peek_n(1)._type == TokenType::Equal {
+                "assigned to"
+            } else {
+                "used as an expression"
+            };
+            Err(Self::reserved_word(self.tokens.peek(), usage))
+        } else if self.tokens.check(&TokenType::Dot) {
+            Err(Self::leading_dot(self.tokens.peek()))
+        } else if let Some(name) = self.tokens.match_any(&[TokenType::Identifier]) {
+            Ok(Expression::Variable(name))
         } else {
-            Ok(Expression::Variable(self.consume()))
+            Err(Self::expected_expression(self.tokens.peek()))
         }
     }
 
-    fn check_and_consume(&mut self, token_type: TokenType) -> ParserResult<()> {
-        let token = self.peek();
-        if token._type != token_type {
-            return Err(ParserError::new(
-                &format!("expected {:?}", token_type),
-                &token,
-                ExceptionType::RuntimeException,
-            ));
+    fn parse_interpolation(&mut self) -> ParserResult<Expression> {
+        let segments = [TokenType::StringMiddle, TokenType::StringTail];
+        let mut parts: Vec<Expression> = Vec::new();
+
+        loop {
+            let segment = self.tokens.advance();
+            let is_tail = segment._type == TokenType::StringTail;
+            parts.push(Expression::Literal(segment));
+            if is_tail {
+                break;
+            }
+
+            if segments.contains(&self.tokens.peek()._type) {
+                return Err(ParserError::new(
+                    codes::EMPTY_INTERPOLATION,
+                    &[],
+                    self.tokens.peek(),
+                ));
+            }
+            parts.push(self.parse_expression()?);
+            if !segments.contains(&self.tokens.peek()._type) {
+                return Err(ParserError::new(
+                    codes::UNCLOSED_INTERPOLATED_EXPRESSION,
+                    &[],
+                    self.tokens.peek(),
+                ));
+            }
         }
 
-        self.consume();
+        Ok(Expression::Interpolation(parts))
+    }
+
+    /// Error for a keyword found where an identifier or expression belongs
+    fn reserved_word(token: &Token, usage: &str) -> ParserError {
+        ParserError::new(codes::RESERVED_WORD, &[&token.lexeme, &usage], token)
+    }
+
+    /// Error for a token that can't start an expression, such as the `*`
+    /// of `1 + * 2`
+    #[inline(never)]
+    fn expected_expression(token: &Token) -> ParserError {
+        ParserError::new(codes::EXPECTED_EXPRESSION, &[], token)
+    }
+
+    /// Error for a `.` following an expression
+    #[inline(never)]
+    fn property_access(dot: &Token) -> ParserError {
+        ParserError::new(codes::PROPERTY_ACCESS, &[], dot)
+    }
+
+    /// Fails if a `.` directly follows the number, as in `12.`, which
+    /// would otherwise be reported as a property access
+    fn reject_trailing_dot(&self, number: &Token) -> ParserResult<()> {
+        let next = self.tokens.peek();
+        if number._type == TokenType::Number
+            && next._type == TokenType::Dot
+            && next.offset == number.offset + number.lexeme.len()
+        {
+            return Err(Self::trailing_dot(next));
+        }
         Ok(())
     }
+
+    /// Error for a `.` right after a number
+    #[inline(never)]
+    fn trailing_dot(dot: &Token) -> ParserError {
+        ParserError::new(codes::TRAILING_DOT, &[], dot)
+    }
+
+    /// Error for a `.` starting an expression, most often a number written
+    /// without its leading digit
+    #[inline(never)]
+    fn leading_dot(dot: &Token) -> ParserError {
+        ParserError::new(codes::LEADING_DOT, &[], dot)
+    }
+
+    /// Consumes a token of the type or fails naming the expected type
+    fn check_and_consume(&mut self, token_type: TokenType) -> ParserResult<Token> {
+        let expected = token_type.clone();
+        self.tokens
+            .expect(token_type, codes::EXPECTED_TOKEN, &[&expected])
+    }
 }
 
 #[cfg(test)]
@@ -303,7 +1003,7 @@ mod tests {
 
     fn assert_expression_scenarios(scenarios: Vec<(&str, String)>) {
         for (scenario, expected) in scenarios {
-            let tokens = Scanner::new(scenario.into()).unwrap().tokens;
+            let tokens = Scanner::new(scenario).unwrap().tokens;
             let mut parser = Parser::new(tokens, false);
             let expression: String = parser.parse_expression().unwrap().into();
 
@@ -315,7 +1015,10 @@ mod tests {
     fn parses_assignment_statements_successfuly() {
         let scenarios: Vec<(String, String)> = vec![
             ("let num = 25;".into(), "let num = 25;".into()),
-            ("num;".into(), "num".into()),
+            (
+                "num;".into(),
+                "evaluation error[R001]: undefined variable `num` at line 1 column 1".into(),
+            ),
             ("2 * 4;".into(), "8".into()),
             (
                 "let div_result = 4 / 2;".into(),
@@ -325,6 +1028,15 @@ mod tests {
         assert_statement_scenarios(scenarios);
     }
 
+    #[test]
+    fn parses_literal_expression_statements() {
+        let scenarios: Vec<(String, String)> = vec![
+            ("25;".into(), "25".into()),
+            ("\"text\";".into(), "text".into()),
+        ];
+        assert_statement_scenarios(scenarios);
+    }
+
     #[test]
     fn parses_primary_expressions() {
         let scenarios: Vec<(&str, String)> = vec![
@@ -363,31 +1075,12 @@ mod tests {
         let scenarios: Vec<(&str, String)> = vec![
             (
                 "-1",
-                Expression::Unary(
-                    Token {
-                        line: 1,
-                        lexeme: "-".into(),
-                        _type: TokenType::Minus,
-                        column: 1,
-                    },
-                    Box::new(Expression::Literal(Token::new(
-                        "1",
-                        1,
-                        2,
-                        TokenType::Number,
-                    ))),
-                )
-                .into(),
+                Expression::Literal(Token::new("-1", 1, 1, TokenType::Number)).into(),
             ),
             (
                 "!true",
                 Expression::Unary(
-                    Token {
-                        line: 1,
-                        lexeme: "!".into(),
-                        _type: TokenType::Not,
-                        column: 1,
-                    },
+                    Token::new("!", 1, 1, TokenType::Not),
                     Box::new(Expression::Literal(Token::new(
                         "true",
                         1,
@@ -402,6 +1095,37 @@ mod tests {
         assert_expression_scenarios(scenarios);
     }
 
+    #[test]
+    fn minus_folds_into_the_number_it_precedes() {
+        assert_expression_scenarios(vec![
+            ("-2.5", "-2.5".into()),
+            ("- 5", "(- 5)".into()),
+            ("- 2.5", "(- 2.5)".into()),
+            ("-// five\n5", "(- 5)".into()),
+            ("-5 * 3", "(-5 * 3)".into()),
+            ("-x * y", "((- x) * y)".into()),
+            ("3 - -5", "(3 - -5)".into()),
+            ("--5", "(- -5)".into()),
+            ("-(5)", "(- (group 5))".into()),
+            ("!-5", "(! -5)".into()),
+        ]);
+
+        let tokens = Scanner::new("1 + -9223372036854775808").unwrap().tokens;
+        let expression = Parser::new(tokens, false).parse_expression().unwrap();
+        let Expression::Binary(_, _, number) = &expression else {
+            panic!("expected a binary expression");
+        };
+        let Expression::Literal(token) = number.as_ref() else {
+            panic!("expected a literal");
+        };
+        assert_eq!((token.line, token.column, token.offset), (1, 5, 4));
+        assert_eq!(token.span().len, "-9223372036854775808".len());
+        assert_eq!(
+            number.evaluate(&mut crate::Environment::new()).unwrap(),
+            crate::Literal::Integer(i64::MIN)
+        );
+    }
+
     #[test]
     fn parses_factor_expressions() {
         let scenarios: Vec<(&str, String)> = vec![
@@ -414,12 +1138,7 @@ mod tests {
                         1,
                         TokenType::Number,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: "*".into(),
-                        _type: TokenType::Star,
-                        column: 3,
-                    },
+                    Token::new("*", 1, 3, TokenType::Star),
                     Box::new(Expression::Literal(Token::new(
                         "5",
                         1,
@@ -438,12 +1157,7 @@ mod tests {
                         1,
                         TokenType::Number,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: "/".into(),
-                        _type: TokenType::Slash,
-                        column: 4,
-                    },
+                    Token::new("/", 1, 4, TokenType::Slash),
                     Box::new(Expression::Literal(Token::new(
                         "5",
                         1,
@@ -470,12 +1184,7 @@ mod tests {
                         1,
                         TokenType::Number,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: "==".into(),
-                        _type: TokenType::EqualEqual,
-                        column: 3,
-                    },
+                    Token::new("==", 1, 3, TokenType::EqualEqual),
                     Box::new(Expression::Literal(Token::new(
                         "4",
                         1,
@@ -494,12 +1203,7 @@ mod tests {
                         1,
                         TokenType::Number,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: "!=".into(),
-                        _type: TokenType::NotEqual,
-                        column: 6,
-                    },
+                    Token::new("!=", 1, 6, TokenType::NotEqual),
                     Box::new(Expression::Literal(Token::new(
                         "30",
                         1,
@@ -526,12 +1230,7 @@ mod tests {
                         1,
                         TokenType::Number,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: "+".into(),
-                        _type: TokenType::Plus,
-                        column: 6,
-                    },
+                    Token::new("+", 1, 6, TokenType::Plus),
                     Box::new(Expression::Literal(Token::new(
                         "30",
                         1,
@@ -550,12 +1249,7 @@ mod tests {
                         1,
                         TokenType::Number,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: "-".into(),
-                        _type: TokenType::Minus,
-                        column: 6,
-                    },
+                    Token::new("-", 1, 6, TokenType::Minus),
                     Box::new(Expression::Literal(Token::new(
                         "30",
                         1,
@@ -582,12 +1276,7 @@ mod tests {
                         1,
                         TokenType::True,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: "||".into(),
-                        _type: TokenType::Or,
-                        column: 6,
-                    },
+                    Token::new("||", 1, 6, TokenType::Or),
                     Box::new(Expression::Binary(
                         Box::new(Expression::Literal(Token::new(
                             "3",
@@ -615,12 +1304,7 @@ mod tests {
                         1,
                         TokenType::True,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: "&&".into(),
-                        _type: TokenType::And,
-                        column: 6,
-                    },
+                    Token::new("&&", 1, 6, TokenType::And),
                     Box::new(Expression::Literal(Token::new(
                         "true",
                         1,
@@ -639,12 +1323,7 @@ mod tests {
                         1,
                         TokenType::Number,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: "<".into(),
-                        _type: TokenType::Less,
-                        column: 3,
-                    },
+                    Token::new("<", 1, 3, TokenType::Less),
                     Box::new(Expression::Literal(Token::new(
                         "2",
                         1,
@@ -663,12 +1342,7 @@ mod tests {
                         1,
                         TokenType::Number,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: "<=".into(),
-                        _type: TokenType::LessEqual,
-                        column: 3,
-                    },
+                    Token::new("<=", 1, 3, TokenType::LessEqual),
                     Box::new(Expression::Literal(Token::new(
                         "2",
                         1,
@@ -687,12 +1361,7 @@ mod tests {
                         1,
                         TokenType::Number,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: ">".into(),
-                        _type: TokenType::Greater,
-                        column: 3,
-                    },
+                    Token::new(">", 1, 3, TokenType::Greater),
                     Box::new(Expression::Literal(Token::new(
                         "4",
                         1,
@@ -711,12 +1380,7 @@ mod tests {
                         1,
                         TokenType::Number,
                     ))),
-                    Token {
-                        line: 1,
-                        lexeme: ">=".into(),
-                        _type: TokenType::GreaterEqual,
-                        column: 3,
-                    },
+                    Token::new(">=", 1, 3, TokenType::GreaterEqual),
                     Box::new(Expression::Literal(Token::new(
                         "10",
                         1,
@@ -730,4 +1394,637 @@ mod tests {
 
         assert_expression_scenarios(scenarios);
     }
+
+    #[test]
+    fn parses_bitwise_expressions_by_precedence() {
+        let scenarios: Vec<(&str, String)> = vec![
+            ("1 | 2 & 3", "(1 | (2 & 3))".into()),
+            ("1 ^ 2 | 3", "((1 ^ 2) | 3)".into()),
+            ("1 << 2 & 3", "((1 << 2) & 3)".into()),
+            ("1 & 2 == 2", "((1 & 2) == 2)".into()),
+            ("~1 >> 2", "((~ 1) >> 2)".into()),
+        ];
+
+        assert_expression_scenarios(scenarios);
+    }
+
+    #[test]
+    fn parses_nil_coalescing_below_equality() {
+        let scenarios: Vec<(&str, String)> = vec![
+            ("a ?? b == c", "(a ?? (b == c))".into()),
+            ("a ?? b ?? c", "((a ?? b) ?? c)".into()),
+            ("a == b ?? c || d", "((a == b) ?? (c || d))".into()),
+            ("a = b ?? 1", "(a = (b ?? 1))".into()),
+        ];
+
+        assert_expression_scenarios(scenarios);
+    }
+
+    #[test]
+    fn skips_comment_tokens() {
+        let tokens = Scanner::with_comments("// lead\nlet a = 2 * 3; // tail", true)
+            .unwrap()
+            .tokens;
+        let mut parser = Parser::new(tokens, true);
+        let statements = parser.parse().unwrap();
+
+        assert_eq!(statements.len(), 1);
+        assert_eq!(get_statement_string(statements[0].clone()), "let a = 6;");
+    }
+
+    fn assert_parse_errors(scenarios: Vec<(&str, &str)>) {
+        for (scenario, expected) in scenarios {
+            let tokens = Scanner::new(scenario).unwrap().tokens;
+            let mut parser = Parser::new(tokens, true);
+            let error = parser.parse().unwrap_err();
+
+            assert_eq!(error.to_string(), expected, "{}", scenario);
+        }
+    }
+
+    #[test]
+    fn parses_call_expressions() {
+        let scenarios: Vec<(&str, String)> = vec![
+            ("f()", "(call f)".into()),
+            ("f(1, a + 2)(nil)", "(call (call f 1 (a + 2)) nil)".into()),
+            ("-f(1) * 2", "((- (call f 1)) * 2)".into()),
+        ];
+
+        assert_expression_scenarios(scenarios);
+        assert_parse_errors(vec![
+            (
+                "f(1;",
+                "runtime exception[P001]: expected `)` at line 1 column 4",
+            ),
+            (
+                "f(1) = 2;",
+                "runtime exception[P008]: cannot assign to a call at line 1 column 1",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn rejects_dots_outside_numbers() {
+        assert_parse_errors(vec![
+            (
+                ".5;",
+                "runtime exception[P015]: unexpected `.`; numbers must have a leading digit, as in `0.5` at line 1 column 1",
+            ),
+            (
+                "1 + .25;",
+                "runtime exception[P015]: unexpected `.`; numbers must have a leading digit, as in `0.5` at line 1 column 5",
+            ),
+            (
+                ".",
+                "runtime exception[P015]: unexpected `.`; numbers must have a leading digit, as in `0.5` at line 1 column 1",
+            ),
+            (
+                "1; . 5;",
+                "runtime exception[P015]: unexpected `.`; numbers must have a leading digit, as in `0.5` at line 1 column 4",
+            ),
+            (
+                "a.b;",
+                "runtime exception[P014]: property access is not supported yet at line 1 column 2",
+            ),
+            (
+                "a . b;",
+                "runtime exception[P014]: property access is not supported yet at line 1 column 3",
+            ),
+            (
+                "f().x;",
+                "runtime exception[P014]: property access is not supported yet at line 1 column 4",
+            ),
+            (
+                "12.;",
+                "runtime exception[P016]: expected digits after `.` at line 1 column 3",
+            ),
+            (
+                "let a = -1.;",
+                "runtime exception[P016]: expected digits after `.` at line 1 column 11",
+            ),
+            (
+                "12 .5;",
+                "runtime exception[P014]: property access is not supported yet at line 1 column 4",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn parses_assignment_expressions() {
+        let scenarios: Vec<(&str, String)> = vec![
+            ("a = 1", "(a = 1)".into()),
+            ("a = b = 2 * 3", "(a = (b = (2 * 3)))".into()),
+            ("a = b = c = 0", "(a = (b = (c = 0)))".into()),
+            ("(a = 2) + 1", "((group (a = 2)) + 1)".into()),
+        ];
+
+        assert_expression_scenarios(scenarios);
+    }
+
+    #[test]
+    fn rejects_invalid_assignment_targets() {
+        assert_parse_errors(vec![
+            (
+                "(a) = 1;",
+                "runtime exception[P008]: cannot assign to a grouped expression, remove the parentheses at line 1 column 1",
+            ),
+            (
+                "a = (b) = 0;",
+                "runtime exception[P008]: cannot assign to a grouped expression, remove the parentheses at line 1 column 5",
+            ),
+            (
+                "a + ((b)) = 0;",
+                "runtime exception[P008]: cannot assign to a binary expression at line 1 column 1",
+            ),
+            (
+                "print ((b)) = 0;",
+                "runtime exception[P008]: cannot assign to a grouped expression at line 1 column 7",
+            ),
+            (
+                "(a + b) = 0;",
+                "runtime exception[P008]: cannot assign to a grouped expression at line 1 column 1",
+            ),
+            (
+                "1 = 2;",
+                "runtime exception[P008]: cannot assign to a literal at line 1 column 1",
+            ),
+            (
+                "\"s\" = 3;",
+                "runtime exception[P008]: cannot assign to a literal at line 1 column 1",
+            ),
+            (
+                "x; a + b = c;",
+                "runtime exception[P008]: cannot assign to a binary expression at line 1 column 4",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn rejects_keywords_used_as_names() {
+        assert_parse_errors(vec![
+            (
+                "let if = 3;",
+                "runtime exception[P011]: `if` is a reserved word and cannot be used as a variable name at line 1 column 5",
+            ),
+            (
+                "let class = 1;",
+                "runtime exception[P011]: `class` is a reserved word and cannot be used as a variable name at line 1 column 5",
+            ),
+            (
+                "print = 3;",
+                "runtime exception[P011]: `print` is a reserved word and cannot be assigned to at line 1 column 1",
+            ),
+            (
+                "let a = 1; a = return = 2;",
+                "runtime exception[P011]: `return` is a reserved word and cannot be assigned to at line 1 column 16",
+            ),
+            (
+                "1 + while;",
+                "runtime exception[P011]: `while` is a reserved word and cannot be used as an expression at line 1 column 5",
+            ),
+            (
+                "f(class);",
+                "runtime exception[P011]: `class` is a reserved word and cannot be used as an expression at line 1 column 3",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn rejects_this_and_super_outside_of_classes() {
+        assert_parse_errors(vec![
+            (
+                "print this;",
+                "runtime exception[P012]: `this` can only be used inside class methods at line 1 column 7",
+            ),
+            (
+                "let a = 1;\n{ a = \"${this}\" + 1; }",
+                "runtime exception[P012]: `this` can only be used inside class methods at line 2 column 10",
+            ),
+            (
+                "this;",
+                "runtime exception[P012]: `this` can only be used inside class methods at line 1 column 1",
+            ),
+            (
+                "f(1, super.init());",
+                "runtime exception[P013]: `super` can only be used inside a subclass method at line 1 column 6",
+            ),
+            (
+                "let this = 1;",
+                "runtime exception[P011]: `this` is a reserved word and cannot be used as a variable name at line 1 column 5",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn parses_this_and_super_inside_methods() {
+        let tokens = Scanner::new("this == super.name;").unwrap().tokens;
+        let mut parser = Parser::new(tokens, true);
+        parser.in_method = true;
+        parser.in_subclass = true;
+
+        match parser.parse().unwrap().remove(0) {
+            Statement::Expression(expr) => assert_eq!(String::from(expr), "(this == super.name)"),
+            statement => panic!("expected an expression, got {:?}", statement),
+        }
+
+        let tokens = Scanner::new("super;").unwrap().tokens;
+        let mut parser = Parser::new(tokens, true);
+        parser.in_subclass = true;
+        assert_eq!(parser.parse().unwrap_err().code(), codes::EXPECTED_TOKEN);
+    }
+
+    #[test]
+    fn parses_print_and_write_statements() {
+        assert_statement_scenarios(vec![
+            ("print 1 + 2;".into(), "(print (1 + 2))".into()),
+            ("write \"a\";".into(), "(write a)".into()),
+            ("{ print nil; }".into(), "(print nil)\n".into()),
+        ]);
+        assert_parse_errors(vec![
+            (
+                "print",
+                "runtime exception[P003]: unexpected end of input, expected an expression at line 1 column 6",
+            ),
+            (
+                "write = 1;",
+                "runtime exception[P011]: `write` is a reserved word and cannot be assigned to at line 1 column 1",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn parses_attempt_statements() {
+        assert_eq!(
+            String::from(
+                Parser::new(
+                    Scanner::new("attempt { a; } handle (err) { print err; }")
+                        .unwrap()
+                        .tokens,
+                    true
+                )
+                .parse()
+                .unwrap()
+                .remove(0)
+            ),
+            "(attempt (block a) handle err (block (print err)))"
+        );
+        assert_parse_errors(vec![
+            (
+                "attempt print 1; handle (err) { }",
+                "runtime exception[P001]: expected `{` at line 1 column 9",
+            ),
+            (
+                "attempt { } { }",
+                "runtime exception[P001]: expected `handle` at line 1 column 13",
+            ),
+            (
+                "attempt { } handle err { }",
+                "runtime exception[P001]: expected `(` at line 1 column 20",
+            ),
+            (
+                "attempt { } handle (nil) { }",
+                "runtime exception[P011]: `nil` is a reserved word and cannot be used as a variable name at line 1 column 21",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn parses_assertions_with_optional_messages() {
+        let tokens = Scanner::new("assert a == 1; assert b, \"${b} is false\"")
+            .unwrap()
+            .tokens;
+        let statements: Vec<String> = Parser::new(tokens, false)
+            .parse()
+            .unwrap()
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        assert_eq!(
+            statements,
+            vec![
+                "(assert (a == 1))",
+                "(assert b (interpolate \"\" b \" is false\"))",
+            ]
+        );
+        assert_parse_errors(vec![(
+            "assert a,",
+            "runtime exception[P003]: unexpected end of input, expected an expression at line 1 column 10",
+        )]);
+    }
+
+    #[test]
+    fn parses_interpolated_strings() {
+        let scenarios: Vec<(&str, String)> = vec![
+            ("\"plain\"", "plain".into()),
+            (
+                "\"sum ${a + b}!\"",
+                "(interpolate \"sum \" (a + b) \"!\")".into(),
+            ),
+            (
+                "\"${a} and ${\"${b}\"}\"",
+                "(interpolate \"\" a \" and \" (interpolate \"\" b \"\") \"\")".into(),
+            ),
+        ];
+
+        assert_expression_scenarios(scenarios);
+    }
+
+    #[test]
+    fn rejects_malformed_interpolations() {
+        assert_parse_errors(vec![
+            (
+                "\"${}\";",
+                "runtime exception[P006]: expected an expression inside interpolation at line 1 column 4",
+            ),
+            (
+                "\"${} and ${1}\";",
+                "runtime exception[P006]: expected an expression inside interpolation at line 1 column 4",
+            ),
+            (
+                "\"${a b}\";",
+                "runtime exception[P007]: expected `}` after interpolated expression at line 1 column 6",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn parses_token_vectors_without_eof() {
+        let statements = Parser::new(vec![], true).parse().unwrap();
+        assert!(statements.is_empty());
+
+        let eof = Token::new("", 1, 1, TokenType::Eof);
+        let statements = Parser::new(vec![eof], true).parse().unwrap();
+        assert!(statements.is_empty());
+
+        let name = Token::new("a", 1, 1, TokenType::Identifier);
+        let statements = Parser::new(vec![name.clone()], false).parse().unwrap();
+        assert_eq!(
+            statements,
+            vec![Statement::Variable(Expression::Variable(name.clone()))]
+        );
+
+        let error = Parser::new(vec![name], true).parse().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "runtime exception[P001]: unexpected end of input, expected `;` at line 1 column 2"
+        );
+    }
+
+    #[test]
+    fn rejects_tokens_after_eof() {
+        let tokens = vec![
+            Token::new("a", 1, 1, TokenType::Identifier),
+            Token::new(";", 1, 2, TokenType::SemiColon),
+            Token::new("", 1, 3, TokenType::Eof),
+            Token::new("b", 2, 1, TokenType::Identifier),
+            Token::new(";", 2, 2, TokenType::SemiColon),
+        ];
+
+        let error = Parser::new(tokens.clone(), true).parse().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "runtime exception[P004]: unexpected tokens after end of input at line 2 column 1"
+        );
+
+        // Statements before the Eof are still parsed
+        let (statements, errors) = Parser::new(tokens, true).parse_recovering();
+        assert_eq!(statements.len(), 1);
+        assert_eq!(errors.len(), 1);
+
+        // Repeated Eof tokens are not trailing garbage
+        let eof = Token::new("", 1, 1, TokenType::Eof);
+        assert!(Parser::new(vec![eof.clone(), eof], true).parse().is_ok());
+    }
+
+    #[test]
+    fn reports_unexpected_end_of_input() {
+        assert_parse_errors(vec![
+            (
+                "1 +",
+                "runtime exception[P003]: unexpected end of input, expected an expression at line 1 column 4",
+            ),
+            (
+                "let",
+                "runtime exception[P002]: unexpected end of input, expected an identifier at line 1 column 4",
+            ),
+            (
+                "{ 1;",
+                "runtime exception[P001]: unexpected end of input, expected `}` at line 1 column 5",
+            ),
+            (
+                "exit",
+                "runtime exception[P003]: unexpected end of input, expected an expression at line 1 column 5",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn input_ending_mid_construct_is_reported_once_at_its_end() {
+        let scenarios = [
+            (
+                "let a = 1;\nprint a +",
+                "P003",
+                "an expression at line 2 column 10",
+            ),
+            (
+                "let a = 1;\n{\n    { print a;\n",
+                "P001",
+                "`}` at line 3 column 15",
+            ),
+            (
+                "print \"\"\"one\ntwo\"\"\"",
+                "P001",
+                "`;` at line 2 column 7",
+            ),
+            ("write 'a${1}b'", "P001", "`;` at line 1 column 15"),
+            (
+                "attempt { 1; } handle (e",
+                "P001",
+                "`)` at line 1 column 25",
+            ),
+            ("print (1 + 2 // two\n\n", "P001", "`)` at line 1 column 13"),
+        ];
+        for (source, code, expected) in scenarios {
+            let tokens = Scanner::new(source).unwrap().tokens;
+            let (_, errors) = Parser::new(tokens, true).parse_recovering();
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            let expected = format!(
+                "runtime exception[{}]: unexpected end of input, expected {}",
+                code, expected
+            );
+            assert_eq!(errors, vec![expected], "{}", source);
+        }
+
+        // A `;` or a source without tokens isn't the end of a construct
+        for (source, expected) in [
+            ("1 + ;", "expected an expression at line 1 column 5"),
+            ("print 1 +;", "expected an expression at line 1 column 10"),
+        ] {
+            let tokens = Scanner::new(source).unwrap().tokens;
+            let (_, errors) = Parser::new(tokens, true).parse_recovering();
+            assert_eq!(
+                errors[0].to_string(),
+                format!("runtime exception[P003]: {}", expected)
+            );
+        }
+        let error = Parser::new(Vec::new(), true)
+            .parse_expression()
+            .unwrap_err();
+        assert_eq!(error.msg(), "expected an expression");
+
+        let error = Scanner::new("let a = 1;\nprint \"open").err().unwrap();
+        assert_eq!(error.location(), crate::LocationInfo::new(2, 7, 0));
+    }
+
+    #[test]
+    fn lone_semicolons_are_empty_statements() {
+        let tokens = Scanner::new(";\n1;;\n{ ; };").unwrap().tokens;
+        let statements = Parser::new(tokens, true).parse().unwrap();
+        let printed: Vec<String> = statements.into_iter().map(String::from).collect();
+        assert_eq!(
+            printed,
+            ["(empty)", "1", "(empty)", "(block (empty))", "(empty)"]
+        );
+    }
+
+    #[test]
+    fn recovers_from_errors_inside_blocks() {
+        let source = "let a = 1;
+{
+  let = 2;
+  let 3 = 4;
+  let b = 3;
+  (1 + 2;
+  { (b; }
+  b;
+}
+a;";
+        let tokens = Scanner::new(source).unwrap().tokens;
+        let (statements, errors) = Parser::new(tokens, true).parse_recovering();
+
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "runtime exception[P002]: expected an identifier at line 3 column 7",
+                "runtime exception[P002]: expected an identifier at line 4 column 7",
+                "runtime exception[P001]: expected `)` at line 6 column 9",
+                "runtime exception[P001]: expected `)` at line 7 column 7",
+            ]
+        );
+
+        // The block is kept with the statements that did parse
+        let statements: Vec<String> = statements.into_iter().map(String::from).collect();
+        assert_eq!(
+            statements,
+            vec!["(let a 1)", "(block (let b 3) (block) b)", "a"]
+        );
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        let (_, errors) = Parser::new(tokens, true).parse_recovering();
+        errors.iter().map(|error| error.to_string()).collect()
+    }
+
+    fn error_count(source: &str) -> usize {
+        errors(source).len()
+    }
+
+    #[test]
+    fn single_mistakes_are_reported_once() {
+        let sources = [
+            (
+                "let a = (1 + 2;\nlet b = 3;",
+                "runtime exception[P001]: expected `)` at line 1 column 15",
+            ),
+            (
+                "let a = f(1, 2;\na;",
+                "runtime exception[P001]: expected `)` at line 1 column 15",
+            ),
+            (
+                "{ let a = 1;\nlet b = 2;",
+                "runtime exception[P001]: unexpected end of input, expected `}` at line 2 column 11",
+            ),
+            (
+                "{ { let a = 1; }",
+                "runtime exception[P001]: unexpected end of input, expected `}` at line 1 column 17",
+            ),
+            (
+                "let a = 1 + * 2;\na;",
+                "runtime exception[P003]: expected an expression at line 1 column 13",
+            ),
+            (
+                "{ 1 +",
+                "runtime exception[P003]: unexpected end of input, expected an expression at line 1 column 6",
+            ),
+            (
+                "{ { let a = (1",
+                "runtime exception[P001]: unexpected end of input, expected `)` at line 1 column 15",
+            ),
+            (
+                "let a = 1 let b = 2;",
+                "runtime exception[P001]: expected `;` at line 1 column 11",
+            ),
+            (
+                "print );",
+                "runtime exception[P003]: expected an expression at line 1 column 7",
+            ),
+        ];
+
+        for (source, error) in sources {
+            assert_eq!(errors(source), vec![error.to_string()], "{}", source);
+        }
+    }
+
+    #[test]
+    fn independent_mistakes_are_all_reported() {
+        assert_eq!(error_count("let = 1;\nlet b = (2;"), 2);
+        assert_eq!(error_count("{ let = 1; }\n{ 1 + ; }"), 2);
+        assert_eq!(error_count("let = 1;\nlet = 2;\nlet = 3;"), 3);
+    }
+
+    #[test]
+    fn parse_fails_with_the_first_recovered_error() {
+        assert_parse_errors(vec![(
+            "{ let = 1; } let = 2;",
+            "runtime exception[P002]: expected an identifier at line 1 column 7",
+        )]);
+    }
+
+    #[test]
+    fn trivia_is_attached_to_the_statements_around_it() {
+        let source = "// a\n\n// b\n\nlet x = 1; // c\n{\n    x; // d\n\n    // e\n}\nattempt {} handle (e) {\n    // f\n}\n// g";
+        let scanner = Scanner::with_comments(source, true).unwrap();
+        let mut parser = Parser::with_trivia(scanner.tokens, scanner.blank_lines, true);
+        let (statements, closing) = parser.parse_with_trivia().unwrap();
+
+        let comments = |comments: &[LineComment]| -> Vec<(usize, String)> {
+            comments
+                .iter()
+                .map(|comment| (comment.blank_lines, comment.token.lexeme.clone()))
+                .collect()
+        };
+        let (_, first) = &statements[0];
+        assert_eq!(
+            comments(&first.leading),
+            vec![(0, " a".to_string()), (1, " b".to_string())]
+        );
+        assert_eq!(first.blank_lines, 1);
+        assert_eq!(first.trailing.as_ref().unwrap().lexeme, " c");
+
+        let (_, block) = &statements[1];
+        assert_eq!(comments(&block.closing), vec![(1, " e".to_string())]);
+        assert_eq!(block.nested.len(), 1);
+        assert_eq!(block.nested[0].trailing.as_ref().unwrap().lexeme, " d");
+
+        let (_, attempt) = &statements[2];
+        assert_eq!(attempt.nested.len(), 2);
+        assert_eq!(
+            comments(&attempt.nested[1].closing),
+            vec![(0, " f".to_string())]
+        );
+        assert_eq!(comments(&closing), vec![(0, " g".to_string())]);
+    }
 }
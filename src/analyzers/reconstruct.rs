@@ -0,0 +1,234 @@
+//! Source rebuilt from tokens alone, to check that they keep everything the
+//! scanner read
+//!
+//! Tokens are separated by a space only where they would otherwise run
+//! together, strings are quoted again with the quotes the
+//! [formatter](super::formatter) would pick, numbers are written in a
+//! canonical form and every comment ends its line.
+use super::formatter::quote;
+use crate::types::{Token, TokenType};
+
+/// Canonical source scanning to the same tokens, up to their locations and
+/// the spelling of numbers
+///
+/// ## Examples
+/// ```rust
+/// use lox::{reconstruct_source, Scanner};
+///
+/// let scanner = Scanner::new("let  a=007 ;\nprint 'say \"${a}\"'  ==1.50;").unwrap();
+/// assert_eq!(
+///     reconstruct_source(&scanner.tokens),
+///     "let a=7;print'say \"${a}\"'==1.5;"
+/// );
+/// ```
+pub fn reconstruct_source(tokens: &[Token]) -> String {
+    let quotes = string_quotes(tokens);
+    let mut source = String::new();
+    for (token, quote) in tokens.iter().zip(quotes) {
+        let text = match token._type {
+            TokenType::Eof => continue,
+            TokenType::Comment => format!("//{}\n", token.lexeme),
+            TokenType::Number => canonical_number(&token.lexeme),
+            TokenType::String => format!("{}{}{}", quote, token.lexeme, quote),
+            TokenType::StringHead => format!("{}{}${{", quote, token.lexeme),
+            TokenType::StringMiddle => format!("}}{}${{", token.lexeme),
+            TokenType::StringTail => format!("}}{}{}", token.lexeme, quote),
+            _ => token.lexeme.clone(),
+        };
+        if let (Some(last), Some(first)) = (source.chars().last(), text.chars().next()) {
+            if runs_together(last, first) {
+                source.push(' ');
+            }
+        }
+        source.push_str(&text);
+    }
+    source
+}
+
+/// Spelling of a number lexeme without leading zeros or trailing zeros in
+/// its fraction, keeping `1.0` apart from the integer `1`
+pub fn canonical_number(lexeme: &str) -> String {
+    if !lexeme.contains('.') {
+        let digits = lexeme.trim_start_matches('0');
+        return if digits.is_empty() { "0" } else { digits }.into();
+    }
+    match lexeme.parse::<f32>() {
+        Ok(value) if value.is_finite() => {
+            let text = value.to_string();
+            if text.contains('.') {
+                text
+            } else {
+                text + ".0"
+            }
+        }
+        _ => lexeme.into(),
+    }
+}
+
+/// Quotes of each string token, those of an interpolated string being
+/// picked for all of its segments
+fn string_quotes(tokens: &[Token]) -> Vec<&'static str> {
+    let mut quotes = vec![""; tokens.len()];
+    // Indices of the segments of the interpolated strings still open
+    let mut open: Vec<Vec<usize>> = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        match token._type {
+            TokenType::String => quotes[index] = quote(&[&token.lexeme]),
+            TokenType::StringHead => open.push(vec![index]),
+            TokenType::StringMiddle | TokenType::StringTail => {
+                let Some(segments) = open.last_mut() else {
+                    quotes[index] = quote(&[&token.lexeme]);
+                    continue;
+                };
+                segments.push(index);
+                if token._type == TokenType::StringTail {
+                    close(tokens, &open.pop().unwrap(), &mut quotes);
+                }
+            }
+            _ => {}
+        }
+    }
+    for segments in open {
+        close(tokens, &segments, &mut quotes);
+    }
+    quotes
+}
+
+fn close(tokens: &[Token], segments: &[usize], quotes: &mut [&'static str]) {
+    let lexemes: Vec<&str> = segments
+        .iter()
+        .map(|&index| tokens[index].lexeme.as_str())
+        .collect();
+    let quote = quote(&lexemes);
+    for &index in segments {
+        quotes[index] = quote;
+    }
+}
+
+/// Whether text ending with `last` followed by text starting with `first`
+/// would scan differently than the two apart, i.e `a` `b` or `=` `=`
+fn runs_together(last: char, first: char) -> bool {
+    let word = |c: char| c.is_alphanumeric() || c == '_';
+    (word(last) && word(first))
+        // `1.5` rather than the number `1` followed by a dot
+        || (last.is_ascii_digit() && first == '.')
+        // `""` followed by a string opens a multiline one
+        || (last == '"' && first == '"')
+        || matches!(
+            (last, first),
+            ('=' | '!' | '<' | '>', '=')
+                | ('<', '<')
+                | ('>', '>')
+                | ('|', '|')
+                | ('&', '&')
+                | ('?', '?')
+                | ('/', '/')
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::Scanner;
+    use std::fs;
+    use std::path::Path;
+
+    /// Tokens as they would be compared by a scanner test, numbers by their
+    /// canonical spelling and without locations
+    fn comparable(tokens: &[Token]) -> Vec<(TokenType, String)> {
+        tokens
+            .iter()
+            .map(|token| {
+                let lexeme = match token._type {
+                    TokenType::Number => canonical_number(&token.lexeme),
+                    _ => token.lexeme.clone(),
+                };
+                (token._type.clone(), lexeme)
+            })
+            .collect()
+    }
+
+    /// Checks that the source rebuilt from the tokens of `source` scans to
+    /// the same tokens, returning false if `source` doesn't scan
+    fn round_trips(source: &str) -> bool {
+        let Ok(scanner) = Scanner::with_comments(source, true) else {
+            return false;
+        };
+        let rebuilt = reconstruct_source(&scanner.tokens);
+        let rescanned = Scanner::with_comments(&rebuilt, true)
+            .unwrap_or_else(|e| panic!("{:?} rebuilt as {:?}: {}", source, rebuilt, e));
+        assert_eq!(
+            comparable(&rescanned.tokens),
+            comparable(&scanner.tokens),
+            "{:?} rebuilt as {:?}",
+            source,
+            rebuilt
+        );
+        assert_eq!(reconstruct_source(&rescanned.tokens), rebuilt);
+        true
+    }
+
+    #[test]
+    fn tokens_are_separated_only_when_they_would_run_together() {
+        let scanner = Scanner::with_comments(
+            "let a = b == !c <= 1 . 5;\nprint a/ /b // c\n| | & & ?? ?? x;",
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            reconstruct_source(&scanner.tokens),
+            "let a=b==!c<=1 .5;print a/ /b// c\n| |& &?? ??x;"
+        );
+    }
+
+    #[test]
+    fn strings_are_quoted_to_hold_their_text() {
+        let source = "\"\" \"\" 'say \"hi\"' \"\"\"it's \"two\"\nlines\"\"\" \
+                      'a ${ \"b ${c}\" } \"d\" ${e}'";
+        let scanner = Scanner::new(source).unwrap();
+        assert_eq!(
+            reconstruct_source(&scanner.tokens),
+            "\"\" \"\"'say \"hi\"'\"\"\"it's \"two\"\nlines\"\"\"'a ${\"b ${c}\"} \"d\" ${e}'"
+        );
+        assert!(round_trips(source));
+    }
+
+    #[test]
+    fn numbers_are_canonical() {
+        for (lexeme, canonical) in [
+            ("0", "0"),
+            ("007", "7"),
+            ("00.50", "0.5"),
+            ("1.0", "1.0"),
+            ("3.14159", "3.14159"),
+            ("99999999999999999999", "99999999999999999999"),
+        ] {
+            assert_eq!(canonical_number(lexeme), canonical);
+        }
+    }
+
+    #[test]
+    fn corpus_programs_scan_the_same_once_rebuilt() {
+        let sources = [
+            "let a = 1;\n{ let b = a + 2.5; print b; }",
+            "attempt { exit 1; } handle (e) { write e ?? \"none\"; }",
+            "assert clock() >= 0, 'time ${\"flows\"}';",
+            "let x = -1 - -2 * (3 / 4) ~ 5 ^ 6 << 7 >> 8 | 9 & 10;",
+            "print true and !false or nil; // done",
+            "a.b; .5; 12.; this.super;",
+        ];
+        for source in sources {
+            assert!(round_trips(source), "{:?} doesn't scan", source);
+        }
+
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+        let mut scanned = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "lox") {
+                scanned += usize::from(round_trips(&fs::read_to_string(path).unwrap()));
+            }
+        }
+        assert!(scanned > 10);
+    }
+}
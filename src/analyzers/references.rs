@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use crate::types::{Expression, Span, Statement, Token, TokenType};
+
+/// Variable reference found in a program along with where it was declared
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub name: String,
+    pub usage_span: Span,
+    /// Declaration the reference resolves to, `None` for undeclared names
+    pub declaration_span: Option<Span>,
+}
+
+/// Finds the variable reference at the byte offset of the source
+///
+/// The reference is resolved the same way the interpreter resolves variables:
+/// to the innermost declaration that is visible at the point of use, so a
+/// variable used before its declaration in a block resolves to the enclosing
+/// scope. Declared names resolve to themselves. Returns `None` when the
+/// offset isn't on a variable.
+pub fn find_reference(stmts: &[Statement], offset: usize) -> Option<Reference> {
+    let mut finder = ReferenceFinder {
+        scopes: vec![HashMap::new()],
+        offset,
+    };
+    finder.statements(stmts)
+}
+
+struct ReferenceFinder {
+    scopes: Vec<HashMap<String, Span>>,
+    offset: usize,
+}
+
+impl ReferenceFinder {
+    fn statements(&mut self, stmts: &[Statement]) -> Option<Reference> {
+        stmts.iter().find_map(|stmt| self.statement(stmt))
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> Option<Reference> {
+        match stmt {
+            Statement::Expression(expr)
+            | Statement::Variable(expr)
+            | Statement::Exit(_, expr)
+            | Statement::Print(_, expr) => self.expression(expr),
+            Statement::Assert(_, condition, message) => self.expression(condition).or_else(|| {
+                message
+                    .as_ref()
+                    .and_then(|message| self.expression(message))
+            }),
+            Statement::Assign(name, initializer) => {
+                let found = self.expression(initializer);
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(name.lexeme.clone(), name.span());
+                found.or_else(|| self.reference(name))
+            }
+            Statement::Block(_, stmts) => {
+                self.scopes.push(HashMap::new());
+                let found = self.statements(stmts);
+                self.scopes.pop();
+                found
+            }
+            Statement::Attempt(body, name, handler) => self.statement(body).or_else(|| {
+                // The name is declared in a scope of its own around the handler
+                self.scopes
+                    .push(HashMap::from([(name.lexeme.clone(), name.span())]));
+                let found = self.reference(name).or_else(|| self.statement(handler));
+                self.scopes.pop();
+                found
+            }),
+            Statement::Empty(_) => None,
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) -> Option<Reference> {
+        match expr {
+            Expression::Variable(token) => self.reference(token),
+            Expression::Assignment(token, expr) => {
+                self.reference(token).or_else(|| self.expression(expr))
+            }
+            Expression::Unary(_, expr) | Expression::Grouping(expr) => self.expression(expr),
+            Expression::Binary(left, _, right) => {
+                self.expression(left).or_else(|| self.expression(right))
+            }
+            Expression::Interpolation(parts) => parts.iter().find_map(|part| self.expression(part)),
+            Expression::Call(callee, _, arguments) => self.expression(callee).or_else(|| {
+                arguments
+                    .iter()
+                    .find_map(|argument| self.expression(argument))
+            }),
+            Expression::Literal(_) | Expression::This(_) | Expression::Super(_, _) => None,
+        }
+    }
+
+    fn reference(&self, token: &Token) -> Option<Reference> {
+        if token._type != TokenType::Identifier || !token.span().contains(self.offset) {
+            return None;
+        }
+
+        let declaration_span = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&token.lexeme).copied());
+        Some(Reference {
+            name: token.lexeme.clone(),
+            usage_span: token.span(),
+            declaration_span,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::{Parser, Scanner};
+
+    fn find(source: &str, needle: &str, occurrence: usize) -> Option<Reference> {
+        let offset = source.match_indices(needle).nth(occurrence).unwrap().0;
+        let tokens = Scanner::new(source).unwrap().tokens;
+        let statements = Parser::new(tokens, true).parse().unwrap();
+        find_reference(&statements, offset)
+    }
+
+    fn span(offset: usize, len: usize) -> Span {
+        Span { offset, len }
+    }
+
+    #[test]
+    fn resolves_shadowed_variables_to_the_innermost_declaration() {
+        let source = "let a = 1;\n{ let a = 2; { a + 1; } }\na;";
+
+        let inner = find(source, "a", 1).unwrap();
+        assert_eq!(inner.usage_span, span(17, 1));
+        assert_eq!(inner.declaration_span, Some(span(17, 1)));
+
+        let nested = find(source, "a", 2).unwrap();
+        assert_eq!(nested.usage_span, span(26, 1));
+        assert_eq!(nested.declaration_span, Some(span(17, 1)));
+
+        let outer = find(source, "a", 3).unwrap();
+        assert_eq!(outer.usage_span, span(37, 1));
+        assert_eq!(outer.name, "a");
+        assert_eq!(outer.declaration_span, Some(span(4, 1)));
+    }
+
+    #[test]
+    fn usage_before_declaration_resolves_to_enclosing_scope() {
+        let source = "let a = 1;\n{ a; let a = 2; }";
+
+        let usage = find(source, "a", 1).unwrap();
+        assert_eq!(usage.declaration_span, Some(span(4, 1)));
+
+        let source = "{ a; let a = 2; }";
+        let usage = find(source, "a", 0).unwrap();
+        assert_eq!(usage.declaration_span, None);
+    }
+
+    #[test]
+    fn initializers_resolve_before_the_declaration() {
+        let source = "let a = 1;\n{ let a = a * 2; }";
+
+        let usage = find(source, "a", 2).unwrap();
+        assert_eq!(usage.declaration_span, Some(span(4, 1)));
+    }
+
+    #[test]
+    fn undeclared_names_have_no_declaration() {
+        let reference = find("let a = b;", "b", 0).unwrap();
+
+        assert_eq!(reference.name, "b");
+        assert_eq!(reference.declaration_span, None);
+    }
+
+    #[test]
+    fn offsets_outside_variables_find_nothing() {
+        assert_eq!(find("let a = 1 +  b;", "  ", 0), None);
+        assert_eq!(find("let a = 1 + b;", "1", 0), None);
+    }
+}
@@ -1,13 +1,19 @@
+use std::{borrow::Cow, fmt};
+
 use crate::{
-    errors::ScanError,
+    errors::{
+        codes::{self, ErrorCode},
+        ScanError,
+    },
     token::{Token, TokenType},
+    LanguageVersion,
 };
 
 pub type ScannerResult<T> = Result<T, ScanError>;
 
 // Addition of single characters to the syntax should be done
 // here
-const SINGLE_CHAR_TOKENS: [TokenType; 11] = [
+const SINGLE_CHAR_TOKENS: [TokenType; 13] = [
     TokenType::RightParen,
     TokenType::LeftParen,
     TokenType::RightBrace,
@@ -19,8 +25,13 @@ const SINGLE_CHAR_TOKENS: [TokenType; 11] = [
     TokenType::Slash,
     TokenType::Plus,
     TokenType::Star,
+    TokenType::BitXor,
+    TokenType::BitNot,
 ];
 
+/// Opens and closes strings that may span lines
+const TRIPLE_QUOTE: &str = "\"\"\"";
+
 const FORMATTING_TOKENS: [TokenType; 4] = [
     TokenType::NewLine,
     TokenType::Tab,
@@ -28,38 +39,270 @@ const FORMATTING_TOKENS: [TokenType; 4] = [
     TokenType::Space,
 ];
 
+/// Highlighting class of a span of source text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenClass {
+    Keyword,
+    Identifier,
+    Number,
+    String,
+    Operator,
+    Punctuation,
+    Comment,
+    Whitespace,
+    Error,
+}
+
+impl From<&TokenType> for TokenClass {
+    fn from(value: &TokenType) -> Self {
+        match value {
+            TokenType::Identifier => TokenClass::Identifier,
+            TokenType::Number => TokenClass::Number,
+            TokenType::String | TokenType::StringHead | TokenType::StringTail => TokenClass::String,
+            TokenType::Comment => TokenClass::Comment,
+            TokenType::LeftParen
+            | TokenType::RightParen
+            | TokenType::LeftBrace
+            | TokenType::RightBrace
+            | TokenType::Comma
+            | TokenType::Dot
+            | TokenType::SemiColon => TokenClass::Punctuation,
+            TokenType::NewLine
+            | TokenType::Tab
+            | TokenType::CarriageReturn
+            | TokenType::Space
+            | TokenType::Eof => TokenClass::Whitespace,
+            TokenType::Attempt
+            | TokenType::Class
+            | TokenType::Else
+            | TokenType::Exit
+            | TokenType::False
+            | TokenType::For
+            | TokenType::Handle
+            | TokenType::If
+            | TokenType::Print
+            | TokenType::Return
+            | TokenType::Super
+            | TokenType::This
+            | TokenType::True
+            | TokenType::Let
+            | TokenType::While
+            | TokenType::Write => TokenClass::Keyword,
+            _ => TokenClass::Operator,
+        }
+    }
+}
+
+/// Byte range of the source along with its highlighting class
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassifiedSpan {
+    pub start_offset: usize,
+    pub len: usize,
+    pub class: TokenClass,
+}
+
 /// Scanner is used for lexically analysis string content
 ///
 /// The scanner performs lexical analysis on string content afterwhich it
 /// provides access to the token read from the content. Panics if invalid
 /// token is read.
-pub struct Scanner {
+///
+/// The scanner borrows the source rather than copying it, only the lexemes
+/// of the tokens are allocated. Source given a piece at a time to
+/// [resume](Scanner::resume) is copied instead.
+pub struct Scanner<'src> {
     pub tokens: Vec<Token>,
-    source: Vec<char>,
+    /// Lines holding nothing but whitespace, in order, recorded along with
+    /// the comments when they're kept
+    pub blank_lines: Vec<usize>,
+    /// Source that is left to scan, preceded by what was already scanned
+    /// unless the scanner was resumed
+    source: Cow<'src, str>,
+    /// Byte offset of the cursor in `source`
     next: usize,
+    current_offset: usize,
     current_col: usize,
     current_row: usize,
+    keep_comments: bool,
+    interpolations: Vec<Interpolation>,
+    version: LanguageVersion,
 }
 
-impl Scanner {
-    pub fn new(source: &str) -> ScannerResult<Self> {
+/// Interpolation within a string that is currently being scanned
+#[derive(Clone)]
+struct Interpolation {
+    /// Braces opened within the interpolated expression
+    depth: usize,
+    line: usize,
+    column: usize,
+    /// Line and column of the opening quote of the string
+    quote: (usize, usize),
+    /// Quote character closing the string
+    delimiter: char,
+    /// The string is in triple quotes
+    multiline: bool,
+}
+
+impl<'src> Scanner<'src> {
+    pub fn new(source: &'src str) -> ScannerResult<Self> {
+        Self::with_comments(source, false)
+    }
+
+    /// Scans the source, emitting [TokenType::Comment] tokens for line
+    /// comments when `keep_comments` is set instead of discarding them
+    pub fn with_comments(source: &'src str, keep_comments: bool) -> ScannerResult<Self> {
+        let mut scanner = Self::unscanned(source, keep_comments);
+
+        scanner.scan_tokens()?;
+        Ok(scanner)
+    }
+
+    /// Scans the source as written for the language version, strings don't
+    /// contain interpolations before [LanguageVersion::V2]
+    pub fn with_version(source: &'src str, version: LanguageVersion) -> ScannerResult<Self> {
+        Self::with_comments_and_version(source, false, version)
+    }
+
+    /// Scans the source as written for the language version, keeping the
+    /// comments like [Scanner::with_comments]
+    pub(crate) fn with_comments_and_version(
+        source: &'src str,
+        keep_comments: bool,
+        version: LanguageVersion,
+    ) -> ScannerResult<Self> {
         let mut scanner = Self {
+            version,
+            ..Self::unscanned(source, keep_comments)
+        };
+
+        scanner.scan_tokens()?;
+        Ok(scanner)
+    }
+
+    fn unscanned(source: &'src str, keep_comments: bool) -> Self {
+        Self {
             tokens: Vec::new(),
-            source: source.chars().collect(),
+            blank_lines: Vec::new(),
+            source: Cow::Borrowed(source),
             next: 0,
+            current_offset: 0,
             current_row: 1,
             current_col: 1,
-        };
+            keep_comments,
+            interpolations: Vec::new(),
+            version: LanguageVersion::default(),
+        }
+    }
 
-        if let Err(e) = scanner.scan_tokens() {
-            return Err(ScanError {
-                line: scanner.current_row,
-                column: scanner.current_col,
-                msg: e,
-            });
+    /// Creates a scanner that is given the source a piece at a time with
+    /// [resume](Scanner::resume), such as the lines of a REPL entry
+    pub fn incremental(version: LanguageVersion) -> Self {
+        Self {
+            version,
+            ..Self::unscanned("", false)
         }
+    }
 
-        Ok(scanner)
+    /// Scans `more` source following the pieces given so far, returning the
+    /// tokens it completed
+    ///
+    /// A token reaching the end of the pieces so far, such as a string
+    /// missing its closing quote or a name that could go on, is in progress:
+    /// it's scanned again once the next piece arrives and returned when a
+    /// piece completes it or by [finish](Scanner::finish). Together the
+    /// returned tokens are the same as those of scanning every piece at once.
+    /// Only the token in progress is kept from earlier pieces, so resuming
+    /// costs the length of the new piece rather than of the whole source.
+    ///
+    /// Errors in tokens that are complete are returned right away, the
+    /// scanner can't be resumed after one.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use lox::{LanguageVersion, Scanner};
+    ///
+    /// let mut scanner = Scanner::incremental(LanguageVersion::default());
+    /// assert_eq!(scanner.resume("let text = \"\"\"one\n").unwrap().len(), 3);
+    /// assert!(scanner.is_incomplete());
+    ///
+    /// let tokens = scanner.resume("two\"\"\";\n").unwrap();
+    /// assert_eq!(tokens[0].lexeme, "one\ntwo");
+    /// assert!(!scanner.is_incomplete());
+    /// ```
+    pub fn resume(&mut self, more: &str) -> ScannerResult<&[Token]> {
+        let emitted = self.tokens.len();
+        let mut source = String::with_capacity(self.source.len() - self.next + more.len());
+        source.push_str(&self.source[self.next..]);
+        source.push_str(more);
+        self.source = Cow::Owned(source);
+        self.next = 0;
+
+        while self.has_next() {
+            let checkpoint = (
+                self.current_offset,
+                self.current_row,
+                self.current_col,
+                self.tokens.len(),
+                self.interpolations.clone(),
+            );
+            let start = self.next;
+            match self.scan_token() {
+                // Whitespace is complete, it doesn't join with what follows
+                Ok(TokenClass::Whitespace) => {}
+                Ok(_) | Err(_) if !self.has_next() => {
+                    let (offset, row, col, tokens, interpolations) = checkpoint;
+                    self.next = start;
+                    self.current_offset = offset;
+                    self.current_row = row;
+                    self.current_col = col;
+                    self.tokens.truncate(tokens);
+                    self.interpolations = interpolations;
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(&self.tokens[emitted..])
+    }
+
+    /// Scans the token in progress as if the source ended there, returning
+    /// the tokens left and reporting strings and interpolations left open
+    pub fn finish(&mut self) -> ScannerResult<&[Token]> {
+        let emitted = self.tokens.len();
+        self.scan_tokens()?;
+        Ok(&self.tokens[emitted..])
+    }
+
+    /// Whether the pieces given to [resume](Scanner::resume) end in the
+    /// middle of a token or of an interpolation
+    pub fn is_incomplete(&self) -> bool {
+        self.has_next() || self.in_interpolation()
+    }
+
+    /// Creates a scanner for the rest of a source that starts at `offset`,
+    /// `line` and `column` of the whole source
+    pub(super) fn resuming(rest: &'src str, offset: usize, line: usize, column: usize) -> Self {
+        Self {
+            current_offset: offset,
+            current_row: line,
+            current_col: column,
+            ..Self::unscanned(rest, false)
+        }
+    }
+
+    /// Byte offset of the cursor in the whole source
+    pub(super) fn offset(&self) -> usize {
+        self.current_offset
+    }
+
+    /// Line and column of the cursor
+    pub(super) fn line_column(&self) -> (usize, usize) {
+        (self.current_row, self.current_col)
+    }
+
+    pub(super) fn in_interpolation(&self) -> bool {
+        !self.interpolations.is_empty()
     }
 
     fn next_type(&self) -> Option<TokenType> {
@@ -71,52 +314,158 @@ impl Scanner {
         }
     }
 
-    fn scan_tokens(&mut self) -> Result<(), String> {
-        while self.next < self.source.len() {
-            let line = self.current_row;
-            let col = self.current_col;
+    fn scan_tokens(&mut self) -> ScannerResult<()> {
+        while self.has_next() {
+            self.scan_token()?;
+        }
+
+        if let Some(interpolation) = self.interpolations.first() {
+            return Err(ScanError::new(
+                codes::UNCLOSED_INTERPOLATION,
+                &[],
+                interpolation.line,
+                interpolation.column,
+            ));
+        }
+        Ok(())
+    }
 
-            let mut is_new_line = false;
+    /// Scans the token or trivia starting at the cursor and returns its class
+    pub(super) fn scan_token(&mut self) -> ScannerResult<TokenClass> {
+        let line = self.current_row;
+        let col = self.current_col;
+        let offset = self.current_offset;
 
-            let mut lexeme: Vec<char> = vec![self.source[self.next]];
-            let mut token_type: TokenType = match TokenType::try_from(lexeme[0]) {
-                Ok(token_type) => token_type,
-                Err(e) => {
-                    return Err(e);
+        // Trivia is skipped before a lexeme is allocated, whitespace a whole
+        // run at a time
+        let first = self.peek_next().unwrap();
+        if Self::is_whitespace(first) {
+            // A line is blank when the run of whitespace covers all of it
+            let mut whole_line = col == 1;
+            while let Some(c) = self.peek_next().filter(|&c| Self::is_whitespace(c)) {
+                if c == '\n' && self.keep_comments {
+                    if whole_line {
+                        self.blank_lines.push(self.current_row);
+                    }
+                    whole_line = true;
                 }
-            };
-
-            if SINGLE_CHAR_TOKENS
-                .to_vec()
-                .iter()
-                .any(|_type| *_type == token_type)
-            {
                 self.next();
-                self.add_token(token_type, lexeme.iter().collect::<String>(), line, col);
-            } else if FORMATTING_TOKENS
-                .to_vec()
-                .iter()
-                .any(|_type| *_type == token_type)
-            {
+            }
+            return Ok(TokenClass::Whitespace);
+        }
+        if first == '/' && self.peek_after_next() == Some('/') {
+            let start = self.next;
+            while self.has_next() && !self.next_matches('\n') {
                 self.next();
-                if token_type == TokenType::NewLine {
-                    is_new_line = true;
+            }
+            if self.keep_comments {
+                let text = self.source[start + 2..self.next].to_string();
+                self.add_token(TokenType::Comment, text, line, col, offset);
+            }
+            return Ok(TokenClass::Comment);
+        }
+
+        let mut lexeme: Vec<char> = vec![first];
+        let mut token_type: TokenType = TokenType::try_from(first)
+            .map_err(|_| self.error_here(codes::UNKNOWN_CHARACTER, &[&first.escape_debug()]))?;
+
+        let class = if token_type == TokenType::RightBrace
+            && self
+                .interpolations
+                .last()
+                .is_some_and(|interpolation| interpolation.depth == 0)
+        {
+            self.next();
+            let interpolation = self.interpolations.pop().unwrap();
+            lexeme.clear();
+            token_type = self.read_string(
+                &mut lexeme,
+                TokenType::StringTail,
+                TokenType::StringMiddle,
+                interpolation.quote,
+                interpolation.delimiter,
+                interpolation.multiline,
+            )?;
+            self.add_token(
+                token_type,
+                lexeme.iter().collect::<String>(),
+                line,
+                col,
+                offset,
+            );
+            TokenClass::String
+        } else if SINGLE_CHAR_TOKENS.contains(&token_type) {
+            self.next();
+            if let Some(interpolation) = self.interpolations.last_mut() {
+                if token_type == TokenType::LeftBrace {
+                    interpolation.depth += 1;
+                } else if token_type == TokenType::RightBrace {
+                    interpolation.depth -= 1;
                 }
-            } else {
-                self.next();
-                token_type = match self.read_next_token(&mut lexeme) {
-                    Ok(token_type) => token_type,
-                    Err(e) => return Err(e),
-                };
-                self.add_token(token_type, lexeme.iter().collect::<String>(), line, col);
             }
+            let class = TokenClass::from(&token_type);
+            self.add_token(
+                token_type,
+                lexeme.iter().collect::<String>(),
+                line,
+                col,
+                offset,
+            );
+            class
+        } else {
+            self.next();
+            token_type = self.read_next_token(&mut lexeme, (line, col))?;
+            let class = TokenClass::from(&token_type);
+            self.add_token(
+                token_type,
+                lexeme.iter().collect::<String>(),
+                line,
+                col,
+                offset,
+            );
+            class
+        };
 
-            if is_new_line {
-                self.current_row += 1;
-                self.current_col = 1;
+        Ok(class)
+    }
+
+    /// Classifies every byte of the source for syntax highlighting
+    ///
+    /// Unlike [Scanner::new](Scanner::new) this keeps trivia such as comments
+    /// and whitespace, and records invalid input as [TokenClass::Error] spans
+    /// instead of aborting. The returned spans are ordered and cover the whole
+    /// source without gaps or overlaps.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use lox::{Scanner, TokenClass};
+    ///
+    /// let spans = Scanner::classify("let a = 1; // one");
+    /// assert_eq!(spans[0].class, TokenClass::Keyword);
+    /// assert_eq!(spans.last().unwrap().class, TokenClass::Comment);
+    /// ```
+    pub fn classify(source: &str) -> Vec<ClassifiedSpan> {
+        let mut scanner = Scanner::unscanned(source, false);
+        let mut spans: Vec<ClassifiedSpan> = Vec::new();
+
+        while scanner.has_next() {
+            let start_offset = scanner.next;
+            let class = scanner.scan_token().unwrap_or(TokenClass::Error);
+            let len = scanner.next - start_offset;
+
+            match spans.last_mut() {
+                Some(span) if span.class == class && class == TokenClass::Whitespace => {
+                    span.len += len;
+                }
+                _ => spans.push(ClassifiedSpan {
+                    start_offset,
+                    len,
+                    class,
+                }),
             }
         }
-        Ok(())
+
+        spans
     }
 
     fn next_matches(&self, s: char) -> bool {
@@ -127,67 +476,161 @@ impl Scanner {
         }
     }
 
-    fn has_next(&self) -> bool {
+    pub(super) fn has_next(&self) -> bool {
         self.source.len() > self.next
     }
 
     fn peek_next(&self) -> Option<char> {
-        if !self.has_next() {
-            None
-        } else {
-            Some(self.source[self.next])
-        }
+        self.source[self.next..].chars().next()
+    }
+
+    /// Character following the next one
+    fn peek_after_next(&self) -> Option<char> {
+        self.source[self.next..].chars().nth(1)
     }
 
+    /// Moves past the next character, a `\n` anywhere in the source, even
+    /// inside a string, starts a new line
+    ///
+    /// A `\r` takes up a column like any other character, so in `\r\n` only
+    /// the `\n` ends the line.
     fn next(&mut self) -> Option<char> {
         if let Some(value) = self.peek_next() {
-            self.next += 1;
-            self.current_col += 1;
+            self.next += value.len_utf8();
+            self.current_offset += value.len_utf8();
+            if value == '\n' {
+                self.current_row += 1;
+                self.current_col = 1;
+            } else {
+                self.current_col += 1;
+            }
             Some(value)
         } else {
             None
         }
     }
 
-    fn consume_until(&mut self, buf: &mut Vec<char>, until: char) -> Result<(), String> {
+    /// Error located at the cursor
+    fn error_here(&self, code: ErrorCode, args: &[&dyn fmt::Display]) -> ScanError {
+        ScanError::new(code, args, self.current_row, self.current_col)
+    }
+
+    /// Reads string content up to the closing `delimiter` or the start of an
+    /// interpolation, returning `complete` or `interrupted` respectively
+    ///
+    /// Only `multiline` strings, opened with `"""`, may contain newlines.
+    /// Other strings are reported at the end of the line they start on,
+    /// strings running to the end of the source at their opening `quote`,
+    /// where the missing closing quote is easiest to spot.
+    fn read_string(
+        &mut self,
+        buf: &mut Vec<char>,
+        complete: TokenType,
+        interrupted: TokenType,
+        quote: (usize, usize),
+        delimiter: char,
+        multiline: bool,
+    ) -> ScannerResult<TokenType> {
         loop {
-            if self.next_matches(until) {
-                self.next();
-                break;
-            } else {
-                match self.next() {
-                    Some(val) => buf.push(val),
-                    None => {
-                        return Err(format!("missing `{}`", until));
+            match self.peek_next() {
+                Some(c) if c == delimiter && !multiline => {
+                    self.next();
+                    return Ok(complete);
+                }
+                Some('"') if self.source[self.next..].starts_with(TRIPLE_QUOTE) => {
+                    for _ in 0..TRIPLE_QUOTE.len() {
+                        self.next();
                     }
+                    return Ok(complete);
+                }
+                Some('\n') if !multiline => {
+                    return Err(self.error_here(codes::NEWLINE_IN_STRING, &[]));
+                }
+                Some('$')
+                    if self.version.has_extensions() && self.peek_after_next() == Some('{') =>
+                {
+                    self.interpolations.push(Interpolation {
+                        depth: 0,
+                        line: self.current_row,
+                        column: self.current_col,
+                        quote,
+                        delimiter,
+                        multiline,
+                    });
+                    self.next();
+                    self.next();
+                    return Ok(interrupted);
+                }
+                Some(_) => buf.push(self.next().unwrap()),
+                None => {
+                    let lines = self.current_row - quote.0 + 1;
+                    let closing = if multiline {
+                        TRIPLE_QUOTE.to_string()
+                    } else {
+                        delimiter.to_string()
+                    };
+                    let error = if lines == 1 {
+                        ScanError::new(codes::UNTERMINATED_STRING, &[&closing], quote.0, quote.1)
+                    } else {
+                        ScanError::new(
+                            codes::UNTERMINATED_MULTILINE_STRING,
+                            &[&lines, &closing],
+                            quote.0,
+                            quote.1,
+                        )
+                    };
+                    return Err(error);
                 }
             }
         }
-
-        Ok(())
     }
 
-    fn read_next_token(&mut self, lexeme: &mut Vec<char>) -> Result<TokenType, String> {
+    /// Reads the rest of the token starting at `start`, the first character
+    /// of which is already in the lexeme
+    fn read_next_token(
+        &mut self,
+        lexeme: &mut Vec<char>,
+        start: (usize, usize),
+    ) -> ScannerResult<TokenType> {
         let char_rep = lexeme[0];
-        let token_type: TokenType;
 
         match char_rep {
             '"' => {
-                token_type = TokenType::String;
                 lexeme.clear();
-
-                if let Err(e) = self.consume_until(lexeme, char_rep) {
-                    Err(format!("unclosed {} {}", TokenType::String, e))
-                } else {
-                    Ok(token_type)
+                // `""` followed by a quote always opens a multiline string
+                let multiline = self.source[self.next..].starts_with("\"\"");
+                if multiline {
+                    self.next();
+                    self.next();
                 }
+                self.read_string(
+                    lexeme,
+                    TokenType::String,
+                    TokenType::StringHead,
+                    start,
+                    '"',
+                    multiline,
+                )
+            }
+            // Single quotes work like double quotes, each can be written
+            // inside strings delimited by the other one
+            '\'' => {
+                lexeme.clear();
+                self.read_string(
+                    lexeme,
+                    TokenType::String,
+                    TokenType::StringHead,
+                    start,
+                    '\'',
+                    false,
+                )
             }
             '|' => {
                 if self.next_matches(char_rep) {
                     lexeme.push(self.next().unwrap());
                     Ok(TokenType::Or)
                 } else {
-                    Err("unknown character".into())
+                    Ok(TokenType::BitOr)
                 }
             }
             '&' => {
@@ -195,13 +638,30 @@ impl Scanner {
                     lexeme.push(self.next().unwrap());
                     Ok(TokenType::And)
                 } else {
-                    Err("unknown character".into())
+                    Ok(TokenType::BitAnd)
+                }
+            }
+            // A single `?` is kept for a conditional operator
+            '?' => {
+                if self.next_matches('?') {
+                    lexeme.push(self.next().unwrap());
+                    Ok(TokenType::NilCoalesce)
+                } else {
+                    Err(ScanError::new(
+                        codes::SINGLE_QUESTION_MARK,
+                        &[],
+                        start.0,
+                        start.1,
+                    ))
                 }
             }
             '<' => {
                 if self.next_matches('=') {
                     lexeme.push(self.next().unwrap());
                     Ok(TokenType::LessEqual)
+                } else if self.next_matches('<') {
+                    lexeme.push(self.next().unwrap());
+                    Ok(TokenType::ShiftLeft)
                 } else {
                     Ok(TokenType::Less)
                 }
@@ -210,6 +670,9 @@ impl Scanner {
                 if self.next_matches('=') {
                     lexeme.push(self.next().unwrap());
                     Ok(TokenType::GreaterEqual)
+                } else if self.next_matches('>') {
+                    lexeme.push(self.next().unwrap());
+                    Ok(TokenType::ShiftRight)
                 } else {
                     Ok(TokenType::Greater)
                 }
@@ -232,14 +695,29 @@ impl Scanner {
             }
             _ => {
                 if Self::is_digit(char_rep) {
-                    loop {
-                        if self.next_type().is_none()
-                            || !Self::is_numeric(self.peek_next().unwrap())
-                        {
-                            break;
-                        } else {
-                            lexeme.push(self.next().unwrap());
+                    self.read_digits(lexeme);
+
+                    // A fraction needs a digit after the dot, `12.` is the
+                    // number `12` followed by a dot
+                    if self.next_matches('.') && self.peek_after_next().is_some_and(Self::is_digit)
+                    {
+                        lexeme.push(self.next().unwrap());
+                        self.read_digits(lexeme);
+                    }
+
+                    // `2fast` is a misspelled name rather than a number
+                    // followed by one, the whole run is skipped so the
+                    // error covers it
+                    if self.peek_next().is_some_and(Self::is_alphabetic) {
+                        while self.peek_next().is_some_and(Self::is_alphanumeric) {
+                            self.next();
                         }
+                        return Err(ScanError::new(
+                            codes::DIGIT_STARTED_IDENTIFIER,
+                            &[],
+                            start.0,
+                            start.1,
+                        ));
                     }
 
                     Ok(TokenType::Number)
@@ -254,51 +732,84 @@ impl Scanner {
                         }
                     }
 
-                    Ok(Self::process_identifier(&lexeme.iter().collect::<String>()))
+                    Ok(Self::process_identifier(lexeme))
                 } else {
-                    return Err("unknown character".into());
+                    Err(self.error_here(codes::UNKNOWN_CHARACTER, &[&char_rep.escape_debug()]))
                 }
             }
         }
     }
 
-    fn add_token(&mut self, _type: TokenType, lexeme: String, line: usize, column: usize) {
+    fn read_digits(&mut self, lexeme: &mut Vec<char>) {
+        while self.peek_next().is_some_and(Self::is_digit) {
+            lexeme.push(self.next().unwrap());
+        }
+    }
+
+    fn add_token(
+        &mut self,
+        _type: TokenType,
+        lexeme: String,
+        line: usize,
+        column: usize,
+        offset: usize,
+    ) {
         let token = Token {
             _type,
             lexeme,
             line,
             column,
+            offset,
         };
         self.tokens.push(token);
     }
 
-    fn process_identifier(identifier: &str) -> TokenType {
-        match identifier {
-            "class" => TokenType::Class,
-            "else" => TokenType::Else,
-            "false" => TokenType::False,
-            "for" => TokenType::For,
-            "if" => TokenType::If,
-            "print" => TokenType::Print,
-            "return" => TokenType::Return,
-            "super" => TokenType::Super,
-            "true" => TokenType::True,
-            "let" => TokenType::Let,
-            "while" => TokenType::While,
+    /// Keyword the lexeme spells or [TokenType::Identifier]
+    fn process_identifier(identifier: &[char]) -> TokenType {
+        // Keywords are short ASCII words, copying the lexeme to the stack
+        // avoids allocating a string for every identifier scanned
+        let mut bytes = [0u8; 7];
+        if identifier.len() > bytes.len() || !identifier.iter().all(char::is_ascii) {
+            return TokenType::Identifier;
+        }
+        for (byte, c) in bytes.iter_mut().zip(identifier) {
+            *byte = *c as u8;
+        }
+
+        match &bytes[..identifier.len()] {
+            b"assert" => TokenType::Assert,
+            b"attempt" => TokenType::Attempt,
+            b"class" => TokenType::Class,
+            b"else" => TokenType::Else,
+            b"exit" => TokenType::Exit,
+            b"false" => TokenType::False,
+            b"for" => TokenType::For,
+            b"handle" => TokenType::Handle,
+            b"if" => TokenType::If,
+            b"print" => TokenType::Print,
+            b"return" => TokenType::Return,
+            b"super" => TokenType::Super,
+            b"this" => TokenType::This,
+            b"true" => TokenType::True,
+            b"let" => TokenType::Let,
+            b"nil" => TokenType::Nil,
+            b"while" => TokenType::While,
+            b"write" => TokenType::Write,
             _ => TokenType::Identifier,
         }
     }
 
     fn is_digit(c: char) -> bool {
-        c.is_numeric()
+        c.is_ascii_digit()
     }
 
-    fn is_numeric(c: char) -> bool {
-        c == '.' || c.is_numeric()
+    /// Returns true if identifiers can start with the character
+    fn is_whitespace(c: char) -> bool {
+        TokenType::try_from(c).is_ok_and(|token_type| FORMATTING_TOKENS.contains(&token_type))
     }
 
     fn is_alphabetic(c: char) -> bool {
-        c.is_alphabetic()
+        c.is_alphabetic() || c == '_'
     }
 
     fn is_alphanumeric(c: char) -> bool {
@@ -328,7 +839,7 @@ mod tests {
     #[test]
     fn captures_single_character_tokens() {
         let content = "(){},.-+;/ *";
-        let scanner = Scanner::new(content.into()).unwrap();
+        let scanner = Scanner::new(content).unwrap();
 
         let expected = vec![
             (TokenType::LeftParen, "(".to_string(), 1, 1),
@@ -349,7 +860,7 @@ mod tests {
     #[test]
     fn captures_string_and_number_tokens() {
         let content = "\"Hey there 2\" 25 12.32";
-        let scanner = Scanner::new(content.into()).unwrap();
+        let scanner = Scanner::new(content).unwrap();
 
         let expected = vec![
             (TokenType::String, "Hey there 2".to_string(), 1, 1),
@@ -359,10 +870,226 @@ mod tests {
         assert_expected_tokens(scanner, expected);
     }
 
+    #[test]
+    fn captures_numbers_at_the_end_of_the_source() {
+        let scenarios = vec![
+            ("1.5", vec![(TokenType::Number, "1.5".to_string(), 1, 1)]),
+            ("42", vec![(TokenType::Number, "42".to_string(), 1, 1)]),
+            ("1.5\n", vec![(TokenType::Number, "1.5".to_string(), 1, 1)]),
+            (
+                "12.",
+                vec![
+                    (TokenType::Number, "12".to_string(), 1, 1),
+                    (TokenType::Dot, ".".to_string(), 1, 3),
+                ],
+            ),
+            (
+                ".5",
+                vec![
+                    (TokenType::Dot, ".".to_string(), 1, 1),
+                    (TokenType::Number, "5".to_string(), 1, 2),
+                ],
+            ),
+            (
+                "1.2.3",
+                vec![
+                    (TokenType::Number, "1.2".to_string(), 1, 1),
+                    (TokenType::Dot, ".".to_string(), 1, 4),
+                    (TokenType::Number, "3".to_string(), 1, 5),
+                ],
+            ),
+            (".", vec![(TokenType::Dot, ".".to_string(), 1, 1)]),
+        ];
+
+        for (content, expected) in scenarios {
+            assert_expected_tokens(Scanner::new(content).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn rejects_identifiers_starting_with_digits() {
+        for (content, column) in [
+            ("let 2fast = 1;", 5),
+            ("1 + 2fast;", 5),
+            ("2or 3", 1),
+            ("2e3x", 1),
+            ("1.5x", 1),
+            ("let a = 12_b;", 9),
+        ] {
+            assert_eq!(
+                Scanner::new(content).err().unwrap().to_string(),
+                format!(
+                    "scan error[S006] at 1:{}; identifiers cannot start with a digit",
+                    column
+                ),
+                "{}",
+                content
+            );
+        }
+
+        assert_expected_tokens(
+            Scanner::new("2+fast 2 fast").unwrap(),
+            vec![
+                (TokenType::Number, "2".to_string(), 1, 1),
+                (TokenType::Plus, "+".to_string(), 1, 2),
+                (TokenType::Identifier, "fast".to_string(), 1, 3),
+                (TokenType::Number, "2".to_string(), 1, 8),
+                (TokenType::Identifier, "fast".to_string(), 1, 10),
+            ],
+        );
+    }
+
+    #[test]
+    fn captures_interpolated_string_tokens() {
+        let content = "\"sum ${a + {b}} is ${\"${1}\"}!\" \"${}\"";
+        let scanner = Scanner::new(content).unwrap();
+
+        let expected = vec![
+            (TokenType::StringHead, "sum ".to_string(), 1, 1),
+            (TokenType::Identifier, "a".to_string(), 1, 8),
+            (TokenType::Plus, "+".to_string(), 1, 10),
+            (TokenType::LeftBrace, "{".to_string(), 1, 12),
+            (TokenType::Identifier, "b".to_string(), 1, 13),
+            (TokenType::RightBrace, "}".to_string(), 1, 14),
+            (TokenType::StringMiddle, " is ".to_string(), 1, 15),
+            (TokenType::StringHead, "".to_string(), 1, 22),
+            (TokenType::Number, "1".to_string(), 1, 25),
+            (TokenType::StringTail, "".to_string(), 1, 26),
+            (TokenType::StringTail, "!".to_string(), 1, 28),
+            (TokenType::StringHead, "".to_string(), 1, 32),
+            (TokenType::StringTail, "".to_string(), 1, 35),
+        ];
+        assert_expected_tokens(scanner, expected);
+    }
+
+    #[test]
+    fn reports_unclosed_interpolations_at_their_start() {
+        let error = Scanner::new("let a = \"x ${1 + {2}").err().unwrap();
+
+        assert_eq!(error.line, 1);
+        assert_eq!(error.column, 12);
+        assert_eq!(error.msg, "unclosed interpolation missing `}`");
+    }
+
+    #[test]
+    fn reports_unterminated_strings_at_the_opening_quote() {
+        let error = Scanner::new("let a = 1;\nlet b = \"\"\"open;\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "scan error[S003] at 2:9; unterminated string spanning 2 lines missing `\"\"\"`"
+        );
+
+        let error = Scanner::new("1;\n\n\n   let s = \"\"\"one\r\ntwo\nthree\"\"")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "scan error[S003] at 4:12; unterminated string spanning 3 lines missing `\"\"\"`"
+        );
+
+        let error = Scanner::new("\"a\" + \"b").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "scan error[S002] at 1:7; unterminated string missing `\"`"
+        );
+
+        // The rest of an interpolated string belongs to the string it started
+        let error = Scanner::new("x;\n  \"\"\"${1}\nend").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "scan error[S003] at 2:3; unterminated string spanning 2 lines missing `\"\"\"`"
+        );
+        let error = Scanner::new("\"${\n1\n}").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "scan error[S003] at 1:1; unterminated string spanning 3 lines missing `\"`"
+        );
+    }
+
+    #[test]
+    fn single_quoted_strings_work_like_double_quoted_ones() {
+        let scanner = Scanner::new("'say \"hi\"' \"it's\" 'a ${'b'}'").unwrap();
+        let expected = vec![
+            (TokenType::String, "say \"hi\"".to_string(), 1, 1),
+            (TokenType::String, "it's".to_string(), 1, 12),
+            (TokenType::StringHead, "a ".to_string(), 1, 19),
+            (TokenType::String, "b".to_string(), 1, 24),
+            (TokenType::StringTail, "".to_string(), 1, 27),
+        ];
+        assert_expected_tokens(scanner, expected);
+
+        let error = Scanner::new("print 'open;").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "scan error[S002] at 1:7; unterminated string missing `'`"
+        );
+        // A double quote doesn't close a single-quoted string
+        let error = Scanner::new("'a\" + 1;\n2;").err().unwrap();
+        assert_eq!((error.code, error.line), (codes::NEWLINE_IN_STRING, 1));
+        // Nor do three single quotes open a multiline string
+        assert_eq!(Scanner::new("'''';").unwrap().tokens.len(), 3);
+    }
+
+    #[test]
+    fn reports_newlines_in_strings_where_the_line_ends() {
+        for source in ["let a = \"open;\nlet b = 2;", "let a = \"open;\r\n"] {
+            let error = Scanner::new(source).err().unwrap();
+            assert_eq!((error.code, error.line), (codes::NEWLINE_IN_STRING, 1));
+            assert_eq!(error.column, source.find('\n').unwrap() + 1);
+        }
+
+        // Backslashes are kept as they are, they don't continue the string
+        let error = Scanner::new("\"a\\\nb\"").err().unwrap();
+        assert_eq!((error.line, error.column), (1, 4));
+        let scanner = Scanner::new("\"a\\n\";").unwrap();
+        assert_eq!(scanner.tokens[0].lexeme, "a\\n");
+
+        // Interpolated expressions may span lines, the rest of the string may not
+        let scanner = Scanner::new("\"${\n1\n}!\";").unwrap();
+        assert_eq!(scanner.tokens.last().unwrap().line, 3);
+        let error = Scanner::new("\"${1}\n\";").err().unwrap();
+        assert_eq!(
+            (error.code, error.line, error.column),
+            (codes::NEWLINE_IN_STRING, 1, 6)
+        );
+    }
+
+    #[test]
+    fn triple_quoted_strings_span_lines() {
+        let scanner = Scanner::new("\"\"\"one\n\"two\" \"\"\n${3}\"\"\";\nx;").unwrap();
+        let expected = vec![
+            (
+                TokenType::StringHead,
+                "one\n\"two\" \"\"\n".to_string(),
+                1,
+                1,
+            ),
+            (TokenType::Number, "3".to_string(), 3, 3),
+            (TokenType::StringTail, "".to_string(), 3, 4),
+            (TokenType::SemiColon, ";".to_string(), 3, 8),
+            (TokenType::Identifier, "x".to_string(), 4, 1),
+            (TokenType::SemiColon, ";".to_string(), 4, 2),
+        ];
+        assert_expected_tokens(scanner, expected);
+
+        // Two quotes are an empty string, a third one opens a multiline string
+        let scanner = Scanner::new("\"\" + \"\"\"\"\"\"").unwrap();
+        let lexemes: Vec<_> = scanner.tokens.iter().map(|t| t.lexeme.as_str()).collect();
+        assert_eq!(lexemes, ["", "+", ""]);
+
+        let error = Scanner::new("\"\"\"one").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "scan error[S002] at 1:1; unterminated string missing `\"\"\"`"
+        );
+    }
+
     #[test]
     fn captures_two_character_tokens() {
         let content = "<=<>=>||&&";
-        let scanner = Scanner::new(content.into()).unwrap();
+        let scanner = Scanner::new(content).unwrap();
 
         let eexpected = vec![
             (TokenType::LessEqual, "<=".to_string(), 1, 1),
@@ -375,10 +1102,44 @@ mod tests {
         assert_expected_tokens(scanner, eexpected);
     }
 
+    #[test]
+    fn captures_bitwise_tokens() {
+        let content = "& | ^ ~ << >> <<=";
+        let scanner = Scanner::new(content).unwrap();
+
+        let expected = vec![
+            (TokenType::BitAnd, "&".to_string(), 1, 1),
+            (TokenType::BitOr, "|".to_string(), 1, 3),
+            (TokenType::BitXor, "^".to_string(), 1, 5),
+            (TokenType::BitNot, "~".to_string(), 1, 7),
+            (TokenType::ShiftLeft, "<<".to_string(), 1, 9),
+            (TokenType::ShiftRight, ">>".to_string(), 1, 12),
+            (TokenType::ShiftLeft, "<<".to_string(), 1, 15),
+            (TokenType::Equal, "=".to_string(), 1, 17),
+        ];
+        assert_expected_tokens(scanner, expected);
+    }
+
+    #[test]
+    fn captures_nil_coalescing_tokens() {
+        let error = Scanner::new("a ?? b???c").err().unwrap();
+        assert_eq!((error.line, error.column), (1, 9));
+        assert_eq!(error.msg, "unexpected `?`, did you mean `??`");
+
+        let expected = vec![
+            (TokenType::Identifier, "a".to_string(), 1, 1),
+            (TokenType::NilCoalesce, "??".to_string(), 1, 3),
+            (TokenType::Identifier, "b".to_string(), 1, 6),
+            (TokenType::NilCoalesce, "??".to_string(), 1, 7),
+            (TokenType::Identifier, "c".to_string(), 1, 9),
+        ];
+        assert_expected_tokens(Scanner::new("a ?? b??c").unwrap(), expected);
+    }
+
     #[test]
     fn captures_identifiers_accurately() {
         let content = "class else false for if print return super true let while some_identifier someIdentifier identifier32";
-        let scanner = Scanner::new(content.into()).unwrap();
+        let scanner = Scanner::new(content).unwrap();
 
         let expected = vec![
             (TokenType::Class, "class".to_string(), 1, 1),
@@ -399,10 +1160,35 @@ mod tests {
         assert_expected_tokens(scanner, expected);
     }
 
+    #[test]
+    fn words_close_to_keywords_are_identifiers() {
+        let content = "classes le whilst nil exit returns Let éxit";
+        let types: Vec<TokenType> = Scanner::new(content)
+            .unwrap()
+            .tokens
+            .into_iter()
+            .map(|token| token._type)
+            .collect();
+
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::Nil,
+                TokenType::Exit,
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::Identifier,
+            ]
+        );
+    }
+
     #[test]
     fn captures_content_successfully() {
         let content = "let num = 23;\nprint(num);";
-        let scanner = Scanner::new(content.into()).unwrap();
+        let scanner = Scanner::new(content).unwrap();
 
         let expected = vec![
             (TokenType::Let, "let".to_string(), 1, 1),
@@ -418,4 +1204,336 @@ mod tests {
         ];
         assert_expected_tokens(scanner, expected);
     }
+
+    #[test]
+    fn skips_line_comments() {
+        let content = "let num = 23; // the number\n// done\nnum / 2;";
+        let scanner = Scanner::new(content).unwrap();
+
+        let expected = vec![
+            (TokenType::Let, "let".to_string(), 1, 1),
+            (TokenType::Identifier, "num".to_string(), 1, 5),
+            (TokenType::Equal, "=".to_string(), 1, 9),
+            (TokenType::Number, "23".to_string(), 1, 11),
+            (TokenType::SemiColon, ";".to_string(), 1, 13),
+            (TokenType::Identifier, "num".to_string(), 3, 1),
+            (TokenType::Slash, "/".to_string(), 3, 5),
+            (TokenType::Number, "2".to_string(), 3, 7),
+            (TokenType::SemiColon, ";".to_string(), 3, 8),
+        ];
+        assert_expected_tokens(scanner, expected);
+    }
+
+    #[test]
+    fn columns_are_relative_to_their_line() {
+        for newline in ["\n", "\r\n"] {
+            let content = ["let a = 1;", "  a;", "    {  a; }"].join(newline);
+            let scanner = Scanner::new(&content).unwrap();
+
+            let expected = vec![
+                (TokenType::Let, "let".to_string(), 1, 1),
+                (TokenType::Identifier, "a".to_string(), 1, 5),
+                (TokenType::Equal, "=".to_string(), 1, 7),
+                (TokenType::Number, "1".to_string(), 1, 9),
+                (TokenType::SemiColon, ";".to_string(), 1, 10),
+                (TokenType::Identifier, "a".to_string(), 2, 3),
+                (TokenType::SemiColon, ";".to_string(), 2, 4),
+                (TokenType::LeftBrace, "{".to_string(), 3, 5),
+                (TokenType::Identifier, "a".to_string(), 3, 8),
+                (TokenType::SemiColon, ";".to_string(), 3, 9),
+                (TokenType::RightBrace, "}".to_string(), 3, 11),
+            ];
+            assert_expected_tokens(scanner, expected);
+        }
+    }
+
+    #[test]
+    fn strings_spanning_lines_move_later_tokens() {
+        for newline in ["\n", "\r\n"] {
+            let content = format!("\"\"\"one{0}two\"\"\";{0}  x;", newline);
+            let scanner = Scanner::new(&content).unwrap();
+
+            let expected = vec![
+                (TokenType::String, format!("one{}two", newline), 1, 1),
+                (TokenType::SemiColon, ";".to_string(), 2, 7),
+                (TokenType::Identifier, "x".to_string(), 3, 3),
+                (TokenType::SemiColon, ";".to_string(), 3, 4),
+            ];
+            assert_expected_tokens(scanner, expected);
+        }
+    }
+
+    #[test]
+    fn never_emits_eof_tokens() {
+        // The parser treats tokens after an Eof as garbage, scanned sources
+        // must never contain any
+        let sources = [
+            "",
+            "let a = 1;",
+            "// only a comment",
+            "\"${a} and ${\"${b}\"}\";\n{ exit 0; }",
+            "1 +\n\n",
+        ];
+        for source in sources {
+            let scanner = Scanner::with_comments(source, true).unwrap();
+            assert!(
+                scanner.tokens.iter().all(|t| t._type != TokenType::Eof),
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn records_byte_offsets_of_tokens() {
+        let content = "let é = \"ü\";\né;";
+        let scanner = Scanner::new(content).unwrap();
+
+        let offsets: Vec<usize> = scanner.tokens.iter().map(|token| token.offset).collect();
+        assert_eq!(offsets, vec![0, 4, 7, 9, 13, 15, 17]);
+    }
+
+    #[test]
+    fn keeps_comments_when_requested() {
+        let content = "// first\nlet a = \"// not a comment\"; // trailing";
+        let scanner = Scanner::with_comments(content, true).unwrap();
+
+        let expected = vec![
+            (TokenType::Comment, " first".to_string(), 1, 1),
+            (TokenType::Let, "let".to_string(), 2, 1),
+            (TokenType::Identifier, "a".to_string(), 2, 5),
+            (TokenType::Equal, "=".to_string(), 2, 7),
+            (TokenType::String, "// not a comment".to_string(), 2, 9),
+            (TokenType::SemiColon, ";".to_string(), 2, 27),
+            (TokenType::Comment, " trailing".to_string(), 2, 29),
+        ];
+        assert_expected_tokens(scanner, expected);
+    }
+
+    #[test]
+    fn records_blank_lines_along_with_comments() {
+        let content = "\nlet a = 1;\n  \n\n// note\n\t\nlet b = \"\"\"\n\n\"\"\";";
+        assert_eq!(
+            Scanner::with_comments(content, true).unwrap().blank_lines,
+            vec![1, 3, 4, 6]
+        );
+        assert!(Scanner::new(content).unwrap().blank_lines.is_empty());
+    }
+
+    #[test]
+    fn classifies_every_byte_of_the_source() {
+        let content = "let café = \"hé\" @ 2.5; // note\n{ x <= 1 }";
+        let spans = Scanner::classify(content);
+
+        let mut offset = 0;
+        for span in spans.iter() {
+            assert_eq!(span.start_offset, offset, "{:#?}", spans);
+            assert!(span.len > 0, "{:#?}", spans);
+            offset += span.len;
+        }
+        assert_eq!(offset, content.len());
+
+        let classes: Vec<(&str, TokenClass)> = spans
+            .iter()
+            .filter(|span| span.class != TokenClass::Whitespace)
+            .map(|span| {
+                (
+                    &content[span.start_offset..span.start_offset + span.len],
+                    span.class,
+                )
+            })
+            .collect();
+        assert_eq!(
+            classes,
+            vec![
+                ("let", TokenClass::Keyword),
+                ("café", TokenClass::Identifier),
+                ("=", TokenClass::Operator),
+                ("\"hé\"", TokenClass::String),
+                ("@", TokenClass::Error),
+                ("2.5", TokenClass::Number),
+                (";", TokenClass::Punctuation),
+                ("// note", TokenClass::Comment),
+                ("{", TokenClass::Punctuation),
+                ("x", TokenClass::Identifier),
+                ("<=", TokenClass::Operator),
+                ("1", TokenClass::Number),
+                ("}", TokenClass::Punctuation),
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_unterminated_strings_as_errors() {
+        let content = "a \"open";
+        let spans = Scanner::classify(content);
+
+        assert_eq!(
+            spans.last().unwrap(),
+            &ClassifiedSpan {
+                start_offset: 2,
+                len: 5,
+                class: TokenClass::Error
+            }
+        );
+    }
+
+    #[test]
+    fn classifies_unterminated_single_quoted_strings_as_one_error() {
+        let spans = Scanner::classify("print 'it is open;");
+
+        assert_eq!(
+            spans.last().unwrap(),
+            &ClassifiedSpan {
+                start_offset: 6,
+                len: 12,
+                class: TokenClass::Error
+            }
+        );
+        assert_eq!(
+            spans
+                .iter()
+                .filter(|span| span.class == TokenClass::Error)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn classifies_names_starting_with_digits_as_one_error() {
+        let spans = Scanner::classify("1 + 2fast;");
+
+        assert_eq!(
+            spans[4],
+            ClassifiedSpan {
+                start_offset: 4,
+                len: 5,
+                class: TokenClass::Error
+            }
+        );
+        assert_eq!(spans[5].class, TokenClass::Punctuation);
+    }
+
+    type Scanned = Result<Vec<(TokenType, String, usize, usize, usize)>, String>;
+
+    fn summary(tokens: &[Token]) -> Vec<(TokenType, String, usize, usize, usize)> {
+        tokens
+            .iter()
+            .map(|t| {
+                (
+                    t._type.clone(),
+                    t.lexeme.clone(),
+                    t.line,
+                    t.column,
+                    t.offset,
+                )
+            })
+            .collect()
+    }
+
+    fn scan_in_pieces(pieces: &[&str]) -> Scanned {
+        let mut scanner = Scanner::incremental(LanguageVersion::default());
+        let mut tokens = Vec::new();
+        for piece in pieces {
+            tokens.extend(summary(scanner.resume(piece).map_err(|e| e.to_string())?));
+        }
+        tokens.extend(summary(scanner.finish().map_err(|e| e.to_string())?));
+        Ok(tokens)
+    }
+
+    #[test]
+    fn resuming_matches_scanning_everything_at_once() {
+        const FRAGMENTS: [&str; 24] = [
+            "let", " ", "\n", "a", "1", ".", "5", "\"", "${", "}", "{", "=", "/", "//", "?", "é",
+            "|", "&", "<", "!", ";", "x2", "\r\n", "@",
+        ];
+        // xorshift, the cases only need to be varied and reproducible
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % bound
+        };
+
+        for _ in 0..2000 {
+            let source: String = (0..random(16))
+                .map(|_| FRAGMENTS[random(FRAGMENTS.len())])
+                .collect();
+            let mut pieces = Vec::new();
+            let mut rest = source.as_str();
+            while !rest.is_empty() {
+                let mut split = random(rest.len() + 1);
+                while !rest.is_char_boundary(split) {
+                    split += 1;
+                }
+                let (piece, tail) = rest.split_at(split);
+                pieces.push(piece);
+                rest = tail;
+            }
+
+            let whole: Scanned = Scanner::new(&source)
+                .map(|scanner| summary(&scanner.tokens))
+                .map_err(|e| e.to_string());
+            assert_eq!(scan_in_pieces(&pieces), whole, "{:?}", pieces);
+        }
+    }
+
+    #[test]
+    fn strings_continue_into_the_next_piece() {
+        let mut scanner = Scanner::incremental(LanguageVersion::default());
+
+        let tokens = scanner.resume("a = \"\"\"first\n").unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert!(scanner.is_incomplete());
+        assert!(scanner.resume("second\"").unwrap().is_empty());
+        let tokens = summary(scanner.resume("\"\" + 1;\n").unwrap());
+        assert_eq!(
+            tokens[0],
+            (TokenType::String, "first\nsecond".into(), 1, 5, 4)
+        );
+        assert_eq!(tokens.len(), 4);
+        assert!(!scanner.is_incomplete());
+
+        let mut scanner = Scanner::incremental(LanguageVersion::default());
+        assert_eq!(
+            scanner.resume("a = \"first\n").unwrap_err().to_string(),
+            "scan error[S007] at 1:11; newline in string, close the quote or use `\"\"\"` for a \
+             multiline string"
+        );
+    }
+
+    #[test]
+    fn interpolations_and_operators_continue_into_the_next_piece() {
+        assert_eq!(
+            scan_in_pieces(&["\"a ${", "{ 1 =", "= 1 }", "}\"; x ?", "? y"]),
+            scan_in_pieces(&["\"a ${{ 1 == 1 }}\"; x ?? y"])
+        );
+
+        let mut scanner = Scanner::incremental(LanguageVersion::default());
+        scanner.resume("\"${1}").unwrap();
+        assert!(scanner.is_incomplete());
+        assert_eq!(
+            scanner.finish().unwrap_err().msg,
+            "unterminated string missing `\"`"
+        );
+    }
+
+    #[test]
+    fn errors_are_reported_once_their_token_is_complete() {
+        let mut scanner = Scanner::incremental(LanguageVersion::default());
+
+        assert!(scanner.resume("a;\n2f").unwrap().len() == 2);
+        let e = scanner.resume("ast + 1;").unwrap_err();
+        assert_eq!(
+            (e.line, e.column, e.code),
+            (2, 1, codes::DIGIT_STARTED_IDENTIFIER)
+        );
+
+        let mut scanner = Scanner::incremental(LanguageVersion::default());
+        assert_eq!(
+            scanner.resume("@ 1").unwrap_err().msg,
+            "unknown character `@`"
+        );
+    }
 }
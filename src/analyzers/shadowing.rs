@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use crate::errors::{codes, Warning};
+use crate::types::{Statement, Token};
+
+/// Finds declarations in a block hiding a variable declared in an enclosing
+/// block or at the top level, such as `let a = 1; { let a = 2; }`
+///
+/// Warnings point at the inner declaration and give the location of the
+/// outer one. Declaring a name again in the same scope replaces the variable
+/// rather than shadowing it and isn't reported, neither are names starting
+/// with `_`.
+pub fn shadowed_variables(stmts: &[Statement]) -> Vec<Warning> {
+    let mut finder = ShadowFinder {
+        scopes: vec![HashMap::new()],
+        warnings: Vec::new(),
+    };
+    finder.statements(stmts);
+    finder.warnings
+}
+
+struct ShadowFinder {
+    /// Declarations visible in every scope
+    scopes: Vec<HashMap<String, Token>>,
+    warnings: Vec<Warning>,
+}
+
+impl ShadowFinder {
+    fn statements(&mut self, stmts: &[Statement]) {
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Assign(name, _) => self.declare(name),
+            Statement::Block(_, stmts) => {
+                self.scopes.push(HashMap::new());
+                self.statements(stmts);
+                self.scopes.pop();
+            }
+            Statement::Attempt(body, name, handler) => {
+                self.statement(body);
+                self.scopes.push(HashMap::new());
+                self.declare(name);
+                self.statement(handler);
+                self.scopes.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let (scope, outer) = self.scopes.split_last_mut().unwrap();
+        if !name.lexeme.starts_with('_') && !scope.contains_key(&name.lexeme) {
+            let shadowed = outer.iter().rev().find_map(|scope| scope.get(&name.lexeme));
+            if let Some(shadowed) = shadowed {
+                self.warnings.push(Warning::new(
+                    codes::SHADOWED_VARIABLE,
+                    &[&name.lexeme, &shadowed.line, &shadowed.column],
+                    name.line,
+                    name.column,
+                ));
+            }
+        }
+        scope.insert(name.lexeme.clone(), name.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::{Parser, Scanner};
+
+    fn shadowed(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        let statements = Parser::new(tokens, true).parse().unwrap();
+        shadowed_variables(&statements)
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn inner_declarations_report_both_locations() {
+        assert_eq!(
+            shadowed("let a = 1;\n{\n  let b = 2;\n  { let a = 3; let b = 4; }\n}"),
+            vec![
+                "warning[W007]: `a` shadows the variable declared at line 1 column 5 at line 4 column 9",
+                "warning[W007]: `b` shadows the variable declared at line 3 column 7 at line 4 column 20",
+            ]
+        );
+        assert_eq!(
+            shadowed("let e = 1;\nattempt { } handle (e) { }"),
+            vec!["warning[W007]: `e` shadows the variable declared at line 1 column 5 at line 2 column 21"]
+        );
+    }
+
+    #[test]
+    fn redeclarations_and_sibling_blocks_are_not_shadowing() {
+        assert!(shadowed("let a = 1; let a = 2;\n{ let b = 1; }\n{ let b = 2; }").is_empty());
+        assert!(shadowed("{ let a = 1; }\nlet a = 2;\nlet _c = 1; { let _c = 2; }").is_empty());
+    }
+}
@@ -0,0 +1,311 @@
+use crate::types::{Expression, Span, Statement, Token, TokenType};
+
+/// Declarations of a program and every use of them, see [index_symbols]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolIndex {
+    /// Declarations in source order
+    pub symbols: Vec<Symbol>,
+    /// Uses of names with no declaration visible, in source order
+    pub unresolved: Vec<Usage>,
+}
+
+/// Variable declared by a `let` or by the handler of an attempt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub declaration: Span,
+    /// Position of the scope declaring the variable, the index of every
+    /// enclosing scope among the scopes opened directly in its parent,
+    /// empty for the top level
+    pub scope: Vec<usize>,
+    /// Places the value is read, in source order
+    pub reads: Vec<Span>,
+    /// Places a value is assigned, in source order
+    pub writes: Vec<Span>,
+}
+
+impl Symbol {
+    /// Number of blocks enclosing the declaration
+    pub fn depth(&self) -> usize {
+        self.scope.len()
+    }
+}
+
+/// Use of a name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+    pub name: String,
+    pub span: Span,
+    /// Assignment rather than a read
+    pub write: bool,
+}
+
+/// Lists the declarations of the program with the places they are used
+///
+/// Uses resolve like they do when the program runs: to the innermost
+/// declaration visible at that point, so a name used in a block before the
+/// block declares it resolves to the enclosing scope. Every `let` is a
+/// symbol of its own, a redeclaration in the same scope takes over the uses
+/// that follow it. The index only depends on the structure of the program,
+/// indexing an unchanged program gives the same result.
+///
+/// ## Examples
+/// ```rust
+/// use lox::{index_symbols, Parser, Scanner};
+///
+/// let tokens = Scanner::new("let a = 1; { let a = 2; a = a + 1; } b;").unwrap().tokens;
+/// let index = index_symbols(&Parser::new(tokens, true).parse().unwrap());
+///
+/// assert_eq!(index.symbols.len(), 2);
+/// assert_eq!(index.symbols[1].scope, vec![0]);
+/// assert_eq!((index.symbols[1].reads.len(), index.symbols[1].writes.len()), (1, 1));
+/// assert_eq!(index.unresolved[0].name, "b");
+/// ```
+pub fn index_symbols(stmts: &[Statement]) -> SymbolIndex {
+    let mut indexer = Indexer {
+        index: SymbolIndex::default(),
+        scopes: vec![Scope::default()],
+        path: Vec::new(),
+    };
+    indexer.statements(stmts);
+
+    // Assigned values are visited before the names they are assigned to
+    let mut index = indexer.index;
+    for symbol in &mut index.symbols {
+        symbol.reads.sort_by_key(|span| span.offset);
+        symbol.writes.sort_by_key(|span| span.offset);
+    }
+    index.unresolved.sort_by_key(|usage| usage.span.offset);
+    index
+}
+
+#[derive(Default)]
+struct Scope {
+    /// Names declared so far with the index of their symbol
+    declared: Vec<(String, usize)>,
+    /// Scopes opened in this one so far
+    children: usize,
+}
+
+struct Indexer {
+    index: SymbolIndex,
+    scopes: Vec<Scope>,
+    /// Index of every open scope among its siblings
+    path: Vec<usize>,
+}
+
+impl Indexer {
+    fn statements(&mut self, stmts: &[Statement]) {
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Expression(expr)
+            | Statement::Variable(expr)
+            | Statement::Exit(_, expr)
+            | Statement::Print(_, expr) => self.expression(expr),
+            Statement::Assert(_, condition, message) => {
+                self.expression(condition);
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+            }
+            Statement::Assign(name, initializer) => {
+                // The initializer still sees the previous declaration
+                self.expression(initializer);
+                self.declare(name);
+            }
+            Statement::Block(_, stmts) => {
+                self.enter_scope();
+                self.statements(stmts);
+                self.leave_scope();
+            }
+            Statement::Attempt(body, name, handler) => {
+                self.statement(body);
+                // The name is declared in a scope of its own around the handler
+                self.enter_scope();
+                self.declare(name);
+                self.statement(handler);
+                self.leave_scope();
+            }
+            Statement::Empty(_) => {}
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Variable(token) => self.usage(token, false),
+            Expression::Assignment(token, expr) => {
+                // The value is evaluated before it is assigned
+                self.expression(expr);
+                self.usage(token, true);
+            }
+            Expression::Unary(_, expr) | Expression::Grouping(expr) => self.expression(expr),
+            Expression::Binary(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Interpolation(parts) => {
+                for part in parts {
+                    self.expression(part);
+                }
+            }
+            Expression::Call(callee, _, arguments) => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Expression::Literal(_) | Expression::This(_) | Expression::Super(_, _) => {}
+        }
+    }
+
+    fn enter_scope(&mut self) {
+        let parent = self.scopes.last_mut().unwrap();
+        self.path.push(parent.children);
+        parent.children += 1;
+        self.scopes.push(Scope::default());
+    }
+
+    fn leave_scope(&mut self) {
+        self.scopes.pop();
+        self.path.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let symbol = self.index.symbols.len();
+        self.index.symbols.push(Symbol {
+            name: name.lexeme.clone(),
+            declaration: name.span(),
+            scope: self.path.clone(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+        });
+
+        let declared = &mut self.scopes.last_mut().unwrap().declared;
+        match declared
+            .iter_mut()
+            .find(|(declared, _)| *declared == name.lexeme)
+        {
+            Some((_, index)) => *index = symbol,
+            None => declared.push((name.lexeme.clone(), symbol)),
+        }
+    }
+
+    fn usage(&mut self, token: &Token, write: bool) {
+        if token._type != TokenType::Identifier {
+            return;
+        }
+
+        let symbol = self.scopes.iter().rev().find_map(|scope| {
+            scope
+                .declared
+                .iter()
+                .find(|(name, _)| *name == token.lexeme)
+                .map(|&(_, symbol)| symbol)
+        });
+        match symbol {
+            Some(symbol) if write => self.index.symbols[symbol].writes.push(token.span()),
+            Some(symbol) => self.index.symbols[symbol].reads.push(token.span()),
+            None => self.index.unresolved.push(Usage {
+                name: token.lexeme.clone(),
+                span: token.span(),
+                write,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::{Parser, Scanner};
+
+    fn index(source: &str) -> SymbolIndex {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        index_symbols(&Parser::new(tokens, true).parse().unwrap())
+    }
+
+    fn span(offset: usize, len: usize) -> Span {
+        Span { offset, len }
+    }
+
+    #[test]
+    fn shadowed_names_are_separate_symbols() {
+        let source = "let a = 1;\n{ let a = a + 1; { a; } }\n{ let a = 3; }\na;";
+        let index = index(source);
+
+        let scopes: Vec<(&str, Vec<usize>)> = index
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.scope.clone()))
+            .collect();
+        assert_eq!(scopes, vec![("a", vec![]), ("a", vec![0]), ("a", vec![1])]);
+        // The initializer reads the outer `a`, the nested block the inner one
+        assert_eq!(index.symbols[0].reads, vec![span(21, 1), span(52, 1)]);
+        assert_eq!(index.symbols[1].reads, vec![span(30, 1)]);
+        assert!(index.symbols[2].reads.is_empty());
+        assert_eq!(index.symbols[1].depth(), 1);
+    }
+
+    #[test]
+    fn writes_are_told_apart_from_reads() {
+        let index = index("let total = 0;\n{ total = 1; total = total + 2; }");
+
+        let total = &index.symbols[0];
+        assert_eq!(total.writes, vec![span(17, 5), span(28, 5)]);
+        assert_eq!(total.reads, vec![span(36, 5)]);
+
+        let index = index_symbols(&[]);
+        assert!(index.symbols.is_empty() && index.unresolved.is_empty());
+    }
+
+    #[test]
+    fn undeclared_names_are_unresolved() {
+        let index = index("{ b; let b = 1; }\nmissing = b;");
+
+        assert_eq!(
+            index.unresolved,
+            vec![
+                Usage {
+                    name: "b".into(),
+                    span: span(2, 1),
+                    write: false
+                },
+                Usage {
+                    name: "missing".into(),
+                    span: span(18, 7),
+                    write: true
+                },
+                Usage {
+                    name: "b".into(),
+                    span: span(28, 1),
+                    write: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn redeclarations_take_over_later_uses() {
+        let index = index("let a = 1;\na;\nlet a = a;\na;");
+
+        assert_eq!(index.symbols.len(), 2);
+        assert_eq!(index.symbols[0].reads, vec![span(11, 1), span(22, 1)]);
+        assert_eq!(index.symbols[1].reads, vec![span(25, 1)]);
+    }
+
+    #[test]
+    fn handler_names_are_scoped_to_the_handler() {
+        let index = index("{ }\nattempt { 1; } handle (err) { print err; }\nerr;");
+
+        let err = &index.symbols[0];
+        assert_eq!(err.name, "err");
+        assert_eq!(err.scope, vec![2]);
+        assert_eq!(err.reads.len(), 1);
+        assert_eq!(index.unresolved.len(), 1);
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use crate::errors::{codes, Warning};
+use crate::types::{Expression, Statement, Token, TokenType};
+
+/// Finds variables that are declared in a block but never read
+///
+/// Assigning to a variable doesn't count as reading it. Variables declared
+/// at the top level are skipped as later code, such as the next line typed at
+/// the prompt, may still use them, and so are names starting with `_`.
+/// Warnings are ordered by the location of the declaration.
+pub fn unused_variables(stmts: &[Statement]) -> Vec<Warning> {
+    let mut finder = UnusedFinder {
+        scopes: vec![HashMap::new()],
+        unused: Vec::new(),
+    };
+    finder.statements(stmts);
+
+    finder.unused.sort_by_key(|token| token.offset);
+    finder
+        .unused
+        .into_iter()
+        .map(|token| {
+            Warning::new(
+                codes::UNUSED_VARIABLE,
+                &[&token.lexeme],
+                token.line,
+                token.column,
+            )
+        })
+        .collect()
+}
+
+/// Finds expression statements in blocks whose value is thrown away without
+/// anything else happening, such as `a == 5;` typed instead of `a = 5;`
+///
+/// Expressions made only of literals, variable reads, groupings, unary and
+/// binary operators and interpolations are reported, assignments and calls
+/// are not. Top-level expression statements are skipped as their value is
+/// written to the output.
+pub fn unused_results(stmts: &[Statement]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for stmt in stmts {
+        block_results(stmt, &mut warnings);
+    }
+    warnings
+}
+
+/// Checks the statements of the block, or of both blocks of an attempt
+fn block_results(stmt: &Statement, warnings: &mut Vec<Warning>) {
+    match stmt {
+        Statement::Block(_, stmts) => discarded_results(stmts, warnings),
+        Statement::Attempt(body, _, handler) => {
+            block_results(body, warnings);
+            block_results(handler, warnings);
+        }
+        _ => {}
+    }
+}
+
+fn discarded_results(stmts: &[Statement], warnings: &mut Vec<Warning>) {
+    for stmt in stmts {
+        match stmt {
+            Statement::Expression(expr) | Statement::Variable(expr) if is_pure(expr) => {
+                let warning = match expr {
+                    Expression::Binary(_, operator, _)
+                        if operator._type == TokenType::EqualEqual =>
+                    {
+                        Warning::new(
+                            codes::UNUSED_COMPARISON,
+                            &[],
+                            operator.line,
+                            operator.column,
+                        )
+                    }
+                    _ => {
+                        let token = expr.leftmost_token();
+                        Warning::new(codes::UNUSED_RESULT, &[], token.line, token.column)
+                    }
+                };
+                warnings.push(warning);
+            }
+            Statement::Block(_, _) | Statement::Attempt(_, _, _) => block_results(stmt, warnings),
+            _ => {}
+        }
+    }
+}
+
+/// Returns true if evaluating the expression can't have side effects
+fn is_pure(expr: &Expression) -> bool {
+    match expr {
+        Expression::Literal(_)
+        | Expression::Variable(_)
+        | Expression::This(_)
+        | Expression::Super(_, _) => true,
+        Expression::Unary(_, expr) | Expression::Grouping(expr) => is_pure(expr),
+        Expression::Binary(left, _, right) => is_pure(left) && is_pure(right),
+        Expression::Interpolation(parts) => parts.iter().all(is_pure),
+        Expression::Assignment(_, _) | Expression::Call(_, _, _) => false,
+    }
+}
+
+struct UnusedFinder {
+    /// Declarations visible in every scope and whether they have been read
+    scopes: Vec<HashMap<String, (Token, bool)>>,
+    unused: Vec<Token>,
+}
+
+impl UnusedFinder {
+    fn statements(&mut self, stmts: &[Statement]) {
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Expression(expr)
+            | Statement::Variable(expr)
+            | Statement::Exit(_, expr)
+            | Statement::Print(_, expr) => self.expression(expr),
+            Statement::Assert(_, condition, message) => {
+                self.expression(condition);
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+            }
+            Statement::Assign(name, initializer) => {
+                self.expression(initializer);
+                let scope = self.scopes.last_mut().unwrap();
+                // Declaring the name again in the same scope hides the first one
+                if let Some(previous) = scope.insert(name.lexeme.clone(), (name.clone(), false)) {
+                    let top_level = self.scopes.len() == 1;
+                    self.report(previous, top_level);
+                }
+            }
+            Statement::Block(_, stmts) => {
+                self.scopes.push(HashMap::new());
+                self.statements(stmts);
+                let scope = self.scopes.pop().unwrap();
+                for declaration in scope.into_values() {
+                    self.report(declaration, false);
+                }
+            }
+            Statement::Attempt(body, name, handler) => {
+                self.statement(body);
+                self.scopes.push(HashMap::from([(
+                    name.lexeme.clone(),
+                    (name.clone(), false),
+                )]));
+                self.statement(handler);
+                let scope = self.scopes.pop().unwrap();
+                for declaration in scope.into_values() {
+                    self.report(declaration, false);
+                }
+            }
+            Statement::Empty(_) => {}
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Variable(token) => self.read(token),
+            Expression::Assignment(_, expr)
+            | Expression::Unary(_, expr)
+            | Expression::Grouping(expr) => self.expression(expr),
+            Expression::Binary(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Interpolation(parts) => {
+                for part in parts {
+                    self.expression(part);
+                }
+            }
+            Expression::Call(callee, _, arguments) => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Expression::Literal(_) | Expression::This(_) | Expression::Super(_, _) => {}
+        }
+    }
+
+    fn read(&mut self, token: &Token) {
+        let declaration = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(&token.lexeme));
+        if let Some((_, used)) = declaration {
+            *used = true;
+        }
+    }
+
+    /// Records the declaration if it was never read
+    fn report(&mut self, (name, used): (Token, bool), top_level: bool) {
+        if !used && !top_level && !name.lexeme.starts_with('_') {
+            self.unused.push(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::{Parser, Scanner};
+
+    fn unused(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        let statements = Parser::new(tokens, true).parse().unwrap();
+        unused_variables(&statements)
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn unused_locals_are_reported() {
+        assert_eq!(
+            unused("{ let result = 1 + 2; let other = 3; other; }"),
+            vec!["warning[W001]: unused variable `result` at line 1 column 7"]
+        );
+    }
+
+    #[test]
+    fn assignments_are_not_reads() {
+        assert_eq!(
+            unused("{ let a = 1; a = 2; let b = 0; b = b + 1; }"),
+            vec!["warning[W001]: unused variable `a` at line 1 column 7"]
+        );
+    }
+
+    #[test]
+    fn reads_in_nested_blocks_count() {
+        let source = "{ let a = 1; let b = 2; { { a + 1; } \"${b}\"; } }";
+
+        assert_eq!(unused(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn top_level_and_underscore_names_are_skipped() {
+        let source = "let a = 1; { let _b = 2; let _ = 3; }";
+
+        assert_eq!(unused(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn unused_error_names_are_reported() {
+        let source = "attempt { let a = 1; a == 2; } handle (err) { print 1; }\n\
+            attempt { } handle (error) { print error; }";
+
+        assert_eq!(
+            unused(source),
+            vec!["warning[W001]: unused variable `err` at line 1 column 40"]
+        );
+        assert_eq!(
+            results(source),
+            vec![
+                "warning[W002]: expression result is unused; did you mean `=`? at line 1 column 24"
+            ]
+        );
+    }
+
+    fn results(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        let statements = Parser::new(tokens, true).parse().unwrap();
+        unused_results(&statements)
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn discarded_comparisons_suggest_assignment() {
+        assert_eq!(
+            results("let a = 1;\n{\n  a == 5;\n}"),
+            vec![
+                "warning[W002]: expression result is unused; did you mean `=`? at line 3 column 5"
+            ]
+        );
+    }
+
+    #[test]
+    fn discarded_values_in_blocks_are_reported() {
+        let source = "{ 1; { -a; \"${a}\"; } (a + 1) * 2; }";
+
+        assert_eq!(
+            results(source),
+            vec![
+                "warning[W003]: expression result is unused at line 1 column 3",
+                "warning[W003]: expression result is unused at line 1 column 8",
+                "warning[W003]: expression result is unused at line 1 column 12",
+                "warning[W003]: expression result is unused at line 1 column 23",
+            ]
+        );
+    }
+
+    #[test]
+    fn assignments_calls_and_top_level_values_are_used() {
+        let source = "let a = 1; a == 5; { a = 2; len(\"a\"); (b = a) == 2; }";
+
+        assert_eq!(results(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn shadowed_declarations_are_reported_at_their_location() {
+        let source = "{\n  let a = 1;\n  { let a = 2; a; }\n  let b = 1;\n  let b = b;\n}";
+
+        assert_eq!(
+            unused(source),
+            vec![
+                "warning[W001]: unused variable `a` at line 2 column 7",
+                "warning[W001]: unused variable `b` at line 5 column 7",
+            ]
+        );
+    }
+}
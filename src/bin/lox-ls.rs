@@ -0,0 +1,19 @@
+use lox::lsp::{read_message, write_message, Session};
+use std::io;
+use std::process::exit;
+
+fn main() -> io::Result<()> {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    let mut session = Session::new();
+
+    while let Some(message) = read_message(&mut input)? {
+        for reply in session.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+        if session.has_exited() {
+            break;
+        }
+    }
+    exit(session.exit_code())
+}
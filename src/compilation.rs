@@ -0,0 +1,359 @@
+use std::cell::OnceCell;
+use std::fmt;
+
+use crate::analyzers::lints::{self, LintConfig};
+use crate::analyzers::{index_symbols, LineComment, Parser, Scanner, SymbolIndex, Trivia};
+use crate::errors::codes::ErrorCode;
+use crate::errors::{InterpreterErrorKind, ParserError, ScanError, Warning};
+use crate::{ClassifiedSpan, LanguageVersion, LocationInfo, Statement, Token};
+
+/// How [Compilation::analyze] reads the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnalyzeOptions {
+    pub version: LanguageVersion,
+    /// Statements must end with `;`, as in scripts but not at the prompt
+    pub strict: bool,
+    /// Comments and blank lines are kept around the statements, see
+    /// [Compilation::trivia]. Parsing then stops at the first error.
+    pub trivia: bool,
+    /// Levels of the lints, before those set by a `// lox-lint:` comment in
+    /// the source
+    pub lints: LintConfig,
+}
+
+impl Default for AnalyzeOptions {
+    fn default() -> Self {
+        Self::new(LanguageVersion::default(), true)
+    }
+}
+
+impl AnalyzeOptions {
+    pub fn new(version: LanguageVersion, strict: bool) -> Self {
+        Self {
+            version,
+            strict,
+            trivia: false,
+            lints: LintConfig::default(),
+        }
+    }
+
+    pub fn with_lints(mut self, lints: LintConfig) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    pub fn with_trivia(mut self, trivia: bool) -> Self {
+        self.trivia = trivia;
+        self
+    }
+}
+
+/// Scan error, parse error or lint warning found by a [Compilation]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// [Scan](InterpreterErrorKind::Scan), [Parse](InterpreterErrorKind::Parse)
+    /// or [Lint](InterpreterErrorKind::Lint)
+    pub kind: InterpreterErrorKind,
+    /// Missing for an invalid `// lox-lint:` comment
+    pub code: Option<ErrorCode>,
+    /// Message without the code and location
+    pub msg: String,
+    /// Missing for an invalid `// lox-lint:` comment
+    pub location: Option<LocationInfo>,
+    /// The error or warning as it's printed on its own
+    text: String,
+}
+
+impl Finding {
+    fn scan(error: ScanError) -> Self {
+        Self {
+            kind: InterpreterErrorKind::Scan,
+            code: Some(error.code),
+            location: Some(error.location()),
+            text: error.to_string(),
+            msg: error.msg,
+        }
+    }
+
+    fn parse(error: ParserError) -> Self {
+        Self {
+            kind: InterpreterErrorKind::Parse,
+            code: Some(error.code()),
+            msg: error.msg().into(),
+            location: Some(error.location()),
+            text: error.to_string(),
+        }
+    }
+
+    fn lint(warning: &Warning) -> Self {
+        Self {
+            kind: InterpreterErrorKind::Lint,
+            code: Some(warning.code),
+            msg: warning.msg.clone(),
+            location: Some(LocationInfo::new(warning.line, warning.column, 0)),
+            text: warning.to_string(),
+        }
+    }
+
+    fn lint_comment(msg: String) -> Self {
+        Self {
+            kind: InterpreterErrorKind::Lint,
+            code: None,
+            text: msg.clone(),
+            msg,
+            location: None,
+        }
+    }
+
+    /// Whether it's a lint warning rather than a problem keeping the program
+    /// from being analyzed as written
+    pub fn is_warning(&self) -> bool {
+        self.kind == InterpreterErrorKind::Lint && self.code.is_some()
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Everything known about a source without running it, for tools that would
+/// otherwise each scan and parse it again
+///
+/// The source is scanned, parsed and linted once when analyzed, later
+/// stages are skipped when an earlier one fails, and the derived data such
+/// as the [symbols](Compilation::symbols) is only computed the first time
+/// it's asked for.
+///
+/// ## Examples
+/// ```rust
+/// use lox::{AnalyzeOptions, Compilation};
+///
+/// let compilation = Compilation::analyze("print b;\nlet = 1;", AnalyzeOptions::default());
+///
+/// assert_eq!(compilation.statements().len(), 1);
+/// assert_eq!(compilation.diagnostics()[0].msg, "expected an identifier");
+/// assert!(!compilation.is_valid());
+/// assert_eq!(compilation.symbols().unresolved[0].name, "b");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Compilation {
+    source: String,
+    tokens: Vec<Token>,
+    statements: Vec<Statement>,
+    trivia: Vec<Trivia>,
+    closing: Vec<LineComment>,
+    warnings: Vec<Warning>,
+    diagnostics: Vec<Finding>,
+    symbols: OnceCell<SymbolIndex>,
+    spans: OnceCell<Vec<ClassifiedSpan>>,
+}
+
+impl Compilation {
+    /// Scans, parses and lints the source, collecting every problem found
+    /// instead of failing
+    pub fn analyze(source: &str, options: AnalyzeOptions) -> Self {
+        let mut compilation = Self {
+            source: source.into(),
+            tokens: Vec::new(),
+            statements: Vec::new(),
+            trivia: Vec::new(),
+            closing: Vec::new(),
+            warnings: Vec::new(),
+            diagnostics: Vec::new(),
+            symbols: OnceCell::new(),
+            spans: OnceCell::new(),
+        };
+
+        let scanner =
+            match Scanner::with_comments_and_version(source, options.trivia, options.version) {
+                Ok(scanner) => scanner,
+                Err(e) => {
+                    compilation.diagnostics.push(Finding::scan(e));
+                    return compilation;
+                }
+            };
+        compilation.tokens = scanner.tokens.clone();
+
+        if options.trivia {
+            let mut parser = Parser::with_trivia_and_version(
+                scanner.tokens,
+                scanner.blank_lines,
+                options.strict,
+                options.version,
+            );
+            match parser.parse_with_trivia() {
+                Ok((statements, closing)) => {
+                    (compilation.statements, compilation.trivia) = statements.into_iter().unzip();
+                    compilation.closing = closing;
+                }
+                Err(e) => compilation.diagnostics.push(Finding::parse(e)),
+            }
+        } else {
+            let (statements, errors) =
+                Parser::with_version(scanner.tokens, options.strict, options.version)
+                    .parse_recovering();
+            compilation.statements = statements;
+            compilation
+                .diagnostics
+                .extend(errors.into_iter().map(Finding::parse));
+        }
+        if !compilation.diagnostics.is_empty() {
+            return compilation;
+        }
+
+        // An invalid `lox-lint` comment leaves the levels of the options
+        let mut config = options.lints.clone();
+        if let Err(msg) = config.apply_comment(source) {
+            compilation.diagnostics.push(Finding::lint_comment(msg));
+            config = options.lints;
+        }
+        compilation.warnings = lints::run(&compilation.statements, &config);
+        compilation
+            .diagnostics
+            .extend(compilation.warnings.iter().map(Finding::lint));
+        compilation
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Tokens of the source, with its comments when the trivia is kept, or
+    /// none if it doesn't scan
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Statements that parsed, only fit to be executed when the compilation
+    /// [is valid](Compilation::is_valid)
+    pub fn statements(&self) -> &[Statement] {
+        &self.statements
+    }
+
+    /// Comments and blank lines around each of the statements, none unless
+    /// [AnalyzeOptions::trivia] is set
+    pub fn trivia(&self) -> &[Trivia] {
+        &self.trivia
+    }
+
+    /// Comments following the last statement when the trivia is kept
+    pub fn closing_comments(&self) -> &[LineComment] {
+        &self.closing
+    }
+
+    /// Scan and parse errors, or the lint warnings of a program that
+    /// parses, in source order
+    pub fn diagnostics(&self) -> &[Finding] {
+        &self.diagnostics
+    }
+
+    /// Lint warnings, with the level of their lint
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Whether the source scans and parses with nothing but lint warnings
+    pub fn is_valid(&self) -> bool {
+        self.diagnostics.iter().all(Finding::is_warning)
+    }
+
+    /// Declarations and usages of the variables of the statements
+    pub fn symbols(&self) -> &SymbolIndex {
+        self.symbols.get_or_init(|| index_symbols(&self.statements))
+    }
+
+    /// Highlighting classes of the source, see
+    /// [Scanner::classify](crate::Scanner::classify)
+    pub fn spans(&self) -> &[ClassifiedSpan] {
+        self.spans.get_or_init(|| Scanner::classify(&self.source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::codes;
+    use crate::TokenClass;
+
+    fn analyze(source: &str) -> Compilation {
+        Compilation::analyze(source, AnalyzeOptions::default())
+    }
+
+    #[test]
+    fn problems_of_each_stage_are_diagnosed() {
+        let scan = analyze("let a = 1;\nlet b = \"open;");
+        assert!(scan.tokens().is_empty() && scan.statements().is_empty());
+        assert_eq!(scan.diagnostics().len(), 1);
+        assert_eq!(scan.diagnostics()[0].kind, InterpreterErrorKind::Scan);
+        assert_eq!(
+            scan.diagnostics()[0].location,
+            Some(LocationInfo::new(2, 9, 0))
+        );
+
+        let parse = analyze("let = 1;\nprint 2;\nlet a 3;");
+        let found: Vec<(Option<ErrorCode>, String)> = parse
+            .diagnostics()
+            .iter()
+            .map(|finding| (finding.code, finding.to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Some(codes::EXPECTED_IDENTIFIER),
+                    "runtime exception[P002]: expected an identifier at line 1 column 5".into()
+                ),
+                (
+                    Some(codes::EXPECTED_TOKEN),
+                    "runtime exception[P001]: expected `=` at line 3 column 7".into()
+                ),
+            ]
+        );
+        assert_eq!(parse.statements().len(), 1);
+        assert!(parse.warnings().is_empty());
+        assert!(!parse.is_valid());
+
+        let lint = analyze("// lox-lint: deny(shadowing\n{ let a = 1; }");
+        assert_eq!(lint.diagnostics().len(), 2);
+        assert_eq!(
+            lint.diagnostics()[0].to_string(),
+            "invalid `lox-lint` comment `// lox-lint: deny(shadowing`"
+        );
+        assert_eq!(lint.diagnostics()[1].msg, "unused variable `a`");
+        assert!(lint.diagnostics()[1].is_warning());
+        assert_eq!(lint.warnings().len(), 1);
+        assert!(!lint.is_valid());
+
+        let valid = analyze("{ let a = 1; }");
+        assert_eq!(valid.tokens().len(), 7);
+        assert!(valid.is_valid());
+    }
+
+    #[test]
+    fn trivia_is_kept_when_asked_for() {
+        let source = "// one\nlet a = 1; // two\n// end";
+        let compilation = Compilation::analyze(source, AnalyzeOptions::default().with_trivia(true));
+
+        assert!(compilation.is_valid());
+        assert_eq!(compilation.trivia().len(), compilation.statements().len());
+        assert_eq!(compilation.trivia()[0].leading[0].token.lexeme, " one");
+        assert_eq!(compilation.closing_comments()[0].token.lexeme, " end");
+        assert!(analyze(source).trivia().is_empty());
+    }
+
+    #[test]
+    fn derived_data_is_computed_once() {
+        let compilation = analyze("let a = 1;\na = b;");
+
+        let symbols = compilation.symbols();
+        assert_eq!(symbols.symbols[0].writes.len(), 1);
+        assert_eq!(symbols.unresolved[0].name, "b");
+        assert!(std::ptr::eq(symbols, compilation.symbols()));
+
+        let spans = compilation.spans();
+        assert_eq!(spans[0].class, TokenClass::Keyword);
+        assert!(std::ptr::eq(spans, compilation.spans()));
+    }
+}
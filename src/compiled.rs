@@ -0,0 +1,265 @@
+use crate::analyzers::{Parser, Scanner};
+use crate::errors::{codes, EvaluationError, InterpreterError, ParserError};
+use crate::types::expression::Context;
+use crate::types::{Expression, Token};
+use crate::{Counters, Environment, Literal, Native};
+
+/// Expression parsed once and evaluated any number of times, such as a
+/// formula evaluated against the same variables on every tick
+///
+/// Evaluating doesn't need an [Interpreter](crate::Interpreter) and only
+/// reads the environment, so a compiled expression can be shared between
+/// threads and evaluated by all of them at once. Unlike in programs,
+/// reading an undefined variable is an error and assignments are rejected
+/// when compiling.
+///
+/// ## Examples
+/// ```rust
+/// use lox::{CompiledExpression, Environment, Literal};
+///
+/// let formula = CompiledExpression::compile("price * quantity").unwrap();
+/// let mut env = Environment::new();
+/// env.define("price".into(), Literal::Integer(3));
+/// env.define("quantity".into(), Literal::Integer(4));
+///
+/// assert_eq!(formula.eval(&env).unwrap(), Literal::Integer(12));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompiledExpression {
+    expr: Expression,
+}
+
+impl CompiledExpression {
+    /// Scans and parses a single expression, without a trailing `;`
+    pub fn compile(source: &str) -> Result<Self, InterpreterError> {
+        let tokens = Scanner::new(source)?.tokens;
+        let expr = Parser::new(tokens, true).parse_standalone_expression()?;
+        if let Some(assignment) = find_assignment(&expr) {
+            return Err(ParserError::new(codes::ASSIGNMENT_IN_EXPRESSION, &[], assignment).into());
+        }
+        Ok(Self { expr })
+    }
+
+    /// Evaluates the expression against the variables of `env`
+    ///
+    /// Natives get a copy of the environment's settings, so natives with
+    /// state such as `random` start from the same state on every call, see
+    /// [eval_mut](CompiledExpression::eval_mut).
+    pub fn eval(&self, env: &Environment) -> Result<Literal, EvaluationError> {
+        self.expr.evaluate_in(&mut Formula::Shared(env))
+    }
+
+    /// Same as [eval](CompiledExpression::eval), natives get the environment
+    /// itself so `random` gives a new number every time
+    pub fn eval_mut(&self, env: &mut Environment) -> Result<Literal, EvaluationError> {
+        self.expr.evaluate_in(&mut Formula::Exclusive(env))
+    }
+}
+
+/// Environment a compiled expression is evaluated against
+enum Formula<'a> {
+    Shared(&'a Environment),
+    Exclusive(&'a mut Environment),
+}
+
+impl Context for Formula<'_> {
+    fn lookup(&mut self, name: &str) -> Option<&Literal> {
+        match self {
+            Formula::Shared(env) => env.get(name),
+            Formula::Exclusive(env) => env.lookup(name),
+        }
+    }
+
+    fn assign(&mut self, _: &str, _: Literal) -> bool {
+        unreachable!("assignments are rejected when compiling")
+    }
+
+    fn call(&mut self, native: &Native, arguments: &[Literal]) -> Result<Literal, String> {
+        match self {
+            Formula::Shared(env) => (native.function)(&mut env.detached(), arguments),
+            Formula::Exclusive(env) => (native.function)(env, arguments),
+        }
+    }
+
+    fn environment(&self) -> &Environment {
+        match self {
+            Formula::Shared(env) => env,
+            Formula::Exclusive(env) => env,
+        }
+    }
+
+    fn counters(&mut self) -> Option<&mut Counters> {
+        match self {
+            Formula::Shared(_) => None,
+            Formula::Exclusive(env) => Some(env.counters_mut()),
+        }
+    }
+}
+
+/// Name of the first assignment in the expression
+fn find_assignment(expr: &Expression) -> Option<&Token> {
+    match expr {
+        Expression::Assignment(name, _) => Some(name),
+        Expression::Unary(_, expr) | Expression::Grouping(expr) => find_assignment(expr),
+        Expression::Binary(left, _, right) => {
+            find_assignment(left).or_else(|| find_assignment(right))
+        }
+        Expression::Interpolation(parts) => parts.iter().find_map(find_assignment),
+        Expression::Call(callee, _, arguments) => {
+            find_assignment(callee).or_else(|| arguments.iter().find_map(find_assignment))
+        }
+        Expression::Literal(_)
+        | Expression::Variable(_)
+        | Expression::This(_)
+        | Expression::Super(_, _) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn env() -> Environment {
+        let mut env = Environment::new();
+        crate::stdlib::install(&mut env);
+        env.define("a".into(), Literal::Integer(6));
+        env.define("b".into(), Literal::Number(1.5));
+        env.define("name".into(), Literal::String("lox".into()));
+        env
+    }
+
+    fn eval(source: &str) -> Result<Literal, String> {
+        let compiled = CompiledExpression::compile(source).map_err(|e| e.msg)?;
+        compiled.eval(&env()).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn reads_variables() {
+        assert_eq!(eval("a * 2"), Ok(Literal::Integer(12)));
+        assert_eq!(eval("a + b"), Ok(Literal::Number(7.5)));
+        assert_eq!(eval("max(a, 10) - abs(-1)"), Ok(Literal::Integer(9)));
+        assert_eq!(
+            eval("\"${name} ${a}\""),
+            Ok(Literal::String("lox 6".into()))
+        );
+
+        // Block scopes are visible too
+        let mut env = env();
+        env.enter_block();
+        env.define("a".into(), Literal::Integer(1));
+        let compiled = CompiledExpression::compile("a").unwrap();
+        assert_eq!(compiled.eval(&env).unwrap(), Literal::Integer(1));
+        assert_eq!(compiled.eval_mut(&mut env).unwrap(), Literal::Integer(1));
+    }
+
+    #[test]
+    fn unknown_variables_are_errors() {
+        assert_eq!(
+            eval("a + c"),
+            Err("evaluation error[R001]: undefined variable `c` at line 1 column 5".into())
+        );
+        assert_eq!(
+            eval("c == nil"),
+            Err("evaluation error[R001]: undefined variable `c` at line 1 column 1".into())
+        );
+        assert_eq!(
+            eval("nmae"),
+            Err(
+                "evaluation error[R001]: undefined variable `nmae`; did you mean `name`? at line 1 column 1"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn only_single_expressions_compile() {
+        let scenarios = [
+            (
+                "a = 2",
+                "runtime exception[P009]: assignment not allowed in expression context at line 1 column 1",
+            ),
+            (
+                "max(a, (b = 1))",
+                "runtime exception[P009]: assignment not allowed in expression context at line 1 column 9",
+            ),
+            (
+                "a;",
+                "runtime exception[P005]: expected the end of the expression at line 1 column 2",
+            ),
+            (
+                "a b",
+                "runtime exception[P005]: expected the end of the expression at line 1 column 3",
+            ),
+            ("", "runtime exception[P003]: expected an expression at line 1 column 1"),
+            ("let a = 1", "runtime exception[P011]: `let` is a reserved word and cannot be used as an expression at line 1 column 1"),
+        ];
+
+        for (source, expected) in scenarios {
+            assert_eq!(eval(source), Err(expected.into()), "{}", source);
+        }
+    }
+
+    #[test]
+    fn random_only_advances_with_eval_mut() {
+        let compiled = CompiledExpression::compile("random()").unwrap();
+        let mut env = env();
+
+        assert_eq!(compiled.eval(&env).unwrap(), compiled.eval(&env).unwrap());
+        assert_ne!(
+            compiled.eval_mut(&mut env).unwrap(),
+            compiled.eval_mut(&mut env).unwrap()
+        );
+    }
+
+    #[test]
+    fn compiled_expressions_are_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CompiledExpression>();
+
+        let compiled = Arc::new(CompiledExpression::compile("a * n").unwrap());
+        let handles: Vec<_> = (0..4)
+            .map(|n| {
+                let compiled = Arc::clone(&compiled);
+                thread::spawn(move || {
+                    let mut env = env();
+                    env.define("n".into(), Literal::Integer(n));
+                    compiled.eval(&env)
+                })
+            })
+            .collect();
+
+        for (n, handle) in handles.into_iter().enumerate() {
+            assert_eq!(
+                handle.join().unwrap().unwrap(),
+                Literal::Integer(6 * n as i64)
+            );
+        }
+    }
+
+    #[test]
+    fn evaluating_formulas_only_walks_their_expressions() {
+        let mut env = env();
+        let formulas: Vec<CompiledExpression> = (0..1000)
+            .map(|i| CompiledExpression::compile(&format!("(a + {}) * b - a / 2", i)).unwrap())
+            .collect();
+
+        for _ in 0..10 {
+            for (i, formula) in formulas.iter().enumerate() {
+                let expected = Literal::Number((6 + i) as f32 * 1.5 - 3.0);
+                assert_eq!(formula.eval_mut(&mut env).unwrap(), expected);
+            }
+        }
+        // Ten nodes and three variable reads per evaluation
+        let counters = env.counters();
+        assert_eq!(
+            (
+                counters.expressions,
+                counters.lookups,
+                counters.literal_clones
+            ),
+            (100_000, 30_000, 30_000)
+        );
+    }
+}
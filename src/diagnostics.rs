@@ -0,0 +1,217 @@
+//! Rendering of errors and warnings for a terminal
+//!
+//! Diagnostics are rendered from their messages, such as
+//! `evaluation error[R007]: operand true of `&` is not a number at line 2
+//! column 7`, so anything implementing `Display` in [errors](crate::errors)
+//! can be colored.
+//!
+//! ## Examples
+//! ```rust
+//! use lox::diagnostics::{render, Style};
+//!
+//! let message = "warning[W001]: unused variable `a` at line 1 column 7";
+//! assert_eq!(render(message, Style::Plain), message);
+//! assert_eq!(
+//!     render(message, Style::Colored),
+//!     "\x1b[1;33mwarning[W001]\x1b[0m: unused variable `a` \x1b[1mat line 1 column 7\x1b[0m"
+//! );
+//! ```
+use std::env;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// How diagnostics are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    /// ANSI colors, errors in red, warnings in yellow and locations in bold
+    Colored,
+}
+
+/// When to color diagnostics, parsed from the values of the `--color` flag
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color diagnostics written to a terminal unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Style of diagnostics written to stderr
+    pub fn style(self) -> Style {
+        let colored = match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && io::stderr().is_terminal()
+            }
+        };
+        if colored {
+            Style::Colored
+        } else {
+            Style::Plain
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "unknown color choice `{}`, expected auto, always or never",
+                s
+            )),
+        }
+    }
+}
+
+/// Renders a diagnostic message, every line of it is rendered on its own
+/// so messages listing several errors are colored throughout
+pub fn render(message: &str, style: Style) -> String {
+    match style {
+        Style::Plain => message.into(),
+        Style::Colored => message
+            .split('\n')
+            .map(colored_line)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn colored_line(line: &str) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+
+    // The tag is everything before the first `:` or ` at `, i.e
+    // `runtime exception[P001]` or `scan error[S002]`, and is colored along
+    // with its code
+    let tag_end = [rest.find(':'), rest.find(" at ")]
+        .into_iter()
+        .flatten()
+        .min();
+    if let Some(end) = tag_end {
+        let tag = &rest[..end];
+        let kind = tag.split('[').next().unwrap_or(tag);
+        let color = if kind == "warning" {
+            Some(YELLOW)
+        } else if kind.ends_with("error") || kind.ends_with("exception") {
+            Some(RED)
+        } else {
+            None
+        };
+        if let Some(color) = color {
+            out.push_str(&format!("{}{}{}", color, tag, RESET));
+            rest = &rest[end..];
+        }
+    }
+
+    while let Some(start) = rest.find("at ") {
+        let is_word = start == 0 || rest[..start].ends_with(' ');
+        match location_len(&rest[start..]).filter(|_| is_word) {
+            Some(len) => {
+                out.push_str(&rest[..start]);
+                out.push_str(&format!("{}{}{}", BOLD, &rest[start..start + len], RESET));
+                rest = &rest[start + len..];
+            }
+            None => {
+                out.push_str(&rest[..start + 3]);
+                rest = &rest[start + 3..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Length of the location at the start of `text`, either
+/// `at line 2 column 7` or `at 2:7`
+fn location_len(text: &str) -> Option<usize> {
+    let digits = |text: &str| text.bytes().take_while(u8::is_ascii_digit).count();
+    let rest = text.strip_prefix("at ")?;
+
+    if let Some(line) = rest.strip_prefix("line ") {
+        let line_len = digits(line);
+        let column = line[line_len..].strip_prefix(" column ")?;
+        let column_len = digits(column);
+        return (line_len > 0 && column_len > 0).then(|| text.len() - column.len() + column_len);
+    }
+
+    let line_len = digits(rest);
+    let column = rest[line_len..].strip_prefix(':')?;
+    let column_len = digits(column);
+    (line_len > 0 && column_len > 0).then(|| text.len() - column.len() + column_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACE: &str =
+        "evaluation error[R007]: operand true of `&` is not a number at line 2 column 7
+    in block started at line 1 column 1";
+
+    #[test]
+    fn plain_diagnostics_are_unchanged() {
+        assert_eq!(render(TRACE, Style::Plain), TRACE);
+    }
+
+    #[test]
+    fn tags_and_locations_are_colored() {
+        assert_eq!(
+            render(TRACE, Style::Colored),
+            "\x1b[1;31mevaluation error[R007]\x1b[0m: operand true of `&` is not a number \
+             \x1b[1mat line 2 column 7\x1b[0m
+    in block started \x1b[1mat line 1 column 1\x1b[0m"
+        );
+        assert_eq!(
+            render(
+                "scan error[S002] at 3:14; unterminated string",
+                Style::Colored
+            ),
+            "\x1b[1;31mscan error[S002]\x1b[0m \x1b[1mat 3:14\x1b[0m; unterminated string"
+        );
+        assert_eq!(
+            render(
+                "runtime exception[P001]: expected `;` at line 1 column 2\naborting due to 1 warning",
+                Style::Colored
+            ),
+            "\x1b[1;31mruntime exception[P001]\x1b[0m: expected `;` \x1b[1mat line 1 column 2\x1b[0m
+aborting due to 1 warning"
+        );
+    }
+
+    #[test]
+    fn words_resembling_locations_are_left_alone() {
+        let message =
+            "evaluation error[R011]: `what` is not a number at line x column 1 in generated code";
+
+        assert_eq!(
+            render(message, Style::Colored),
+            "\x1b[1;31mevaluation error[R011]\x1b[0m: `what` is not a number at line x column 1 in generated code"
+        );
+    }
+
+    #[test]
+    fn color_choices_are_parsed() {
+        assert_eq!("always".parse(), Ok(ColorChoice::Always));
+        assert_eq!(
+            "never".parse::<ColorChoice>().unwrap().style(),
+            Style::Plain
+        );
+        assert_eq!(ColorChoice::Always.style(), Style::Colored);
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+    }
+}
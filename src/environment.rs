@@ -0,0 +1,871 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::errors::{codes, Warning};
+use crate::stdlib::{Clock, Rng};
+use crate::{Literal, Token};
+
+/// Variables visible to a lox program along with the settings natives read
+///
+/// Variables live in a stack of scopes, the global scope at the bottom and
+/// one scope for every block entered on top of it. Lookups and assignments
+/// go to the innermost scope declaring the name, so declarations in a block
+/// shadow the ones outside of it until the block is left. Scopes remember
+/// the order variables were declared in, so iterating over an environment
+/// gives the same result on every run.
+///
+/// An environment isn't synchronized, it's `Send` and `Sync` so it can be
+/// moved to another thread or shared behind a lock, but every
+/// [Interpreter](crate::Interpreter) owns its own and programs running in
+/// different interpreters never see each other's variables.
+///
+/// Scopes aren't linked to their parent but owned by the environment, so a
+/// scope can't enclose itself and lookups never loop. Cloning copies every
+/// scope: changes to the clone, such as a snapshot taken before running a
+/// line, never show up in the original. Debug output only lists the
+/// variables of the innermost scope and summarizes the scopes around it.
+///
+/// ## Examples
+/// ```rust
+/// use lox::{Environment, Literal};
+///
+/// let mut env = Environment::new();
+/// env.define("a".into(), Literal::Integer(1));
+///
+/// env.enter_block();
+/// env.define("a".into(), Literal::Integer(2));
+/// assert!(matches!(env.get("a"), Some(Literal::Integer(2))));
+///
+/// env.leave_block();
+/// assert!(matches!(env.get("a"), Some(Literal::Integer(1))));
+/// assert!(!env.contains("b"));
+/// ```
+#[derive(Clone)]
+pub struct Environment {
+    scopes: Vec<Scope>,
+    /// Number of variables declared with [declare](Environment::declare) in
+    /// every scope
+    declared: Vec<usize>,
+    max_variables: Option<usize>,
+    max_string_len: Option<usize>,
+    rng: Rng,
+    file_io: bool,
+    env_access: bool,
+    /// Arguments passed to the script
+    args: Vec<String>,
+    /// Variables read by the `env` native instead of the process environment
+    env_vars: Option<HashMap<String, String>>,
+    clock: Clock,
+    /// Decimal places of numbers written to the output
+    precision: Option<usize>,
+    /// Scope and position of the last variable looked up, cleared whenever
+    /// a declaration could move or shadow it
+    cached: Option<(usize, usize)>,
+    counters: Counters,
+    undefined: UndefinedVariablePolicy,
+    /// Undefined variables read as nil since the reads were last cleared
+    undefined_reads: Vec<Warning>,
+}
+
+/// What reading a variable that was never declared evaluates to
+///
+/// Programs are expected to declare what they read, but hosts using
+/// expressions as templates over data they don't control may rather have a
+/// missing field read as nil than have the template fail.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UndefinedVariablePolicy {
+    /// The read fails with an undefined variable error
+    #[default]
+    Error,
+    /// The read evaluates to nil and is recorded as a warning, see
+    /// [undefined_reads](Environment::undefined_reads)
+    Nil,
+}
+
+/// Work done evaluating expressions against an [Environment], reported in
+/// [Stats](crate::Stats)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// Expressions evaluated, nested ones included
+    pub expressions: usize,
+    /// Variables found by name, for reading or assigning them
+    pub lookups: usize,
+    /// Lookups answered by the position the previous one found, without
+    /// searching the scopes
+    pub cached_lookups: usize,
+    /// Values copied out of or into variables
+    pub literal_clones: usize,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment {
+    /// Creates an environment with an empty global scope, see
+    /// [stdlib::install](crate::stdlib::install) to add the standard library
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![Scope::default()],
+            declared: vec![0],
+            max_variables: None,
+            max_string_len: None,
+            rng: Rng::default(),
+            file_io: false,
+            env_access: false,
+            args: Vec::new(),
+            env_vars: None,
+            clock: Clock::default(),
+            precision: None,
+            cached: None,
+            counters: Counters::default(),
+            undefined: UndefinedVariablePolicy::default(),
+            undefined_reads: Vec::new(),
+        }
+    }
+
+    /// Environment with the same settings and no variables, for natives
+    /// called without mutable access to this one
+    pub(crate) fn detached(&self) -> Self {
+        Self {
+            max_variables: self.max_variables,
+            max_string_len: self.max_string_len,
+            rng: self.rng.clone(),
+            file_io: self.file_io,
+            env_access: self.env_access,
+            args: self.args.clone(),
+            env_vars: self.env_vars.clone(),
+            clock: self.clock.clone(),
+            precision: self.precision,
+            undefined: self.undefined,
+            scopes: vec![Scope::default()],
+            declared: vec![0],
+            cached: None,
+            counters: Counters::default(),
+            undefined_reads: Vec::new(),
+        }
+    }
+
+    /// Declares the variable in the innermost scope, replacing the value of
+    /// any variable with the same name declared in that scope
+    pub fn define(&mut self, name: String, value: Literal) {
+        self.cached = None;
+        self.scopes.last_mut().unwrap().insert(name, value, None);
+    }
+
+    /// Declares a variable for a program at the line and column of its name,
+    /// failing if it would exceed the
+    /// [max_variables](Environment::max_variables) limit
+    ///
+    /// Only new names count towards the limit, redeclaring a variable in the
+    /// same scope replaces it. Variables defined by the host with
+    /// [define](Environment::define), such as the standard library, don't
+    /// count.
+    pub fn declare(
+        &mut self,
+        name: String,
+        value: Literal,
+        location: (usize, usize),
+    ) -> Result<(), String> {
+        if self.scopes.last().unwrap().get(&name).is_none() {
+            let declared: usize = self.declared.iter().sum();
+            if self.max_variables.is_some_and(|max| declared >= max) {
+                return Err(codes::VARIABLE_LIMIT.message(&[&self.max_variables.unwrap()]));
+            }
+            *self.declared.last_mut().unwrap() += 1;
+        }
+        self.cached = None;
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name, value, Some(location));
+        Ok(())
+    }
+
+    /// Returns true if the innermost scope declares the name
+    pub fn is_declared_locally(&self, name: &str) -> bool {
+        self.scopes.last().unwrap().get(name).is_some()
+    }
+
+    /// Line and column of the innermost visible declaration of the name,
+    /// `None` if it wasn't declared by a program
+    pub fn declared_at(&self, name: &str) -> Option<(usize, usize)> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.index.get(name).map(|&i| &scope.variables[i]))
+            .and_then(|variable| variable.location)
+    }
+
+    /// Updates the innermost visible variable with the name, returns false
+    /// if no such variable has been declared
+    pub fn assign(&mut self, name: &str, value: Literal) -> bool {
+        match self.position(name) {
+            Some((scope, index)) => {
+                self.scopes[scope].variables[index].value = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Same as [get](Environment::get), remembering where the variable was
+    /// found so reading it again skips the search through the scopes
+    pub fn lookup(&mut self, name: &str) -> Option<&Literal> {
+        let (scope, index) = self.position(name)?;
+        Some(&self.scopes[scope].variables[index].value)
+    }
+
+    /// Scope and position of the innermost visible variable with the name
+    fn position(&mut self, name: &str) -> Option<(usize, usize)> {
+        self.counters.lookups += 1;
+        // Assignments don't move variables, so the cached position holds
+        // until a declaration or block changes what the name resolves to
+        if let Some((scope, index)) = self.cached {
+            if self.scopes[scope].variables[index].name == name {
+                self.counters.cached_lookups += 1;
+                return self.cached;
+            }
+        }
+
+        let position = self
+            .scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, scope)| scope.index.get(name).map(|&index| (depth, index)));
+        if position.is_some() {
+            self.cached = position;
+        }
+        position
+    }
+
+    /// Value of the innermost visible variable with the name
+    pub fn get(&self, name: &str) -> Option<&Literal> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Visible variables from the innermost scope outwards, variables hidden
+    /// by a declaration in an inner scope are skipped
+    ///
+    /// Variables of the same scope come in the order they were first
+    /// declared, assigning or redeclaring them doesn't move them.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Literal)> {
+        let mut seen = HashSet::new();
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(Scope::iter)
+            .filter(move |(name, _)| seen.insert(*name))
+    }
+
+    /// Every variable along with the depth of its scope, from the global
+    /// scope inwards and in declaration order within a scope
+    ///
+    /// Unlike [iter](Environment::iter) this includes shadowed variables,
+    /// once for every scope declaring them.
+    pub fn iter_scopes(&self) -> impl Iterator<Item = (usize, &str, &Literal)> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .flat_map(|(depth, scope)| scope.iter().map(move |(name, value)| (depth, name, value)))
+    }
+
+    /// Declared name the misspelled `name` most likely meant, for error
+    /// messages
+    ///
+    /// Names a few typos away are candidates, as are longer names
+    /// starting with `name` once it is long enough to be a meaningful prefix.
+    /// The closest candidate wins, from the innermost scope when scopes tie.
+    /// Candidates equally close within one scope are ambiguous and none is
+    /// suggested, as is nothing once there are too many names to compare.
+    pub fn similar_name(&self, name: &str) -> Option<&str> {
+        const MAX_CANDIDATES: usize = 1000;
+        const MIN_PREFIX_LEN: usize = 4;
+
+        if self
+            .scopes
+            .iter()
+            .map(|scope| scope.variables.len())
+            .sum::<usize>()
+            > MAX_CANDIDATES
+        {
+            return None;
+        }
+
+        // Short names are a letter or two away from most other names
+        let max_distance = (name.chars().count() / 3).min(2);
+        // Distance, scope and name of the closest candidate, no name if two
+        // in the same scope are equally close
+        let mut best: Option<(usize, usize, Option<&str>)> = None;
+        for (depth, scope) in self.scopes.iter().enumerate().rev() {
+            for variable in &scope.variables {
+                let candidate = variable.name.as_str();
+                let distance = match edit_distance(name, candidate, max_distance) {
+                    Some(0) => continue,
+                    Some(distance) => distance,
+                    None if name.chars().count() >= MIN_PREFIX_LEN
+                        && candidate.starts_with(name) =>
+                    {
+                        max_distance + 1
+                    }
+                    None => continue,
+                };
+                match best {
+                    Some((closest, _, _)) if distance > closest => {}
+                    Some((closest, scope, _)) if distance == closest => {
+                        if scope == depth {
+                            best = Some((distance, depth, None));
+                        }
+                    }
+                    _ => best = Some((distance, depth, Some(candidate))),
+                }
+            }
+        }
+        best.and_then(|(_, _, candidate)| candidate)
+    }
+
+    /// Starts a child scope, variables declared until the matching
+    /// [leave_block](Environment::leave_block) are dropped with it
+    pub fn enter_block(&mut self) {
+        self.cached = None;
+        self.scopes.push(Scope::default());
+        self.declared.push(0);
+    }
+
+    /// Drops the innermost scope
+    ///
+    /// # Panics
+    /// If no block has been entered, the global scope is never dropped.
+    pub fn leave_block(&mut self) {
+        assert!(self.scopes.len() > 1, "cannot leave the global scope");
+        self.cached = None;
+        self.scopes.pop();
+        self.declared.pop();
+    }
+
+    /// Runs `f` in a child scope that is dropped when it returns
+    pub fn in_block<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.enter_block();
+        let value = f(self);
+        self.leave_block();
+        value
+    }
+
+    /// Number of blocks currently entered
+    pub fn depth(&self) -> usize {
+        self.scopes.len() - 1
+    }
+
+    /// Most variables programs may have declared at once across every scope,
+    /// `None` for no limit
+    pub fn max_variables(&self) -> Option<usize> {
+        self.max_variables
+    }
+
+    pub fn set_max_variables(&mut self, max: Option<usize>) {
+        self.max_variables = max;
+    }
+
+    /// Most characters a string built by a program may have, `None` for no
+    /// limit
+    pub fn max_string_len(&self) -> Option<usize> {
+        self.max_string_len
+    }
+
+    pub fn set_max_string_len(&mut self, max: Option<usize>) {
+        self.max_string_len = max;
+    }
+
+    /// Fails if a string of `len` characters exceeds the
+    /// [max_string_len](Environment::max_string_len) limit
+    pub fn check_string_len(&self, len: usize) -> Result<(), String> {
+        match self.max_string_len {
+            Some(max) if len > max => Err(codes::STRING_LENGTH_LIMIT.message(&[&max])),
+            _ => Ok(()),
+        }
+    }
+
+    /// Random number generator used by the `random` natives
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Returns true if natives may access the file system
+    pub fn file_io(&self) -> bool {
+        self.file_io
+    }
+
+    pub fn set_file_io(&mut self, enabled: bool) {
+        self.file_io = enabled;
+    }
+
+    /// Returns true if natives may read the script arguments and
+    /// environment variables
+    pub fn env_access(&self) -> bool {
+        self.env_access
+    }
+
+    pub fn set_env_access(&mut self, enabled: bool) {
+        self.env_access = enabled;
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    /// Value of the environment variable, from the injected variables if
+    /// any were set with [set_env_vars](Environment::set_env_vars)
+    pub fn env_var(&self, name: &str) -> Option<String> {
+        match &self.env_vars {
+            Some(vars) => vars.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        }
+    }
+
+    /// Replaces the process environment seen by natives with `vars`, `None`
+    /// goes back to the process environment
+    pub fn set_env_vars(&mut self, vars: Option<HashMap<String, String>>) {
+        self.env_vars = vars;
+    }
+
+    /// Clock read by the `clock` native
+    pub fn clock(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    /// Decimal places of numbers written to the output, `None` writes the
+    /// shortest representation
+    pub fn precision(&self) -> Option<usize> {
+        self.precision
+    }
+
+    pub fn set_precision(&mut self, precision: Option<usize>) {
+        self.precision = precision;
+    }
+
+    /// Work done by the expressions evaluated since the counters were last
+    /// reset
+    pub fn counters(&self) -> Counters {
+        self.counters
+    }
+
+    pub(crate) fn counters_mut(&mut self) -> &mut Counters {
+        &mut self.counters
+    }
+
+    pub fn reset_counters(&mut self) {
+        self.counters = Counters::default();
+    }
+
+    pub fn undefined_variable_policy(&self) -> UndefinedVariablePolicy {
+        self.undefined
+    }
+
+    pub fn set_undefined_variable_policy(&mut self, policy: UndefinedVariablePolicy) {
+        self.undefined = policy;
+    }
+
+    /// Value the undefined variable `name` is read as, recording the read
+    /// when it's nil, or none when reading it is an error
+    pub(crate) fn read_undefined(&mut self, name: &Token) -> Option<Literal> {
+        match self.undefined {
+            UndefinedVariablePolicy::Error => None,
+            UndefinedVariablePolicy::Nil => {
+                self.undefined_reads.push(Warning::new(
+                    codes::UNDEFINED_VARIABLE,
+                    &[&name.lexeme],
+                    name.line,
+                    name.column,
+                ));
+                Some(Literal::Nil)
+            }
+        }
+    }
+
+    /// Undefined variables read as nil, in the order they were read, since
+    /// the reads were last cleared
+    pub fn undefined_reads(&self) -> &[Warning] {
+        &self.undefined_reads
+    }
+
+    pub fn clear_undefined_reads(&mut self) {
+        self.undefined_reads.clear();
+    }
+}
+
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (innermost, enclosing) = self.scopes.split_last().unwrap();
+        let variables: usize = enclosing.iter().map(|scope| scope.variables.len()).sum();
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        f.debug_struct("Environment")
+            .field("depth", &self.depth())
+            .field("variables", innermost)
+            .field(
+                "enclosing",
+                &format_args!(
+                    "{} scope{} with {} variable{}",
+                    enclosing.len(),
+                    plural(enclosing.len()),
+                    variables,
+                    plural(variables)
+                ),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+/// Edit distance between the names, counting a swap of adjacent characters
+/// as a single edit, `None` once it exceeds `max`
+fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    // Distances from the prefixes of `a` ending before the last two
+    // characters read to every prefix of `b`
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 0..a.len() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for j in 0..b.len() {
+            let substitution = previous[j] + usize::from(a[i] != b[j]);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                current[j + 1] = current[j + 1].min(before[j - 1] + 1);
+            }
+        }
+        // Every later row is at least as far as the closest cell of this one
+        if current.iter().min().is_some_and(|&min| min > max) {
+            return None;
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    Some(previous[b.len()]).filter(|&distance| distance <= max)
+}
+
+#[derive(Debug, Clone)]
+struct Variable {
+    name: String,
+    value: Literal,
+    /// Line and column of the latest declaration by a program
+    location: Option<(usize, usize)>,
+}
+
+/// Variables of a scope in declaration order, indexed by name
+#[derive(Clone, Default)]
+struct Scope {
+    variables: Vec<Variable>,
+    index: HashMap<String, usize>,
+}
+
+impl Scope {
+    fn get(&self, name: &str) -> Option<&Literal> {
+        self.index.get(name).map(|&i| &self.variables[i].value)
+    }
+
+    /// Adds the variable or replaces the one with the same name in place
+    fn insert(&mut self, name: String, value: Literal, location: Option<(usize, usize)>) {
+        match self.index.get(&name) {
+            Some(&i) => {
+                self.variables[i].value = value;
+                self.variables[i].location = location;
+            }
+            None => {
+                self.index.insert(name.clone(), self.variables.len());
+                self.variables.push(Variable {
+                    name,
+                    value,
+                    location,
+                });
+            }
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&str, &Literal)> {
+        self.variables
+            .iter()
+            .map(|variable| (variable.name.as_str(), &variable.value))
+    }
+}
+
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(env: &Environment) -> Vec<(String, String)> {
+        env.iter()
+            .map(|(name, value)| (name.to_string(), value.clone().into()))
+            .collect()
+    }
+
+    #[test]
+    fn debug_lists_the_innermost_scope_only() {
+        let mut env = Environment::new();
+        env.define("a".into(), Literal::Integer(1));
+        env.define("b".into(), Literal::Boolean(true));
+        assert_eq!(
+            format!("{:?}", env),
+            "Environment { depth: 0, variables: {\"a\": Integer(1), \"b\": Boolean(true)}, \
+             enclosing: 0 scopes with 0 variables, .. }"
+        );
+
+        env.enter_block();
+        env.define("c".into(), Literal::Nil);
+        for _ in 0..10_000 {
+            env.enter_block();
+        }
+        env.define("a".into(), Literal::Integer(2));
+        assert_eq!(
+            format!("{:?}", env),
+            "Environment { depth: 10001, variables: {\"a\": Integer(2)}, \
+             enclosing: 10001 scopes with 3 variables, .. }"
+        );
+    }
+
+    #[test]
+    fn clones_copy_every_scope() {
+        let mut env = Environment::new();
+        env.define("a".into(), Literal::Integer(1));
+        env.enter_block();
+        env.define("b".into(), Literal::Integer(2));
+
+        let mut clone = env.clone();
+        clone.assign("a", Literal::Integer(10));
+        clone.assign("b", Literal::Integer(20));
+        clone.leave_block();
+
+        assert_eq!(
+            names(&env),
+            [("b".into(), "2".into()), ("a".into(), "1".into())]
+        );
+        assert_eq!(names(&clone), [("a".into(), "10".into())]);
+        assert_eq!(env.depth(), 1);
+    }
+
+    #[test]
+    fn iterates_over_visible_variables() {
+        let mut env = Environment::new();
+        env.define("a".into(), Literal::Integer(1));
+        env.define("b".into(), Literal::Integer(2));
+
+        env.in_block(|env| {
+            env.define("a".into(), Literal::String("inner".into()));
+            env.define("c".into(), Literal::Nil);
+            assert_eq!(
+                names(env),
+                vec![
+                    ("a".into(), "inner".into()),
+                    ("c".into(), "nil".into()),
+                    ("b".into(), "2".into()),
+                ]
+            );
+        });
+
+        assert_eq!(
+            names(&env),
+            vec![("a".into(), "1".into()), ("b".into(), "2".into())]
+        );
+    }
+
+    #[test]
+    fn variables_keep_their_declaration_order() {
+        let mut env = Environment::new();
+        for name in ["zeta", "alpha", "mid", "beta"] {
+            env.define(name.into(), Literal::Nil);
+        }
+        assert!(env.assign("alpha", Literal::Integer(1)));
+        env.define("zeta".into(), Literal::Integer(2));
+
+        assert_eq!(
+            names(&env),
+            vec![
+                ("zeta".into(), "2".into()),
+                ("alpha".into(), "1".into()),
+                ("mid".into(), "nil".into()),
+                ("beta".into(), "nil".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn scopes_list_shadowed_variables_once_per_scope() {
+        let mut env = Environment::new();
+        env.define("b".into(), Literal::Integer(1));
+        env.define("a".into(), Literal::Integer(2));
+        env.enter_block();
+        env.define("c".into(), Literal::Integer(3));
+        env.define("b".into(), Literal::Integer(4));
+        env.enter_block();
+        env.define("a".into(), Literal::Integer(5));
+        assert!(env.assign("b", Literal::Integer(6)));
+
+        let scopes: Vec<(usize, &str, String)> = env
+            .iter_scopes()
+            .map(|(depth, name, value)| (depth, name, value.clone().into()))
+            .collect();
+        assert_eq!(
+            scopes,
+            vec![
+                (0, "b", "1".into()),
+                (0, "a", "2".into()),
+                (1, "c", "3".into()),
+                (1, "b", "6".into()),
+                (2, "a", "5".into()),
+            ]
+        );
+        assert_eq!(
+            names(&env),
+            vec![
+                ("a".into(), "5".into()),
+                ("c".into(), "3".into()),
+                ("b".into(), "6".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn assigns_the_innermost_declaration() {
+        let mut env = Environment::new();
+        env.define("a".into(), Literal::Integer(1));
+
+        env.in_block(|env| {
+            assert!(env.assign("a", Literal::Integer(2)));
+            env.define("a".into(), Literal::Integer(3));
+            assert!(env.assign("a", Literal::Integer(4)));
+            assert!(!env.assign("b", Literal::Nil));
+        });
+
+        assert!(matches!(env.get("a"), Some(Literal::Integer(2))));
+        assert_eq!(env.depth(), 0);
+    }
+
+    #[test]
+    fn lookups_follow_shadowing_declarations() {
+        let mut env = Environment::new();
+        env.define("a".into(), Literal::Integer(1));
+        env.define("b".into(), Literal::Integer(2));
+        assert!(matches!(env.lookup("a"), Some(Literal::Integer(1))));
+
+        env.enter_block();
+        assert!(matches!(env.lookup("a"), Some(Literal::Integer(1))));
+        env.define("a".into(), Literal::Integer(3));
+        assert!(matches!(env.lookup("a"), Some(Literal::Integer(3))));
+        assert!(env.assign("a", Literal::Integer(4)));
+        assert!(matches!(env.lookup("b"), Some(Literal::Integer(2))));
+        assert!(matches!(env.lookup("a"), Some(Literal::Integer(4))));
+
+        env.leave_block();
+        assert!(matches!(env.lookup("a"), Some(Literal::Integer(1))));
+        assert!(env.lookup("c").is_none());
+    }
+
+    #[test]
+    fn declarations_are_limited_across_scopes() {
+        let mut env = Environment::new();
+        env.define("native".into(), Literal::Nil);
+        env.set_max_variables(Some(2));
+
+        env.declare("a".into(), Literal::Integer(1), (1, 1))
+            .unwrap();
+        env.declare("a".into(), Literal::Integer(2), (1, 1))
+            .unwrap();
+        env.in_block(|env| {
+            env.declare("b".into(), Literal::Nil, (1, 1)).unwrap();
+            assert_eq!(
+                env.declare("c".into(), Literal::Nil, (1, 1)),
+                Err("variable limit of 2 reached".into())
+            );
+            assert!(env.declare("b".into(), Literal::Integer(3), (1, 1)).is_ok());
+        });
+
+        // Leaving the block freed its variables
+        env.declare("c".into(), Literal::Nil, (1, 1)).unwrap();
+        assert!(env.declare("d".into(), Literal::Nil, (1, 1)).is_err());
+        assert!(!env.contains("d"));
+    }
+
+    #[test]
+    fn similar_names_are_suggested() {
+        let mut env = Environment::new();
+        for name in ["length", "total", "a", "b", "counter"] {
+            env.define(name.into(), Literal::Nil);
+        }
+
+        assert_eq!(env.similar_name("lenght"), Some("length"));
+        assert_eq!(env.similar_name("totl"), Some("total"));
+        assert_eq!(env.similar_name("count"), Some("counter"));
+        // Short names and unrelated names get no suggestion
+        assert_eq!(env.similar_name("c"), None);
+        assert_eq!(env.similar_name("width"), None);
+        assert_eq!(env.similar_name("length"), None);
+    }
+
+    #[test]
+    fn suggestions_prefer_the_closest_scope() {
+        let mut env = Environment::new();
+        env.define("value".into(), Literal::Nil);
+        env.define("valve".into(), Literal::Nil);
+        env.define("items".into(), Literal::Nil);
+        env.define("item".into(), Literal::Nil);
+
+        env.in_block(|env| {
+            // Shadowed names are still candidates
+            env.define("items".into(), Literal::Integer(1));
+            assert_eq!(env.similar_name("itemz"), Some("items"));
+
+            // Equally close names in one scope are ambiguous until an inner
+            // scope declares one of them
+            assert_eq!(env.similar_name("valie"), None);
+            env.in_block(|env| {
+                env.define("valve".into(), Literal::Nil);
+                assert_eq!(env.similar_name("valie"), Some("valve"));
+            });
+        });
+    }
+
+    #[test]
+    fn crowded_environments_get_no_suggestions() {
+        let mut env = Environment::new();
+        for i in 0..2000 {
+            env.define(format!("name{}", i), Literal::Nil);
+        }
+
+        assert_eq!(env.similar_name("nam1"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot leave the global scope")]
+    fn global_scope_cannot_be_left() {
+        Environment::new().leave_block();
+    }
+
+    #[test]
+    fn environments_can_be_sent_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Environment>();
+    }
+}
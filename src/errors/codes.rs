@@ -0,0 +1,564 @@
+//! Catalogue of every error and warning the interpreter reports
+//!
+//! Each kind of problem has a stable code, such as `P001`, that tools and
+//! tests can match on instead of the wording. The code starts with the stage
+//! reporting it: `S` for the scanner, `P` for the parser, `R` for running
+//! programs and `W` for warnings. Messages are filled from the template of
+//! their code so they are all worded alike: lowercase, without a trailing
+//! period and with lexemes quoted in backticks.
+//!
+//! ## Examples
+//! ```rust
+//! use lox::errors::codes::{self, EXPECTED_TOKEN};
+//!
+//! assert_eq!(EXPECTED_TOKEN.message(&[&";"]), "expected `;`");
+//! assert!(codes::explain("P001").unwrap().contains("statement"));
+//! assert!(codes::explain("P999").is_none());
+//! ```
+use std::fmt::{self, Write};
+
+/// How serious a problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The program can't run, or stopped
+    Error,
+    /// The program runs but likely doesn't do what was meant
+    Warning,
+}
+
+/// Kind of problem along with its canonical wording and explanation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub severity: Severity,
+    /// Message with a `{}` for every value filled in by
+    /// [message](ErrorCode::message)
+    pub template: &'static str,
+    pub explanation: &'static str,
+}
+
+impl ErrorCode {
+    /// Fills the placeholders of the template with `args` in order
+    pub fn message(&self, args: &[&dyn fmt::Display]) -> String {
+        let mut parts = self.template.split("{}");
+        let mut msg = parts.next().unwrap_or_default().to_string();
+        let mut args = args.iter();
+        for part in parts {
+            if let Some(arg) = args.next() {
+                let _ = write!(msg, "{}", arg);
+            }
+            msg.push_str(part);
+        }
+        debug_assert!(
+            args.next().is_none(),
+            "too many arguments for {}",
+            self.code
+        );
+        msg
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code)
+    }
+}
+
+const fn error(code: &'static str, template: &'static str, explanation: &'static str) -> ErrorCode {
+    ErrorCode {
+        code,
+        severity: Severity::Error,
+        template,
+        explanation,
+    }
+}
+
+const fn warning(
+    code: &'static str,
+    template: &'static str,
+    explanation: &'static str,
+) -> ErrorCode {
+    ErrorCode {
+        code,
+        severity: Severity::Warning,
+        template,
+        explanation,
+    }
+}
+
+pub const UNKNOWN_CHARACTER: ErrorCode = error(
+    "S001",
+    "unknown character `{}`",
+    "The character isn't part of any token of the language. Outside of strings \
+     and comments only letters, digits, `_`, whitespace and the characters of \
+     operators and punctuation may appear.",
+);
+pub const UNTERMINATED_STRING: ErrorCode = error(
+    "S002",
+    "unterminated string missing `{}`",
+    "The string runs to the end of the source without its closing quote. The \
+     error points at the opening quote, add the closing one where the string \
+     should end.",
+);
+pub const UNTERMINATED_MULTILINE_STRING: ErrorCode = error(
+    "S003",
+    "unterminated string spanning {} lines missing `{}`",
+    "The string runs to the end of the source without its closing quote and \
+     took every line after its opening quote with it. Only strings in triple \
+     quotes span lines, so a missing `\"\"\"` often shows up far from where \
+     it was forgotten.",
+);
+pub const UNCLOSED_INTERPOLATION: ErrorCode = error(
+    "S004",
+    "unclosed interpolation missing `}`",
+    "An interpolation opened with `${` inside a string is never closed. Close \
+     it with `}` before the rest of the string, as in \"total: ${a + b}\".",
+);
+pub const SINGLE_QUESTION_MARK: ErrorCode = error(
+    "S005",
+    "unexpected `?`, did you mean `??`",
+    "A single `?` is reserved for a conditional operator. `a ?? b` gives `b` \
+     when `a` is nil and `a` otherwise.",
+);
+pub const DIGIT_STARTED_IDENTIFIER: ErrorCode = error(
+    "S006",
+    "identifiers cannot start with a digit",
+    "Names may contain digits but not start with one, so `2fast` is neither a \
+     number nor a name. Rename it, i.e. to `fast2`, or separate the number \
+     from the name with an operator.",
+);
+pub const NEWLINE_IN_STRING: ErrorCode = error(
+    "S007",
+    "newline in string, close the quote or use `\"\"\"` for a multiline string",
+    "Strings in single or double quotes end on the line they start, so a forgotten \
+     closing quote is reported where the line ends instead of swallowing the \
+     rest of the source. Strings that span lines are opened and \
+     closed with `\"\"\"` instead and keep their newlines.",
+);
+
+pub const EXPECTED_TOKEN: ErrorCode = error(
+    "P001",
+    "expected `{}`",
+    "The statement or expression isn't complete: the parser needs the token \
+     shown at this point, such as the `;` ending a statement or the `)` \
+     closing a group.",
+);
+pub const EXPECTED_IDENTIFIER: ErrorCode = error(
+    "P002",
+    "expected an identifier",
+    "`let` must be followed by the name of the variable it declares, as in \
+     `let total = 0;`.",
+);
+pub const EXPECTED_EXPRESSION: ErrorCode = error(
+    "P003",
+    "expected an expression",
+    "A value is needed, such as after an operator or an `=`, but the source \
+     ends or has a token that can't start one there, such as the `)` in \
+     `let a = );`.",
+);
+pub const TRAILING_TOKENS: ErrorCode = error(
+    "P004",
+    "unexpected tokens after end of input",
+    "Tokens follow the end of input marker of a token stream. Streams built \
+     by the scanner never contain such tokens, they come from token streams \
+     assembled by hand.",
+);
+pub const EXPECTED_END_OF_EXPRESSION: ErrorCode = error(
+    "P005",
+    "expected the end of the expression",
+    "A compiled expression is a single expression without a trailing `;`, \
+     anything following the expression is rejected.",
+);
+pub const EMPTY_INTERPOLATION: ErrorCode = error(
+    "P006",
+    "expected an expression inside interpolation",
+    "`${}` in a string needs an expression whose value is inserted, as in \
+     \"total: ${a + b}\".",
+);
+pub const UNCLOSED_INTERPOLATED_EXPRESSION: ErrorCode = error(
+    "P007",
+    "expected `}` after interpolated expression",
+    "The expression inside `${...}` is followed by something other than the \
+     closing `}`, an interpolation holds a single expression.",
+);
+pub const INVALID_ASSIGNMENT_TARGET: ErrorCode = error(
+    "P008",
+    "cannot assign to {}",
+    "Only variables can be assigned to, the left side of `=` must be a plain \
+     name such as `a = 1`.",
+);
+pub const ASSIGNMENT_IN_EXPRESSION: ErrorCode = error(
+    "P009",
+    "assignment not allowed in expression context",
+    "Compiled expressions only read variables, they are evaluated against an \
+     environment they can't change.",
+);
+pub const UNAVAILABLE_IN_VERSION: ErrorCode = error(
+    "P010",
+    "`{}` is not available in language version {}",
+    "The construct was added in a later version of the language than the one \
+     the program is run as. Pick a newer version, i.e. with `--lang=v2`.",
+);
+pub const RESERVED_WORD: ErrorCode = error(
+    "P011",
+    "`{}` is a reserved word and cannot be {}",
+    "Keywords, including those reserved for future use such as `class` and \
+     `while`, can't be used as names. Pick another name for the variable.",
+);
+pub const THIS_OUTSIDE_CLASS: ErrorCode = error(
+    "P012",
+    "`this` can only be used inside class methods",
+    "`this` refers to the instance a method was called on, outside of a \
+     method there is none. Classes aren't supported yet, so there are no \
+     methods to use it in.",
+);
+pub const SUPER_OUTSIDE_SUBCLASS: ErrorCode = error(
+    "P013",
+    "`super` can only be used inside a subclass method",
+    "`super.method` looks up a method of the superclass, outside of a method \
+     of a subclass there is none. Classes aren't supported yet, so there are \
+     no subclasses to use it in.",
+);
+pub const PROPERTY_ACCESS: ErrorCode = error(
+    "P014",
+    "property access is not supported yet",
+    "`.` followed by a name, as in `point.x`, reads a property of an object. \
+     Classes aren't supported yet, so there are no objects with properties \
+     to read.",
+);
+pub const LEADING_DOT: ErrorCode = error(
+    "P015",
+    "unexpected `.`; numbers must have a leading digit, as in `0.5`",
+    "Numbers start with a digit, `.5` is a `.` followed by the number 5. \
+     Write the zero before the point.",
+);
+pub const TRAILING_DOT: ErrorCode = error(
+    "P016",
+    "expected digits after `.`",
+    "A fraction needs a digit after the point, `12.` is the number 12 \
+     followed by a `.`. Write `12.0`, or leave out the point.",
+);
+
+pub const UNDEFINED_VARIABLE: ErrorCode = error(
+    "R001",
+    "undefined variable `{}`",
+    "The variable is read but no `let` declaring it ran before in this scope \
+     or an enclosing one. Check the spelling or declare it first.",
+);
+pub const UNDECLARED_ASSIGNMENT: ErrorCode = error(
+    "R002",
+    "cannot assign to undeclared variable `{}`",
+    "Assignments change existing variables, declare the variable with `let` \
+     before assigning to it.",
+);
+pub const UNDEFINED_FUNCTION: ErrorCode = error(
+    "R003",
+    "undefined function `{}`",
+    "No function of this name exists. Functions are provided by the standard \
+     library and by the program embedding the interpreter.",
+);
+pub const UNSUPPORTED_OPERANDS: ErrorCode = error(
+    "R004",
+    "operator `{}` is not supported for {} and {}",
+    "The operator can't combine values of these types. Arithmetic and \
+     comparisons take numbers, `&&` and `||` take booleans and `==` compares \
+     values of any type. Strings are built with interpolation rather than `+`.",
+);
+pub const WRONG_OPERAND_TYPE: ErrorCode = error(
+    "R005",
+    "operator `{}` requires {}, got {}",
+    "The unary operator was applied to a value of the wrong type: `-` takes \
+     a number and `!` a boolean.",
+);
+pub const NOT_AN_INTEGER: ErrorCode = error(
+    "R006",
+    "operand {} of `{}` is not a whole number within integer range",
+    "Bitwise operators work on 64-bit integers, their operands must be whole \
+     numbers that fit in one.",
+);
+pub const NOT_A_NUMBER: ErrorCode = error(
+    "R007",
+    "operand {} of `{}` is not a number",
+    "Bitwise operators only take numbers.",
+);
+pub const SHIFT_OUT_OF_RANGE: ErrorCode = error(
+    "R008",
+    "shift amount {} is out of range",
+    "Integers are shifted by 0 to 63 bits, larger and negative amounts are \
+     rejected rather than wrapped around.",
+);
+pub const WRONG_ARGUMENT_COUNT: ErrorCode = error(
+    "R009",
+    "wrong number of arguments for `{}`, expected {} got {}",
+    "The function was called with a different number of arguments than it \
+     takes.",
+);
+pub const NOT_CALLABLE: ErrorCode = error(
+    "R010",
+    "can only call functions, got {}",
+    "Only functions can be called, the value before the `(` is of another \
+     type.",
+);
+pub const NATIVE_FAILED: ErrorCode = error(
+    "R011",
+    "{}",
+    "A function of the standard library or of the program embedding the \
+     interpreter failed. The message starts with the name of the function and \
+     says which argument it rejected.",
+);
+pub const ASSERTION_FAILED: ErrorCode = error(
+    "R012",
+    "assertion failed: {}",
+    "The condition of an `assert` statement was `false` or `nil`. The message \
+     shows the condition, followed by the message of the assertion if it has \
+     one.",
+);
+pub const INTERRUPTED: ErrorCode = error(
+    "R013",
+    "interrupted",
+    "The program was stopped from outside while it was running, i.e. by \
+     Ctrl-C or by the program embedding the interpreter. `attempt` doesn't \
+     catch interrupts.",
+);
+pub const STATEMENT_LIMIT: ErrorCode = error(
+    "R014",
+    "statement limit of {} reached",
+    "The program executed more statements than the limit set by the program \
+     embedding the interpreter allows.",
+);
+pub const VARIABLE_LIMIT: ErrorCode = error(
+    "R015",
+    "variable limit of {} reached",
+    "The program declared more variables than the limit allows, raise it \
+     with `--max-vars`.",
+);
+pub const STRING_LENGTH_LIMIT: ErrorCode = error(
+    "R016",
+    "string length limit of {} exceeded",
+    "The program built a string longer than the limit allows, raise it with \
+     `--max-string`.",
+);
+pub const OUTPUT_FAILED: ErrorCode = error(
+    "R017",
+    "cannot write output",
+    "Writing the output of a `print` or `write` statement failed, i.e. \
+     because the program reading it went away. This is not a problem in the \
+     program.",
+);
+pub const INVALID_EXIT_CODE: ErrorCode = error(
+    "R018",
+    "exit code must be a number, got {}",
+    "`exit` takes the code the process ends with, a number between 0 and 255.",
+);
+pub const ALREADY_DECLARED: ErrorCode = error(
+    "R019",
+    "`{}` is already declared in this scope, first at line {} column {}",
+    "Redeclaring a variable in the same scope is rejected by the \
+     redeclaration policy in effect. Assign to the variable instead, or \
+     declare it in a block of its own.",
+);
+pub const ALREADY_DEFINED: ErrorCode = error(
+    "R020",
+    "`{}` is already defined by the interpreter",
+    "The name belongs to a variable or function defined by the interpreter, \
+     such as the standard library, and the redeclaration policy in effect \
+     rejects replacing it. Pick another name.",
+);
+pub const UNEXPECTED_TOKEN: ErrorCode = error(
+    "R021",
+    "unexpected `{}`",
+    "The token can't be evaluated where it was found, such as `this` \
+     outside of a method.",
+);
+pub const UNKNOWN_OPERATOR: ErrorCode = error(
+    "R022",
+    "unknown operator `{}`",
+    "The operator has no meaning on its own here. This is a bug in the \
+     interpreter, please report it.",
+);
+pub const INVALID_LITERAL: ErrorCode = error(
+    "R023",
+    "invalid literal `{}`",
+    "The literal can't be turned into a value. This is a bug in the \
+     interpreter, please report it.",
+);
+
+pub const UNUSED_VARIABLE: ErrorCode = warning(
+    "W001",
+    "unused variable `{}`",
+    "The variable is declared but never read. Remove it, or read it where it \
+     was meant to be used.",
+);
+pub const UNUSED_COMPARISON: ErrorCode = warning(
+    "W002",
+    "expression result is unused; did you mean `=`?",
+    "The comparison is computed and thrown away, which does nothing. `==` \
+     compares values, `=` assigns one.",
+);
+pub const UNUSED_RESULT: ErrorCode = warning(
+    "W003",
+    "expression result is unused",
+    "The value of the expression is computed and thrown away without anything \
+     else happening. Expression statements in blocks aren't printed, unlike \
+     those at the top level.",
+);
+pub const DOUBLE_NOT: ErrorCode = warning(
+    "W004",
+    "`!!` negates twice and has no effect, use the operand as is",
+    "Negating a boolean twice gives the same boolean.",
+);
+pub const DOUBLE_MINUS: ErrorCode = warning(
+    "W005",
+    "`--` negates twice and has no effect, write `a = a - 1` to decrement",
+    "There is no decrement operator, `--a` is `-(-a)` which is `a`.",
+);
+pub const DOUBLE_MINUS_VALUE: ErrorCode = warning(
+    "W009",
+    "`--` negates twice and has no effect, use the operand as is",
+    "Negating a number twice gives the same number, `--5` is `-(-5)` which \
+     is `5`.",
+);
+pub const EMPTY_BLOCK: ErrorCode = warning(
+    "W006",
+    "empty block",
+    "The block has no statements so running it does nothing, which usually \
+     means code is missing. Remove the block if it isn't needed.",
+);
+pub const SHADOWED_VARIABLE: ErrorCode = warning(
+    "W007",
+    "`{}` shadows the variable declared at line {} column {}",
+    "The declaration hides a variable of the same name from an enclosing \
+     block until the end of its own block, so reads and assignments there no \
+     longer reach the outer variable. Rename one of them if that isn't meant.",
+);
+pub const EMPTY_STATEMENT: ErrorCode = warning(
+    "W008",
+    "empty statement",
+    "A `;` on its own, such as the one in `{ 1; };` or `1;;`, is a statement \
+     doing nothing. Remove it.",
+);
+
+/// Every code, in order
+pub const ALL: &[ErrorCode] = &[
+    UNKNOWN_CHARACTER,
+    UNTERMINATED_STRING,
+    UNTERMINATED_MULTILINE_STRING,
+    UNCLOSED_INTERPOLATION,
+    SINGLE_QUESTION_MARK,
+    DIGIT_STARTED_IDENTIFIER,
+    NEWLINE_IN_STRING,
+    EXPECTED_TOKEN,
+    EXPECTED_IDENTIFIER,
+    EXPECTED_EXPRESSION,
+    TRAILING_TOKENS,
+    EXPECTED_END_OF_EXPRESSION,
+    EMPTY_INTERPOLATION,
+    UNCLOSED_INTERPOLATED_EXPRESSION,
+    INVALID_ASSIGNMENT_TARGET,
+    ASSIGNMENT_IN_EXPRESSION,
+    UNAVAILABLE_IN_VERSION,
+    RESERVED_WORD,
+    THIS_OUTSIDE_CLASS,
+    SUPER_OUTSIDE_SUBCLASS,
+    PROPERTY_ACCESS,
+    LEADING_DOT,
+    TRAILING_DOT,
+    UNDEFINED_VARIABLE,
+    UNDECLARED_ASSIGNMENT,
+    UNDEFINED_FUNCTION,
+    UNSUPPORTED_OPERANDS,
+    WRONG_OPERAND_TYPE,
+    NOT_AN_INTEGER,
+    NOT_A_NUMBER,
+    SHIFT_OUT_OF_RANGE,
+    WRONG_ARGUMENT_COUNT,
+    NOT_CALLABLE,
+    NATIVE_FAILED,
+    ASSERTION_FAILED,
+    INTERRUPTED,
+    STATEMENT_LIMIT,
+    VARIABLE_LIMIT,
+    STRING_LENGTH_LIMIT,
+    OUTPUT_FAILED,
+    INVALID_EXIT_CODE,
+    ALREADY_DECLARED,
+    ALREADY_DEFINED,
+    UNEXPECTED_TOKEN,
+    UNKNOWN_OPERATOR,
+    INVALID_LITERAL,
+    UNUSED_VARIABLE,
+    UNUSED_COMPARISON,
+    UNUSED_RESULT,
+    DOUBLE_NOT,
+    DOUBLE_MINUS,
+    EMPTY_BLOCK,
+    SHADOWED_VARIABLE,
+    EMPTY_STATEMENT,
+    DOUBLE_MINUS_VALUE,
+];
+
+/// The code, case insensitively, such as `p001`
+pub fn find(code: &str) -> Option<ErrorCode> {
+    ALL.iter()
+        .find(|known| known.code.eq_ignore_ascii_case(code))
+        .copied()
+}
+
+/// Explanation of the code, None for unknown codes
+pub fn explain(code: &str) -> Option<&'static str> {
+    find(code).map(|code| code.explanation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_unique_and_ordered_by_stage() {
+        let mut previous: Option<&str> = None;
+        for code in ALL {
+            let (stage, number) = code.code.split_at(1);
+            assert!(
+                number.len() == 3 && number.bytes().all(|b| b.is_ascii_digit()),
+                "{}",
+                code
+            );
+            assert_eq!(stage == "W", code.severity == Severity::Warning, "{}", code);
+            if let Some(previous) = previous {
+                let order = |code: &str| ("SPRW".find(&code[..1]), code[1..].to_string());
+                assert!(
+                    order(previous) < order(code.code),
+                    "{} after {}",
+                    code,
+                    previous
+                );
+            }
+            previous = Some(code.code);
+        }
+    }
+
+    #[test]
+    fn templates_follow_the_message_style() {
+        for code in ALL {
+            let template = code.template;
+            assert!(!template.ends_with('.'), "{}", code);
+            assert!(!template.contains('\''), "{} quotes with backticks", code);
+            assert!(
+                !template.starts_with(|c: char| c.is_uppercase()),
+                "{}",
+                code
+            );
+            assert!(code.explanation.ends_with('.'), "{}", code);
+        }
+    }
+
+    #[test]
+    fn messages_fill_placeholders_in_order() {
+        assert_eq!(
+            ALREADY_DECLARED.message(&[&"a", &2, &5]),
+            "`a` is already declared in this scope, first at line 2 column 5"
+        );
+        assert_eq!(INTERRUPTED.message(&[]), "interrupted");
+        assert_eq!(find("r013"), Some(INTERRUPTED));
+    }
+}
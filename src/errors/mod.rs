@@ -0,0 +1,404 @@
+use std::{fmt, io};
+
+use crate::analyzers::lints::LintLevel;
+use crate::{LocationInfo, Token};
+use codes::ErrorCode;
+
+pub mod codes;
+
+/// Statement that was being executed when an [EvaluationError] occurred
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// What the statement is, i.e "block"
+    pub kind: &'static str,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Frame {
+    pub fn new(kind: &'static str, token: &Token) -> Self {
+        Self {
+            kind,
+            line: token.line,
+            column: token.column,
+        }
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "in {} started {}",
+            self.kind,
+            location(self.line, self.column)
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EvaluationError {
+    code: ErrorCode,
+    msg: Box<str>,
+    line: usize,
+    column: usize,
+    /// Statements enclosing the failure, outermost first
+    trace: Box<[Frame]>,
+}
+
+impl EvaluationError {
+    /// Error of the kind `code`, with the placeholders of its template filled
+    /// by `args`
+    pub fn new(code: ErrorCode, args: &[&dyn fmt::Display], line: usize, column: usize) -> Self {
+        Self::located(code, &code.message(args), line, column)
+    }
+
+    /// Error of the kind `code` found at the token
+    pub fn at(code: ErrorCode, args: &[&dyn fmt::Display], token: &Token) -> Self {
+        Self::with_message(code, &code.message(args), token)
+    }
+
+    /// Error of the kind `code` found at the token with a message worded
+    /// elsewhere, such as by a native function
+    pub(crate) fn with_message(code: ErrorCode, msg: &str, token: &Token) -> Self {
+        Self::located(code, msg, token.line, token.column)
+    }
+
+    fn located(code: ErrorCode, msg: &str, line: usize, column: usize) -> Self {
+        Self {
+            code,
+            msg: msg.into(),
+            line,
+            column,
+            trace: Box::default(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// What went wrong, without the code and location
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn trace(&self) -> &[Frame] {
+        &self.trace
+    }
+
+    /// Where the error occurred, line `0` for generated code
+    pub fn location(&self) -> LocationInfo {
+        LocationInfo::new(self.line, self.column, 0)
+    }
+
+    /// Attaches the statements that were executing when the error occurred,
+    /// keeping the trace already attached by a more deeply nested statement
+    pub(crate) fn with_trace(mut self, trace: &[Frame]) -> Self {
+        if self.trace.is_empty() {
+            self.trace = trace.into();
+        }
+        self
+    }
+}
+
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "evaluation error[{}]: {} {}",
+            self.code,
+            self.msg,
+            location(self.line, self.column)
+        )?;
+        for frame in self.trace.iter().rev() {
+            write!(f, "\n    {}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the location of an error, line `0` marks code that was generated
+/// rather than read from the source
+fn location(line: usize, column: usize) -> String {
+    if line == 0 {
+        "in generated code".into()
+    } else {
+        format!("at line {} column {}", line, column)
+    }
+}
+
+/// Problem in a program that doesn't stop it from running
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    pub code: ErrorCode,
+    pub msg: String,
+    pub line: usize,
+    pub column: usize,
+    /// Level of the lint reporting the warning, denied warnings are shown as
+    /// errors
+    pub level: LintLevel,
+}
+
+impl Warning {
+    pub fn new(code: ErrorCode, args: &[&dyn fmt::Display], line: usize, column: usize) -> Self {
+        Self {
+            code,
+            msg: code.message(args),
+            line,
+            column,
+            level: LintLevel::Warn,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.level {
+            LintLevel::Deny => "error",
+            LintLevel::Allow | LintLevel::Warn => "warning",
+        };
+        write!(
+            f,
+            "{}[{}]: {} {}",
+            kind,
+            self.code,
+            self.msg,
+            location(self.line, self.column)
+        )
+    }
+}
+
+/// Stage of the interpreter an [InterpreterError] originated from
+#[derive(Clone, Debug, PartialEq)]
+pub enum InterpreterErrorKind {
+    /// The program source could not be read
+    Source,
+    Scan,
+    Parse,
+    Evaluation,
+    /// Warnings were found while warnings are treated as errors
+    Lint,
+    /// Program output could not be written, this is not a bug in the script
+    Output(io::ErrorKind),
+    /// The program was stopped with an
+    /// [InterruptHandle](crate::interpreter::InterruptHandle)
+    Interrupted,
+}
+
+#[derive(Clone, Debug)]
+pub struct InterpreterError {
+    pub msg: String,
+    pub kind: InterpreterErrorKind,
+    /// Code of the scan, parse or evaluation error, the first one if there
+    /// are several
+    pub code: Option<ErrorCode>,
+}
+
+impl InterpreterError {
+    pub fn new(msg: &str, kind: InterpreterErrorKind) -> Self {
+        Self {
+            msg: msg.into(),
+            kind,
+            code: None,
+        }
+    }
+
+    /// Returns true if the error was caused by the output being closed, i.e
+    /// when piping into a program that exits early
+    pub fn is_broken_pipe(&self) -> bool {
+        self.kind == InterpreterErrorKind::Output(io::ErrorKind::BrokenPipe)
+    }
+}
+
+impl From<ScanError> for InterpreterError {
+    fn from(value: ScanError) -> Self {
+        Self {
+            code: Some(value.code),
+            ..Self::new(&value.to_string(), InterpreterErrorKind::Scan)
+        }
+    }
+}
+
+impl From<ParserError> for InterpreterError {
+    fn from(value: ParserError) -> Self {
+        Self {
+            code: Some(value.code),
+            ..Self::new(&value.to_string(), InterpreterErrorKind::Parse)
+        }
+    }
+}
+
+/// Reports every error on its own line
+impl From<Vec<ParserError>> for InterpreterError {
+    fn from(value: Vec<ParserError>) -> Self {
+        let messages: Vec<String> = value.iter().map(ParserError::to_string).collect();
+        Self {
+            code: value.first().map(|error| error.code),
+            ..Self::new(&messages.join("\n"), InterpreterErrorKind::Parse)
+        }
+    }
+}
+
+impl From<EvaluationError> for InterpreterError {
+    fn from(value: EvaluationError) -> Self {
+        Self {
+            code: Some(value.code),
+            ..Self::new(&value.to_string(), InterpreterErrorKind::Evaluation)
+        }
+    }
+}
+
+impl From<io::Error> for InterpreterError {
+    fn from(value: io::Error) -> Self {
+        Self::new(
+            &format!("failed to write output: {}", value),
+            InterpreterErrorKind::Output(value.kind()),
+        )
+    }
+}
+
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl std::error::Error for InterpreterError {}
+
+#[derive(Clone, Debug)]
+pub struct ScanError {
+    pub code: ErrorCode,
+    pub line: usize,
+    pub column: usize,
+    pub msg: String,
+}
+
+impl ScanError {
+    pub fn new(code: ErrorCode, args: &[&dyn fmt::Display], line: usize, column: usize) -> Self {
+        Self {
+            code,
+            line,
+            column,
+            msg: code.message(args),
+        }
+    }
+
+    pub fn location(&self) -> LocationInfo {
+        LocationInfo::new(self.line, self.column, 0)
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "scan error[{}] at {}; {}",
+            self.code,
+            self.location(),
+            self.msg
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExceptionType {
+    RuntimeException,
+}
+
+impl fmt::Display for ExceptionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExceptionType::RuntimeException => write!(f, "runtime exception"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParserError {
+    exc_type: ExceptionType,
+    code: ErrorCode,
+    location: LocationInfo,
+    msg: String,
+}
+
+impl ParserError {
+    /// Error of the kind `code` found at the token, with the placeholders of
+    /// its template filled by `args`
+    ///
+    /// Errors found at the [TokenType::Eof](crate::TokenType::Eof) following
+    /// the last token are worded as running out of input, unlike those of
+    /// an input without tokens, whose Eof is at its very start.
+    pub fn new(code: ErrorCode, args: &[&dyn fmt::Display], token: &Token) -> Self {
+        let mut msg = code.message(args);
+        if token._type == crate::TokenType::Eof && token.offset > 0 {
+            msg = format!("unexpected end of input, {}", msg);
+        }
+        Self {
+            msg,
+            code,
+            location: token.location(),
+            exc_type: ExceptionType::RuntimeException,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// What went wrong, without the code and location
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    /// Location of the token the error was found at, line `0` for
+    /// generated code
+    pub fn location(&self) -> LocationInfo {
+        self.location
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {} {}",
+            self.exc_type,
+            self.code,
+            self.msg,
+            location(self.location.line(), self.location.column())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenType;
+
+    #[test]
+    fn errors_in_synthetic_tokens_mention_generated_code() {
+        let token = Token::synthetic(TokenType::SemiColon, ";");
+        let error = ParserError::new(codes::EXPECTED_EXPRESSION, &[], &token);
+        assert_eq!(
+            error.to_string(),
+            "runtime exception[P003]: expected an expression in generated code"
+        );
+
+        let error = EvaluationError::new(codes::INVALID_LITERAL, &[&"1x"], 2, 4);
+        assert_eq!(
+            error.to_string(),
+            "evaluation error[R023]: invalid literal `1x` at line 2 column 4"
+        );
+    }
+
+    #[test]
+    fn interpreter_errors_keep_the_code() {
+        let error = InterpreterError::from(ScanError::new(codes::UNKNOWN_CHARACTER, &[&'@'], 1, 3));
+        assert_eq!(error.code, Some(codes::UNKNOWN_CHARACTER));
+        assert_eq!(error.msg, "scan error[S001] at 1:3; unknown character `@`");
+
+        let io = io::Error::new(io::ErrorKind::BrokenPipe, "closed");
+        assert_eq!(InterpreterError::from(io).code, None);
+    }
+}
@@ -1,12 +1,150 @@
-use crate::analyzers::{Parser, Scanner};
-use crate::{Environment, EvaluationError, InterpreterError, Literal, Statement};
-use std::error::Error;
-use std::fs;
-use std::path::PathBuf;
+use crate::analyzers::lints::LintConfig;
+use crate::errors::{codes, Frame, InterpreterErrorKind, Warning};
+use crate::stdlib::{self, Clock, Rng};
+use crate::{
+    Environment, EvaluationError, InterpreterError, LanguageVersion, Literal, Pipeline, Statement,
+    TestConfig, TokenType, UndefinedVariablePolicy,
+};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "cli")]
+use std::time::Instant;
+
+/// Resource usage of the last [Interpreter::interpret] call
+///
+/// Durations are only measured with the `cli` feature and are zero otherwise,
+/// as some targets such as wasm32-unknown-unknown have no clock.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub tokens: usize,
+    /// Statements executed, including the ones nested in blocks
+    pub statements: usize,
+    /// Deepest block nesting reached by the environment
+    pub peak_depth: usize,
+    /// Expressions evaluated, nested ones included
+    pub expressions: usize,
+    /// Variables found by name, for reading or assigning them
+    pub lookups: usize,
+    /// Values copied out of or into variables
+    pub literal_clones: usize,
+    /// Bytes of values echoed and printed to the output
+    pub bytes_written: usize,
+    pub scan_time: Duration,
+    pub parse_time: Duration,
+    pub interpret_time: Duration,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "scan:      {:?} ({} tokens)",
+            self.scan_time, self.tokens
+        )?;
+        writeln!(f, "parse:     {:?}", self.parse_time)?;
+        write!(
+            f,
+            "interpret: {:?} ({} statements, peak environment depth {})",
+            self.interpret_time, self.statements, self.peak_depth
+        )?;
+        write!(
+            f,
+            "\nevaluate:  {} expressions, {} lookups, {} literal clones, {} bytes written",
+            self.expressions, self.lookups, self.literal_clones, self.bytes_written
+        )
+    }
+}
+
+/// Asks an [Interpreter] to stop the program it's running, from another
+/// thread or a signal handler
+///
+/// The program stops before its next statement with an
+/// [Interrupted](InterpreterErrorKind::Interrupted) error, and the
+/// interpreter can run programs again afterwards. An interrupt requested
+/// while no program runs stops the next one.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if an interrupt is waiting for the interpreter to stop
+    pub fn is_interrupted(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Clears the interrupt, returning true if there was one
+    fn take(&self) -> bool {
+        self.0.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// What a `let` declaring a name already declared in the same scope does
+///
+/// The prompt allows redeclaring so lines can be retyped, while scripts run
+/// from the command line reject it inside blocks where it's likely a
+/// mistake. Shadowing a variable of an outer scope is never a redeclaration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedeclarationPolicy {
+    /// The declaration replaces the variable
+    #[default]
+    Allow,
+    /// Redeclaring fails inside blocks and is allowed in the global scope
+    ErrorLocals,
+    /// Redeclaring always fails
+    ErrorAll,
+}
+
+impl RedeclarationPolicy {
+    /// Returns true if redeclaring in a scope `depth` blocks deep fails
+    fn rejects(self, depth: usize) -> bool {
+        match self {
+            RedeclarationPolicy::Allow => false,
+            RedeclarationPolicy::ErrorLocals => depth > 0,
+            RedeclarationPolicy::ErrorAll => true,
+        }
+    }
+}
+
+/// Parses the values of the `--redeclare` flag, `deny` is
+/// [ErrorAll](RedeclarationPolicy::ErrorAll)
+impl FromStr for RedeclarationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(RedeclarationPolicy::Allow),
+            "locals" => Ok(RedeclarationPolicy::ErrorLocals),
+            "deny" => Ok(RedeclarationPolicy::ErrorAll),
+            _ => Err(format!(
+                "unknown redeclaration policy `{}`, expected allow, locals or deny",
+                s
+            )),
+        }
+    }
+}
 
 /// Interpreter implementation for the lox language
 ///
-/// The interpreter can be used to parse and execute lox statements.
+/// The interpreter can be used to parse and execute lox statements. Values of
+/// expression statements are written to the interpreter output, which is
+/// stdout unless another writer is provided.
+///
+/// ## Threading
+/// Programs run on the thread calling [interpret](Interpreter::interpret)
+/// and the interpreter holds no locks or shared state, its variables live in
+/// an [Environment] it owns. An interpreter is `Send` and `Sync` when its
+/// output is, so separate interpreters can run on separate threads, and one
+/// interpreter can be shared between threads behind a `Mutex`, in which case
+/// every program runs to completion before the next one starts.
 ///
 /// ## Examples
 /// ```rust
@@ -18,75 +156,1386 @@ use std::path::PathBuf;
 ///
 /// interpreter.interpret(strict_mode);
 /// ```
-pub struct Interpreter {
+pub struct Interpreter<W: Write = io::Stdout> {
     content: String,
     enclosing: Environment,
+    output: W,
+    stats: Stats,
+    exit_code: Option<i32>,
+    /// Value the last expression statement wrote to the output
+    last_value: Option<Literal>,
+    /// Statements being executed, outermost first
+    trace: Vec<Frame>,
+    version: LanguageVersion,
+    /// Levels of the lints run by [lint](Interpreter::lint)
+    lints: LintConfig,
+    redeclaration: RedeclarationPolicy,
+    /// Programs made of a single block write the value of its last
+    /// expression statement, see [set_block_values](Interpreter::set_block_values)
+    block_values: bool,
+    max_statements: Option<usize>,
+    interrupt: InterruptHandle,
+    /// Set when the program stopped because of an interrupt, which attempts
+    /// don't catch
+    interrupted: bool,
+    /// Failure to write the output of a `print` or `write` statement, which
+    /// stops the program with an [EvaluationError] until it's reported
+    output_error: Option<io::Error>,
 }
 
 impl Interpreter {
     pub fn new(content: String) -> Self {
+        Self::with_output(content, io::stdout())
+    }
+
+    pub fn from_file(path: PathBuf) -> Result<Self, InterpreterError> {
+        Self::from_file_limited(path, None)
+    }
+
+    /// Reads the program at `path`, refusing files larger than `max_size`
+    /// bytes
+    ///
+    /// Errors name the path, and tell files that can't be read apart from
+    /// files that aren't valid UTF-8, which are most likely binary files
+    /// passed by mistake.
+    pub fn from_file_limited(
+        path: PathBuf,
+        max_size: Option<u64>,
+    ) -> Result<Self, InterpreterError> {
+        Ok(Self::new(read_source(&path, max_size)?))
+    }
+}
+
+pub(crate) fn read_source(path: &Path, max_size: Option<u64>) -> Result<String, InterpreterError> {
+    let error = |msg: String| InterpreterError::new(&msg, InterpreterErrorKind::Source);
+    let cannot_read = |e: io::Error| error(format!("cannot read {}: {}", path.display(), e));
+
+    let file = File::open(path).map_err(cannot_read)?;
+    let mut bytes = Vec::new();
+    match max_size {
+        // Read one byte past the limit rather than trusting the file size,
+        // the file may grow while it's read
+        Some(max) => file.take(max.saturating_add(1)).read_to_end(&mut bytes),
+        None => (&file).read_to_end(&mut bytes),
+    }
+    .map_err(cannot_read)?;
+    if let Some(max) = max_size.filter(|&max| bytes.len() as u64 > max) {
+        return Err(error(format!(
+            "{} is larger than the limit of {} bytes",
+            path.display(),
+            max
+        )));
+    }
+
+    String::from_utf8(bytes).map_err(|e| {
+        error(format!(
+            "{} is not valid UTF-8 (is it a binary file?), first invalid byte at offset {}",
+            path.display(),
+            e.utf8_error().valid_up_to()
+        ))
+    })
+}
+
+impl<W: Write> Interpreter<W> {
+    /// Creates an interpreter writing program output to `output`
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use lox::Interpreter;
+    ///
+    /// let mut interpreter = Interpreter::with_output("2 * 4;".into(), Vec::new());
+    /// interpreter.interpret(true).unwrap();
+    ///
+    /// assert_eq!(interpreter.output(), b"8\n");
+    /// ```
+    pub fn with_output(content: String, output: W) -> Self {
+        Self::with_stdlib(content, output, true)
+    }
+
+    /// Creates an interpreter writing program output to `output`, leaving
+    /// the environment empty unless `stdlib` is set
+    pub fn with_stdlib(content: String, output: W, stdlib: bool) -> Self {
+        let mut enclosing = Environment::new();
+        if stdlib {
+            stdlib::install(&mut enclosing);
+        }
+        let version = LanguageVersion::default();
+        enclosing.define("LOX_VERSION".into(), Literal::Integer(version.number()));
         Self {
             content,
-            enclosing: Environment::default(),
+            enclosing,
+            output,
+            stats: Stats::default(),
+            exit_code: None,
+            last_value: None,
+            trace: Vec::new(),
+            version,
+            lints: LintConfig::default(),
+            redeclaration: RedeclarationPolicy::default(),
+            block_values: false,
+            max_statements: None,
+            interrupt: InterruptHandle::default(),
+            interrupted: false,
+            output_error: None,
         }
     }
 
-    pub fn from_file(path: PathBuf) -> Result<Self, Box<dyn Error>> {
-        Ok(Self {
-            content: fs::read_to_string(path)?,
-            enclosing: Environment::default(),
-        })
-    }
-
     pub fn set_content(&mut self, content: String) {
         self.content = content;
     }
 
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    pub(crate) fn output_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+
+    /// Runs programs as written for the language version, which is also the
+    /// value of the `LOX_VERSION` global
+    pub fn set_language_version(&mut self, version: LanguageVersion) {
+        self.version = version;
+        self.enclosing
+            .define("LOX_VERSION".into(), Literal::Integer(version.number()));
+    }
+
+    pub fn language_version(&self) -> LanguageVersion {
+        self.version
+    }
+
+    /// Variables of the programs run by the interpreter, hosts can use it to
+    /// define variables before running a program or read them afterwards
+    pub fn environment(&self) -> &Environment {
+        &self.enclosing
+    }
+
+    pub fn environment_mut(&mut self) -> &mut Environment {
+        &mut self.enclosing
+    }
+
+    /// Puts back the environment saved before a run that panicked, along
+    /// with dropping what the run left behind
+    #[cfg(feature = "cli")]
+    pub(crate) fn restore(&mut self, environment: Environment) {
+        self.enclosing = environment;
+        self.trace.clear();
+        self.exit_code = None;
+        self.last_value = None;
+    }
+
+    /// Seeds the generator behind `random` and `randomInt` so programs
+    /// produce the same numbers on every run
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        *self.enclosing.rng() = Rng::new(seed);
+    }
+
+    /// Freezes everything that could make runs of a program differ, see
+    /// [TestConfig]
+    ///
+    /// Environment access is enabled since `env` and `args` only see the
+    /// variables and arguments of the config.
+    pub fn set_test_config(&mut self, config: &TestConfig) {
+        self.enable_env_access(true);
+        self.set_rng_seed(config.seed);
+        self.enclosing.set_clock(Clock::Fake {
+            now: config.clock_start,
+            step: config.clock_step,
+        });
+        self.enclosing.set_env_vars(Some(config.env.clone()));
+        self.set_args(config.args.clone());
+    }
+
+    /// Allows scripts to read and write files, file natives fail with
+    /// "file I/O is disabled" until this is enabled
+    pub fn enable_file_io(&mut self, enabled: bool) {
+        self.enclosing.set_file_io(enabled);
+    }
+
+    /// Allows scripts to read their arguments and environment variables,
+    /// the natives fail with "environment access is disabled" until this is
+    /// enabled
+    pub fn enable_env_access(&mut self, enabled: bool) {
+        self.enclosing.set_env_access(enabled);
+    }
+
+    /// Sets the arguments returned by the `args` native
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.enclosing.set_args(args);
+    }
+
+    /// Writes numbers output by expression statements with `digits` decimal
+    /// places, `None` writes the shortest representation that reads back as
+    /// the same number
+    ///
+    /// Scripts change this with the `printPrecision` native, the setting is
+    /// kept between [interpret](Interpreter::interpret) calls.
+    pub fn set_print_precision(&mut self, digits: Option<usize>) {
+        self.enclosing.set_precision(digits);
+    }
+
+    /// Limits how many variables programs may have declared at once, the
+    /// declaration exceeding it fails with an evaluation error
+    ///
+    /// Along with [set_max_string_len](Interpreter::set_max_string_len) this
+    /// bounds the memory untrusted programs can use. Variables are kept when
+    /// a program fails, so later programs run by the same interpreter can
+    /// still use them.
+    pub fn set_max_variables(&mut self, max: Option<usize>) {
+        self.enclosing.set_max_variables(max);
+    }
+
+    /// Limits the characters of strings built by programs with
+    /// interpolation or natives
+    pub fn set_max_string_len(&mut self, max: Option<usize>) {
+        self.enclosing.set_max_string_len(max);
+    }
+
+    /// Sets what reading an undeclared variable evaluates to, failing with an
+    /// undefined variable error by default
+    ///
+    /// Under [UndefinedVariablePolicy::Nil] the reads of the last
+    /// [interpret](Interpreter::interpret) call are kept as warnings, see
+    /// [undefined_reads](Interpreter::undefined_reads). Assigning an
+    /// undeclared variable still fails.
+    pub fn set_undefined_variable_policy(&mut self, policy: UndefinedVariablePolicy) {
+        self.enclosing.set_undefined_variable_policy(policy);
+    }
+
+    /// Undeclared variables read as nil during the last
+    /// [interpret](Interpreter::interpret) call, in the order they were read
+    pub fn undefined_reads(&self) -> &[Warning] {
+        self.enclosing.undefined_reads()
+    }
+
+    /// Limits how many statements, counting the ones nested in blocks, an
+    /// [interpret](Interpreter::interpret) call may execute, the statement
+    /// exceeding it fails with an evaluation error
+    pub fn set_max_statements(&mut self, max: Option<usize>) {
+        self.max_statements = max;
+    }
+
+    /// Handle stopping the programs this interpreter runs
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Makes the interpreter stop for interrupts of `handle` instead of its
+    /// own, so a signal handler can reach every interpreter
+    #[cfg(feature = "cli")]
+    pub(crate) fn set_interrupt_handle(&mut self, handle: InterruptHandle) {
+        self.interrupt = handle;
+    }
+
+    /// Sets whether programs may redeclare variables in the same scope,
+    /// allowed by default
+    pub fn set_redeclaration_policy(&mut self, policy: RedeclarationPolicy) {
+        self.redeclaration = policy;
+    }
+
+    /// Makes a program made of a single block, such as `{ let a = 1; a + 1 }`
+    /// typed at the prompt, write the value of the block's last statement
+    /// when it's an expression, as top-level expressions do
+    ///
+    /// The block still discards its variables when it's left. Disabled by
+    /// default, blocks of any other program never write values.
+    pub fn set_block_values(&mut self, enabled: bool) {
+        self.block_values = enabled;
+    }
+
+    /// Exit code requested by an `exit` statement during the last
+    /// [interpret](Interpreter::interpret) call
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Value of the last top-level expression written to the output during
+    /// the last [interpret](Interpreter::interpret) call
+    pub fn last_value(&self) -> Option<&Literal> {
+        self.last_value.as_ref()
+    }
+
+    /// Resource usage of the last [interpret](Interpreter::interpret) call,
+    /// plus the statements run by [execute](Interpreter::execute) since
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Clears the stats, which [interpret](Interpreter::interpret) also does
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+        self.enclosing.reset_counters();
+    }
+
+    /// Sets the levels of the lints run by [lint](Interpreter::lint), which
+    /// the `// lox-lint:` comment of the content overrides
+    pub fn set_lint_config(&mut self, lints: LintConfig) {
+        self.lints = lints;
+    }
+
+    /// Scans and parses the current content without executing it, returning
+    /// the warnings found by static analysis
+    pub fn lint(&self, strict: bool) -> Result<Vec<Warning>, InterpreterError> {
+        Pipeline::new(self.version, strict)
+            .with_lints(self.lints.clone())
+            .check(&self.content)
+    }
+
+    /// Executes the current content
+    ///
+    /// Failures to write program output are reported with
+    /// [InterpreterErrorKind::Output] so callers can tell them apart from
+    /// errors in the script itself. Output is flushed before returning.
     pub fn interpret(&mut self, strict: bool) -> Result<(), InterpreterError> {
-        let scanner =
-            Scanner::new(&self.content).map_err(|e| InterpreterError { msg: e.to_string() })?;
-        let mut parser = Parser::new(scanner.tokens, strict);
-        let statements = parser
-            .parse()
-            .map_err(|e| InterpreterError { msg: e.to_string() })?;
+        self.stats = Stats::default();
+        self.exit_code = None;
+        self.last_value = None;
+
+        let pipeline = Pipeline::new(self.version, strict);
+        let (tokens, scan_time) = timed(|| pipeline.scan(&self.content));
+        let tokens = tokens?;
+        self.stats.tokens = tokens.len();
+        self.stats.scan_time = scan_time;
+
+        let (statements, parse_time) = timed(|| pipeline.parse(tokens));
+        let statements = statements?;
+        self.stats.parse_time = parse_time;
+
+        self.execute(statements)
+    }
+
+    /// Executes statements parsed by a [Pipeline], the last stage of
+    /// [interpret](Interpreter::interpret), ignoring the current content
+    ///
+    /// Like interpret, output is flushed before returning and the exit code
+    /// and last value are those of these statements.
+    pub fn execute(&mut self, statements: Vec<Statement>) -> Result<(), InterpreterError> {
+        self.exit_code = None;
+        self.last_value = None;
+        self.enclosing.reset_counters();
+        self.enclosing.clear_undefined_reads();
+
+        let ((result, flushed), interpret_time) = timed(|| {
+            let result = self.execute_all(statements);
+            (result, self.output.flush())
+        });
+        self.stats.interpret_time = interpret_time;
+        let counters = self.enclosing.counters();
+        self.stats.expressions += counters.expressions;
+        self.stats.lookups += counters.lookups;
+        self.stats.literal_clones += counters.literal_clones;
+
+        result?;
+        flushed?;
+        Ok(())
+    }
+
+    fn execute_all(&mut self, statements: Vec<Statement>) -> Result<(), InterpreterError> {
+        let block_values = self.block_values && statements.len() == 1;
         for statement in statements {
-            let literal = self
-                .evaluate_statement(statement)
-                .map_err(|e| InterpreterError { msg: e.to_string() })?;
-            if let Some(literal) = literal {
-                let literal: String = literal.into();
-                println!("{}", literal);
+            let is_block = matches!(statement, Statement::Block(_, _));
+            let literal =
+                self.evaluate_statement(statement)
+                    .map_err(|e| match self.output_error.take() {
+                        Some(output_error) => InterpreterError::from(output_error),
+                        None if std::mem::take(&mut self.interrupted) => InterpreterError {
+                            code: Some(e.code()),
+                            ..InterpreterError::new(
+                                &e.to_string(),
+                                InterpreterErrorKind::Interrupted,
+                            )
+                        },
+                        None => e.into(),
+                    })?;
+            if let Some(literal) = literal.filter(|_| block_values || !is_block) {
+                self.last_value = Some(literal.clone());
+                let literal = self.format_output(literal);
+                writeln!(self.output, "{}", literal)?;
+                self.stats.bytes_written += literal.len() + 1;
+            }
+            if self.exit_code.is_some() {
+                break;
             }
         }
 
         Ok(())
     }
-    fn evaluate_statements(&mut self, statements: Vec<Statement>) -> Result<(), EvaluationError> {
+
+    /// Text written to the output for the value, numbers are written with
+    /// the print precision
+    fn format_output(&self, literal: Literal) -> String {
+        match (literal, self.enclosing.precision()) {
+            (Literal::Number(value), Some(digits)) if value.is_finite() => {
+                fixed_point(value, digits)
+            }
+            (Literal::Integer(value), Some(digits)) if digits > 0 => {
+                format!("{}.{}", value, "0".repeat(digits))
+            }
+            (literal, _) => literal.into(),
+        }
+    }
+
+    /// Evaluates the statements of a block, returning the value of the last
+    /// one if it's an expression statement
+    fn evaluate_statements(
+        &mut self,
+        statements: Vec<Statement>,
+    ) -> Result<Option<Literal>, EvaluationError> {
+        let mut value = None;
         for statement in statements {
-            self.evaluate_statement(statement)?;
+            let is_expression =
+                matches!(statement, Statement::Expression(_) | Statement::Variable(_));
+            value = self
+                .evaluate_statement(statement)?
+                .filter(|_| is_expression);
+            if self.exit_code.is_some() {
+                break;
+            }
         }
-        Ok(())
+        Ok(value)
     }
 
     fn evaluate_statement(
         &mut self,
         statement: Statement,
     ) -> Result<Option<Literal>, EvaluationError> {
+        self.stats.statements += 1;
+        if self.interrupt.take() {
+            self.interrupted = true;
+            let token = statement.leftmost_token();
+            return Err(EvaluationError::at(codes::INTERRUPTED, &[], token));
+        }
+        if let Some(max) = self
+            .max_statements
+            .filter(|&max| self.stats.statements > max)
+        {
+            let token = statement.leftmost_token();
+            return Err(EvaluationError::at(codes::STATEMENT_LIMIT, &[&max], token));
+        }
         match statement {
-            Statement::Expression(expr) => Ok(Some(expr.evaluate(&self.enclosing)?)),
-            Statement::Block(statements) => {
+            Statement::Expression(expr) => Ok(Some(expr.evaluate(&mut self.enclosing)?)),
+            Statement::Block(brace, statements) => {
+                self.trace.push(Frame::new("block", &brace));
                 self.enclosing.enter_block();
-                self.evaluate_statements(statements)?;
+                self.stats.peak_depth = self.stats.peak_depth.max(self.enclosing.depth());
+                let result = self
+                    .evaluate_statements(statements)
+                    .map_err(|e| e.with_trace(&self.trace));
                 self.enclosing.leave_block();
-                Ok(None)
+                self.trace.pop();
+                result
+            }
+            Statement::Variable(expr) => Ok(Some(expr.evaluate(&mut self.enclosing)?)),
+            Statement::Assert(keyword, condition, message) => {
+                if condition.evaluate(&mut self.enclosing)?.is_truthy() {
+                    return Ok(None);
+                }
+                let mut msg = codes::ASSERTION_FAILED.message(&[&String::from(condition)]);
+                if let Some(message) = message {
+                    let message: String = message.evaluate(&mut self.enclosing)?.into();
+                    msg = format!("{}: {}", msg, message);
+                }
+                Err(EvaluationError::with_message(
+                    codes::ASSERTION_FAILED,
+                    &msg,
+                    &keyword,
+                ))
             }
-            Statement::Variable(expr) => Ok(Some(expr.evaluate(&self.enclosing)?)),
+            Statement::Attempt(body, name, handler) => {
+                // Blocks leave their scope and trace frame even when they
+                // fail, so the handler runs in the scope the attempt did
+                let error = match self.evaluate_statement(*body) {
+                    Err(e) if self.output_error.is_none() && !self.interrupted => e,
+                    result => return result.map(|_| None),
+                };
+                self.enclosing.enter_block();
+                let result = self
+                    .enclosing
+                    .declare(
+                        name.lexeme.clone(),
+                        Literal::String(error.msg().into()),
+                        (name.line, name.column),
+                    )
+                    .map_err(|e| EvaluationError::with_message(codes::VARIABLE_LIMIT, &e, &name))
+                    .and_then(|_| self.evaluate_statement(*handler));
+                self.enclosing.leave_block();
+                result.map(|_| None)
+            }
+            Statement::Empty(_) => Ok(None),
+            Statement::Print(keyword, expr) => {
+                let value = expr.evaluate(&mut self.enclosing)?;
+                let mut text = self.format_output(value);
+                if keyword._type == TokenType::Print {
+                    text.push('\n');
+                }
+                match self.output.write_all(text.as_bytes()) {
+                    Ok(()) => {
+                        self.stats.bytes_written += text.len();
+                        Ok(None)
+                    }
+                    Err(e) => {
+                        self.output_error = Some(e);
+                        Err(EvaluationError::at(codes::OUTPUT_FAILED, &[], &keyword))
+                    }
+                }
+            }
+            Statement::Exit(token, expr) => match expr.evaluate(&mut self.enclosing)? {
+                Literal::Number(code) => {
+                    self.exit_code = Some((code.trunc() as i64).rem_euclid(256) as i32);
+                    Ok(None)
+                }
+                Literal::Integer(code) => {
+                    self.exit_code = Some(code.rem_euclid(256) as i32);
+                    Ok(None)
+                }
+                value => Err(EvaluationError::at(
+                    codes::INVALID_EXIT_CODE,
+                    &[&value.type_name()],
+                    &token,
+                )),
+            },
             Statement::Assign(token, expr) => {
                 let name = token.lexeme.to_owned();
-                let literal = expr.evaluate(&self.enclosing)?;
-                self.enclosing.define(name, literal);
+                if self.enclosing.is_declared_locally(&name)
+                    && self.redeclaration.rejects(self.enclosing.depth())
+                {
+                    return Err(match self.enclosing.declared_at(&name) {
+                        Some((line, column)) => EvaluationError::at(
+                            codes::ALREADY_DECLARED,
+                            &[&name, &line, &column],
+                            &token,
+                        ),
+                        None => EvaluationError::at(codes::ALREADY_DEFINED, &[&name], &token),
+                    });
+                }
+
+                let literal = expr.evaluate(&mut self.enclosing)?;
+                self.enclosing
+                    .declare(name, literal, (token.line, token.column))
+                    .map_err(|e| {
+                        EvaluationError::with_message(codes::VARIABLE_LIMIT, &e, &token)
+                    })?;
                 Ok(None)
             }
         }
     }
 }
+
+/// Formats `value` with `digits` decimal places, without a sign when it
+/// rounds to zero
+fn fixed_point(value: f32, digits: usize) -> String {
+    let formatted = format!("{:.*}", digits, value);
+    match formatted.strip_prefix('-') {
+        Some(unsigned) if unsigned.bytes().all(|b| b == b'0' || b == b'.') => unsigned.into(),
+        _ => formatted,
+    }
+}
+
+/// Runs `f` returning its result and how long it took
+#[cfg(feature = "cli")]
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let value = f();
+    (value, start.elapsed())
+}
+
+#[cfg(not(feature = "cli"))]
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    (f(), Duration::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::LoxTest;
+    use crate::types::Expression;
+    use crate::{Native, Token};
+
+    /// Writer that accepts a fixed number of bytes before the pipe "closes"
+    struct ClosingPipe {
+        remaining: usize,
+        written: Vec<u8>,
+    }
+
+    impl Write for ClosingPipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.remaining == 0 {
+                return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+            }
+            let len = buf.len().min(self.remaining);
+            self.remaining -= len;
+            self.written.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn source_files_are_checked_before_running() {
+        let dir = std::env::temp_dir().join(format!("lox-source-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let binary = dir.join("binary.lx");
+        std::fs::write(&binary, b"1;\n\xff\xfe").unwrap();
+        let script = dir.join("script.lx");
+        std::fs::write(&script, "1 + 1;").unwrap();
+
+        let message = |path: &PathBuf, max| {
+            Interpreter::from_file_limited(path.clone(), max)
+                .err()
+                .map(|e| (e.msg, e.kind))
+        };
+        assert_eq!(
+            message(&binary, None),
+            Some((
+                format!(
+                    "{} is not valid UTF-8 (is it a binary file?), first invalid byte at offset 3",
+                    binary.display()
+                ),
+                InterpreterErrorKind::Source
+            ))
+        );
+        assert_eq!(
+            message(&script, Some(5)).unwrap().0,
+            format!("{} is larger than the limit of 5 bytes", script.display())
+        );
+        assert_eq!(message(&script, Some(6)), None);
+
+        let missing = dir.join("missing.lx");
+        let error = message(&missing, None).unwrap().0;
+        assert!(
+            error.starts_with(&format!("cannot read {}: ", missing.display())),
+            "{}",
+            error
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn output_is_written_to_the_provided_writer() {
+        let mut interpreter = Interpreter::with_output("1 + 1;\n\"two\";".into(), Vec::new());
+        interpreter.interpret(true).unwrap();
+
+        assert_eq!(String::from_utf8_lossy(interpreter.output()), "2\ntwo\n");
+    }
+
+    #[test]
+    fn closed_output_is_reported_as_an_output_error() {
+        let pipe = ClosingPipe {
+            remaining: 2,
+            written: Vec::new(),
+        };
+        let mut interpreter = Interpreter::with_output("1;\n2;\n3;".into(), pipe);
+        let error = interpreter.interpret(true).unwrap_err();
+
+        assert!(error.is_broken_pipe(), "{:?}", error);
+        assert_eq!(interpreter.output().written, b"1\n");
+    }
+
+    #[test]
+    fn print_and_write_output_values() {
+        LoxTest::new("write \"a\"; write \"b\"; print \"\";")
+            .expect_output("ab\n")
+            .run();
+        LoxTest::new("print nil; print \"\"; { write 1 + 1; print \"${true}\"; }")
+            .expect_output("nil\n\n2true\n")
+            .run();
+        LoxTest::new("printPrecision(2); write 1; print 2.5; print \"${2.5}\";")
+            .expect_output("nil\n1.002.50\n2.5\n")
+            .run();
+    }
+
+    #[test]
+    fn write_output_comes_before_the_error() {
+        LoxTest::new("write \"partial\";\n{ write 1 & true; }")
+            .expect_output("partial")
+            .expect_error(
+                "evaluation error[R007]: operand true of `&` is not a number at line 2 column 11
+    in block started at line 2 column 1",
+            )
+            .run();
+    }
+
+    #[test]
+    fn closed_output_stops_print_statements() {
+        let pipe = ClosingPipe {
+            remaining: 2,
+            written: Vec::new(),
+        };
+        let source = "{ write \"ab\"; print \"c\"; exit 1; }";
+        let mut interpreter = Interpreter::with_output(source.into(), pipe);
+        let error = interpreter.interpret(true).unwrap_err();
+
+        assert!(error.is_broken_pipe(), "{:?}", error);
+        assert_eq!(interpreter.output().written, b"ab");
+        assert_eq!(interpreter.exit_code(), None);
+    }
+
+    #[test]
+    fn attempts_catch_evaluation_errors() {
+        LoxTest::new(
+            "attempt { print 1; missing = 2; print 3; }\n\
+             handle (err) { print \"caught: ${err}\"; }\n\
+             print \"after\";",
+        )
+        .expect_output("1\ncaught: cannot assign to undeclared variable `missing`\nafter\n")
+        .run();
+    }
+
+    #[test]
+    fn attempts_without_errors_skip_the_handler() {
+        LoxTest::new("let a = 1; attempt { a = 2; } handle (err) { a = 3; } a;")
+            .expect_output("2\n")
+            .expect_var("a", Literal::Integer(2))
+            .run();
+    }
+
+    #[test]
+    fn failed_attempts_leave_their_scopes() {
+        let source = "let a = 1;
+            attempt { let a = 2; { let b = 3; 1 & true; } }
+            handle (err) { print a; let b = 4; }
+            a;";
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.interpret(true).unwrap();
+
+        assert_eq!(String::from_utf8_lossy(interpreter.output()), "1\n1\n");
+        assert_eq!(interpreter.environment().depth(), 0);
+        assert!(!interpreter.environment().contains("b"));
+        assert!(!interpreter.environment().contains("err"));
+    }
+
+    #[test]
+    fn nested_attempts_catch_innermost_first() {
+        LoxTest::new(
+            "attempt {
+               attempt { 1 & true; } handle (err) { print \"inner\"; -\"a\"; }
+               print \"skipped\";
+             } handle (err) { print \"outer: ${err}\"; }",
+        )
+        .expect_output("inner\nouter: operator `-` requires a number, got string \"a\"\n")
+        .run();
+    }
+
+    #[test]
+    fn errors_in_handlers_propagate() {
+        LoxTest::new("attempt { 1 & true; } handle (err) { assert false, err; }")
+            .expect_error(
+                "evaluation error[R012]: assertion failed: false: operand true of `&` is not a number \
+                 at line 1 column 38
+    in block started at line 1 column 36",
+            )
+            .run();
+    }
+
+    #[test]
+    fn exit_is_not_caught_by_attempts() {
+        LoxTest::new("attempt { { exit 3; } } handle (err) { print \"caught\"; }\nprint 1;")
+            .expect_output("")
+            .expect_exit(3)
+            .run();
+    }
+
+    #[test]
+    fn stats_count_tokens_statements_and_depth() {
+        let source = "let a = 1;\n{ let b = 2; { a; } }";
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.interpret(true).unwrap();
+
+        let stats = interpreter.stats();
+        assert_eq!(stats.tokens, 16);
+        assert_eq!(stats.statements, 5);
+        assert_eq!(stats.peak_depth, 2);
+        if cfg!(feature = "cli") {
+            assert!(stats.scan_time + stats.parse_time + stats.interpret_time > Duration::ZERO);
+        }
+    }
+
+    /// The cost of evaluating a program, changes to these numbers should be
+    /// on purpose
+    #[test]
+    fn stats_count_the_work_of_evaluating_expressions() {
+        // Every read of `a` is an expression, a lookup and a clone, the
+        // assignment adds a lookup and a clone of its own
+        let source = "let a = 1;\n{ a; a + a; a = a * 2; }\nprint a;\na;";
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.interpret(true).unwrap();
+
+        let stats = interpreter.stats().clone();
+        assert_eq!(
+            (stats.expressions, stats.lookups, stats.literal_clones),
+            (11, 7, 7)
+        );
+        assert_eq!(stats.bytes_written, "2\n2\n".len());
+
+        interpreter.reset_stats();
+        assert_eq!(interpreter.stats(), &Stats::default());
+        interpreter
+            .execute(vec![Statement::Variable(Expression::Variable(Token::new(
+                "a",
+                1,
+                1,
+                TokenType::Identifier,
+            )))])
+            .unwrap();
+        assert_eq!(
+            (
+                interpreter.stats().lookups,
+                interpreter.stats().bytes_written
+            ),
+            (1, 2)
+        );
+    }
+
+    #[test]
+    fn exit_stops_the_program_with_the_code() {
+        LoxTest::new("\"a\";\nexit(3);\n\"b\";")
+            .expect_output("a\n")
+            .expect_exit(3)
+            .run();
+    }
+
+    #[test]
+    fn exit_unwinds_nested_blocks() {
+        let source = "{ { exit(256 + 7.9); } let a = 1; }\n\"after\";";
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.interpret(true).unwrap();
+
+        assert_eq!(interpreter.exit_code(), Some(7));
+        assert!(interpreter.output().is_empty());
+    }
+
+    #[test]
+    fn passing_assertions_do_nothing() {
+        let source = "let a = 2; assert a == 2; assert a, missing(); assert \"\"; a;";
+
+        assert_eq!(run(source), "2\n");
+    }
+
+    #[test]
+    fn failing_assertions_report_the_condition_and_message() {
+        let scenarios = [
+            (
+                "let a = 1;\nassert a + 1 == 3;",
+                "evaluation error[R012]: assertion failed: ((a + 1) == 3) at line 2 column 1",
+            ),
+            (
+                "let n = 5; { assert n < 0, \"n is ${n}\"; }",
+                "evaluation error[R012]: assertion failed: (n < 0): n is 5 at line 1 column 14
+    in block started at line 1 column 12",
+            ),
+            (
+                "assert nil, 1 & true;",
+                "evaluation error[R007]: operand true of `&` is not a number at line 1 column 15",
+            ),
+        ];
+
+        for (source, expected) in scenarios {
+            let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+            let error = interpreter.interpret(true).unwrap_err();
+            assert_eq!(error.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn exit_requires_a_number() {
+        let mut interpreter = Interpreter::with_output("exit(true);".into(), Vec::new());
+        let error = interpreter.interpret(true).unwrap_err();
+
+        assert!(
+            error.msg.contains("exit code must be a number"),
+            "{}",
+            error
+        );
+        assert_eq!(interpreter.exit_code(), None);
+    }
+
+    fn run(source: &str) -> String {
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.interpret(true).unwrap();
+        String::from_utf8_lossy(interpreter.output()).into_owned()
+    }
+
+    #[test]
+    fn chained_assignments_give_every_target_the_rightmost_value() {
+        LoxTest::new("let a = 1; let b = 2;\na = b = 0;")
+            .expect_output("0\n")
+            .expect_var("a", Literal::Integer(0))
+            .expect_var("b", Literal::Integer(0))
+            .run();
+
+        LoxTest::new("let a = 1; let b = 2; let c = 3;\na = b = c = a + 10;\na; b; c;")
+            .expect_output("11\n11\n11\n11\n")
+            .run();
+    }
+
+    #[test]
+    fn assignments_evaluate_to_the_assigned_value() {
+        let source = "let a = 1;
+(a = 2) + 1;
+a;";
+        assert_eq!(run(source), "3\n2\n");
+
+        let source = "let a = 1;
+{ let b = 5; a = b * 2; }
+a;";
+        assert_eq!(run(source), "10\n");
+    }
+
+    #[test]
+    fn assigning_undeclared_variables_is_an_error() {
+        LoxTest::new("let a = 1;\na = b = 2;")
+            .expect_error(
+                "evaluation error[R002]: cannot assign to undeclared variable `b` at line 2 column 5",
+            )
+            .expect_var("a", Literal::Integer(1))
+            .run();
+    }
+
+    #[test]
+    fn undefined_names_suggest_similar_declarations() {
+        LoxTest::new("let length = 1;\n{ lenght = 2; }")
+            .expect_error(
+                "evaluation error[R002]: cannot assign to undeclared variable `lenght`; did you mean `length`? at line 2 column 3\n    in block started at line 2 column 1",
+            )
+            .run();
+        LoxTest::new("let text = \"abc\";\nlenn(text);")
+            .expect_error("evaluation error[R003]: undefined function `lenn`; did you mean `len`? at line 2 column 10")
+            .run();
+    }
+
+    #[test]
+    fn statements_are_limited_per_run() {
+        let mut interpreter = Interpreter::with_output(String::new(), Vec::new());
+        interpreter.set_max_statements(Some(3));
+
+        interpreter.set_content("let a = 1;\n{ a = 2; }".into());
+        interpreter.interpret(true).unwrap();
+        interpreter.set_content("{ a = 3; print a; a; }".into());
+        assert_eq!(
+            interpreter.interpret(true).unwrap_err().msg,
+            "evaluation error[R014]: statement limit of 3 reached at line 1 column 19\n    in block started at line 1 column 1"
+        );
+        assert_eq!(interpreter.output(), b"3\n");
+    }
+
+    #[test]
+    fn interrupts_stop_before_the_next_statement() {
+        let mut interpreter = Interpreter::with_output("print 1;\n{ print 2; }".into(), Vec::new());
+        interpreter.interrupt_handle().interrupt();
+
+        let error = interpreter.interpret(true).unwrap_err();
+        assert_eq!(error.kind, InterpreterErrorKind::Interrupted);
+        assert_eq!(
+            error.msg,
+            "evaluation error[R013]: interrupted at line 1 column 1"
+        );
+        assert!(!interpreter.interrupt_handle().is_interrupted());
+
+        // The interrupt was used up, running again goes through
+        interpreter.interpret(true).unwrap();
+        assert_eq!(interpreter.output(), b"1\n2\n");
+    }
+
+    thread_local! {
+        static HANDLE: std::cell::RefCell<Option<InterruptHandle>> = Default::default();
+    }
+
+    /// Native interrupting the interpreter of the test that defined it
+    fn interrupting_native(_: &mut Environment, _: &[Literal]) -> Result<Literal, String> {
+        HANDLE.with(|handle| handle.borrow().as_ref().unwrap().interrupt());
+        Ok(Literal::Nil)
+    }
+
+    #[test]
+    fn attempts_do_not_catch_interrupts() {
+        let source =
+            "attempt {\n    { stop(); print \"skipped\"; }\n} handle (err) {\n    print err;\n}";
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.environment_mut().define(
+            "stop".into(),
+            Literal::Native(Native {
+                name: "stop",
+                arity: 0,
+                function: interrupting_native,
+            }),
+        );
+        HANDLE.with(|handle| *handle.borrow_mut() = Some(interpreter.interrupt_handle()));
+
+        let error = interpreter.interpret(true).unwrap_err();
+        assert_eq!(error.kind, InterpreterErrorKind::Interrupted);
+        assert_eq!(
+            error.msg,
+            "evaluation error[R013]: interrupted at line 2 column 15\n    in block started at line 2 column 5\n    in block started at line 1 column 9"
+        );
+        assert_eq!(interpreter.output(), b"");
+        assert_eq!(interpreter.environment().depth(), 0);
+    }
+
+    static STARTED: AtomicBool = AtomicBool::new(false);
+
+    fn starting_native(_: &mut Environment, _: &[Literal]) -> Result<Literal, String> {
+        STARTED.store(true, Ordering::SeqCst);
+        Ok(Literal::Nil)
+    }
+
+    #[test]
+    fn interrupts_from_another_thread_stop_long_programs() {
+        const STATEMENTS: usize = 200_000;
+
+        let source = format!(
+            "let a = 0;\nstarted();\n{}",
+            "a = a + 1;\n".repeat(STATEMENTS)
+        );
+        let mut interpreter = Interpreter::with_output(source, Vec::new());
+        interpreter.environment_mut().define(
+            "started".into(),
+            Literal::Native(Native {
+                name: "started",
+                arity: 0,
+                function: starting_native,
+            }),
+        );
+        let handle = interpreter.interrupt_handle();
+        let interrupter = std::thread::spawn(move || {
+            while !STARTED.load(Ordering::SeqCst) {
+                std::thread::yield_now();
+            }
+            handle.interrupt();
+        });
+
+        let error = interpreter.interpret(true).unwrap_err();
+        interrupter.join().unwrap();
+        assert_eq!(error.kind, InterpreterErrorKind::Interrupted);
+        let Some(&Literal::Integer(count)) = interpreter.environment().get("a") else {
+            panic!("a isn't an integer");
+        };
+        assert!(count < STATEMENTS as i64, "ran all {} statements", count);
+
+        // The interpreter is still usable
+        interpreter.set_content("a = 0;\na + 1;".into());
+        interpreter.interpret(true).unwrap();
+        assert!(interpreter.output().ends_with(b"1\n"));
+    }
+
+    #[test]
+    fn script_errors_are_not_output_errors() {
+        let mut interpreter = Interpreter::with_output("1 & true;".into(), Vec::new());
+        let error = interpreter.interpret(true).unwrap_err();
+
+        assert_eq!(error.kind, InterpreterErrorKind::Evaluation);
+        assert!(!error.is_broken_pipe());
+    }
+
+    #[test]
+    fn errors_list_the_blocks_they_occurred_in() {
+        let source = "let a = 1;
+{
+  let b = 2;
+  { }
+  {
+    a + b;
+      { b & true; }
+  }
+}";
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        let error = interpreter.interpret(true).unwrap_err();
+
+        assert_eq!(
+            error.msg,
+            "evaluation error[R007]: operand true of `&` is not a number at line 7 column 11
+    in block started at line 7 column 7
+    in block started at line 5 column 3
+    in block started at line 2 column 1"
+        );
+        assert!(interpreter.trace.is_empty());
+
+        // Frames of the failed run don't leak into the next one
+        interpreter.set_content("1 & true;".into());
+        let error = interpreter.interpret(true).unwrap_err();
+        assert_eq!(
+            error.msg,
+            "evaluation error[R007]: operand true of `&` is not a number at line 1 column 3"
+        );
+    }
+
+    #[test]
+    fn print_precision_is_kept_between_runs() {
+        let mut interpreter = Interpreter::with_output("1 / 3;".into(), Vec::new());
+        interpreter.set_print_precision(Some(3));
+        interpreter.interpret(true).unwrap();
+        interpreter.set_content("printPrecision(1); 2 / 3;".into());
+        interpreter.interpret(true).unwrap();
+        interpreter.set_content("1 / 4;".into());
+        interpreter.interpret(true).unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(interpreter.output()),
+            "0.333\nnil\n0.7\n0.2\n"
+        );
+    }
+
+    #[test]
+    fn every_parse_error_is_reported_and_nothing_runs() {
+        let mut interpreter =
+            Interpreter::with_output("1;\n{ let = 1; (2 * 3; }\n3;".into(), Vec::new());
+        let error = interpreter.interpret(true).unwrap_err();
+
+        assert_eq!(error.kind, InterpreterErrorKind::Parse);
+        assert_eq!(
+            error.msg,
+            "runtime exception[P002]: expected an identifier at line 2 column 7
+runtime exception[P001]: expected `)` at line 2 column 18"
+        );
+        assert!(interpreter.output().is_empty());
+    }
+
+    #[test]
+    fn hosts_share_variables_through_the_environment() {
+        let mut interpreter = Interpreter::with_output("total = total * 2;".into(), Vec::new());
+        interpreter
+            .environment_mut()
+            .define("total".into(), Literal::Integer(21));
+        interpreter.interpret(true).unwrap();
+
+        assert!(matches!(
+            interpreter.environment().get("total"),
+            Some(Literal::Integer(42))
+        ));
+    }
+
+    fn run_as(version: LanguageVersion, source: &str) -> Result<String, String> {
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.set_language_version(version);
+        interpreter.interpret(true).map_err(|e| e.msg)?;
+        Ok(String::from_utf8_lossy(interpreter.output()).into_owned())
+    }
+
+    #[test]
+    fn extensions_are_rejected_by_the_core_language() {
+        let scenarios = [
+            (
+                "exit 0;",
+                "",
+                "`exit` is not available in language version v1 at line 1 column 1",
+            ),
+            (
+                "1 | 2;",
+                "3\n",
+                "`|` is not available in language version v1 at line 1 column 3",
+            ),
+            (
+                "1 << 2;",
+                "4\n",
+                "`<<` is not available in language version v1 at line 1 column 3",
+            ),
+            (
+                "~1;",
+                "-2\n",
+                "`~` is not available in language version v1 at line 1 column 1",
+            ),
+            (
+                "assert true;",
+                "",
+                "`assert` is not available in language version v1 at line 1 column 1",
+            ),
+            (
+                "attempt { } handle (err) { }",
+                "",
+                "`attempt` is not available in language version v1 at line 1 column 1",
+            ),
+            (
+                "nil ?? 1;",
+                "1\n",
+                "`??` is not available in language version v1 at line 1 column 5",
+            ),
+            (
+                "write 1;",
+                "1",
+                "`write` is not available in language version v1 at line 1 column 1",
+            ),
+        ];
+
+        for (source, output, error) in scenarios {
+            assert_eq!(run_as(LanguageVersion::V2, source).unwrap(), output);
+            assert_eq!(
+                run_as(LanguageVersion::V1, source).unwrap_err(),
+                format!("runtime exception[P010]: {}", error)
+            );
+        }
+    }
+
+    #[test]
+    fn core_language_strings_are_not_interpolated() {
+        let source = "let a = 1; \"a is ${a}\"; LOX_VERSION;";
+
+        assert_eq!(run_as(LanguageVersion::V2, source).unwrap(), "a is 1\n2\n");
+        assert_eq!(
+            run_as(LanguageVersion::V1, source).unwrap(),
+            "a is ${a}\n1\n"
+        );
+    }
+
+    fn run_with(policy: RedeclarationPolicy, source: &str) -> Result<String, String> {
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.set_redeclaration_policy(policy);
+        interpreter.interpret(true).map_err(|e| e.msg)?;
+        Ok(String::from_utf8_lossy(interpreter.output()).into_owned())
+    }
+
+    #[test]
+    fn redeclaration_follows_the_policy() {
+        let global = "let a = 1;\nlet a = a + 1;\na;";
+        let local =
+            "let a = 1; let c = 0;\n{\n  let a = 2; let b = a;\n  let b = b + 1; c = b;\n}\nc;";
+        let builtin = "let len = 1;";
+
+        use RedeclarationPolicy::*;
+        assert_eq!(run_with(Allow, global).unwrap(), "2\n");
+        assert_eq!(run_with(Allow, local).unwrap(), "3\n");
+        assert_eq!(run_with(Allow, builtin).unwrap(), "");
+
+        assert_eq!(run_with(ErrorLocals, global).unwrap(), "2\n");
+        assert_eq!(
+            run_with(ErrorLocals, local).unwrap_err(),
+            "evaluation error[R019]: `b` is already declared in this scope, first at line 3 column 18 at line 4 column 7
+    in block started at line 2 column 1"
+        );
+        assert_eq!(run_with(ErrorLocals, builtin).unwrap(), "");
+
+        assert_eq!(
+            run_with(ErrorAll, global).unwrap_err(),
+            "evaluation error[R019]: `a` is already declared in this scope, first at line 1 column 5 at line 2 column 5"
+        );
+        assert!(run_with(ErrorAll, local).is_err());
+        assert_eq!(
+            run_with(ErrorAll, builtin).unwrap_err(),
+            "evaluation error[R020]: `len` is already defined by the interpreter at line 1 column 5"
+        );
+    }
+
+    #[test]
+    fn redeclaration_policies_are_parsed_from_flags() {
+        assert_eq!("allow".parse(), Ok(RedeclarationPolicy::Allow));
+        assert_eq!("locals".parse(), Ok(RedeclarationPolicy::ErrorLocals));
+        assert_eq!("deny".parse(), Ok(RedeclarationPolicy::ErrorAll));
+        assert_eq!(
+            "never".parse::<RedeclarationPolicy>(),
+            Err("unknown redeclaration policy `never`, expected allow, locals or deny".into())
+        );
+    }
+
+    #[test]
+    fn undefined_variables_follow_the_policy() {
+        let source = "print name ?? \"anonymous\";\nwrite \"${title}|\";\nmissing == nil;";
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        assert_eq!(
+            interpreter.interpret(true).unwrap_err().msg,
+            "evaluation error[R001]: undefined variable `name` at line 1 column 7"
+        );
+        assert!(interpreter.undefined_reads().is_empty());
+
+        // Values that are only printed, tested or stored fail as well
+        for (source, column) in [
+            ("print missing;", 7),
+            ("assert missing;", 8),
+            ("let x = nope;", 9),
+        ] {
+            let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+            let error = interpreter.interpret(true).unwrap_err();
+            assert_eq!(error.code, Some(codes::UNDEFINED_VARIABLE), "{}", source);
+            assert!(error.msg.ends_with(&format!("at line 1 column {}", column)));
+            assert!(interpreter.output().is_empty());
+        }
+        let mut similar = Interpreter::with_output("let name = 1;\nnam;".into(), Vec::new());
+        assert_eq!(
+            similar.interpret(true).unwrap_err().msg,
+            "evaluation error[R001]: undefined variable `nam`; did you mean `name`? at line 2 column 1"
+        );
+
+        interpreter.set_undefined_variable_policy(UndefinedVariablePolicy::Nil);
+        interpreter.output_mut().clear();
+        interpreter.interpret(true).unwrap();
+        assert_eq!(interpreter.output(), b"anonymous\nnil|true\n");
+        let reads: Vec<String> = interpreter
+            .undefined_reads()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            reads,
+            vec![
+                "warning[R001]: undefined variable `name` at line 1 column 7",
+                "warning[R001]: undefined variable `title` at line 2 column 10",
+                "warning[R001]: undefined variable `missing` at line 3 column 1",
+            ]
+        );
+
+        // Only reads are forgiven, and the reads are those of the last run
+        interpreter.set_content("let a = 1;\nb = a;".into());
+        assert_eq!(
+            interpreter.interpret(true).unwrap_err().msg,
+            "evaluation error[R002]: cannot assign to undeclared variable `b` at line 2 column 1"
+        );
+        assert!(interpreter.undefined_reads().is_empty());
+    }
+
+    #[test]
+    fn interpreters_are_send_and_sync_with_their_output() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Interpreter<Vec<u8>>>();
+        assert_send_sync::<Interpreter>();
+        assert_send_sync::<crate::Session>();
+    }
+
+    #[test]
+    fn interpreters_run_concurrently_on_separate_threads() {
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                std::thread::spawn(move || {
+                    let source = format!("let total = {};\n{{ total = total * 10; }}\ntotal;", i);
+                    run(&source)
+                })
+            })
+            .collect();
+
+        let outputs: Vec<String> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(outputs, vec!["0\n", "10\n", "20\n", "30\n"]);
+    }
+
+    #[test]
+    fn shared_interpreters_run_one_program_at_a_time() {
+        use std::sync::{Arc, Mutex};
+
+        let interpreter = Arc::new(Mutex::new(Interpreter::with_output(
+            "let counter = 0;".into(),
+            Vec::new(),
+        )));
+        interpreter.lock().unwrap().interpret(true).unwrap();
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let interpreter = Arc::clone(&interpreter);
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        let mut interpreter = interpreter.lock().unwrap();
+                        interpreter.set_content("counter = counter + 1;".into());
+                        interpreter.interpret(true).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let interpreter = interpreter.lock().unwrap();
+        assert_eq!(
+            interpreter.environment().get("counter"),
+            Some(&Literal::Integer(200))
+        );
+    }
+}
@@ -1,72 +1,78 @@
 mod analyzers;
+mod compilation;
+mod compiled;
+pub mod diagnostics;
+mod environment;
 pub mod errors;
 pub mod interpreter;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+mod pipeline;
+#[cfg(feature = "cli")]
 pub mod repl;
+pub mod session;
+#[cfg(feature = "cli")]
+mod signal;
+pub mod stdlib;
+mod test_config;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
 mod types;
+mod version;
 
-use std::collections::HashMap;
-
+pub use analyzers::lints;
+pub use analyzers::scanner::{ClassifiedSpan, TokenClass};
+pub use analyzers::{
+    ast_diff, canonical_number, double_negations, empty_blocks, empty_statements, extract_docs,
+    find_reference, format_source, format_source_with, index_symbols, is_redundant_group,
+    reconstruct_source, shadowed_variables, unused_results, unused_variables, Difference,
+    FormatOptions, IncrementalParser, LineComment, Parser, Reference, Scanner, Symbol, SymbolIndex,
+    Trivia, Usage,
+};
+pub use compilation::{AnalyzeOptions, Compilation, Finding};
+pub use compiled::CompiledExpression;
+pub use environment::{Counters, Environment, UndefinedVariablePolicy};
 use errors::{EvaluationError, InterpreterError};
-pub use interpreter::Interpreter;
-pub use repl::{run_file, run_prompt};
+pub use interpreter::{Interpreter, InterruptHandle, RedeclarationPolicy, Stats};
+pub use pipeline::Pipeline;
+#[cfg(feature = "cli")]
+pub use repl::{
+    check_scripts, format_script, reconstruct_script, run_directory, run_file, run_inline,
+    run_line, run_prompt, LineResult, Mode, Options, Repl,
+};
+pub use session::{Diagnostic, Outcome, Session};
+pub use test_config::TestConfig;
 use types::*;
+pub use types::{
+    write_tokens, Literal, LocationInfo, Native, Span, Statement, Token, TokenDumpFormat,
+    TokenStream, TokenType,
+};
+pub use version::LanguageVersion;
 
+/// Values the statement evaluates to in an empty environment, or the error
+/// evaluating it fails with
 #[cfg(test)]
 pub fn get_statement_string(statement: Statement) -> String {
-    let environment = Environment::default();
+    let mut environment = Environment::new();
+    let mut value = |expr: Expression| match expr.evaluate(&mut environment) {
+        Ok(literal) => String::from(literal),
+        Err(e) => e.to_string(),
+    };
     match statement {
-        Statement::Assign(token, expr) => {
-            let str_rep: String = expr.evaluate(&environment).unwrap().into();
-            format!("let {} = {};", token.lexeme, str_rep)
-        }
-        Statement::Variable(expr) => expr.evaluate(&environment).unwrap().into(),
-        Statement::Expression(expr) => expr.evaluate(&environment).unwrap().into(),
-        Statement::Block(statements) => {
+        Statement::Assign(token, expr) => format!("let {} = {};", token.lexeme, value(expr)),
+        Statement::Variable(expr) | Statement::Expression(expr) => value(expr),
+        Statement::Exit(_, expr) => format!("exit {};", value(expr)),
+        Statement::Assert(_, _, _)
+        | Statement::Print(_, _)
+        | Statement::Attempt(_, _, _)
+        | Statement::Empty(_) => String::from(statement),
+        Statement::Block(_, statements) => {
             let mut actual = String::new();
             for statement in statements {
                 actual.push_str(&get_statement_string(statement));
-                actual.push_str("\n");
+                actual.push('\n');
             }
             actual
         }
     }
 }
-
-#[derive(Debug)]
-pub struct Environment {
-    scopes: Vec<HashMap<String, Literal>>,
-    depth: usize,
-}
-
-impl Default for Environment {
-    fn default() -> Self {
-        let scopes = vec![HashMap::new()];
-        Self { scopes, depth: 0 }
-    }
-}
-
-impl Environment {
-    pub fn define(&mut self, name: String, value: Literal) {
-        self.scopes[self.depth].insert(name, value);
-    }
-
-    pub fn enter_block(&mut self) {
-        self.depth += 1;
-        self.scopes[self.depth] = HashMap::new();
-    }
-
-    pub fn leave_block(&mut self) {
-        self.scopes.remove(self.depth);
-        self.depth -= 1;
-    }
-
-    pub fn get(&self, name: String) -> Option<Literal> {
-        for i in 0..=self.depth {
-            let option = self.scopes[self.depth - i].get(&name);
-            if let Some(option) = option {
-                return Some(option.clone());
-            }
-        }
-        None
-    }
-}
@@ -0,0 +1,366 @@
+use std::fmt;
+
+/// JSON value of a language server message
+///
+/// Objects keep their keys in insertion order so messages are written the
+/// same way every time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Builds an object from its members
+    pub fn object<const N: usize>(members: [(&str, Json); N]) -> Self {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// Value of the member with the key, None if this isn't an object or
+    /// has no such member
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Parses a complete JSON text, surrounding whitespace is allowed
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut reader = Reader {
+            chars: text.chars().collect(),
+            next: 0,
+        };
+        let value = reader.value()?;
+        reader.skip_whitespace();
+        match reader.peek() {
+            None => Ok(value),
+            Some(c) => Err(reader.error(&format!("unexpected `{}` after the value", c))),
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.into())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+/// Writes the value without any whitespace
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            // Whole numbers such as ids and positions are written without a
+            // fraction
+            Json::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => {
+                write!(f, "{}", *value as i64)
+            }
+            Json::Number(value) if value.is_finite() => write!(f, "{}", value),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Reader {
+    chars: Vec<char>,
+    next: usize,
+}
+
+impl Reader {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.next).copied()
+    }
+
+    fn error(&self, msg: &str) -> String {
+        format!("{} at character {}", msg, self.next)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        {
+            self.next += 1;
+        }
+    }
+
+    fn expect_word(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(&format!("expected `{}`", word)));
+            }
+            self.next += 1;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.expect_word("null", Json::Null),
+            Some('t') => self.expect_word("true", Json::Bool(true)),
+            Some('f') => self.expect_word("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => {
+                self.next += 1;
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.next += 1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.next += 1,
+                        Some(']') => {
+                            self.next += 1;
+                            return Ok(Json::Array(values));
+                        }
+                        _ => return Err(self.error("expected `,` or `]`")),
+                    }
+                }
+            }
+            Some('{') => {
+                self.next += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.next += 1;
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some('"') {
+                        return Err(self.error("expected a key"));
+                    }
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    if self.peek() != Some(':') {
+                        return Err(self.error("expected `:`"));
+                    }
+                    self.next += 1;
+                    members.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.next += 1,
+                        Some('}') => {
+                            self.next += 1;
+                            return Ok(Json::Object(members));
+                        }
+                        _ => return Err(self.error("expected `,` or `}`")),
+                    }
+                }
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(self.error(&format!("unexpected `{}`", c))),
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.next;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.next += 1;
+        }
+        let text: String = self.chars[start..self.next].iter().collect();
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| self.error(&format!("invalid number `{}`", text)))
+    }
+
+    /// Reads a string starting at its opening quote
+    fn string(&mut self) -> Result<String, String> {
+        self.next += 1;
+        let mut value = String::new();
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.next += 1;
+            match c {
+                '"' => return Ok(value),
+                '\\' => {
+                    let escape = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.next += 1;
+                    match escape {
+                        '"' | '\\' | '/' => value.push(escape),
+                        'b' => value.push('\u{8}'),
+                        'f' => value.push('\u{c}'),
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        'u' => {
+                            let mut unit = self.hex_unit()?;
+                            // Characters outside the basic plane are escaped
+                            // as a surrogate pair
+                            if (0xd800..0xdc00).contains(&unit)
+                                && self.chars.get(self.next..self.next + 2) == Some(&['\\', 'u'])
+                            {
+                                self.next += 2;
+                                let low = self.hex_unit()?;
+                                unit = 0x10000
+                                    + ((unit - 0xd800) << 10)
+                                    + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            value.push(char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER));
+                        }
+                        _ => return Err(self.error(&format!("invalid escape `\\{}`", escape))),
+                    }
+                }
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn hex_unit(&mut self) -> Result<u32, String> {
+        let digits: String = self
+            .chars
+            .get(self.next..self.next + 4)
+            .ok_or_else(|| self.error("expected 4 hex digits"))?
+            .iter()
+            .collect();
+        let unit =
+            u32::from_str_radix(&digits, 16).map_err(|_| self.error("expected 4 hex digits"))?;
+        self.next += 4;
+        Ok(unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_round_trip() {
+        let text = r#"{"id":1,"params":{"text":"a\n\"b\"","list":[true,false,null,-2.5]},"e":[]}"#;
+
+        assert_eq!(Json::parse(text).unwrap().to_string(), text);
+    }
+
+    #[test]
+    fn escapes_are_decoded() {
+        let value = Json::parse(r#" "é\t😀\/" "#).unwrap();
+
+        assert_eq!(value.as_str(), Some("é\t😀/"));
+        assert_eq!(Json::from("\u{1}").to_string(), r#""\u0001""#);
+    }
+
+    #[test]
+    fn members_are_found_by_key() {
+        let value = Json::parse(r#"{"a": {"b": "c"}, "n": 3}"#).unwrap();
+
+        assert_eq!(
+            value.get("a").and_then(|a| a.get("b")),
+            Some(&Json::from("c"))
+        );
+        assert_eq!(value.get("n"), Some(&Json::Number(3.0)));
+        assert!(value.get("missing").is_none());
+    }
+
+    #[test]
+    fn malformed_text_is_rejected() {
+        for text in [
+            "",
+            "{",
+            r#"{"a" 1}"#,
+            "[1,]",
+            r#""open"#,
+            "nul",
+            "1 2",
+            r#""\x""#,
+        ] {
+            assert!(Json::parse(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,574 @@
+//! Minimal language server speaking JSON-RPC, enabled by the `lsp` feature
+//!
+//! [Session] handles messages as strings so it can be driven without an
+//! editor, the `lox-ls` binary only moves them between stdio and the
+//! session. Documents are synced in full, opening or changing one publishes
+//! its scan and parse errors, along with lint warnings once it parses, and
+//! the top-level `let` declarations are listed as document symbols.
+//!
+//! ## Examples
+//! ```rust
+//! # #[cfg(feature = "lsp")] {
+//! use lox::lsp::Session;
+//!
+//! let mut session = Session::new();
+//! let replies = session.handle(
+//!     r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":
+//!         {"uri":"file:///a.lox","languageId":"lox","version":1,"text":"let = 1;"}}}"#,
+//! );
+//!
+//! assert!(replies[0].contains(r#""message":"expected an identifier""#));
+//! # }
+//! ```
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::errors::codes::Severity;
+use crate::{
+    AnalyzeOptions, ClassifiedSpan, Compilation, LocationInfo, Span, Statement, SymbolIndex,
+    TokenClass,
+};
+
+mod json;
+
+pub use json::Json;
+
+/// `DiagnosticSeverity` of the protocol
+const ERROR: usize = 1;
+const WARNING: usize = 2;
+/// `SymbolKind` of the protocol
+const VARIABLE: usize = 13;
+
+/// Open documents and the state of the connection
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Text and version of every open document by URI
+    documents: HashMap<String, (String, Json)>,
+    shutdown: bool,
+    exited: bool,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles one JSON-RPC message, returning the responses and
+    /// notifications to send back in order
+    pub fn handle(&mut self, message: &str) -> Vec<String> {
+        let message = match Json::parse(message) {
+            Ok(message) => message,
+            Err(e) => return vec![error_response(Json::Null, -32700, &e).to_string()],
+        };
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let id = message.get("id").cloned();
+
+        let replies = match (method, id) {
+            ("initialize", Some(id)) => vec![response(id, capabilities())],
+            ("shutdown", Some(id)) => {
+                self.shutdown = true;
+                vec![response(id, Json::Null)]
+            }
+            ("exit", _) => {
+                self.exited = true;
+                Vec::new()
+            }
+            ("textDocument/didOpen", None) => {
+                let document = params.get("textDocument").unwrap_or(&Json::Null);
+                let text = document.get("text").and_then(Json::as_str).unwrap_or("");
+                self.update(document, text)
+            }
+            ("textDocument/didChange", None) => {
+                // Full sync, the last change holds the whole text
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(<[Json]>::last)
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str)
+                    .unwrap_or("");
+                self.update(params.get("textDocument").unwrap_or(&Json::Null), text)
+            }
+            ("textDocument/didClose", None) => {
+                let uri = document_uri(params);
+                self.documents.remove(uri);
+                vec![publish(uri, &Json::Null, Vec::new())]
+            }
+            ("textDocument/documentSymbol", Some(id)) => {
+                let symbols = match self.documents.get(document_uri(params)) {
+                    Some((text, _)) => Json::Array(symbols(text)),
+                    None => Json::Null,
+                };
+                vec![response(id, symbols)]
+            }
+            (method, Some(id)) => vec![error_response(
+                id,
+                -32601,
+                &format!("method not found: {}", method),
+            )],
+            // Other notifications, such as `initialized`, need no reply
+            (_, None) => Vec::new(),
+        };
+        replies.into_iter().map(|reply| reply.to_string()).collect()
+    }
+
+    /// Returns true once the client sent `exit`
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Process exit code, the protocol asks for 1 when the client exits
+    /// without shutting the server down first
+    pub fn exit_code(&self) -> i32 {
+        if self.shutdown {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Stores the new text of the document and publishes its diagnostics
+    fn update(&mut self, document: &Json, text: &str) -> Vec<Json> {
+        let uri = document_uri_of(document);
+        let version = document.get("version").cloned().unwrap_or(Json::Null);
+        let published = publish(uri, &version, diagnostics(text));
+        self.documents.insert(uri.into(), (text.into(), version));
+        vec![published]
+    }
+}
+
+/// Serializes a [SymbolIndex], spans are byte offsets and lengths
+impl From<&SymbolIndex> for Json {
+    fn from(index: &SymbolIndex) -> Self {
+        let spans = |spans: &[Span]| Json::Array(spans.iter().map(span_json).collect());
+        let symbols = index
+            .symbols
+            .iter()
+            .map(|symbol| {
+                Json::object([
+                    ("name", symbol.name.as_str().into()),
+                    ("declaration", span_json(&symbol.declaration)),
+                    (
+                        "scope",
+                        Json::Array(symbol.scope.iter().map(|&i| i.into()).collect()),
+                    ),
+                    ("reads", spans(&symbol.reads)),
+                    ("writes", spans(&symbol.writes)),
+                ])
+            })
+            .collect();
+        let unresolved = index
+            .unresolved
+            .iter()
+            .map(|usage| {
+                Json::object([
+                    ("name", usage.name.as_str().into()),
+                    ("span", span_json(&usage.span)),
+                    ("write", usage.write.into()),
+                ])
+            })
+            .collect();
+        Json::object([
+            ("symbols", Json::Array(symbols)),
+            ("unresolved", Json::Array(unresolved)),
+        ])
+    }
+}
+
+fn span_json(span: &Span) -> Json {
+    Json::object([("offset", span.offset.into()), ("len", span.len.into())])
+}
+
+/// Reads a message framed with a `Content-Length` header, None once the
+/// input ends
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut len = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            len = value.trim().parse().ok();
+        }
+    }
+
+    let len = len.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes a message with its `Content-Length` header
+pub fn write_message(writer: &mut impl Write, message: &str) -> io::Result<()> {
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        message.len(),
+        message
+    )?;
+    writer.flush()
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                // Full text sync
+                ("textDocumentSync", 1.into()),
+                ("documentSymbolProvider", true.into()),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", "lox-ls".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+fn response(id: Json, result: Json) -> Json {
+    Json::object([("jsonrpc", "2.0".into()), ("id", id), ("result", result)])
+}
+
+fn error_response(id: Json, code: i32, msg: &str) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object([("code", Json::Number(code as f64)), ("message", msg.into())]),
+        ),
+    ])
+}
+
+fn publish(uri: &str, version: &Json, diagnostics: Vec<Json>) -> Json {
+    let mut params = vec![("uri".to_string(), uri.into())];
+    if *version != Json::Null {
+        params.push(("version".into(), version.clone()));
+    }
+    params.push(("diagnostics".into(), Json::Array(diagnostics)));
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        ("params", Json::Object(params)),
+    ])
+}
+
+fn document_uri(params: &Json) -> &str {
+    document_uri_of(params.get("textDocument").unwrap_or(&Json::Null))
+}
+
+fn document_uri_of(document: &Json) -> &str {
+    document.get("uri").and_then(Json::as_str).unwrap_or("")
+}
+
+/// Scan and parse errors of the text, or its lint warnings if there are none
+fn diagnostics(text: &str) -> Vec<Json> {
+    let compilation = Compilation::analyze(text, AnalyzeOptions::default());
+    let index = LineIndex::new(text);
+    // An invalid `lox-lint` comment has no location to report it at
+    compilation
+        .diagnostics()
+        .iter()
+        .filter_map(|finding| {
+            let (code, location) = finding.code.zip(finding.location)?;
+            let severity = match code.severity {
+                Severity::Error => ERROR,
+                Severity::Warning => WARNING,
+            };
+            let range = index.token_range(compilation.spans(), location);
+            Some(Json::object([
+                ("range", range),
+                ("severity", severity.into()),
+                ("code", code.code.into()),
+                ("source", "lox".into()),
+                ("message", finding.msg.as_str().into()),
+            ]))
+        })
+        .collect()
+}
+
+/// Top-level declarations of the text, none if it doesn't scan
+fn symbols(text: &str) -> Vec<Json> {
+    let compilation = Compilation::analyze(text, AnalyzeOptions::default());
+    let index = LineIndex::new(text);
+    compilation
+        .statements()
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Assign(name, _) => {
+                let range = index.range(name.offset, name.offset + name.lexeme.len());
+                Some(Json::object([
+                    ("name", name.lexeme.as_str().into()),
+                    ("kind", VARIABLE.into()),
+                    ("range", range.clone()),
+                    ("selectionRange", range),
+                ]))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Converts between scanner locations and protocol positions, which count
+/// lines from 0 and characters in UTF-16 code units
+struct LineIndex<'a> {
+    text: &'a str,
+    /// Byte offset of the start of every line
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { text, starts }
+    }
+
+    /// Byte offset of a 1-based line and character column, clamped to the
+    /// end of the line. Line 0 marks generated code, placed at the end.
+    fn offset(&self, line: usize, column: usize) -> usize {
+        let Some(&start) = self.starts.get(line.wrapping_sub(1)) else {
+            return self.text.len();
+        };
+        let line_text = self.text[start..].split('\n').next().unwrap_or("");
+        start
+            + line_text
+                .char_indices()
+                .nth(column.saturating_sub(1))
+                .map_or(line_text.len(), |(i, _)| i)
+    }
+
+    fn position(&self, offset: usize) -> Json {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let character = self.text[self.starts[line]..offset].encode_utf16().count();
+        Json::object([("line", line.into()), ("character", character.into())])
+    }
+
+    fn range(&self, start: usize, end: usize) -> Json {
+        Json::object([("start", self.position(start)), ("end", self.position(end))])
+    }
+
+    /// Range of the token at the location, or of the character there if no
+    /// token starts at it
+    fn token_range(&self, spans: &[ClassifiedSpan], location: LocationInfo) -> Json {
+        let start = self.offset(location.line(), location.column());
+        let end = spans
+            .iter()
+            .find(|span| {
+                span.start_offset == start
+                    && !matches!(span.class, TokenClass::Whitespace | TokenClass::Error)
+            })
+            .map(|span| span.start_offset + span.len)
+            .or_else(|| {
+                self.text[start..]
+                    .chars()
+                    .next()
+                    .filter(|c| *c != '\n')
+                    .map(|c| start + c.len_utf8())
+            })
+            .unwrap_or(start);
+        self.range(start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(session: &mut Session, text: &str) -> Vec<String> {
+        let message = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/didOpen".into()),
+            (
+                "params",
+                Json::object([(
+                    "textDocument",
+                    Json::object([
+                        ("uri", "file:///test.lox".into()),
+                        ("languageId", "lox".into()),
+                        ("version", 1.into()),
+                        ("text", text.into()),
+                    ]),
+                )]),
+            ),
+        ]);
+        session.handle(&message.to_string())
+    }
+
+    fn published(text: &str) -> String {
+        let mut replies = open(&mut Session::new(), text);
+        assert_eq!(replies.len(), 1);
+        let reply = Json::parse(&replies.remove(0)).unwrap();
+        reply
+            .get("params")
+            .unwrap()
+            .get("diagnostics")
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn initialize_advertises_full_sync_and_symbols() {
+        let replies = Session::new().handle(
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"capabilities":{}}}"#,
+        );
+
+        assert_eq!(
+            replies,
+            vec![format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":{{"capabilities":{{"textDocumentSync":1,"documentSymbolProvider":true}},"serverInfo":{{"name":"lox-ls","version":"{}"}}}}}}"#,
+                env!("CARGO_PKG_VERSION")
+            )]
+        );
+    }
+
+    #[test]
+    fn opening_a_document_publishes_parse_errors() {
+        let replies = open(&mut Session::new(), "let a = 1;\nlet = 2;");
+
+        assert_eq!(
+            replies,
+            vec![
+                r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///test.lox","version":1,"diagnostics":[{"range":{"start":{"line":1,"character":4},"end":{"line":1,"character":5}},"severity":1,"code":"P002","source":"lox","message":"expected an identifier"}]}}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn every_parse_error_is_published() {
+        assert_eq!(
+            published("{ let = 1; }\nlet b = 2\nb;"),
+            r#"[{"range":{"start":{"line":0,"character":6},"end":{"line":0,"character":7}},"severity":1,"code":"P002","source":"lox","message":"expected an identifier"},{"range":{"start":{"line":2,"character":0},"end":{"line":2,"character":1}},"severity":1,"code":"P001","source":"lox","message":"expected `;`"}]"#
+        );
+    }
+
+    #[test]
+    fn scan_errors_are_published_at_their_location() {
+        assert_eq!(
+            published("let s = 1;\nlet t = \"open"),
+            r#"[{"range":{"start":{"line":1,"character":8},"end":{"line":1,"character":9}},"severity":1,"code":"S002","source":"lox","message":"unterminated string missing `\"`"}]"#
+        );
+    }
+
+    #[test]
+    fn warnings_are_published_once_the_document_parses() {
+        assert_eq!(
+            published("{ let unused = --1; }"),
+            r#"[{"range":{"start":{"line":0,"character":6},"end":{"line":0,"character":12}},"severity":2,"code":"W001","source":"lox","message":"unused variable `unused`"},{"range":{"start":{"line":0,"character":15},"end":{"line":0,"character":16}},"severity":2,"code":"W009","source":"lox","message":"`--` negates twice and has no effect, use the operand as is"}]"#
+        );
+        assert_eq!(published("let a = 1;"), "[]");
+    }
+
+    #[test]
+    fn characters_are_counted_in_utf16_units() {
+        assert_eq!(
+            published("let s = \"😀é\"; let = 1;"),
+            r#"[{"range":{"start":{"line":0,"character":19},"end":{"line":0,"character":20}},"severity":1,"code":"P002","source":"lox","message":"expected an identifier"}]"#
+        );
+    }
+
+    #[test]
+    fn changes_replace_the_text_and_clear_fixed_errors() {
+        let mut session = Session::new();
+        open(&mut session, "let = 1;");
+        let replies = session.handle(
+            r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///test.lox","version":2},"contentChanges":[{"text":"let a = 1;"}]}}"#,
+        );
+
+        assert_eq!(
+            replies,
+            vec![
+                r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///test.lox","version":2,"diagnostics":[]}}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn document_symbols_list_top_level_declarations() {
+        let mut session = Session::new();
+        open(
+            &mut session,
+            "let total = 0;\n{ let inner = 1; }\nlet é = 2;",
+        );
+        let replies = session.handle(
+            r#"{"jsonrpc":"2.0","id":"s","method":"textDocument/documentSymbol","params":{"textDocument":{"uri":"file:///test.lox"}}}"#,
+        );
+
+        assert_eq!(
+            replies,
+            vec![
+                r#"{"jsonrpc":"2.0","id":"s","result":[{"name":"total","kind":13,"range":{"start":{"line":0,"character":4},"end":{"line":0,"character":9}},"selectionRange":{"start":{"line":0,"character":4},"end":{"line":0,"character":9}}},{"name":"é","kind":13,"range":{"start":{"line":2,"character":4},"end":{"line":2,"character":5}},"selectionRange":{"start":{"line":2,"character":4},"end":{"line":2,"character":5}}}]}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_requests_are_errors_and_notifications_are_ignored() {
+        let mut session = Session::new();
+
+        assert_eq!(
+            session.handle(r#"{"jsonrpc":"2.0","id":7,"method":"textDocument/hover"}"#),
+            vec![
+                r#"{"jsonrpc":"2.0","id":7,"error":{"code":-32601,"message":"method not found: textDocument/hover"}}"#
+            ]
+        );
+        assert!(session
+            .handle(r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#)
+            .is_empty());
+        assert!(session.handle("{").pop().unwrap().contains("-32700"));
+    }
+
+    #[test]
+    fn exit_code_depends_on_shutdown() {
+        let mut session = Session::new();
+        session.handle(r#"{"jsonrpc":"2.0","method":"exit"}"#);
+        assert!(session.has_exited());
+        assert_eq!(session.exit_code(), 1);
+
+        let mut session = Session::new();
+        assert_eq!(
+            session.handle(r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#),
+            vec![r#"{"jsonrpc":"2.0","id":2,"result":null}"#]
+        );
+        session.handle(r#"{"jsonrpc":"2.0","method":"exit"}"#);
+        assert_eq!(session.exit_code(), 0);
+    }
+
+    #[test]
+    fn symbol_indexes_serialize_to_json() {
+        let compilation = Compilation::analyze("let a = 1; { a = b; }", AnalyzeOptions::default());
+
+        assert_eq!(
+            Json::from(compilation.symbols()).to_string(),
+            r#"{"symbols":[{"name":"a","declaration":{"offset":4,"len":1},"scope":[],"reads":[],"writes":[{"offset":13,"len":1}]}],"unresolved":[{"name":"b","span":{"offset":17,"len":1},"write":false}]}"#
+        );
+    }
+
+    #[test]
+    fn messages_are_framed_with_their_length() {
+        let mut framed = Vec::new();
+        write_message(&mut framed, r#"{"a":"é"}"#).unwrap();
+        write_message(&mut framed, "{}").unwrap();
+        assert!(framed.starts_with(b"Content-Length: 10\r\n\r\n{\"a\":\"\xc3\xa9\"}"));
+
+        let mut reader = io::BufReader::new(&framed[..]);
+        assert_eq!(read_message(&mut reader).unwrap().unwrap(), r#"{"a":"é"}"#);
+        assert_eq!(read_message(&mut reader).unwrap().unwrap(), "{}");
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+}
@@ -1,16 +1,38 @@
-use lox::{run_file, run_prompt};
+use lox::errors::{codes, InterpreterErrorKind};
+use lox::lints;
+use lox::{
+    check_scripts, format_script, reconstruct_script, run_directory, run_file, run_inline,
+    run_prompt, Mode, Options,
+};
+use std::path::Path;
 use std::{error::Error, process::exit};
 
 #[cfg(target_os = "windows")]
 const USAGE: &str = "
 USAGE:
-    lox.exe <script.lx>
+    lox.exe [--time] [--no-fs] [--deny-warnings] [--lang=v1|v2] [--recursive] [--redeclare=allow|locals|deny]
+        [--max-vars=N] [--max-string=N] [--max-source-size=N] [--color=auto|always|never] [--deterministic]
+        [--block-values] [--allow=LINT,...] [--warn=LINT,...] [--deny=LINT,...]
+        [script.lx [args...] | directory]
+    lox.exe [--time] [--no-fs] [--lang=v1|v2] [--deterministic] -e SOURCE [-e SOURCE]... [args...]
+    lox.exe --check [--deny-warnings] [--deny=LINT,...] [--lang=v1|v2] [--recursive] (script.lx | directory)...
+    lox.exe --reconstruct [--lang=v1|v2] script.lx
+    lox.exe --fmt [--fmt-strip-parens] script.lx
+    lox.exe --explain CODE|LINT
 ";
 
 #[cfg(not(target_os = "windows"))]
 const USAGE: &str = "
 USAGE:
-    lox <script.lx>
+    lox [--time] [--no-fs] [--deny-warnings] [--lang=v1|v2] [--recursive] [--redeclare=allow|locals|deny]
+        [--max-vars=N] [--max-string=N] [--max-source-size=N] [--color=auto|always|never] [--deterministic]
+        [--block-values] [--allow=LINT,...] [--warn=LINT,...] [--deny=LINT,...]
+        [script.lx [args...] | directory]
+    lox [--time] [--no-fs] [--lang=v1|v2] [--deterministic] -e SOURCE [-e SOURCE]... [args...]
+    lox --check [--deny-warnings] [--deny=LINT,...] [--lang=v1|v2] [--recursive] (script.lx | directory)...
+    lox --reconstruct [--lang=v1|v2] script.lx
+    lox --fmt [--fmt-strip-parens] script.lx
+    lox --explain CODE|LINT
 ";
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -18,15 +40,73 @@ fn main() -> Result<(), Box<dyn Error>> {
 }
 
 fn run_repl() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 2 {
-        println!("{}", USAGE);
-        exit(1);
-    } else if args.len() == 1 {
-        run_prompt().unwrap();
-    } else {
-        run_file(&args[1]).unwrap();
+    let (path, options) = match Options::from_args(std::env::args().skip(1)) {
+        Ok(invocation) => invocation,
+        Err(message) => {
+            println!("error: {}\n{}", message, USAGE);
+            exit(1);
+        }
+    };
+
+    if let Some(code) = &options.explain {
+        exit(explain(code));
     }
 
-    Ok(())
+    let result = match path {
+        Some(path) if options.mode == Mode::Check => {
+            let mut paths = vec![path];
+            paths.extend(options.args.iter().cloned());
+            check_scripts(&paths, &options)
+        }
+        Some(path) if options.mode == Mode::Reconstruct => reconstruct_script(&path, &options),
+        Some(path) if options.mode == Mode::Format => format_script(&path, &options),
+        Some(path) if Path::new(&path).is_dir() => run_directory(Path::new(&path), &options),
+        Some(path) => run_file(&path, &options),
+        None if !options.inline.is_empty() => run_inline(&options.inline, &options),
+        None => run_prompt(&options),
+    };
+
+    match result {
+        // The reader went away, i.e `lox script.lx | head -1`, nothing left to do
+        Err(e) if e.is_broken_pipe() => Ok(()),
+        // Print the message as is, evaluation errors span several lines
+        Err(e) => {
+            eprintln!("{}", options.render(&e));
+            // Same code as a process killed by Ctrl-C
+            exit(if e.kind == InterpreterErrorKind::Interrupted {
+                130
+            } else {
+                1
+            });
+        }
+        Ok(code) => exit(code),
+    }
+}
+
+/// Prints the explanation of an error code, such as `P001`, or of every
+/// code of a lint, such as `shadowing`
+fn explain(code: &str) -> i32 {
+    if let Some(lint) = lints::find(code) {
+        println!("{}: {} by default", lint.name, lint.default);
+        for code in lint.codes {
+            println!("\n{}: {}\n\n{}", code, code.template, code.explanation);
+        }
+        return 0;
+    }
+    match codes::find(code) {
+        Some(code) => {
+            println!("{}: {}\n\n{}", code, code.template, code.explanation);
+            if let Some(lint) = lints::reporting(code) {
+                println!(
+                    "\nReported by the `{}` lint, {} by default.",
+                    lint.name, lint.default
+                );
+            }
+            0
+        }
+        None => {
+            eprintln!("error: unknown error code or lint `{}`", code);
+            1
+        }
+    }
 }
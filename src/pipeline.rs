@@ -0,0 +1,81 @@
+use crate::analyzers::lints::{self, LintConfig};
+use crate::analyzers::{Parser, Scanner};
+use crate::errors::{InterpreterError, InterpreterErrorKind, Warning};
+use crate::{LanguageVersion, Statement, Token};
+
+/// Stages a program goes through before it runs, each callable on its own
+///
+/// Scanning turns the source into tokens, parsing turns the tokens into
+/// statements and analysis finds warnings in the statements. Executing them
+/// is left to [Interpreter::execute](crate::Interpreter::execute), so tools
+/// that only need to know whether a program is valid, such as `lox --check`,
+/// never create an interpreter.
+///
+/// ## Examples
+/// ```rust
+/// use lox::{LanguageVersion, Pipeline};
+///
+/// let pipeline = Pipeline::new(LanguageVersion::default(), true);
+/// let tokens = pipeline.scan("{ let unused = 1; }").unwrap();
+/// let statements = pipeline.parse(tokens).unwrap();
+/// let warnings = pipeline.analyze(&statements);
+///
+/// assert_eq!(warnings[0].msg, "unused variable `unused`");
+/// assert!(pipeline.check("print 1").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    pub version: LanguageVersion,
+    /// Statements must end with `;`, as in scripts but not at the prompt
+    pub strict: bool,
+    /// Levels of the lints run by analysis, before those set by a
+    /// `// lox-lint:` comment in the source
+    pub lints: LintConfig,
+}
+
+impl Pipeline {
+    pub fn new(version: LanguageVersion, strict: bool) -> Self {
+        Self {
+            version,
+            strict,
+            lints: LintConfig::default(),
+        }
+    }
+
+    pub fn with_lints(mut self, lints: LintConfig) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    pub fn scan(&self, source: &str) -> Result<Vec<Token>, InterpreterError> {
+        Ok(Scanner::with_version(source, self.version)?.tokens)
+    }
+
+    /// Parses the tokens reporting every parse error, the statements are only
+    /// returned when there are none
+    pub fn parse(&self, tokens: Vec<Token>) -> Result<Vec<Statement>, InterpreterError> {
+        let (statements, errors) =
+            Parser::with_version(tokens, self.strict, self.version).parse_recovering();
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors.into())
+        }
+    }
+
+    /// Warnings of the lints that aren't allowed, in source order
+    pub fn analyze(&self, statements: &[Statement]) -> Vec<Warning> {
+        lints::run(statements, &self.lints)
+    }
+
+    /// Runs every stage but execution, returning the warnings of a valid
+    /// program with the lint levels of its `// lox-lint:` comment applied
+    pub fn check(&self, source: &str) -> Result<Vec<Warning>, InterpreterError> {
+        let mut config = self.lints.clone();
+        config
+            .apply_comment(source)
+            .map_err(|msg| InterpreterError::new(&msg, InterpreterErrorKind::Lint))?;
+        let statements = self.parse(self.scan(source)?)?;
+        Ok(lints::run(&statements, &config))
+    }
+}
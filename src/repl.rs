@@ -1,35 +1,1365 @@
+use std::fs;
 use std::io;
-use std::io::Write;
+use std::io::{BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 
-use crate::errors::InterpreterError;
-use crate::Interpreter;
+use crate::analyzers::lints::{LintConfig, LintLevel};
+use crate::analyzers::Scanner;
+use crate::diagnostics::{self, ColorChoice};
+use crate::errors::{InterpreterError, InterpreterErrorKind, Warning};
+use crate::interpreter::{self, Interpreter, RedeclarationPolicy};
+use crate::session::Diagnostic;
+use crate::signal;
+use crate::{
+    format_source_with, reconstruct_source, write_tokens, AnalyzeOptions, Compilation,
+    FormatOptions, LanguageVersion, Literal, Pipeline, Statement, TestConfig, TokenDumpFormat,
+    TokenType,
+};
 
 pub type InterpreterResult<T> = Result<T, InterpreterError>;
 
-pub fn run_prompt() -> InterpreterResult<()> {
-    let mut interpreter = Interpreter::new("".into());
+/// Settings for running scripts from the command line
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Report resource usage to stderr after the program output
+    pub report_time: bool,
+    pub file_io: bool,
+    pub env_access: bool,
+    /// Refuse to run scripts with warnings
+    pub deny_warnings: bool,
+    /// Blocks typed alone at the prompt echo the value of their last
+    /// expression, from `--block-values`
+    pub block_values: bool,
+    /// Levels of the lints, from `--allow`, `--warn` and `--deny`
+    pub lints: LintConfig,
+    pub version: LanguageVersion,
+    /// Include scripts in subdirectories when running a directory
+    pub recursive: bool,
+    /// Most variables scripts may have declared at once
+    pub max_variables: Option<usize>,
+    /// Most characters of the strings scripts build
+    pub max_string_len: Option<usize>,
+    /// Largest script in bytes that will be read
+    pub max_source_size: Option<u64>,
+    /// Overrides whether variables can be redeclared, which is allowed at
+    /// the prompt and rejected inside blocks of scripts
+    pub redeclaration: Option<RedeclarationPolicy>,
+    /// When to color errors and warnings, program output is never colored
+    pub color: ColorChoice,
+    /// Arguments passed to the script
+    pub args: Vec<String>,
+    /// Source given with `-e` to run instead of a script, see [run_inline]
+    pub inline: Vec<String>,
+    /// Error code to explain instead of running anything, from
+    /// `--explain P001`
+    pub explain: Option<String>,
+    pub mode: Mode,
+    /// How `--fmt` lays out the script, from `--fmt-strip-parens`
+    pub format: FormatOptions,
+    /// Makes runs reproducible, from `--deterministic`
+    pub test_config: Option<TestConfig>,
+}
+
+/// What is done with the scripts given on the command line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Execute,
+    /// Scan, parse and analyze the scripts without running them, from
+    /// `--check`, every argument after the first script is another script
+    Check,
+    /// Print the source rebuilt from the tokens of the script, from
+    /// `--reconstruct`, see [reconstruct_source](crate::reconstruct_source)
+    Reconstruct,
+    /// Print the script formatted, from `--fmt`, see
+    /// [format_source](crate::format_source)
+    Format,
+}
+
+impl Options {
+    /// Reads the command line arguments following the program name,
+    /// returning the script to run if any along with the options
+    ///
+    /// Flags come before the script, everything after it is passed to the
+    /// script. With `-e` there is no script and every argument after the
+    /// flags is passed to the source given with it.
+    ///
+    /// Unlike the defaults, scripts run from the command line may access
+    /// files and the environment unless `--no-fs` is given.
+    pub fn from_args(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<(Option<String>, Self), String> {
+        let mut options = Options {
+            file_io: true,
+            env_access: true,
+            max_source_size: Some(DEFAULT_MAX_SOURCE_SIZE),
+            ..Options::default()
+        };
+
+        let mut args = args.into_iter();
+        let mut path = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--time" => options.report_time = true,
+                "--no-fs" => options.file_io = false,
+                "--deny-warnings" => options.deny_warnings = true,
+                "--recursive" => options.recursive = true,
+                "--block-values" => options.block_values = true,
+                "--check" => options.mode = Mode::Check,
+                "--reconstruct" => options.mode = Mode::Reconstruct,
+                "--fmt" => options.mode = Mode::Format,
+                "--fmt-strip-parens" => options.format.strip_parens = true,
+                "--deterministic" => {
+                    options.test_config = Some(TestConfig {
+                        root: std::env::current_dir().ok(),
+                        ..TestConfig::default()
+                    });
+                }
+                "--explain" => {
+                    let code = args.next().ok_or("--explain needs an error code")?;
+                    options.explain = Some(code);
+                }
+                "-e" => {
+                    let source = args.next().ok_or("-e needs source code to run")?;
+                    options.inline.push(source);
+                }
+                flag if flag.starts_with("--lang=") => {
+                    options.version = flag["--lang=".len()..].parse()?;
+                }
+                flag if flag.starts_with("--color=") => {
+                    options.color = flag["--color=".len()..].parse()?;
+                }
+                flag if flag.starts_with("--redeclare=") => {
+                    options.redeclaration = Some(flag["--redeclare=".len()..].parse()?);
+                }
+                flag if flag.starts_with("--max-vars=") => {
+                    options.max_variables = Some(limit(flag, "--max-vars=")?);
+                }
+                flag if flag.starts_with("--max-string=") => {
+                    options.max_string_len = Some(limit(flag, "--max-string=")?);
+                }
+                flag if flag.starts_with("--max-source-size=") => {
+                    options.max_source_size = Some(limit(flag, "--max-source-size=")? as u64);
+                }
+                flag if options.lints.parse_flag(flag)? => {}
+                flag if flag.starts_with("--") => return Err(format!("unknown flag `{}`", flag)),
+                _ => {
+                    path = Some(arg);
+                    break;
+                }
+            }
+        }
+        options.args = args.collect();
+        if !options.inline.is_empty() {
+            options.args.splice(0..0, path.take());
+        }
+        if let Some(config) = &mut options.test_config {
+            config.args = options.args.clone();
+        }
+
+        let timed = options.report_time && options.inline.is_empty();
+        if path.is_none() && (timed || options.deny_warnings || options.recursive) {
+            return Err("--time, --deny-warnings and --recursive need a script".into());
+        }
+        if (path.is_some() || !options.inline.is_empty()) && options.block_values {
+            return Err("--block-values only applies to the prompt".into());
+        }
+        if path.is_none() && options.mode == Mode::Check {
+            return Err("--check needs a script or directory".into());
+        }
+        if path.is_none() && options.mode == Mode::Reconstruct {
+            return Err("--reconstruct needs a script".into());
+        }
+        if path.is_none() && options.mode == Mode::Format {
+            return Err("--fmt needs a script".into());
+        }
+        if options.format.strip_parens && options.mode != Mode::Format {
+            return Err("--fmt-strip-parens only applies to --fmt".into());
+        }
+        Ok((path, options))
+    }
+
+    /// Runs scripts with the settings of `config` rather than the ones of
+    /// the process, see [TestConfig]
+    pub fn with_test_config(mut self, config: TestConfig) -> Self {
+        self.test_config = Some(config);
+        self
+    }
+
+    /// Renders an error or warning for stderr, colored depending on
+    /// [color](Options::color)
+    pub fn render(&self, diagnostic: &impl std::fmt::Display) -> String {
+        let text = self.relativize(&diagnostic.to_string());
+        diagnostics::render(&text, self.color.style())
+    }
+
+    /// Number of warnings that stop scripts from running
+    fn denied(&self, warnings: &[Warning]) -> usize {
+        warnings
+            .iter()
+            .filter(|warning| self.deny_warnings || warning.level == LintLevel::Deny)
+            .count()
+    }
+
+    fn display_path(&self, path: &Path) -> String {
+        self.relativize(&path.display().to_string())
+    }
+
+    fn relativize(&self, text: &str) -> String {
+        match &self.test_config {
+            Some(config) => config.relativize(text),
+            None => text.into(),
+        }
+    }
+}
+
+/// Scripts larger than this many bytes are refused unless
+/// `--max-source-size` says otherwise
+pub const DEFAULT_MAX_SOURCE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Reads the value of a limit flag such as `--max-vars=100`
+fn limit(flag: &str, prefix: &str) -> Result<usize, String> {
+    let value = &flag[prefix.len()..];
+    value.parse().map_err(|_| {
+        format!(
+            "invalid value `{}` for {}, expected a whole number",
+            value,
+            prefix.trim_end_matches('=')
+        )
+    })
+}
+
+impl<W: Write> Interpreter<W> {
+    fn apply(&mut self, options: &Options) {
+        self.set_language_version(options.version);
+        self.enable_file_io(options.file_io);
+        self.enable_env_access(options.env_access);
+        self.set_args(options.args.clone());
+        self.set_lint_config(options.lints.clone());
+        self.set_max_variables(options.max_variables);
+        self.set_max_string_len(options.max_string_len);
+        self.set_interrupt_handle(signal::install());
+        if let Some(config) = &options.test_config {
+            self.set_test_config(config);
+        }
+    }
+}
+
+const HELP: &str = "\
+Commands:
+    :help            show this message
+    :tokens <code>   show the tokens scanned from the code without running it
+    :parse <code>    show the syntax tree of the code without running it
+    :type [expr]     show the type and value of the expression, or of `_`
+    :paste           read lines until one holding `:end` and run them at once
+    :quit            leave the prompt, as does Ctrl-D
+
+Statements end with `;`, which is optional at the prompt. Declare variables
+with `let name = value;` and change them with `name = value;`. Values are
+numbers, strings, `true`, `false` and `nil`, strings can embed expressions as
+in \"total: ${a + b}\". The value of the last expression is kept in `_`.
+Expressions use the usual arithmetic, comparison, logical
+(`&&`, `||`, `!`) and bitwise (`&`, `|`, `^`, `~`, `<<`, `>>`) operators,
+`{ ... }` opens a block with its own variables, `assert a == 1, \"message\";`
+fails unless the condition holds and `exit code;` stops the program. Built in
+functions such as len, substring, floor and random are called as
+`len(\"text\")`.
+";
+
+/// Meta-command typed at the prompt instead of lox code
+#[derive(Debug, PartialEq)]
+enum Command<'a> {
+    Help,
+    Tokens(&'a str),
+    Parse(&'a str),
+    Type(&'a str),
+    Paste,
+    Quit,
+    Unknown(&'a str),
+}
+
+impl<'a> Command<'a> {
+    /// Returns the command on the line, or None if it holds lox code
+    fn parse(line: &'a str) -> Option<Self> {
+        let line = line.trim();
+        if line == "help" {
+            return Some(Command::Help);
+        }
+
+        let line = line.strip_prefix(':')?;
+        let (name, code) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        Some(match name {
+            "help" => Command::Help,
+            "tokens" => Command::Tokens(code.trim()),
+            "parse" => Command::Parse(code.trim()),
+            "type" => Command::Type(code.trim()),
+            "paste" => Command::Paste,
+            "quit" => Command::Quit,
+            _ => Command::Unknown(name),
+        })
+    }
+
+    fn run<W: Write>(self, interpreter: &mut Interpreter<W>) -> InterpreterResult<()> {
+        let version = interpreter.language_version();
+        match self {
+            Command::Help => write!(interpreter.output_mut(), "{}", HELP)?,
+            Command::Tokens(code) => {
+                let compilation = Compilation::analyze(code, AnalyzeOptions::new(version, false));
+                let output = interpreter.output_mut();
+                match compilation.diagnostics().first() {
+                    Some(finding) if finding.kind == InterpreterErrorKind::Scan => {
+                        writeln!(output, "{}", finding)?
+                    }
+                    _ => write_tokens(compilation.tokens(), output, TokenDumpFormat::Human)?,
+                }
+            }
+            Command::Parse(code) => {
+                let compilation = Compilation::analyze(code, AnalyzeOptions::new(version, false));
+                let output = interpreter.output_mut();
+                if compilation.is_valid() {
+                    for statement in compilation.statements() {
+                        writeln!(output, "{}", String::from(statement.clone()))?;
+                    }
+                } else {
+                    for finding in compilation.diagnostics() {
+                        writeln!(output, "{}", finding)?;
+                    }
+                }
+            }
+            Command::Type(code) => {
+                let result = match code {
+                    "" => Ok(interpreter.environment().get("_").cloned()),
+                    code => evaluate(interpreter, code).map(Some),
+                };
+                let output = interpreter.output_mut();
+                match result {
+                    Ok(Some(value)) => {
+                        writeln!(output, "{}: {}", value.type_name(), String::from(value))?
+                    }
+                    Ok(None) => writeln!(output, "no value yet, `_` is set by expressions")?,
+                    Err(e) => writeln!(output, "{}", e)?,
+                }
+            }
+            Command::Paste | Command::Quit => writeln!(
+                interpreter.output_mut(),
+                "this command only works at the prompt"
+            )?,
+            Command::Unknown(name) => writeln!(
+                interpreter.output_mut(),
+                "unknown command :{}, try :help",
+                name
+            )?,
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates a single expression without writing it to the output
+fn evaluate<W: Write>(interpreter: &mut Interpreter<W>, code: &str) -> InterpreterResult<Literal> {
+    let pipeline = Pipeline::new(interpreter.language_version(), false);
+    let mut statements = pipeline.parse(pipeline.scan(code)?)?;
+    match (statements.pop(), statements.is_empty()) {
+        (Some(Statement::Expression(expr) | Statement::Variable(expr)), true) => Ok(expr
+            .evaluate(interpreter.environment_mut())
+            .map_err(InterpreterError::from)?),
+        _ => Err(InterpreterError::new(
+            "expected a single expression",
+            InterpreterErrorKind::Parse,
+        )),
+    }
+}
+
+/// Runs a line typed at the prompt, meta-commands write their result to the
+/// interpreter output and everything else is executed as lox code
+///
+/// The value of the last expression written to the output is bound to the
+/// global variable `_`, replacing any `_` declared by the code.
+///
+/// A bug in the interpreter panicking while it runs the line doesn't end the
+/// session: the variables are restored to what they were before the line
+/// and "internal error (please report)" is printed to stderr with the panic
+/// message.
+pub fn run_line<W: Write>(interpreter: &mut Interpreter<W>, line: &str) -> InterpreterResult<()> {
+    run_caught(interpreter, line, false).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        Ok(())
+    })
+}
+
+/// Runs the line like [run_line], returning the internal error to report
+/// instead if the interpreter panicked
+///
+/// Strict lines are always lox code, parsed in strict mode.
+fn run_caught<W: Write>(
+    interpreter: &mut Interpreter<W>,
+    line: &str,
+    strict: bool,
+) -> Result<InterpreterResult<()>, String> {
+    let snapshot = interpreter.environment().clone();
+    let execute = || execute_line(interpreter, line, strict);
+    panic::catch_unwind(AssertUnwindSafe(execute)).map_err(|payload| {
+        interpreter.restore(snapshot);
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+        format!("internal error (please report): {}", message)
+    })
+}
+
+fn execute_line<W: Write>(
+    interpreter: &mut Interpreter<W>,
+    line: &str,
+    strict: bool,
+) -> InterpreterResult<()> {
+    match Command::parse(line).filter(|_| !strict) {
+        Some(command) => {
+            command.run(interpreter)?;
+            interpreter.output_mut().flush()?;
+            Ok(())
+        }
+        None => {
+            interpreter.set_content(line.into());
+            let result = signal::while_running(|| interpreter.interpret(strict));
+            if let Some(value) = interpreter.last_value().cloned() {
+                interpreter.environment_mut().define("_".into(), value);
+            }
+            result
+        }
+    }
+}
+
+/// What a line given to [Repl::eval_line] did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineResult {
+    /// Value of the last expression, which is also bound to `_`
+    pub value: Option<Literal>,
+    /// Output written while running the line, including echoed values and
+    /// what meta-commands show
+    pub output: String,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The line leaves a string, interpolation or bracket open, it runs along
+    /// with the lines that follow once they close it
+    pub needs_more_input: bool,
+    /// Exit code requested by an `exit` statement
+    pub exit_code: Option<i32>,
+    /// The line is `:quit`, asking to leave the session
+    pub quit: bool,
+}
+
+/// Interactive session evaluating the lines typed at a prompt one at a time
+///
+/// Rather than writing to the terminal, what each line did is returned as a
+/// [LineResult] for the frontend to render, which is how [run_prompt] uses
+/// it. Lines are run like [run_line] does once they complete an entry: no
+/// string or interpolation is left open and every bracket is closed. A line
+/// with a scan error completes the entry so the error is reported right away.
+///
+/// After `:paste` lines are kept aside, blank ones included, until a line
+/// holding `:end`, then run at once in strict mode like a script would be.
+/// Pasting a program this way reports its errors once instead of for every
+/// line that doesn't stand on its own.
+///
+/// ## Examples
+/// ```rust
+/// use lox::{Interpreter, Literal, Repl};
+///
+/// let mut repl = Repl::new(Interpreter::with_output(String::new(), Vec::new()));
+/// assert!(repl.eval_line("let a = 2;").diagnostics.is_empty());
+/// assert!(repl.eval_line("a * (1").needs_more_input);
+///
+/// let result = repl.eval_line("+ 2)");
+/// assert_eq!(result.output, "6\n");
+/// assert_eq!(result.value, Some(Literal::Integer(6)));
+///
+/// repl.eval_line(":paste");
+/// repl.eval_line("let b =\n");
+/// repl.eval_line("\n");
+/// assert!(repl.eval_line("a + 1;").needs_more_input);
+/// assert!(repl.eval_line(":end").diagnostics.is_empty());
+/// assert_eq!(repl.eval_line("b").output, "3\n");
+/// ```
+pub struct Repl {
+    interpreter: Interpreter<Vec<u8>>,
+    /// Lines of the entry waiting for more input
+    entry: String,
+    /// Lines pasted since `:paste`, if it's waiting for `:end`
+    pasted: Option<String>,
+    scanner: Scanner<'static>,
+    /// Brackets opened and not yet closed by the entry
+    depth: isize,
+}
+
+impl Repl {
+    pub fn new(interpreter: Interpreter<Vec<u8>>) -> Self {
+        let scanner = Scanner::incremental(interpreter.language_version());
+        Self {
+            interpreter,
+            entry: String::new(),
+            pasted: None,
+            scanner,
+            depth: 0,
+        }
+    }
+
+    pub fn interpreter(&self) -> &Interpreter<Vec<u8>> {
+        &self.interpreter
+    }
+
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter<Vec<u8>> {
+        &mut self.interpreter
+    }
+
+    /// Whether earlier lines are waiting for the rest of their entry
+    pub fn is_continuing(&self) -> bool {
+        !self.entry.is_empty()
+    }
+
+    /// Whether `:paste` is waiting for the `:end` line
+    pub fn is_pasting(&self) -> bool {
+        self.pasted.is_some()
+    }
+
+    /// Adds the line to the entry, running the entry if the line completes
+    /// it or running the line on its own if it's a meta-command
+    ///
+    /// The line ends with a newline whether or not it's given one.
+    pub fn eval_line(&mut self, line: &str) -> LineResult {
+        if let Some(pasted) = &mut self.pasted {
+            if line.trim() == ":end" {
+                return self.finish().unwrap_or_default();
+            }
+            pasted.push_str(line);
+            if !line.ends_with('\n') {
+                pasted.push('\n');
+            }
+            return LineResult {
+                needs_more_input: true,
+                ..LineResult::default()
+            };
+        }
+        if !self.is_continuing() {
+            match Command::parse(line) {
+                Some(Command::Paste) => {
+                    self.pasted = Some(String::new());
+                    return LineResult {
+                        needs_more_input: true,
+                        ..LineResult::default()
+                    };
+                }
+                Some(Command::Quit) => {
+                    return LineResult {
+                        quit: true,
+                        ..LineResult::default()
+                    }
+                }
+                Some(_) => return self.run(line, false),
+                None => {}
+            }
+        }
+
+        let start = self.entry.len();
+        self.entry.push_str(line);
+        if !line.ends_with('\n') {
+            self.entry.push('\n');
+        }
+        let complete = match self.scanner.resume(&self.entry[start..]) {
+            Ok(tokens) => {
+                for token in tokens {
+                    match token._type {
+                        TokenType::LeftBrace | TokenType::LeftParen => self.depth += 1,
+                        TokenType::RightBrace | TokenType::RightParen => self.depth -= 1,
+                        _ => {}
+                    }
+                }
+                self.depth <= 0 && !self.scanner.is_incomplete()
+            }
+            // Running the entry reports the error
+            Err(_) => true,
+        };
+        if !complete {
+            return LineResult {
+                needs_more_input: true,
+                ..LineResult::default()
+            };
+        }
+        self.finish().unwrap_or_default()
+    }
+
+    /// Runs the entry even though it's incomplete, such as when the input
+    /// ends, or the lines pasted so far, returning None if there are neither
+    pub fn finish(&mut self) -> Option<LineResult> {
+        if let Some(pasted) = self.pasted.take() {
+            return Some(self.run(&pasted, true));
+        }
+        if !self.is_continuing() {
+            return None;
+        }
+        let entry = std::mem::take(&mut self.entry);
+        self.scanner = Scanner::incremental(self.interpreter.language_version());
+        self.depth = 0;
+        Some(self.run(&entry, false))
+    }
+
+    /// Runs the line, strict lines being lox code parsed in strict mode
+    fn run(&mut self, line: &str, strict: bool) -> LineResult {
+        let is_code = strict || Command::parse(line).is_none();
+        let result = run_caught(&mut self.interpreter, line, strict).unwrap_or_else(|message| {
+            Err(InterpreterError::new(
+                &message,
+                InterpreterErrorKind::Evaluation,
+            ))
+        });
+        let output = std::mem::take(self.interpreter.output_mut());
+
+        LineResult {
+            value: self.interpreter.last_value().filter(|_| is_code).cloned(),
+            output: String::from_utf8_lossy(&output).into_owned(),
+            diagnostics: result.err().map(Diagnostic::from).into_iter().collect(),
+            needs_more_input: false,
+            exit_code: self.interpreter.exit_code().filter(|_| is_code),
+            quit: false,
+        }
+    }
+}
+
+/// Runs an interactive session until `:quit`, the end of input or an `exit`
+/// statement, returning the requested exit code
+///
+/// Output is written to stdout after each entry runs and errors to stderr,
+/// the session goes on after them.
+pub fn run_prompt(options: &Options) -> InterpreterResult<i32> {
+    let mut interpreter = Interpreter::with_output(String::new(), Vec::new());
+    interpreter.apply(options);
+    interpreter.set_redeclaration_policy(options.redeclaration.unwrap_or_default());
+    interpreter.set_block_values(options.block_values);
+    let mut repl = Repl::new(interpreter);
+
+    let mut stdout = io::stdout();
+    let mut stdin = io::stdin().lock();
     loop {
-        print!("> ");
-        io::stdout().flush().unwrap();
+        write!(
+            stdout,
+            "{}",
+            if repl.is_continuing() || repl.is_pasting() {
+                "... "
+            } else {
+                "> "
+            }
+        )?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        let result = if stdin.read_line(&mut line)? == 0 {
+            match repl.finish() {
+                Some(result) => result,
+                None => break,
+            }
+        } else {
+            repl.eval_line(&line)
+        };
+        if result.quit {
+            break;
+        }
 
-        let mut statement = String::new();
-        io::stdin()
-            .read_line(&mut statement)
-            .expect("failed to read in statement");
+        stdout.write_all(result.output.as_bytes())?;
+        stdout.flush()?;
+        for diagnostic in &result.diagnostics {
+            eprintln!("{}", options.render(&diagnostic.message));
+        }
+        if let Some(code) = result.exit_code {
+            return Ok(code);
+        }
+    }
+
+    Ok(0)
+}
 
-        if statement.len() <= 1 {
+/// Runs the sources given with `-e` one after the other in the same
+/// interpreter, so variables declared by one are seen by those after it,
+/// returning the exit code requested by an `exit`
+///
+/// A source ending with `;` or `}` is a program, parsed in strict mode like
+/// a script. Any other source, such as `1 + 2`, is run like a line typed at
+/// the prompt, where the final `;` is optional, and the value of its last
+/// expression is printed. Sources aren't linted and the first error stops
+/// the ones after it.
+pub fn run_inline(sources: &[String], options: &Options) -> InterpreterResult<i32> {
+    let mut interpreter = Interpreter::new(String::new());
+    interpreter.apply(options);
+    interpreter.set_redeclaration_policy(options.redeclaration.unwrap_or_default());
+
+    let mut result = Ok(());
+    for source in sources {
+        let strict = source.trim_end().ends_with([';', '}']);
+        interpreter.set_content(source.clone());
+        result = signal::while_running(|| interpreter.interpret(strict));
+        if result.is_err() || interpreter.exit_code().is_some() {
             break;
         }
-        interpreter.set_content(statement);
-        interpreter.interpret(false)?
     }
+    if options.report_time {
+        eprintln!("{}", interpreter.stats());
+    }
+    result?;
+    Ok(interpreter.exit_code().unwrap_or(0))
+}
 
-    Ok(())
+/// Executes the script at `path` returning the exit code it requested
+pub fn run_file(path: &str, options: &Options) -> InterpreterResult<i32> {
+    let mut interpreter = Interpreter::from_file_limited(path.into(), options.max_source_size)?;
+    run_script(&mut interpreter, options)?;
+    Ok(interpreter.exit_code().unwrap_or(0))
+}
+
+/// Lints and executes the interpreter content, printing warnings and
+/// resource usage to stderr
+fn run_script<W: Write>(
+    interpreter: &mut Interpreter<W>,
+    options: &Options,
+) -> InterpreterResult<()> {
+    interpreter.apply(options);
+    interpreter.set_redeclaration_policy(
+        options
+            .redeclaration
+            .unwrap_or(RedeclarationPolicy::ErrorLocals),
+    );
+
+    let warnings = interpreter.lint(true)?;
+    for warning in &warnings {
+        eprintln!("{}", options.render(warning));
+    }
+    let denied = options.denied(&warnings);
+    if denied > 0 {
+        let plural = if denied == 1 { "" } else { "s" };
+        return Err(InterpreterError::new(
+            &format!("aborting due to {} warning{}", denied, plural),
+            InterpreterErrorKind::Lint,
+        ));
+    }
+
+    let result = signal::while_running(|| interpreter.interpret(true));
+    if options.report_time {
+        eprintln!("{}", interpreter.stats());
+    }
+    result
+}
+
+/// Runs every `.lx` and `.lox` script in the directory in a fresh
+/// interpreter, in order of their paths, returning 1 if any of them failed
+///
+/// The output of every script follows a `== path ==` header and errors are
+/// printed to stderr prefixed with the path, a failing script doesn't stop
+/// the ones after it. A line per script saying how it went and the totals
+/// are printed at the end. Scripts exiting with a code other than 0 fail.
+pub fn run_directory(path: &Path, options: &Options) -> InterpreterResult<i32> {
+    let mut scripts = Vec::new();
+    find_scripts(path, options.recursive, &mut scripts)?;
+    scripts.sort();
+
+    let mut stdout = io::stdout();
+    let mut results = Vec::new();
+    for script in scripts {
+        writeln!(stdout, "== {} ==", options.display_path(&script))?;
+        let result = Interpreter::from_file_limited(script.clone(), options.max_source_size)
+            .and_then(|mut interpreter| {
+                run_script(&mut interpreter, options)?;
+                Ok(interpreter.exit_code().unwrap_or(0))
+            });
+
+        let status = match result {
+            Ok(0) => "ok".to_string(),
+            Ok(code) => format!("exit code {}", code),
+            // Ctrl-C stops the remaining scripts too
+            Err(e)
+                if matches!(
+                    e.kind,
+                    InterpreterErrorKind::Output(_) | InterpreterErrorKind::Interrupted
+                ) =>
+            {
+                return Err(e)
+            }
+            Err(e) => {
+                eprintln!("{}: {}", options.display_path(&script), options.render(&e));
+                match e.kind {
+                    InterpreterErrorKind::Source => "read error",
+                    InterpreterErrorKind::Scan => "scan error",
+                    InterpreterErrorKind::Parse => "parse error",
+                    InterpreterErrorKind::Evaluation => "runtime error",
+                    InterpreterErrorKind::Lint => "denied warnings",
+                    InterpreterErrorKind::Output(_) | InterpreterErrorKind::Interrupted => {
+                        unreachable!()
+                    }
+                }
+                .to_string()
+            }
+        };
+        results.push((script, status));
+    }
+
+    writeln!(stdout)?;
+    for (script, status) in &results {
+        writeln!(stdout, "{}: {}", options.display_path(script), status)?;
+    }
+    let failed = results.iter().filter(|(_, status)| status != "ok").count();
+    writeln!(
+        stdout,
+        "{} passed, {} failed",
+        results.len() - failed,
+        failed
+    )?;
+    stdout.flush()?;
+
+    Ok(if failed == 0 { 0 } else { 1 })
 }
 
-pub fn run_file(path: &str) -> InterpreterResult<()> {
-    let mut interpreter =
-        Interpreter::from_file(path.into()).map_err(|e| InterpreterError { msg: e.to_string() })?;
-    interpreter.interpret(true)?;
+/// Checks every script without running it, directories are searched like
+/// [run_directory] does, returning 65 if any of them has errors
+///
+/// Nothing is printed for valid scripts, errors and warnings go to stderr
+/// prefixed with the path of their script. Warnings only fail the check
+/// with [deny_warnings](Options::deny_warnings) or when their lint is denied.
+pub fn check_scripts(paths: &[String], options: &Options) -> InterpreterResult<i32> {
+    let mut scripts = Vec::new();
+    for path in paths.iter().map(PathBuf::from) {
+        if path.is_dir() {
+            let mut found = Vec::new();
+            find_scripts(&path, options.recursive, &mut found)?;
+            found.sort();
+            scripts.extend(found);
+        } else {
+            scripts.push(path);
+        }
+    }
+
+    let analyze = AnalyzeOptions::new(options.version, true).with_lints(options.lints.clone());
+    let mut failed = false;
+    for script in scripts {
+        let source = match interpreter::read_source(&script, options.max_source_size) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{}: {}", options.display_path(&script), options.render(&e));
+                failed = true;
+                continue;
+            }
+        };
+        let compilation = Compilation::analyze(&source, analyze.clone());
+        for finding in compilation.diagnostics() {
+            eprintln!(
+                "{}: {}",
+                options.display_path(&script),
+                options.render(finding)
+            );
+        }
+        failed |= !compilation.is_valid() || options.denied(compilation.warnings()) > 0;
+    }
+
+    // EX_DATAERR from sysexits.h, the input was invalid
+    Ok(if failed { 65 } else { 0 })
+}
+
+/// Prints the source rebuilt from the tokens of the script, comments
+/// included, to see what the scanner kept of it
+pub fn reconstruct_script(path: &str, options: &Options) -> InterpreterResult<i32> {
+    let source = interpreter::read_source(Path::new(path), options.max_source_size)?;
+    let scanner = Scanner::with_comments_and_version(&source, true, options.version)?;
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", reconstruct_source(&scanner.tokens))?;
+    stdout.flush()?;
+    Ok(0)
+}
+
+/// Prints the script formatted with the options of `--fmt`
+pub fn format_script(path: &str, options: &Options) -> InterpreterResult<i32> {
+    let source = interpreter::read_source(Path::new(path), options.max_source_size)?;
+    let formatted = format_source_with(&source, &options.format)?;
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "{}", formatted)?;
+    stdout.flush()?;
+    Ok(0)
+}
+
+fn find_scripts(dir: &Path, recursive: bool, scripts: &mut Vec<PathBuf>) -> InterpreterResult<()> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        InterpreterError::new(
+            &format!("cannot read {}: {}", dir.display(), e),
+            InterpreterErrorKind::Source,
+        )
+    })?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| InterpreterError::new(&e.to_string(), InterpreterErrorKind::Source))?
+            .path();
+        if path.is_dir() {
+            if recursive {
+                find_scripts(&path, recursive, scripts)?;
+            }
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "lx" || extension == "lox")
+        {
+            scripts.push(path);
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meta_commands_are_told_apart_from_code() {
+        assert_eq!(Command::parse(":help\n"), Some(Command::Help));
+        assert_eq!(Command::parse("  help "), Some(Command::Help));
+        assert_eq!(
+            Command::parse(":tokens 1 + 2\n"),
+            Some(Command::Tokens("1 + 2"))
+        );
+        assert_eq!(
+            Command::parse(":parse\t{ a; }"),
+            Some(Command::Parse("{ a; }"))
+        );
+        assert_eq!(Command::parse(":parse"), Some(Command::Parse("")));
+        assert_eq!(Command::parse(":type _ + 1"), Some(Command::Type("_ + 1")));
+        assert_eq!(Command::parse(":paste\n"), Some(Command::Paste));
+        assert_eq!(Command::parse(" :quit"), Some(Command::Quit));
+        assert_eq!(Command::parse(":exit now"), Some(Command::Unknown("exit")));
+        assert_eq!(Command::parse("helper;"), None);
+        assert_eq!(Command::parse("let a = \":help\";"), None);
+    }
+
+    fn repl() -> Repl {
+        Repl::new(Interpreter::with_output(String::new(), Vec::new()))
+    }
+
+    /// Lines still waiting for more input after each line
+    fn continuations(repl: &mut Repl, lines: &[&str]) -> Vec<bool> {
+        lines
+            .iter()
+            .map(|line| repl.eval_line(line).needs_more_input)
+            .collect()
+    }
+
+    #[test]
+    fn entries_continue_until_brackets_and_strings_are_closed() {
+        let mut repl = repl();
+        let lines = [
+            "{\n",
+            "  let a = \"\"\"one\n",
+            "\n",
+            "two\"\"\";\n",
+            "  a;\n",
+            "}\n",
+            "1 + (\n",
+            "2);\n",
+            ":tokens {\n",
+            "\"${\n",
+            "1}\";\n",
+        ];
+        assert_eq!(
+            continuations(&mut repl, &lines),
+            [true, true, true, true, true, false, true, false, false, true, false]
+        );
+        assert!(!repl.is_continuing());
+    }
+
+    #[test]
+    fn entries_with_scan_errors_are_run_right_away() {
+        let mut repl = repl();
+        assert_eq!(
+            continuations(&mut repl, &["{ @\n", "}\n", "\"open\n", "{\n"]),
+            [false, false, false, true]
+        );
+
+        let result = repl.finish().unwrap();
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].kind, InterpreterErrorKind::Parse);
+        assert_eq!(repl.finish(), None);
+    }
+
+    #[test]
+    fn pasted_lines_run_once_in_strict_mode() {
+        let mut repl = repl();
+        let pasted = [
+            ":paste\n",
+            "let total =\n",
+            "    1 +\n",
+            "\n",
+            "    2;\n",
+            "\n",
+            "write \"once\";\n",
+        ];
+        assert!(continuations(&mut repl, &pasted).iter().all(|&more| more));
+        assert!(repl.is_pasting());
+        assert_eq!(repl.interpreter().environment().get("total"), None);
+
+        let result = repl.eval_line(":end\n");
+        assert!(!repl.is_pasting());
+        assert_eq!(result.output, "once");
+        assert!(result.diagnostics.is_empty());
+
+        // The session goes on, blank lines don't end it
+        assert_eq!(repl.eval_line("\n"), LineResult::default());
+        assert_eq!(repl.eval_line("total").output, "3\n");
+
+        // Meta-commands are code once pasted and a single error stops the
+        // whole paste
+        continuations(&mut repl, &[":paste", "print 1;", ":help", "total"]);
+        let result = repl.eval_line(":end");
+        assert_eq!(result.output, "");
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].kind, InterpreterErrorKind::Scan);
+        assert!(repl.eval_line(":quit").quit);
+    }
+
+    #[test]
+    fn lines_report_values_output_and_errors_separately() {
+        let mut repl = repl();
+
+        let result = repl.eval_line("let a = 2;");
+        assert_eq!(result, LineResult::default());
+
+        let result = repl.eval_line("print a; a * 5");
+        assert_eq!(result.output, "2\n10\n");
+        assert_eq!(result.value, Some(Literal::Integer(10)));
+        assert!(result.diagnostics.is_empty());
+
+        let result = repl.eval_line("\"before\"; a & true;");
+        // Values echoed before the error are still bound to `_`
+        assert_eq!(result.output, "before\n");
+        assert_eq!(result.value, Some(Literal::String("before".into())));
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(
+            result.diagnostics[0].message,
+            "evaluation error[R007]: operand true of `&` is not a number at line 1 column 13"
+        );
+
+        assert!(repl.eval_line("{ let b = a * 5;").needs_more_input);
+        assert_eq!(repl.eval_line("a = b; }"), LineResult::default());
+        assert!(repl.eval_line("(a").needs_more_input);
+        let result = repl.eval_line("+ 1)");
+        assert_eq!(result.output, "11\n");
+        assert_eq!(result.value, Some(Literal::Integer(11)));
+
+        let result = repl.eval_line(":type _");
+        assert_eq!(result.output, "number: 11\n");
+        assert_eq!((result.value, result.exit_code), (None, None));
+
+        let result = repl.eval_line("exit a;");
+        assert_eq!(result.exit_code, Some(10));
+        assert!(repl.interpreter().output().is_empty());
+    }
+
+    #[test]
+    fn blocks_typed_alone_echo_their_last_expression_on_request() {
+        let mut repl = repl();
+        assert_eq!(repl.eval_line("{ let a = 1; a + 1 }").output, "");
+
+        repl.interpreter_mut().set_block_values(true);
+        let result = repl.eval_line("{ let a = 1; a + 1 }");
+        assert_eq!(result.output, "2\n");
+        assert_eq!(result.value, Some(Literal::Integer(2)));
+        assert!(!repl.interpreter().environment().contains("a"));
+
+        assert_eq!(repl.eval_line("{ 1; let b = 2; }"), LineResult::default());
+        assert_eq!(repl.eval_line("{ 1; { 2 } }").output, "");
+        // Only lines made of the block alone
+        assert_eq!(repl.eval_line("{ 1 } 3").output, "3\n");
+    }
+
+    #[test]
+    fn lines_report_panics_as_internal_errors() {
+        let mut repl = repl();
+        repl.interpreter_mut().environment_mut().define(
+            "crash".into(),
+            Literal::Native(crate::Native {
+                name: "crash",
+                arity: 0,
+                function: crash,
+            }),
+        );
+
+        let result = repl.eval_line("let a = 1; crash();");
+        assert_eq!(
+            result.diagnostics[0].message,
+            "internal error (please report): native crashed"
+        );
+        assert!(!repl.interpreter().environment().contains("a"));
+        assert_eq!(repl.eval_line("1").output, "1\n");
+    }
+
+    fn run_lines(lines: &[&str]) -> String {
+        let mut interpreter = Interpreter::with_output(String::new(), Vec::new());
+        for line in lines {
+            run_line(&mut interpreter, line).unwrap();
+        }
+        String::from_utf8_lossy(interpreter.output()).into_owned()
+    }
+
+    fn crash(_: &mut crate::Environment, _: &[Literal]) -> Result<Literal, String> {
+        panic!("native crashed")
+    }
+
+    #[test]
+    fn panics_restore_the_variables_from_before_the_line() {
+        let mut interpreter = Interpreter::with_output(String::new(), Vec::new());
+        interpreter.environment_mut().define(
+            "crash".into(),
+            Literal::Native(crate::Native {
+                name: "crash",
+                arity: 0,
+                function: crash,
+            }),
+        );
+
+        run_line(&mut interpreter, "let a = 1;").unwrap();
+        run_line(
+            &mut interpreter,
+            "a = 2; let b = 3; { let c = 4; crash(); }",
+        )
+        .unwrap();
+        run_line(&mut interpreter, "a;").unwrap();
+
+        // Output written before the panic stays, `a = 2` is undone
+        assert_eq!(String::from_utf8_lossy(interpreter.output()), "2\n1\n");
+        assert!(!interpreter.environment().contains("b"));
+        assert_eq!(interpreter.environment().depth(), 0);
+    }
+
+    #[test]
+    fn prompt_runs_meta_commands_and_code() {
+        let output = run_lines(&[":help"]);
+        assert!(output.contains(":tokens <code>"), "{}", output);
+        assert!(output.contains("let name = value;"), "{}", output);
+        assert_eq!(run_lines(&["help"]), output);
+
+        assert_eq!(
+            run_lines(&[":tokens let a = \"hi\";"]),
+            "1:1 Let \"let\"\n1:5 Identifier \"a\"\n1:7 Equal \"=\"\n1:9 String \"hi\"\n1:13 SemiColon \";\"\n"
+        );
+        assert_eq!(
+            run_lines(&[":parse let a = -(1 + 2) * 3; { a; exit len(\"x\"); }"]),
+            "(let a ((- (group (1 + 2))) * 3))\n(block a (exit (call len x)))\n"
+        );
+        assert_eq!(
+            run_lines(&[":what", "1 + 1"]),
+            "unknown command :what, try :help\n2\n"
+        );
+    }
+
+    #[test]
+    fn meta_commands_report_errors_without_running_code() {
+        let output = run_lines(&[":tokens \"open", ":parse let = 1;", ":parse exit 3;"]);
+
+        assert!(
+            output.starts_with("scan error[S002] at 1:1; unterminated string"),
+            "{}",
+            output
+        );
+        assert!(
+            output
+                .contains("\nruntime exception[P002]: expected an identifier at line 1 column 5\n"),
+            "{}",
+            output
+        );
+        assert!(output.ends_with("(exit 3)\n"), "{}", output);
+    }
+
+    #[test]
+    fn last_value_is_bound_to_underscore() {
+        let mut interpreter = Interpreter::with_output(String::new(), Vec::new());
+        for line in [
+            ":type",
+            "1 + 2",
+            "let a = 1;",
+            "_ * 2",
+            "{ 5; }",
+            "\"hi\"; 7;",
+        ] {
+            run_line(&mut interpreter, line).unwrap();
+        }
+        assert!(matches!(
+            interpreter.environment().get("_"),
+            Some(Literal::Integer(7))
+        ));
+
+        // Code can declare its own `_`, the next echoed value replaces it
+        run_line(&mut interpreter, "let _ = \"mine\"; _;").unwrap();
+        run_line(&mut interpreter, "true").unwrap();
+        assert!(matches!(
+            interpreter.environment().get("_"),
+            Some(Literal::Boolean(true))
+        ));
+
+        let output = String::from_utf8_lossy(interpreter.output()).into_owned();
+        assert_eq!(
+            output,
+            "no value yet, `_` is set by expressions\n3\n6\nhi\n7\nmine\ntrue\n"
+        );
+    }
+
+    #[test]
+    fn type_command_inspects_values_without_binding_them() {
+        assert_eq!(
+            run_lines(&[
+                "2.5",
+                ":type",
+                ":type _ * 2",
+                ":type \"a${1}\"",
+                ":type len",
+                ":type",
+                ":type 1; 2;",
+                ":type 1 & true",
+            ]),
+            "2.5\nnumber: 2.5\nnumber: 5\nstring: a1\nfunction: <native fn len>\nnumber: 2.5
+expected a single expression
+evaluation error[R007]: operand true of `&` is not a number at line 1 column 3\n"
+        );
+    }
+
+    #[test]
+    fn prompt_stays_usable_after_hitting_limits() {
+        let mut interpreter = Interpreter::with_output(String::new(), Vec::new());
+        interpreter.set_max_variables(Some(50));
+        interpreter.set_max_string_len(Some(8));
+
+        let declarations: String = (0..100).map(|i| format!("let a{} = {};", i, i)).collect();
+        let error = run_line(&mut interpreter, &declarations).unwrap_err();
+        assert_eq!(
+            error.msg,
+            "evaluation error[R015]: variable limit of 50 reached at line 1 column 635"
+        );
+        let error = run_line(&mut interpreter, "\"${a49}${a49}${a49}${a49}${a49}\"").unwrap_err();
+        assert!(
+            error.msg.contains("string length limit of 8 exceeded"),
+            "{}",
+            error
+        );
+        let error = run_line(&mut interpreter, "toUpper(\"long string\")").unwrap_err();
+        assert!(
+            error.msg.contains("string length limit of 8 exceeded"),
+            "{}",
+            error
+        );
+
+        // Declared variables are kept and can still be reassigned
+        run_line(&mut interpreter, "a49 = a49 + 1; a49; \"${a0} ok\"").unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(interpreter.output()),
+            "50\n50\n0 ok\n"
+        );
+    }
+
+    #[test]
+    fn underscore_is_not_defined_in_file_mode() {
+        let mut interpreter = Interpreter::with_output("1 + 1;".into(), Vec::new());
+        interpreter.interpret(true).unwrap();
+
+        assert!(matches!(
+            interpreter.last_value(),
+            Some(Literal::Integer(2))
+        ));
+        assert!(!interpreter.environment().contains("_"));
+    }
+
+    fn from_args(args: &[&str]) -> Result<(Option<String>, Options), String> {
+        Options::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn flags_before_the_script_are_options() {
+        let (path, options) =
+            from_args(&["--lang=v1", "--no-fs", "a.lx", "--lang=v3", "x"]).unwrap();
+
+        assert_eq!(path.as_deref(), Some("a.lx"));
+        assert_eq!(options.version, LanguageVersion::V1);
+        assert!(!options.file_io && options.env_access);
+        assert_eq!(options.args, vec!["--lang=v3", "x"]);
+
+        let (_, options) = from_args(&["--max-vars=10", "--max-string=0"]).unwrap();
+        assert_eq!(options.max_variables, Some(10));
+        assert_eq!(options.max_string_len, Some(0));
+
+        let (_, options) = from_args(&["--redeclare=deny"]).unwrap();
+        assert_eq!(options.redeclaration, Some(RedeclarationPolicy::ErrorAll));
+        assert!(from_args(&["--redeclare=yes"]).is_err());
+
+        let (path, options) = from_args(&["--explain", "P001"]).unwrap();
+        assert_eq!((path, options.explain.as_deref()), (None, Some("P001")));
+        assert!(from_args(&["--explain"]).is_err());
+
+        let (path, options) = from_args(&["--check", "a.lx", "tests"]).unwrap();
+        assert_eq!(path.as_deref(), Some("a.lx"));
+        assert_eq!(
+            (options.mode, options.args),
+            (Mode::Check, vec!["tests".into()])
+        );
+        assert!(from_args(&["--check"]).is_err());
+        let (path, options) = from_args(&["--reconstruct", "a.lx"]).unwrap();
+        assert_eq!(
+            (path.as_deref(), options.mode),
+            (Some("a.lx"), Mode::Reconstruct)
+        );
+        assert!(from_args(&["--reconstruct"]).is_err());
+        let (path, options) = from_args(&["--fmt", "--fmt-strip-parens", "a.lx"]).unwrap();
+        assert_eq!(
+            (path.as_deref(), options.mode, options.format.strip_parens),
+            (Some("a.lx"), Mode::Format, true)
+        );
+        assert!(from_args(&["--fmt"]).is_err());
+        assert!(from_args(&["--fmt-strip-parens", "a.lx"]).is_err());
+
+        assert!(from_args(&["--block-values"]).unwrap().1.block_values);
+        assert!(from_args(&["--block-values", "a.lx"]).is_err());
+
+        let (path, options) = from_args(&["-e", "1;", "--time", "-e", "2", "a", "-e"]).unwrap();
+        assert_eq!(path, None);
+        assert_eq!(options.inline, vec!["1;", "2"]);
+        assert_eq!(options.args, vec!["a", "-e"]);
+        assert!(options.report_time);
+        assert!(from_args(&["-e"]).is_err());
+        assert!(from_args(&["--block-values", "-e", "1"]).is_err());
+
+        let (path, options) = from_args(&[]).unwrap();
+        assert_eq!(path, None);
+        assert_eq!(options.mode, Mode::Execute);
+        assert_eq!(options.version, LanguageVersion::V2);
+        assert!(options.file_io);
+    }
+
+    #[test]
+    fn invalid_flags_are_rejected() {
+        assert_eq!(
+            from_args(&["--lang=3.0", "a.lx"]).unwrap_err(),
+            "unknown language version `3.0`, expected v1 or v2"
+        );
+        assert_eq!(
+            from_args(&["--verbose"]).unwrap_err(),
+            "unknown flag `--verbose`"
+        );
+        assert_eq!(
+            from_args(&["--max-vars=lots"]).unwrap_err(),
+            "invalid value `lots` for --max-vars, expected a whole number"
+        );
+        assert!(from_args(&["--max-string=-1"]).is_err());
+        assert!(from_args(&["--time"]).is_err());
+        assert!(from_args(&["--lang="]).is_err());
+    }
+
+    #[test]
+    fn meta_commands_use_the_interpreter_language_version() {
+        let mut interpreter = Interpreter::with_output(String::new(), Vec::new());
+        interpreter.set_language_version(LanguageVersion::V1);
+        run_line(&mut interpreter, ":parse exit 1;").unwrap();
+
+        let output = String::from_utf8_lossy(interpreter.output()).into_owned();
+        assert!(
+            output.contains("`exit` is not available in language version v1"),
+            "{}",
+            output
+        );
+    }
+}
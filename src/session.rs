@@ -0,0 +1,147 @@
+use std::mem;
+
+use crate::errors::{InterpreterError, InterpreterErrorKind};
+use crate::Interpreter;
+
+/// Problem reported while running a program in a [Session]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub kind: InterpreterErrorKind,
+    pub message: String,
+}
+
+impl From<InterpreterError> for Diagnostic {
+    fn from(value: InterpreterError) -> Self {
+        Self {
+            kind: value.kind,
+            message: value.msg,
+        }
+    }
+}
+
+/// Everything a program produced when run in a [Session]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Outcome {
+    /// Output written by the program before it finished or failed
+    pub output: String,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Exit code requested by an `exit` statement
+    pub exit_code: Option<i32>,
+}
+
+/// Platform independent entry point for embedding the interpreter
+///
+/// A session takes source strings and returns their output as values rather
+/// than reading stdin or writing stdout, so it can be used where there is no
+/// terminal such as a browser playground. Variables declared in one run are
+/// visible to the following runs.
+///
+/// ## Examples
+/// ```rust
+/// use lox::Session;
+///
+/// let mut session = Session::new();
+/// session.run("let a = 2;");
+/// let outcome = session.run("a * 4;");
+///
+/// assert_eq!(outcome.output, "8\n");
+/// assert!(outcome.diagnostics.is_empty());
+/// ```
+pub struct Session {
+    interpreter: Interpreter<Vec<u8>>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::with_output(String::new(), Vec::new()),
+        }
+    }
+
+    /// Scans, parses and executes `source` in strict mode
+    pub fn run(&mut self, source: &str) -> Outcome {
+        self.interpreter.set_content(source.into());
+        let result = self.interpreter.interpret(true);
+        let output = mem::take(self.interpreter.output_mut());
+
+        Outcome {
+            output: String::from_utf8_lossy(&output).into_owned(),
+            diagnostics: result.err().map(Diagnostic::from).into_iter().collect(),
+            exit_code: self.interpreter.exit_code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stdlib::Clock;
+
+    #[test]
+    fn runs_programs_and_collects_output() {
+        let mut session = Session::new();
+        let outcome = session.run("let a = 1;\n\"a is ${a}\";\na + 1;");
+
+        assert_eq!(
+            outcome,
+            Outcome {
+                output: "a is 1\n2\n".into(),
+                diagnostics: vec![],
+                exit_code: None,
+            }
+        );
+    }
+
+    #[test]
+    fn creating_a_session_does_not_read_the_clock() {
+        // Reading it panics on wasm32-unknown-unknown
+        let mut session = Session::new();
+        session.run("let a = 1;");
+        let clock = session.interpreter.environment_mut().clock();
+        assert_eq!(*clock, Clock::System(None));
+
+        session.run("clock();");
+        let clock = session.interpreter.environment_mut().clock();
+        assert!(matches!(clock, Clock::System(Some(_))));
+    }
+
+    #[test]
+    fn keeps_variables_between_runs() {
+        let mut session = Session::new();
+        session.run("let a = 1;");
+        session.run("a = a + 1;");
+
+        assert_eq!(session.run("a;").output, "2\n");
+    }
+
+    #[test]
+    fn reports_errors_as_diagnostics_after_partial_output() {
+        let mut session = Session::new();
+        let outcome = session.run("1;\n1 & true;\n2;");
+
+        assert_eq!(outcome.output, "1\n");
+        assert_eq!(outcome.diagnostics.len(), 1);
+        assert_eq!(
+            outcome.diagnostics[0].kind,
+            InterpreterErrorKind::Evaluation
+        );
+
+        let outcome = session.run("let = 1;");
+        assert_eq!(outcome.output, "");
+        assert_eq!(outcome.diagnostics[0].kind, InterpreterErrorKind::Parse);
+    }
+
+    #[test]
+    fn reports_exit_codes() {
+        let outcome = Session::new().run("\"bye\";\nexit 3;");
+
+        assert_eq!(outcome.output, "bye\n");
+        assert_eq!(outcome.exit_code, Some(3));
+    }
+}
@@ -0,0 +1,119 @@
+//! Ctrl-C handling for the command line, interrupting the running program
+//! rather than killing the process
+//!
+//! The first Ctrl-C while a program runs asks it to stop through the shared
+//! [InterruptHandle], a second one before it stopped exits right away. With
+//! no program running, such as at the prompt, Ctrl-C exits as usual.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::InterruptHandle;
+
+/// Exit code of processes ended by Ctrl-C, 128 + SIGINT
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static HANDLE: OnceLock<InterruptHandle> = OnceLock::new();
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Installs the handler the first time it's called, returning the handle it
+/// interrupts
+pub(crate) fn install() -> InterruptHandle {
+    HANDLE
+        .get_or_init(|| {
+            platform::install();
+            InterruptHandle::default()
+        })
+        .clone()
+}
+
+/// Runs `f` with Ctrl-C interrupting it
+pub(crate) fn while_running<T>(f: impl FnOnce() -> T) -> T {
+    RUNNING.store(true, Ordering::SeqCst);
+    let value = f();
+    RUNNING.store(false, Ordering::SeqCst);
+    value
+}
+
+/// Reacts to a Ctrl-C, returning false if the process should exit
+///
+/// Called from a signal handler, so this only touches atomics.
+fn on_interrupt() -> bool {
+    let Some(handle) = HANDLE.get() else {
+        return false;
+    };
+    if !RUNNING.load(Ordering::SeqCst) || handle.is_interrupted() {
+        return false;
+    }
+    handle.interrupt();
+    true
+}
+
+const MESSAGE: &[u8] = b"^C interrupting...\n";
+
+#[cfg(unix)]
+mod platform {
+    use std::os::raw::c_int;
+
+    const SIGINT: c_int = 2;
+    const STDERR: c_int = 2;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+        fn write(fd: c_int, buf: *const u8, count: usize) -> isize;
+        fn _exit(status: c_int) -> !;
+    }
+
+    extern "C" fn handler(_: c_int) {
+        // Only async-signal-safe functions may be called here, which rules
+        // out the standard library's stderr and exit
+        if super::on_interrupt() {
+            unsafe { write(STDERR, super::MESSAGE.as_ptr(), super::MESSAGE.len()) };
+        } else {
+            unsafe { _exit(super::INTERRUPTED_EXIT_CODE) }
+        }
+    }
+
+    pub(super) fn install() {
+        unsafe {
+            signal(SIGINT, handler);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::io::Write;
+
+    const CTRL_C_EVENT: u32 = 0;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleCtrlHandler(
+            handler: Option<unsafe extern "system" fn(u32) -> i32>,
+            add: i32,
+        ) -> i32;
+    }
+
+    /// Runs on a thread of its own, returning 0 lets the default handler
+    /// end the process
+    unsafe extern "system" fn handler(event: u32) -> i32 {
+        if event == CTRL_C_EVENT && super::on_interrupt() {
+            let _ = std::io::stderr().write_all(super::MESSAGE);
+            1
+        } else {
+            0
+        }
+    }
+
+    pub(super) fn install() {
+        unsafe {
+            SetConsoleCtrlHandler(Some(handler), 1);
+        }
+    }
+}
+
+/// Ctrl-C keeps its default behavior elsewhere
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    pub(super) fn install() {}
+}
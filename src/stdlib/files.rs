@@ -0,0 +1,156 @@
+//! File functions, these fail unless file I/O has been enabled with
+//! [Interpreter::enable_file_io](crate::Interpreter::enable_file_io)
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use super::string;
+use crate::{Environment, Literal, Native};
+
+pub(super) const FUNCTIONS: [Native; 4] = [
+    Native {
+        name: "readFile",
+        arity: 1,
+        function: read_file,
+    },
+    Native {
+        name: "writeFile",
+        arity: 2,
+        function: write_file,
+    },
+    Native {
+        name: "appendFile",
+        arity: 2,
+        function: append_file,
+    },
+    Native {
+        name: "fileExists",
+        arity: 1,
+        function: file_exists,
+    },
+];
+
+/// Returns the path argument if the environment allows file I/O
+fn path<'a>(env: &Environment, function: &str, value: &'a Literal) -> Result<&'a str, String> {
+    if !env.file_io() {
+        return Err(format!("{}: file I/O is disabled", function));
+    }
+    string(function, "path", value)
+}
+
+fn read_file(env: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let path = path(env, "readFile", &args[0])?;
+    fs::read_to_string(path)
+        .map(Literal::String)
+        .map_err(|e| format!("readFile: cannot read '{}': {}", path, e))
+}
+
+fn write_file(env: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let path = path(env, "writeFile", &args[0])?;
+    let contents = string("writeFile", "contents", &args[1])?;
+    fs::write(path, contents)
+        .map(|_| Literal::Nil)
+        .map_err(|e| format!("writeFile: cannot write '{}': {}", path, e))
+}
+
+fn append_file(env: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let path = path(env, "appendFile", &args[0])?;
+    let contents = string("appendFile", "contents", &args[1])?;
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map(|_| Literal::Nil)
+        .map_err(|e| format!("appendFile: cannot write '{}': {}", path, e))
+}
+
+fn file_exists(env: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let path = path(env, "fileExists", &args[0])?;
+    Ok(Literal::Boolean(Path::new(path).exists()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use crate::Interpreter;
+
+    /// Empty directory unique to the test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("lox-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn interpret(source: &str, file_io: bool) -> (String, Option<String>) {
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.enable_file_io(file_io);
+        let error = interpreter.interpret(true).err().map(|e| e.msg);
+        let output = String::from_utf8_lossy(interpreter.output()).into_owned();
+        (output, error)
+    }
+
+    #[test]
+    fn reads_and_writes_files() {
+        let dir = temp_dir("files");
+        let path = dir.join("notes.txt").display().to_string();
+        let source = format!(
+            "let path = {:?};
+            fileExists(path);
+            writeFile(path, \"\"\"one\n\"\"\");
+            appendFile(path, \"two\");
+            fileExists(path);
+            readFile(path);",
+            path
+        );
+
+        let (output, error) = interpret(&source, true);
+        assert_eq!(error, None);
+        assert_eq!(output, "false\nnil\nnil\ntrue\none\ntwo\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reading_missing_files_reports_the_os_error() {
+        let dir = temp_dir("missing");
+        let path = dir.join("missing.txt").display().to_string();
+
+        let (_, error) = interpret(&format!("readFile({:?});", path), true);
+        let error = error.unwrap();
+        assert!(
+            error.contains(&format!("readFile: cannot read '{}': ", path)),
+            "{}",
+            error
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn file_io_is_disabled_by_default() {
+        let dir = temp_dir("disabled");
+        let path = dir.join("created.txt");
+        let source = format!("writeFile({:?}, \"data\");", path.display().to_string());
+
+        let mut interpreter = Interpreter::with_output(source, Vec::new());
+        let error = interpreter.interpret(true).unwrap_err();
+        assert!(
+            error.msg.contains("writeFile: file I/O is disabled"),
+            "{}",
+            error
+        );
+        assert!(!path.exists());
+
+        let (_, error) = interpret("fileExists(\"x\");", false);
+        assert_eq!(
+            error.unwrap(),
+            "evaluation error[R011]: fileExists: file I/O is disabled at line 1 column 15"
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
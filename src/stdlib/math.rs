@@ -0,0 +1,274 @@
+//! Math functions
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use super::{number, whole_number};
+use crate::{Environment, Literal, Native};
+
+pub(super) const FUNCTIONS: [Native; 10] = [
+    Native {
+        name: "floor",
+        arity: 1,
+        function: floor,
+    },
+    Native {
+        name: "ceil",
+        arity: 1,
+        function: ceil,
+    },
+    Native {
+        name: "round",
+        arity: 1,
+        function: round,
+    },
+    Native {
+        name: "abs",
+        arity: 1,
+        function: abs,
+    },
+    Native {
+        name: "sqrt",
+        arity: 1,
+        function: sqrt,
+    },
+    Native {
+        name: "min",
+        arity: 2,
+        function: min,
+    },
+    Native {
+        name: "max",
+        arity: 2,
+        function: max,
+    },
+    Native {
+        name: "pow",
+        arity: 2,
+        function: pow,
+    },
+    Native {
+        name: "random",
+        arity: 0,
+        function: random,
+    },
+    Native {
+        name: "randomInt",
+        arity: 2,
+        function: random_int,
+    },
+];
+
+/// Pseudo random number generator behind `random` and `randomInt`
+///
+/// Generators created with the same seed produce the same sequence, the
+/// default one is seeded differently for every program.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed so similar seeds give unrelated sequences, the
+        // xorshift state must never be zero
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self {
+            state: (state ^ (state >> 31)) | 1,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a number in `[0, 1)`
+    pub fn next_float(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(RandomState::new().build_hasher().finish())
+    }
+}
+
+/// Applies a rounding function to floats, integers are returned as is
+fn rounded(function: &str, value: &Literal, round: fn(f32) -> f32) -> Result<Literal, String> {
+    match value {
+        Literal::Integer(value) => Ok(Literal::Integer(*value)),
+        value => Ok(Literal::Number(round(number(function, "x", value)?))),
+    }
+}
+
+fn floor(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    rounded("floor", &args[0], f32::floor)
+}
+
+fn ceil(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    rounded("ceil", &args[0], f32::ceil)
+}
+
+/// Rounds half way cases away from zero
+fn round(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    rounded("round", &args[0], f32::round)
+}
+
+fn abs(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    match &args[0] {
+        Literal::Integer(value) => Ok(value
+            .checked_abs()
+            .map_or(Literal::Number((*value as f32).abs()), Literal::Integer)),
+        value => Ok(Literal::Number(number("abs", "x", value)?.abs())),
+    }
+}
+
+fn sqrt(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let value = number("sqrt", "x", &args[0])?;
+    if value < 0.0 {
+        return Err(format!("sqrt: 'x' must not be negative, got {}", value));
+    }
+    Ok(Literal::Number(value.sqrt()))
+}
+
+fn min(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    if let [Literal::Integer(a), Literal::Integer(b)] = args {
+        return Ok(Literal::Integer(*a.min(b)));
+    }
+    let a = number("min", "a", &args[0])?;
+    let b = number("min", "b", &args[1])?;
+    Ok(Literal::Number(a.min(b)))
+}
+
+fn max(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    if let [Literal::Integer(a), Literal::Integer(b)] = args {
+        return Ok(Literal::Integer(*a.max(b)));
+    }
+    let a = number("max", "a", &args[0])?;
+    let b = number("max", "b", &args[1])?;
+    Ok(Literal::Number(a.max(b)))
+}
+
+fn pow(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let base = number("pow", "base", &args[0])?;
+    let exp = number("pow", "exp", &args[1])?;
+    Ok(Literal::Number(base.powf(exp)))
+}
+
+/// Number in `[0, 1)`
+fn random(env: &mut Environment, _: &[Literal]) -> Result<Literal, String> {
+    Ok(Literal::Number(env.rng().next_float()))
+}
+
+/// Whole number between `lo` and `hi`, both included
+fn random_int(env: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let lo = whole_number("randomInt", "lo", &args[0])?;
+    let hi = whole_number("randomInt", "hi", &args[1])?;
+    if lo > hi {
+        return Err(format!(
+            "randomInt: 'lo' must not be greater than 'hi', got {} and {}",
+            lo, hi
+        ));
+    }
+
+    let range = (hi as i128 - lo as i128 + 1) as u128;
+    let offset = (env.rng().next_u64() as u128 % range) as i128;
+    Ok(Literal::Integer((lo as i128 + offset) as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stdlib::tests::{error, run};
+    use crate::Interpreter;
+
+    #[test]
+    fn math_functions_are_evaluated() {
+        let source = "floor(-1.5); ceil(1.2); round(2.5); round(-2.5); abs(-3);
+            sqrt(16); pow(2, 10); pow(4, 0.5);";
+
+        assert_eq!(run(source), "-2\n2\n3\n-3\n3\n4\n1024\n2\n");
+    }
+
+    #[test]
+    fn min_and_max_ignore_argument_order() {
+        let source = "min(1, 2); min(2, 1); max(1, 2); max(2, 1); min(-0.5, -1);";
+
+        assert_eq!(run(source), "1\n1\n2\n2\n-1\n");
+    }
+
+    #[test]
+    fn integer_arguments_stay_exact() {
+        let source = "abs(-9007199254740993); floor(9007199254740993);
+            max(9007199254740993, 9007199254740992); parseNumber(\"9007199254740993\") + 2;";
+
+        assert_eq!(
+            run(source),
+            "9007199254740993\n9007199254740993\n9007199254740993\n9007199254740995\n"
+        );
+    }
+
+    #[test]
+    fn arguments_are_checked() {
+        assert_eq!(
+            error("sqrt(-1);"),
+            "evaluation error[R011]: sqrt: 'x' must not be negative, got -1 at line 1 column 8"
+        );
+        assert_eq!(
+            error("max(1, \"2\");"),
+            "evaluation error[R011]: max: 'b' must be a number, got string at line 1 column 11"
+        );
+        assert_eq!(
+            error("random(1);"),
+            "evaluation error[R009]: wrong number of arguments for `random`, expected 0 got 1 at line 1 column 9"
+        );
+        assert_eq!(
+            error("randomInt(1.5, 2);"),
+            "evaluation error[R011]: randomInt: 'lo' must be a whole number, got 1.5 at line 1 column 17"
+        );
+        assert_eq!(
+            error("randomInt(3, 2);"),
+            "evaluation error[R011]: randomInt: 'lo' must not be greater than 'hi', got 3 and 2 at line 1 column 15"
+        );
+    }
+
+    fn random_output(seed: u64) -> String {
+        let source = "random(); random(); randomInt(1, 6); randomInt(-10, 10); random();";
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.set_rng_seed(seed);
+        interpreter.interpret(true).unwrap();
+        String::from_utf8_lossy(interpreter.output()).into_owned()
+    }
+
+    #[test]
+    fn seeded_interpreters_produce_the_same_numbers() {
+        assert_eq!(random_output(7), random_output(7));
+        assert_ne!(random_output(7), random_output(8));
+
+        let output = random_output(42);
+        let values: Vec<f32> = output.lines().map(|line| line.parse().unwrap()).collect();
+        for value in [values[0], values[1], values[4]] {
+            assert!((0.0..1.0).contains(&value), "{}", value);
+        }
+        assert!((1.0..=6.0).contains(&values[2]) && values[2].fract() == 0.0);
+        assert!((-10.0..=10.0).contains(&values[3]) && values[3].fract() == 0.0);
+    }
+
+    #[test]
+    fn random_ints_cover_the_whole_range() {
+        let source = "randomInt(1, 3);".repeat(100);
+        let mut interpreter = Interpreter::with_output(source, Vec::new());
+        interpreter.set_rng_seed(1);
+        interpreter.interpret(true).unwrap();
+
+        let output = String::from_utf8_lossy(interpreter.output()).into_owned();
+        for value in ["1", "2", "3"] {
+            assert!(output.lines().any(|line| line == value), "{}", output);
+        }
+        assert!(output.lines().all(|line| ["1", "2", "3"].contains(&line)));
+    }
+}
@@ -0,0 +1,97 @@
+//! Native functions available to every lox program
+use crate::{Environment, Literal};
+
+mod files;
+mod math;
+mod output;
+mod process;
+mod strings;
+
+pub use math::Rng;
+pub use process::Clock;
+
+/// Defines the standard library functions in the environment
+pub fn install(env: &mut Environment) {
+    let functions = strings::FUNCTIONS
+        .into_iter()
+        .chain(math::FUNCTIONS)
+        .chain(files::FUNCTIONS)
+        .chain(output::FUNCTIONS)
+        .chain(process::FUNCTIONS);
+    for native in functions {
+        env.define(native.name.into(), Literal::Native(native));
+    }
+}
+
+pub(super) fn string<'a>(
+    function: &str,
+    parameter: &str,
+    value: &'a Literal,
+) -> Result<&'a str, String> {
+    match value {
+        Literal::String(value) => Ok(value),
+        value => Err(format!(
+            "{}: '{}' must be a string, got {}",
+            function,
+            parameter,
+            value.type_name()
+        )),
+    }
+}
+
+pub(super) fn number(function: &str, parameter: &str, value: &Literal) -> Result<f32, String> {
+    match value {
+        Literal::Number(value) => Ok(*value),
+        Literal::Integer(value) => Ok(*value as f32),
+        value => Err(format!(
+            "{}: '{}' must be a number, got {}",
+            function,
+            parameter,
+            value.type_name()
+        )),
+    }
+}
+
+/// Integer, or float without a fractional part small enough to be
+/// represented exactly
+pub(super) fn whole_number(
+    function: &str,
+    parameter: &str,
+    value: &Literal,
+) -> Result<i64, String> {
+    if let Literal::Integer(value) = value {
+        return Ok(*value);
+    }
+    let value = number(function, parameter, value)?;
+    if value.fract() != 0.0 || value.abs() >= (1u64 << 24) as f32 {
+        return Err(format!(
+            "{}: '{}' must be a whole number, got {}",
+            function, parameter, value
+        ));
+    }
+    Ok(value as i64)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::Interpreter;
+
+    pub fn run(source: &str) -> String {
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.interpret(true).unwrap();
+        String::from_utf8_lossy(interpreter.output()).into_owned()
+    }
+
+    pub fn error(source: &str) -> String {
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.interpret(true).unwrap_err().msg
+    }
+
+    #[test]
+    fn stdlib_can_be_left_out() {
+        let mut interpreter = Interpreter::with_stdlib("len(\"a\");".into(), Vec::new(), false);
+        let error = interpreter.interpret(true).unwrap_err();
+
+        assert!(error.msg.contains("undefined function `len`"), "{}", error);
+    }
+}
@@ -0,0 +1,94 @@
+//! Functions controlling how values are written to the output
+use super::whole_number;
+use crate::{Environment, Literal, Native};
+
+pub(super) const FUNCTIONS: [Native; 1] = [Native {
+    name: "printPrecision",
+    arity: 1,
+    function: print_precision,
+}];
+
+/// Most decimal places `printPrecision` accepts, far more than a number
+/// holds but little enough that every number written stays short
+const MAX_DIGITS: i64 = 100;
+
+/// Writes numbers output by expression statements with `digits` decimal
+/// places, `nil` goes back to the shortest representation
+///
+/// Numbers in strings, including interpolated ones, and in error messages
+/// are not affected.
+fn print_precision(env: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let precision = match &args[0] {
+        Literal::Nil => None,
+        value => {
+            let digits = whole_number("printPrecision", "digits", value)?;
+            if digits < 0 {
+                return Err(format!(
+                    "printPrecision: 'digits' must not be negative, got {}",
+                    digits
+                ));
+            }
+            if digits > MAX_DIGITS {
+                return Err(format!(
+                    "printPrecision: 'digits' must be at most {}, got {}",
+                    MAX_DIGITS, digits
+                ));
+            }
+            Some(digits as usize)
+        }
+    };
+    env.set_precision(precision);
+    Ok(Literal::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stdlib::tests::{error, run};
+
+    #[test]
+    fn precision_sets_the_decimal_places_of_output_numbers() {
+        let source = "let pi = 3.14159;
+            printPrecision(2); pi; 2; -0.001; 1 / 0;
+            printPrecision(0); 2.5; printPrecision(nil); pi;";
+
+        assert_eq!(
+            run(source),
+            "nil\n3.14\n2.00\n0.00\ninf\nnil\n2\nnil\n3.14159\n"
+        );
+    }
+
+    #[test]
+    fn precision_only_applies_to_output_numbers() {
+        let source = "printPrecision(1); \"${1.25}\"; substring(\"abc\", 0, 1.0);";
+        assert_eq!(run(source), "nil\n1.25\na\n");
+
+        assert_eq!(
+            error("printPrecision(1); sqrt(-2.25);"),
+            "evaluation error[R011]: sqrt: 'x' must not be negative, got -2.25 at line 1 column 30"
+        );
+    }
+
+    #[test]
+    fn precision_must_be_a_whole_number() {
+        assert_eq!(
+            error("printPrecision(-1);"),
+            "evaluation error[R011]: printPrecision: 'digits' must not be negative, got -1 at line 1 column 18"
+        );
+        assert_eq!(
+            error("printPrecision(1000000);"),
+            "evaluation error[R011]: printPrecision: 'digits' must be at most 100, got 1000000 at line 1 column 23"
+        );
+        assert_eq!(
+            run("printPrecision(100); 0.5;").len(),
+            "nil\n0.\n".len() + 100
+        );
+        assert_eq!(
+            error("printPrecision(1.5);"),
+            "evaluation error[R011]: printPrecision: 'digits' must be a whole number, got 1.5 at line 1 column 19"
+        );
+        assert_eq!(
+            error("printPrecision(\"2\");"),
+            "evaluation error[R011]: printPrecision: 'digits' must be a number, got string at line 1 column 19"
+        );
+    }
+}
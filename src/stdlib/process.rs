@@ -0,0 +1,148 @@
+//! Functions giving access to the process running the script, these fail
+//! unless enabled with
+//! [Interpreter::enable_env_access](crate::Interpreter::enable_env_access),
+//! except for `clock`
+use std::time::Instant;
+
+use super::string;
+use crate::{Environment, Literal, Native};
+
+pub(super) const FUNCTIONS: [Native; 3] = [
+    Native {
+        name: "args",
+        arity: 0,
+        function: args,
+    },
+    Native {
+        name: "clock",
+        arity: 0,
+        function: clock,
+    },
+    Native {
+        name: "env",
+        arity: 1,
+        function: env,
+    },
+];
+
+/// Source of the seconds returned by `clock`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Clock {
+    /// Seconds elapsed since the clock was first read, the start is only
+    /// taken then so targets without a system clock can run programs that
+    /// don't call `clock`
+    System(Option<Instant>),
+    /// Starts at `now` and moves forward by `step` every time it's read, so
+    /// runs see the same times whatever machine they run on
+    Fake { now: f32, step: f32 },
+}
+
+impl Clock {
+    pub fn now(&mut self) -> f32 {
+        match self {
+            Clock::System(start) => start
+                .get_or_insert_with(Instant::now)
+                .elapsed()
+                .as_secs_f32(),
+            Clock::Fake { now, step } => {
+                let time = *now;
+                *now += *step;
+                time
+            }
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::System(None)
+    }
+}
+
+fn check_access(env: &Environment, function: &str) -> Result<(), String> {
+    if env.env_access() {
+        Ok(())
+    } else {
+        Err(format!("{}: environment access is disabled", function))
+    }
+}
+
+/// Arguments passed to the script joined by spaces
+// TODO: return a list once the language has them
+fn args(env: &mut Environment, _: &[Literal]) -> Result<Literal, String> {
+    check_access(env, "args")?;
+    Ok(Literal::String(env.args().join(" ")))
+}
+
+/// Seconds since `clock` was first called, for measuring how long code takes
+fn clock(env: &mut Environment, _: &[Literal]) -> Result<Literal, String> {
+    Ok(Literal::Number(env.clock().now()))
+}
+
+/// Value of the environment variable, `nil` if it isn't set
+fn env(env: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    check_access(env, "env")?;
+    let name = string("env", "name", &args[0])?;
+    Ok(match env.env_var(name) {
+        Some(value) => Literal::String(value),
+        None => Literal::Nil,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Clock;
+    use crate::Interpreter;
+
+    fn interpret(source: &str, env_access: bool) -> Result<String, String> {
+        let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+        interpreter.enable_env_access(env_access);
+        interpreter.set_args(vec!["one".into(), "two words".into()]);
+        interpreter.interpret(true).map_err(|e| e.msg)?;
+        Ok(String::from_utf8_lossy(interpreter.output()).into_owned())
+    }
+
+    #[test]
+    fn reads_arguments_and_environment_variables() {
+        let source = "args(); env(\"CARGO_MANIFEST_DIR\"); env(\"LOX_UNSET_VARIABLE\");";
+
+        // Cargo sets the variable for the tests it runs
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let expected = format!("one two words\n{}\nnil\n", dir);
+        assert_eq!(interpret(source, true).unwrap(), expected);
+    }
+
+    #[test]
+    fn environment_access_is_disabled_by_default() {
+        assert_eq!(
+            interpret("env(\"HOME\");", false).unwrap_err(),
+            "evaluation error[R011]: env: environment access is disabled at line 1 column 11"
+        );
+
+        let mut interpreter = Interpreter::with_output("args();".into(), Vec::new());
+        let error = interpreter.interpret(true).unwrap_err();
+        assert!(error.msg.contains("args: environment access is disabled"));
+    }
+
+    #[test]
+    fn fake_clocks_advance_by_their_step() {
+        let mut interpreter = Interpreter::with_output("clock();\nclock();".into(), Vec::new());
+        interpreter.environment_mut().set_clock(Clock::Fake {
+            now: 10.0,
+            step: 0.5,
+        });
+        interpreter.interpret(true).unwrap();
+        assert_eq!(String::from_utf8_lossy(interpreter.output()), "10\n10.5\n");
+    }
+
+    #[test]
+    fn injected_variables_replace_the_process_environment() {
+        let mut interpreter =
+            Interpreter::with_output("env(\"HOME\");\nenv(\"LOX\");".into(), Vec::new());
+        interpreter.enable_env_access(true);
+        let vars = [("LOX".to_string(), "fake".to_string())].into();
+        interpreter.environment_mut().set_env_vars(Some(vars));
+        interpreter.interpret(true).unwrap();
+        assert_eq!(String::from_utf8_lossy(interpreter.output()), "nil\nfake\n");
+    }
+}
@@ -0,0 +1,167 @@
+//! String functions, these work on characters rather than bytes so indexes
+//! and lengths are the same for `"héllo"` as for `"hello"`
+use super::{number, string};
+use crate::{Environment, Literal, Native};
+
+pub(super) const FUNCTIONS: [Native; 7] = [
+    Native {
+        name: "len",
+        arity: 1,
+        function: len,
+    },
+    Native {
+        name: "substring",
+        arity: 3,
+        function: substring,
+    },
+    Native {
+        name: "indexOf",
+        arity: 2,
+        function: index_of,
+    },
+    Native {
+        name: "toUpper",
+        arity: 1,
+        function: to_upper,
+    },
+    Native {
+        name: "toLower",
+        arity: 1,
+        function: to_lower,
+    },
+    Native {
+        name: "trim",
+        arity: 1,
+        function: trim,
+    },
+    Native {
+        name: "parseNumber",
+        arity: 1,
+        function: parse_number,
+    },
+];
+
+/// Number of characters in a string
+fn len(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let value = string("len", "s", &args[0])?;
+    Ok(Literal::Integer(value.chars().count() as i64))
+}
+
+/// Characters from `start` up to but excluding `end`, indexes are truncated
+/// and clamped to the string so out of range indexes give a shorter result
+fn substring(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let value = string("substring", "s", &args[0])?;
+    let start = number("substring", "start", &args[1])?;
+    let end = number("substring", "end", &args[2])?;
+
+    let len = value.chars().count();
+    let clamp = |index: f32| (index.max(0.0) as usize).min(len);
+    let (start, end) = (clamp(start), clamp(end));
+    Ok(Literal::String(
+        value
+            .chars()
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .collect(),
+    ))
+}
+
+/// Character index of the first occurrence of `needle`, `-1` if there is none
+fn index_of(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let value = string("indexOf", "s", &args[0])?;
+    let needle = string("indexOf", "needle", &args[1])?;
+
+    let index = match value.find(needle) {
+        Some(offset) => value[..offset].chars().count() as i64,
+        None => -1,
+    };
+    Ok(Literal::Integer(index))
+}
+
+fn to_upper(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let value = string("toUpper", "s", &args[0])?;
+    Ok(Literal::String(value.to_uppercase()))
+}
+
+fn to_lower(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let value = string("toLower", "s", &args[0])?;
+    Ok(Literal::String(value.to_lowercase()))
+}
+
+/// The string without leading and trailing whitespace
+fn trim(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let value = string("trim", "s", &args[0])?;
+    Ok(Literal::String(value.trim().into()))
+}
+
+/// Number written in the string, `nil` if it isn't one
+///
+/// Whole numbers without a decimal point are integers as in lox source.
+fn parse_number(_: &mut Environment, args: &[Literal]) -> Result<Literal, String> {
+    let value = string("parseNumber", "s", &args[0])?.trim();
+    if let Ok(number) = value.parse::<i64>() {
+        return Ok(Literal::Integer(number));
+    }
+    Ok(match value.parse::<f32>() {
+        Ok(number) => Literal::Number(number),
+        Err(_) => Literal::Nil,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stdlib::tests::{error, run};
+
+    #[test]
+    fn string_functions_work_on_characters() {
+        let source = "len(\"héllo wörld\");
+            substring(\"héllo wörld\", 1, 4);
+            indexOf(\"héllo wörld\", \"wö\");
+            indexOf(\"héllo\", \"x\");
+            toUpper(\"straße\");
+            toLower(\"ÀB\");
+            \"[${trim(\"\"\"  \tmid  \n\"\"\")}]\";";
+
+        assert_eq!(run(source), "11\néll\n6\n-1\nSTRASSE\nàb\n[mid]\n");
+    }
+
+    #[test]
+    fn substring_clamps_out_of_range_indexes() {
+        let source = "substring(\"héllo\", -3, 2);
+            substring(\"héllo\", 3, 100);
+            substring(\"héllo\", 4, 1);
+            substring(\"héllo\", 1.9, 3.2);";
+
+        assert_eq!(run(source), "hé\nlo\n\nél\n");
+    }
+
+    #[test]
+    fn parse_number_returns_nil_on_failure() {
+        let source = "parseNumber(\" 2.5 \") * 2;
+            parseNumber(\"two\");
+            parseNumber(\"\") == nil;
+            parseNumber(\"1\") != nil;";
+
+        assert_eq!(run(source), "5\nnil\ntrue\ntrue\n");
+    }
+
+    #[test]
+    fn arguments_are_checked() {
+        assert_eq!(
+            error("substring(\"abc\", \"1\", 2);"),
+            "evaluation error[R011]: substring: 'start' must be a number, got string at line 1 column 24"
+        );
+        assert_eq!(
+            error("indexOf(3, \"a\");"),
+            "evaluation error[R011]: indexOf: 's' must be a string, got number at line 1 column 15"
+        );
+        assert_eq!(
+            error("trim(\"a\", \"b\");"),
+            "evaluation error[R009]: wrong number of arguments for `trim`, expected 1 got 2 at line 1 column 14"
+        );
+        assert_eq!(
+            error("toUpper(nil);"),
+            "evaluation error[R011]: toUpper: 's' must be a string, got nil at line 1 column 12"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Settings freezing everything a program could observe that changes
+/// between runs or machines, so the same program always gives byte for byte
+/// the same output
+///
+/// [Interpreter::set_test_config](crate::Interpreter::set_test_config)
+/// seeds the generator behind `random`, replaces the clock read by `clock`
+/// with a fake one and makes `env` and `args` read the variables and
+/// arguments given here. Diagnostics printed by the command line runner
+/// show paths relative to [root](TestConfig::root).
+///
+/// ## Examples
+/// ```rust
+/// use lox::{Interpreter, TestConfig};
+///
+/// let run = || {
+///     let mut interpreter =
+///         Interpreter::with_output("random();\nclock();".into(), Vec::new());
+///     interpreter.set_test_config(&TestConfig::default());
+///     interpreter.interpret(true).unwrap();
+///     interpreter.output().clone()
+/// };
+/// assert_eq!(run(), run());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestConfig {
+    /// Seed of the generator behind `random` and `randomInt`
+    pub seed: u64,
+    /// Seconds returned by the first `clock()`
+    pub clock_start: f32,
+    /// Seconds the clock moves forward by every time it's read, 0 stops it
+    pub clock_step: f32,
+    /// Variables read by `env` instead of the process environment
+    pub env: HashMap<String, String>,
+    /// Arguments returned by `args`
+    pub args: Vec<String>,
+    /// Directory paths in diagnostics are made relative to
+    pub root: Option<PathBuf>,
+}
+
+impl Default for TestConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            clock_start: 0.0,
+            clock_step: 1.0,
+            env: HashMap::new(),
+            args: Vec::new(),
+            root: None,
+        }
+    }
+}
+
+impl TestConfig {
+    /// Replaces the paths under [root](TestConfig::root) in `text` by paths
+    /// relative to it
+    pub fn relativize(&self, text: &str) -> String {
+        let Some(root) = &self.root else {
+            return text.into();
+        };
+        let root = root.display().to_string();
+        let root = root.trim_end_matches(std::path::MAIN_SEPARATOR);
+        text.replace(&format!("{}{}", root, std::path::MAIN_SEPARATOR), "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn paths_under_the_root_become_relative() {
+        let config = TestConfig {
+            root: Some(PathBuf::from("/home/ci/lox/")),
+            ..TestConfig::default()
+        };
+        assert_eq!(
+            config.relativize("cannot read /home/ci/lox/tests/a.lox: denied"),
+            "cannot read tests/a.lox: denied"
+        );
+        assert_eq!(config.relativize("/tmp/a.lox"), "/tmp/a.lox");
+        assert_eq!(TestConfig::default().relativize("/a/b.lox"), "/a/b.lox");
+    }
+}
@@ -0,0 +1,239 @@
+//! Helpers for testing lox programs, enabled by the `test-util` feature
+//!
+//! [LoxTest] is the recommended way to test how an embedding behaves for lox
+//! code: it runs a program in a fresh [Interpreter] and checks its output,
+//! variables, error and exit code, panicking with everything that didn't
+//! match at once.
+//!
+//! ## Examples
+//! ```rust
+//! # #[cfg(feature = "test-util")] {
+//! use lox::testing::LoxTest;
+//! use lox::Literal;
+//!
+//! LoxTest::new("let a = 1 + 2;\na;")
+//!     .expect_output("3\n")
+//!     .expect_var("a", Literal::Integer(3))
+//!     .run();
+//! # }
+//! ```
+use std::fmt::Write;
+
+use crate::{Interpreter, Literal, TestConfig};
+
+/// Program run along with what it's expected to do, checked by
+/// [run](LoxTest::run)
+///
+/// Only the expectations that were set are checked, except that the program
+/// must not fail unless [expect_error](LoxTest::expect_error) is used.
+/// Programs run with a default [TestConfig] so `random` and `clock` give the
+/// same values on every run.
+#[derive(Debug, Clone)]
+pub struct LoxTest {
+    source: String,
+    config: TestConfig,
+    output: Option<String>,
+    variables: Vec<(String, Literal)>,
+    error: Option<String>,
+    exit_code: Option<i32>,
+}
+
+impl LoxTest {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.into(),
+            config: TestConfig::default(),
+            output: None,
+            variables: Vec::new(),
+            error: None,
+            exit_code: None,
+        }
+    }
+
+    /// Runs the program with `config` instead of the default one
+    pub fn with_test_config(mut self, config: TestConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Everything the program writes to its output
+    pub fn expect_output(mut self, output: &str) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// Value of a global variable once the program is done, integers and
+    /// floats with the same value are equal
+    pub fn expect_var(mut self, name: &str, value: Literal) -> Self {
+        self.variables.push((name.into(), value));
+        self
+    }
+
+    /// Message of the error the program fails with, including the location
+    pub fn expect_error(mut self, msg: &str) -> Self {
+        self.error = Some(msg.into());
+        self
+    }
+
+    /// Code the program requests with an `exit` statement
+    pub fn expect_exit(mut self, code: i32) -> Self {
+        self.exit_code = Some(code);
+        self
+    }
+
+    /// Runs the program in strict mode
+    ///
+    /// # Panics
+    /// If any expectation isn't met, listing every mismatch along with a
+    /// diff of the output and the error the program failed with.
+    pub fn run(self) {
+        let mut interpreter = Interpreter::with_output(self.source.clone(), Vec::new());
+        interpreter.set_test_config(&self.config);
+        let error = interpreter.interpret(true).err().map(|e| e.msg);
+        let output = String::from_utf8_lossy(interpreter.output()).into_owned();
+
+        let mut failures = Vec::new();
+        match (&self.error, &error) {
+            (Some(expected), Some(actual)) if expected != actual => failures.push(format!(
+                "expected the error\n    {}\nbut got\n    {}",
+                expected, actual
+            )),
+            (Some(expected), None) => {
+                failures.push(format!("expected the error\n    {}\nbut it ran", expected))
+            }
+            (None, Some(_)) => failures.push("expected no error".into()),
+            _ => {}
+        }
+        if let Some(expected) = &self.output {
+            if *expected != output {
+                failures.push(format!("output differs\n{}", diff(expected, &output)));
+            }
+        }
+        for (name, expected) in &self.variables {
+            match interpreter.environment().get(name) {
+                Some(actual) if actual == expected => {}
+                Some(actual) => failures.push(format!(
+                    "expected `{}` to be {:?} but it is {:?}",
+                    name, expected, actual
+                )),
+                None => failures.push(format!(
+                    "expected `{}` to be {:?} but it is not declared",
+                    name, expected
+                )),
+            }
+        }
+        if self.exit_code.is_some() && self.exit_code != interpreter.exit_code() {
+            failures.push(format!(
+                "expected exit code {:?} but got {:?}",
+                self.exit_code,
+                interpreter.exit_code()
+            ));
+        }
+
+        if !failures.is_empty() {
+            let mut report = String::from("lox test failed\n");
+            for failure in failures {
+                let _ = writeln!(report, "\n{}", failure);
+            }
+            let _ = write!(
+                report,
+                "\nsource:\n{}\n\nerror: {}",
+                self.source,
+                error.as_deref().unwrap_or("none")
+            );
+            panic!("{}", report);
+        }
+    }
+}
+
+/// Lines of both texts, those only in the expected text are marked with `-`
+/// and those only in the actual text with `+`
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.split_inclusive('\n').collect();
+    let actual: Vec<&str> = actual.split_inclusive('\n').collect();
+
+    // Longest common subsequence of lines, from the end so it's read forwards
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let line = |out: &mut String, marker: char, text: &str| {
+        let _ = writeln!(out, "{} {:?}", marker, text);
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            line(&mut out, ' ', expected[i]);
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1])
+        {
+            line(&mut out, '-', expected[i]);
+            i += 1;
+        } else {
+            line(&mut out, '+', actual[j]);
+            j += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_mark_missing_and_extra_lines() {
+        assert_eq!(
+            diff("1\n2\n3\n", "1\n3\n4\n"),
+            "  \"1\\n\"\n- \"2\\n\"\n  \"3\\n\"\n+ \"4\\n\"\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "output differs\n  \"1\\n\"\n- \"3\\n\"\n+ \"2\\n\"")]
+    fn mismatched_output_is_shown_as_a_diff() {
+        LoxTest::new("1; 2;").expect_output("1\n3\n").run();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected no error")]
+    fn unexpected_errors_fail_the_test() {
+        LoxTest::new("1 & true;").run();
+    }
+
+    #[test]
+    fn programs_run_with_the_test_config() {
+        let config = TestConfig {
+            clock_start: 5.0,
+            env: [("USER".to_string(), "ci".to_string())].into(),
+            args: vec!["one".into()],
+            ..TestConfig::default()
+        };
+        let mut interpreter = Interpreter::with_output("random();".into(), Vec::new());
+        interpreter.set_test_config(&config);
+        interpreter.interpret(true).unwrap();
+        let random = String::from_utf8_lossy(interpreter.output()).into_owned();
+
+        LoxTest::new("random(); clock(); clock(); args();")
+            .with_test_config(config)
+            .expect_output(&format!("{}5\n6\none\n", random))
+            .run();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected `a` to be Integer(2) but it is Integer(1)")]
+    fn variables_are_compared() {
+        LoxTest::new("let a = 1;")
+            .expect_var("a", Literal::Integer(2))
+            .run();
+    }
+}
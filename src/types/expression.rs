@@ -1,4 +1,8 @@
-use crate::{Environment, EvaluationError, Literal, Token, TokenType};
+use std::cmp::Ordering;
+
+use super::printer;
+use crate::errors::codes::{self, ErrorCode};
+use crate::{Counters, Environment, EvaluationError, Literal, Native, Token, TokenType};
 
 #[derive(Clone, Debug)]
 /// These are instructions for the interpreter to perform
@@ -9,148 +13,583 @@ pub enum Expression {
     Literal(Token),
     Variable(Token),
     Assignment(Token, Box<Expression>),
+    /// String segments interleaved with the interpolated expressions
+    Interpolation(Vec<Expression>),
+    /// Callee, the closing parenthesis and the arguments
+    Call(Box<Expression>, Token, Vec<Expression>),
+    /// `this` within a method
+    This(Token),
+    /// `super` and the name of the method looked up on the superclass
+    Super(Token, Token),
+}
+
+/// Expressions are equal when they have the same structure and equivalent
+/// tokens, see [Token::is_equivalent]. Groupings are significant so `(a)` and
+/// `a` are not equal.
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expression::Unary(op, expr), Expression::Unary(other_op, other_expr)) => {
+                op.is_equivalent(other_op) && expr == other_expr
+            }
+            (
+                Expression::Binary(left, op, right),
+                Expression::Binary(other_left, other_op, other_right),
+            ) => op.is_equivalent(other_op) && left == other_left && right == other_right,
+            (Expression::Grouping(expr), Expression::Grouping(other_expr)) => expr == other_expr,
+            (Expression::Literal(token), Expression::Literal(other_token))
+            | (Expression::Variable(token), Expression::Variable(other_token)) => {
+                token.is_equivalent(other_token)
+            }
+            (
+                Expression::Assignment(name, expr),
+                Expression::Assignment(other_name, other_expr),
+            ) => name.is_equivalent(other_name) && expr == other_expr,
+            (Expression::Interpolation(parts), Expression::Interpolation(other_parts)) => {
+                parts == other_parts
+            }
+            (
+                Expression::Call(callee, _, arguments),
+                Expression::Call(other_callee, _, other_arguments),
+            ) => callee == other_callee && arguments == other_arguments,
+            (Expression::This(_), Expression::This(_)) => true,
+            (Expression::Super(_, method), Expression::Super(_, other_method)) => {
+                method.is_equivalent(other_method)
+            }
+            _ => false,
+        }
+    }
 }
 
 impl Expression {
-    pub fn evaluate(&self, environment: &Environment) -> Result<Literal, EvaluationError> {
+    /// Returns the first token of the expression in source order
+    pub fn leftmost_token(&self) -> &Token {
         match self {
-            Expression::Grouping(expr) => expr.evaluate(environment),
-            Expression::Variable(token) => {
-                if token._type == TokenType::Identifier {
-                    if let Some(literal) = environment.get(token.lexeme.clone()) {
-                        Ok(literal)
-                    } else {
-                        Ok(Literal::Variable(token.lexeme.clone()))
+            Expression::Unary(token, _)
+            | Expression::Literal(token)
+            | Expression::Variable(token)
+            | Expression::Assignment(token, _)
+            | Expression::This(token)
+            | Expression::Super(token, _) => token,
+            Expression::Binary(expr, _, _)
+            | Expression::Grouping(expr)
+            | Expression::Call(expr, _, _) => expr.leftmost_token(),
+            Expression::Interpolation(parts) => parts[0].leftmost_token(),
+        }
+    }
+
+    pub fn evaluate(&self, environment: &mut Environment) -> Result<Literal, EvaluationError> {
+        self.evaluate_in(environment)
+    }
+
+    /// Evaluates the expression reading and writing variables through the
+    /// context
+    pub(crate) fn evaluate_in<C: Context>(
+        &self,
+        environment: &mut C,
+    ) -> Result<Literal, EvaluationError> {
+        if let Some(counters) = environment.counters() {
+            counters.expressions += 1;
+        }
+        match self {
+            Expression::Grouping(expr) => expr.evaluate_in(environment),
+            Expression::Variable(token) => match environment.lookup(&token.lexeme).cloned() {
+                Some(literal) => {
+                    if let Some(counters) = environment.counters() {
+                        counters.literal_clones += 1;
                     }
-                } else {
-                    Err(EvaluationError::new(
-                        "unexpected variable type",
-                        token.line,
-                        token.column,
-                    ))
+                    Ok(literal)
                 }
-            }
+                None => environment.read_undefined(token).ok_or_else(|| {
+                    undefined_error(
+                        codes::UNDEFINED_VARIABLE,
+                        &token.lexeme,
+                        token,
+                        environment.environment(),
+                    )
+                }),
+            },
             Expression::Assignment(token, expr) => {
-                if let Expression::Variable(_) = expr.as_ref() {
-                    expr.evaluate(environment)
+                let value = expr.evaluate_in(environment)?;
+                if let Some(counters) = environment.counters() {
+                    counters.literal_clones += 1;
+                }
+                if environment.assign(&token.lexeme, value.clone()) {
+                    Ok(value)
                 } else {
-                    Err(EvaluationError::new(
-                        "unqualified variable name",
-                        token.line,
-                        token.column,
+                    Err(undefined_error(
+                        codes::UNDECLARED_ASSIGNMENT,
+                        &token.lexeme,
+                        token,
+                        environment.environment(),
                     ))
                 }
             }
             Expression::Unary(token, expr) => {
-                let right = expr.evaluate(environment)?;
+                let right = expr.evaluate_in(environment)?;
                 match token._type {
-                    TokenType::Minus => {
-                        if let Literal::Number(value) = right {
-                            Ok(Literal::Number(-value))
-                        } else {
-                            Err(EvaluationError::new(
-                                "expected a number",
-                                token.line,
-                                token.column,
-                            ))
-                        }
-                    }
-                    TokenType::Not => {
-                        if let Literal::Boolean(value) = right {
-                            Ok(Literal::Boolean(!value))
-                        } else {
-                            Err(EvaluationError::new(
-                                "expected a number",
-                                token.line,
-                                token.column,
-                            ))
-                        }
+                    TokenType::Minus => match right {
+                        Literal::Number(value) => Ok(Literal::Number(-value)),
+                        Literal::Integer(value) => Ok(value
+                            .checked_neg()
+                            .map_or(Literal::Number(-(value as f32)), Literal::Integer)),
+                        right => Err(operand_error(token, "a number", &right)),
+                    },
+                    TokenType::BitNot => {
+                        let value = to_integer(&right, token)?;
+                        Ok(Literal::Integer(!value))
                     }
-                    _ => Err(EvaluationError::new(
-                        "unknown expression",
-                        token.line,
-                        token.column,
+                    TokenType::Not => match right {
+                        Literal::Boolean(value) => Ok(Literal::Boolean(!value)),
+                        right => Err(operand_error(token, "a boolean", &right)),
+                    },
+                    _ => Err(EvaluationError::at(
+                        codes::UNKNOWN_OPERATOR,
+                        &[&token.lexeme],
+                        token,
                     )),
                 }
             }
             Expression::Binary(expr, token, rexpr) => {
-                let left = expr.evaluate(environment)?;
-                let right = rexpr.evaluate(environment)?;
+                let left = expr.evaluate_in(environment)?;
+                if token._type == TokenType::NilCoalesce {
+                    // Only nil falls back, the right side isn't evaluated
+                    // otherwise
+                    return match left {
+                        Literal::Nil => rexpr.evaluate_in(environment),
+                        left => Ok(left),
+                    };
+                }
+                if let Literal::Boolean(value) = left {
+                    // `false && ...` and `true || ...` don't evaluate the
+                    // right side, it can't change the result
+                    if value == (token._type == TokenType::Or)
+                        && matches!(token._type, TokenType::And | TokenType::Or)
+                    {
+                        return Ok(Literal::Boolean(value));
+                    }
+                }
+                let right = rexpr.evaluate_in(environment)?;
+                if token._type.is_bitwise() {
+                    return evaluate_bitwise(&left, token, &right);
+                }
+                let unsupported = EvaluationError::at(
+                    codes::UNSUPPORTED_OPERANDS,
+                    &[&token.lexeme, &left.type_name(), &right.type_name()],
+                    token,
+                );
                 let values = (left, right);
 
                 match values {
-                    (Literal::Number(left), Literal::Number(right)) => match token._type {
-                        TokenType::Plus => Ok(Literal::Number(left + right)),
-                        TokenType::Minus => Ok(Literal::Number(left - right)),
-                        TokenType::Star => Ok(Literal::Number(left * right)),
-                        TokenType::Slash => Ok(Literal::Number(left / right)),
-                        TokenType::LessEqual => Ok(Literal::Boolean(left <= right)),
-                        TokenType::Less => Ok(Literal::Boolean(left < right)),
-                        TokenType::GreaterEqual => Ok(Literal::Boolean(left >= right)),
-                        TokenType::Greater => Ok(Literal::Boolean(left > right)),
-                        TokenType::NotEqual => Ok(Literal::Boolean(left != right)),
-                        TokenType::EqualEqual => Ok(Literal::Boolean(left == right)),
-                        _ => todo!(),
-                    },
+                    (
+                        left @ (Literal::Number(_) | Literal::Integer(_)),
+                        right @ (Literal::Number(_) | Literal::Integer(_)),
+                    ) => evaluate_numbers(&left, token, &right),
                     (Literal::Boolean(left), Literal::Boolean(right)) => match token._type {
                         TokenType::Or => Ok(Literal::Boolean(left || right)),
                         TokenType::And => Ok(Literal::Boolean(left && right)),
-                        _ => todo!(),
+                        TokenType::NotEqual => Ok(Literal::Boolean(left != right)),
+                        TokenType::EqualEqual => Ok(Literal::Boolean(left == right)),
+                        _ => Err(unsupported),
+                    },
+                    (Literal::Nil, right) | (right, Literal::Nil) => match token._type {
+                        TokenType::NotEqual => Ok(Literal::Boolean(!matches!(right, Literal::Nil))),
+                        TokenType::EqualEqual => {
+                            Ok(Literal::Boolean(matches!(right, Literal::Nil)))
+                        }
+                        _ => Err(unsupported),
                     },
-                    _ => Err(EvaluationError::new(
-                        "unknown operator",
-                        token.line,
-                        token.column,
+                    // Values of any other types can be compared for equality
+                    (left, right)
+                        if matches!(token._type, TokenType::EqualEqual | TokenType::NotEqual) =>
+                    {
+                        let equal = left == right;
+                        Ok(Literal::Boolean(
+                            equal == (token._type == TokenType::EqualEqual),
+                        ))
+                    }
+                    _ => Err(unsupported),
+                }
+            }
+            Expression::Interpolation(parts) => {
+                let mut value = String::new();
+                let mut len = 0;
+                for part in parts {
+                    let part: String = part.evaluate_in(environment)?.into();
+                    len += part.chars().count();
+                    environment
+                        .environment()
+                        .check_string_len(len)
+                        .map_err(|e| {
+                            let token = self.leftmost_token();
+                            EvaluationError::with_message(codes::STRING_LENGTH_LIMIT, &e, token)
+                        })?;
+                    value.push_str(&part);
+                }
+                Ok(Literal::String(value))
+            }
+            Expression::Call(callee, paren, arguments) => {
+                // A name that isn't declared is reported as a function
+                let callee = callee
+                    .evaluate_in(environment)
+                    .map_err(|e| match &**callee {
+                        Expression::Variable(name) if e.code() == codes::UNDEFINED_VARIABLE => {
+                            undefined_error(
+                                codes::UNDEFINED_FUNCTION,
+                                &name.lexeme,
+                                paren,
+                                environment.environment(),
+                            )
+                        }
+                        _ => e,
+                    })?;
+                let mut values = Vec::new();
+                for argument in arguments {
+                    values.push(argument.evaluate_in(environment)?);
+                }
+
+                match callee {
+                    Literal::Native(native) => {
+                        if values.len() != native.arity {
+                            return Err(EvaluationError::at(
+                                codes::WRONG_ARGUMENT_COUNT,
+                                &[&native.name, &native.arity, &values.len()],
+                                paren,
+                            ));
+                        }
+                        let error = |code: ErrorCode, msg: String| {
+                            EvaluationError::with_message(code, &msg, paren)
+                        };
+                        let value = environment
+                            .call(&native, &values)
+                            .map_err(|msg| error(codes::NATIVE_FAILED, msg))?;
+                        if let Literal::String(s) = &value {
+                            environment
+                                .environment()
+                                .check_string_len(s.chars().count())
+                                .map_err(|msg| error(codes::STRING_LENGTH_LIMIT, msg))?;
+                        }
+                        Ok(value)
+                    }
+                    value => Err(EvaluationError::at(
+                        codes::NOT_CALLABLE,
+                        &[&value.type_name()],
+                        paren,
                     )),
                 }
             }
+            // Only parsed within methods, and there are no classes to
+            // declare them in yet
+            Expression::This(token) | Expression::Super(token, _) => Err(EvaluationError::at(
+                codes::UNEXPECTED_TOKEN,
+                &[&token.lexeme],
+                token,
+            )),
             Expression::Literal(token) => match token._type {
                 TokenType::Number => {
+                    if !token.lexeme.contains('.') {
+                        if let Ok(value) = token.lexeme.parse::<i64>() {
+                            return Ok(Literal::Integer(value));
+                        }
+                    }
                     let value = token.lexeme.parse::<f32>().map_err(|_| {
-                        EvaluationError::new("expected a number", token.line, token.column)
+                        EvaluationError::at(codes::INVALID_LITERAL, &[&token.lexeme], token)
                     })?;
                     Ok(Literal::Number(value))
                 }
+                TokenType::Nil => Ok(Literal::Nil),
                 TokenType::True | TokenType::False => {
                     let value = token.lexeme.parse::<bool>().map_err(|_| {
-                        EvaluationError::new("expected a boolean", token.line, token.column)
+                        EvaluationError::at(codes::INVALID_LITERAL, &[&token.lexeme], token)
                     })?;
                     Ok(Literal::Boolean(value))
                 }
-                TokenType::String => {
+                TokenType::String
+                | TokenType::StringHead
+                | TokenType::StringMiddle
+                | TokenType::StringTail => {
                     let value = token.lexeme.clone();
                     Ok(Literal::String(value))
                 }
-                _ => Err(EvaluationError::new(
-                    "unknown value",
-                    token.line,
-                    token.column,
+                _ => Err(EvaluationError::at(
+                    codes::INVALID_LITERAL,
+                    &[&token.lexeme],
+                    token,
                 )),
             },
         }
     }
 }
 
+/// Variables an expression is evaluated against
+///
+/// Programs evaluate expressions against their [Environment] directly,
+/// [CompiledExpression](crate::CompiledExpression) wraps it to evaluate
+/// without a mutable borrow.
+pub(crate) trait Context {
+    fn lookup(&mut self, name: &str) -> Option<&Literal>;
+
+    /// Value an undefined variable is read as, none if reading it is an
+    /// error
+    fn read_undefined(&mut self, _name: &Token) -> Option<Literal> {
+        None
+    }
+
+    /// Updates a declared variable, returns false if there is none
+    fn assign(&mut self, name: &str, value: Literal) -> bool;
+
+    fn call(&mut self, native: &Native, arguments: &[Literal]) -> Result<Literal, String>;
+
+    /// Settings such as the string length limit
+    fn environment(&self) -> &Environment;
+
+    /// Counters to update while evaluating, if the work is counted
+    fn counters(&mut self) -> Option<&mut Counters>;
+}
+
+impl Context for Environment {
+    fn lookup(&mut self, name: &str) -> Option<&Literal> {
+        Environment::lookup(self, name)
+    }
+
+    fn read_undefined(&mut self, name: &Token) -> Option<Literal> {
+        Environment::read_undefined(self, name)
+    }
+
+    fn assign(&mut self, name: &str, value: Literal) -> bool {
+        Environment::assign(self, name, value)
+    }
+
+    fn call(&mut self, native: &Native, arguments: &[Literal]) -> Result<Literal, String> {
+        (native.function)(self, arguments)
+    }
+
+    fn environment(&self) -> &Environment {
+        self
+    }
+
+    fn counters(&mut self) -> Option<&mut Counters> {
+        Some(self.counters_mut())
+    }
+}
+
+/// Error for a name nothing is declared as, suggesting a similar name that
+/// is
+fn undefined_error(
+    code: ErrorCode,
+    name: &str,
+    location: &Token,
+    environment: &Environment,
+) -> EvaluationError {
+    let mut msg = code.message(&[&name]);
+    if let Some(similar) = environment.similar_name(name) {
+        msg.push_str(&format!("; did you mean `{}`?", similar));
+    }
+    EvaluationError::with_message(code, &msg, location)
+}
+
+/// Error for an operator applied to a value of the wrong type, showing the
+/// start of the value
+fn operand_error(operator: &Token, expected: &str, value: &Literal) -> EvaluationError {
+    const EXCERPT_LEN: usize = 20;
+
+    let got = match value {
+        Literal::Nil => "nil".to_string(),
+        Literal::String(value) if value.chars().count() > EXCERPT_LEN => {
+            let excerpt: String = value.chars().take(EXCERPT_LEN).collect();
+            format!("string \"{}...\"", excerpt)
+        }
+        Literal::String(value) => format!("string \"{}\"", value),
+        value => format!("{} {}", value.type_name(), String::from(value.clone())),
+    };
+    EvaluationError::at(
+        codes::WRONG_OPERAND_TYPE,
+        &[&operator.lexeme, &expected, &got],
+        operator,
+    )
+}
+
+/// Converts a bitwise operand to an integer by truncation.
+///
+/// Operands must be whole numbers within the range of an `i64`, anything else
+/// is reported as an error naming the offending operand.
+fn to_integer(value: &Literal, operator: &Token) -> Result<i64, EvaluationError> {
+    match value {
+        Literal::Integer(number) => Ok(*number),
+        Literal::Number(number) => {
+            if number.fract() != 0.0 || *number < i64::MIN as f32 || *number >= i64::MAX as f32 {
+                Err(EvaluationError::at(
+                    codes::NOT_AN_INTEGER,
+                    &[number, &operator.lexeme],
+                    operator,
+                ))
+            } else {
+                Ok(number.trunc() as i64)
+            }
+        }
+        _ => {
+            let value: String = value.clone().into();
+            Err(EvaluationError::at(
+                codes::NOT_A_NUMBER,
+                &[&value, &operator.lexeme],
+                operator,
+            ))
+        }
+    }
+}
+
+fn evaluate_bitwise(
+    left: &Literal,
+    operator: &Token,
+    right: &Literal,
+) -> Result<Literal, EvaluationError> {
+    let left = to_integer(left, operator)?;
+    let right = to_integer(right, operator)?;
+
+    let value = match operator._type {
+        TokenType::BitAnd => left & right,
+        TokenType::BitOr => left | right,
+        TokenType::BitXor => left ^ right,
+        TokenType::ShiftLeft | TokenType::ShiftRight => {
+            let shifted = u32::try_from(right).ok().and_then(|amount| {
+                if operator._type == TokenType::ShiftLeft {
+                    left.checked_shl(amount)
+                } else {
+                    left.checked_shr(amount)
+                }
+            });
+            shifted.ok_or_else(|| {
+                EvaluationError::at(codes::SHIFT_OUT_OF_RANGE, &[&right], operator)
+            })?
+        }
+        _ => {
+            return Err(EvaluationError::at(
+                codes::UNKNOWN_OPERATOR,
+                &[&operator.lexeme],
+                operator,
+            ))
+        }
+    };
+
+    Ok(Literal::Integer(value))
+}
+
+/// Evaluates an arithmetic or comparison operator on two numbers, see
+/// [Literal] for how integers and floats are combined
+fn evaluate_numbers(
+    left: &Literal,
+    operator: &Token,
+    right: &Literal,
+) -> Result<Literal, EvaluationError> {
+    if let (Literal::Integer(left), Literal::Integer(right)) = (left, right) {
+        let exact = match operator._type {
+            TokenType::Plus => left.checked_add(*right),
+            TokenType::Minus => left.checked_sub(*right),
+            TokenType::Star => left.checked_mul(*right),
+            TokenType::Slash => left
+                .checked_rem(*right)
+                .filter(|remainder| *remainder == 0)
+                .and_then(|_| left.checked_div(*right)),
+            _ => None,
+        };
+        if let Some(value) = exact {
+            return Ok(Literal::Integer(value));
+        }
+    }
+
+    let unsupported = || {
+        EvaluationError::at(
+            codes::UNSUPPORTED_OPERANDS,
+            &[&operator.lexeme, &left.type_name(), &right.type_name()],
+            operator,
+        )
+    };
+    let ordering = left.compare_numbers(right);
+    let (left, right) = (
+        left.as_float().unwrap_or(f32::NAN),
+        right.as_float().unwrap_or(f32::NAN),
+    );
+    match operator._type {
+        TokenType::Plus => Ok(Literal::Number(left + right)),
+        TokenType::Minus => Ok(Literal::Number(left - right)),
+        TokenType::Star => Ok(Literal::Number(left * right)),
+        TokenType::Slash => Ok(Literal::Number(left / right)),
+        TokenType::LessEqual => Ok(Literal::Boolean(matches!(
+            ordering,
+            Some(Ordering::Less | Ordering::Equal)
+        ))),
+        TokenType::Less => Ok(Literal::Boolean(ordering == Some(Ordering::Less))),
+        TokenType::GreaterEqual => Ok(Literal::Boolean(matches!(
+            ordering,
+            Some(Ordering::Greater | Ordering::Equal)
+        ))),
+        TokenType::Greater => Ok(Literal::Boolean(ordering == Some(Ordering::Greater))),
+        TokenType::NotEqual => Ok(Literal::Boolean(ordering != Some(Ordering::Equal))),
+        TokenType::EqualEqual => Ok(Literal::Boolean(ordering == Some(Ordering::Equal))),
+        _ => Err(unsupported()),
+    }
+}
+
+/// Prints the expression as an s-expression, such as `(1 + (2 * 3))`
 impl From<Expression> for String {
     fn from(val: Expression) -> String {
-        match val {
-            Expression::Unary(token, expr) => {
-                let expr: String = expr.as_ref().to_owned().into();
-                format!("({} {})", token.lexeme, expr)
+        printer::expression(&val)
+    }
+}
+
+/// Drops nested expressions from a heap allocated stack, the derived drop
+/// glue recurses once per level and overflows on long operator chains
+impl Drop for Expression {
+    fn drop(&mut self) {
+        let mut children = Vec::new();
+        self.take_children(&mut children);
+        while let Some(mut expr) = children.pop() {
+            expr.take_children(&mut children);
+        }
+    }
+}
+
+impl Expression {
+    /// Moves the subexpressions having subexpressions of their own into
+    /// `children`, leaving empty interpolations behind as they don't
+    /// allocate. The others are dropped in place, which doesn't recurse.
+    fn take_children(&mut self, children: &mut Vec<Expression>) {
+        let mut take = |expr: &mut Box<Expression>| {
+            if !expr.is_leaf() {
+                let leaf = Expression::Interpolation(Vec::new());
+                children.push(std::mem::replace(expr.as_mut(), leaf));
             }
-            Expression::Binary(expr, token, r_expr) => {
-                let expr: String = expr.as_ref().to_owned().into();
-                let r_expr: String = r_expr.as_ref().to_owned().into();
-                format!("({} {} {})", expr, token.lexeme, r_expr)
+        };
+        match self {
+            Expression::Unary(_, expr)
+            | Expression::Grouping(expr)
+            | Expression::Assignment(_, expr) => take(expr),
+            Expression::Binary(left, _, right) => {
+                take(left);
+                take(right);
             }
-            Expression::Grouping(expr) => {
-                let expr: String = expr.as_ref().to_owned().into();
-                format!("(group {})", expr)
+            Expression::Call(callee, _, arguments) => {
+                take(callee);
+                children.extend(arguments.drain(..).filter(|expr| !expr.is_leaf()));
             }
-            Expression::Literal(token) | Expression::Variable(token) => token.lexeme,
-            Expression::Assignment(token, literal) => {
-                format!("({} = {:?})", token.lexeme.clone(), literal)
+            Expression::Interpolation(parts) => {
+                children.extend(parts.drain(..).filter(|expr| !expr.is_leaf()))
             }
+            Expression::Literal(_)
+            | Expression::Variable(_)
+            | Expression::This(_)
+            | Expression::Super(_, _) => {}
+        }
+    }
+
+    fn is_leaf(&self) -> bool {
+        match self {
+            Expression::Literal(_)
+            | Expression::Variable(_)
+            | Expression::This(_)
+            | Expression::Super(_, _) => true,
+            Expression::Interpolation(parts) => parts.is_empty(),
+            _ => false,
         }
     }
 }
@@ -159,9 +598,11 @@ impl From<Expression> for String {
 mod tests {
     use crate::analyzers::{Parser, Scanner};
     use crate::get_statement_string;
+    use crate::types::{Literal, Statement};
+    use crate::Environment;
 
     fn evaluate_statement(expr: &str) -> String {
-        let scanner = Scanner::new(expr.into()).unwrap();
+        let scanner = Scanner::new(expr).unwrap();
         let mut parser = Parser::new(scanner.tokens, true);
         let statements = parser.parse().unwrap();
         let mut out = String::new();
@@ -223,4 +664,309 @@ mod tests {
         let expression = "(2 + 4) != 10;";
         assert_eq!(evaluate_statement(expression), "true");
     }
+
+    #[test]
+    fn special_float_values_follow_ieee_semantics() {
+        let scenarios = vec![
+            ("0 / 0;", "nan"),
+            ("1 / 0;", "inf"),
+            ("0 - 1 / 0;", "-inf"),
+            ("-0;", "0"),
+            ("0 * -1;", "0"),
+            ("-0 == 0;", "true"),
+            ("(0 / 0) == (0 / 0);", "false"),
+            ("(0 / 0) != (0 / 0);", "true"),
+            ("(0 / 0) < 1;", "false"),
+            ("(0 / 0) >= 1;", "false"),
+            ("!((0 / 0) < 1) == ((0 / 0) >= 1);", "false"),
+            ("(1 / 0) == (2 / 0);", "true"),
+            ("(1 / 0) - (1 / 0);", "nan"),
+        ];
+
+        for (expression, expected) in scenarios {
+            assert_eq!(evaluate_statement(expression), expected, "{}", expression);
+        }
+    }
+
+    #[test]
+    fn bitwise_expressions_are_evaluated_successfully() {
+        let expression = "5 & 3;";
+        assert_eq!(evaluate_statement(expression), "1");
+
+        let expression = "5 | 3;";
+        assert_eq!(evaluate_statement(expression), "7");
+
+        let expression = "5 ^ 3;";
+        assert_eq!(evaluate_statement(expression), "6");
+
+        let expression = "~5;";
+        assert_eq!(evaluate_statement(expression), "-6");
+
+        let expression = "1 << 10;";
+        assert_eq!(evaluate_statement(expression), "1024");
+
+        let expression = "1024 >> 3;";
+        assert_eq!(evaluate_statement(expression), "128");
+    }
+
+    #[test]
+    fn interpolated_strings_are_evaluated_successfully() {
+        let expression = "\"plain\";";
+        assert_eq!(evaluate_statement(expression), "plain");
+
+        let expression = "\"sum is ${1 + 2}\";";
+        assert_eq!(evaluate_statement(expression), "sum is 3");
+
+        let expression = "\"${\"{braces}\"} and ${\"nested ${2 * 2}\"}!\";";
+        assert_eq!(evaluate_statement(expression), "{braces} and nested 4!");
+    }
+
+    #[test]
+    fn interpolated_expression_errors_report_inner_location() {
+        let scanner = Scanner::new("\"a\";\n\"value ${1 & true}\";").unwrap();
+        let mut parser = Parser::new(scanner.tokens, true);
+        let statements = parser.parse().unwrap();
+        let error = match &statements[1] {
+            crate::Statement::Expression(expr) => {
+                expr.evaluate(&mut Environment::new()).unwrap_err()
+            }
+            statement => panic!("unexpected statement {:?}", statement),
+        };
+
+        assert!(
+            error.to_string().ends_with("at line 2 column 12"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn nil_coalescing_only_falls_back_on_nil() {
+        let scenarios = [
+            ("nil ?? 1;", "1"),
+            ("false ?? 1;", "false"),
+            ("0 ?? 1;", "0"),
+            ("\"\" ?? \"fallback\";", ""),
+            ("nil ?? nil;", "nil"),
+            ("nil ?? nil ?? 3;", "3"),
+            ("nil ?? 2 ?? 3;", "2"),
+            ("(1 == 2) ?? 3;", "false"),
+        ];
+
+        for (scenario, expected) in scenarios {
+            assert_eq!(evaluate_statement(scenario), expected, "{}", scenario);
+        }
+    }
+
+    #[test]
+    fn nil_coalescing_short_circuits() {
+        let mut env = Environment::new();
+        crate::stdlib::install(&mut env);
+        let scenarios = [
+            ("1 ?? 1 & true;", Ok(Literal::Integer(1))),
+            (
+                "nil ?? 1 & true;",
+                Err("operand true of `&` is not a number"),
+            ),
+            ("missing ?? 1;", Err("undefined variable `missing`")),
+        ];
+
+        for (scenario, expected) in scenarios {
+            let scanner = Scanner::new(scenario).unwrap();
+            let statement = Parser::new(scanner.tokens, true).parse().unwrap().remove(0);
+            let Statement::Expression(expr) = &statement else {
+                panic!("expected an expression statement");
+            };
+            match (expr.evaluate(&mut env), expected) {
+                (Ok(value), Ok(expected)) => assert_eq!(value, expected, "{}", scenario),
+                (Err(error), Err(expected)) => {
+                    assert!(error.to_string().contains(expected), "{}", error)
+                }
+                (result, _) => panic!("{} gave {:?}", scenario, result),
+            }
+        }
+    }
+
+    #[test]
+    fn bitwise_expressions_reject_non_integer_operands() {
+        let scenarios = vec![
+            ("1.5 & 1;", "operand 1.5 of `&`"),
+            ("1 | true;", "operand true of `|`"),
+            ("1 << 64;", "shift amount 64"),
+        ];
+
+        for (scenario, expected) in scenarios {
+            let scanner = Scanner::new(scenario).unwrap();
+            let mut parser = Parser::new(scanner.tokens, true);
+            let statements = parser.parse().unwrap();
+            let error = match &statements[0] {
+                crate::Statement::Expression(expr) => {
+                    expr.evaluate(&mut Environment::new()).unwrap_err()
+                }
+                statement => panic!("unexpected statement {:?}", statement),
+            };
+
+            assert!(error.to_string().contains(expected), "{}", error);
+        }
+    }
+
+    #[test]
+    fn unary_errors_show_the_operand() {
+        let scenarios = [
+            (
+                "-!true;",
+                "evaluation error[R005]: operator `-` requires a number, got boolean false at line 1 column 1",
+            ),
+            (
+                "1;\n  ! -5;",
+                "evaluation error[R005]: operator `!` requires a boolean, got number -5 at line 2 column 3",
+            ),
+            (
+                "-\"abc\";",
+                "evaluation error[R005]: operator `-` requires a number, got string \"abc\" at line 1 column 1",
+            ),
+            (
+                "-\"a rather long string value\";",
+                "evaluation error[R005]: operator `-` requires a number, got string \"a rather long string...\" at line 1 column 1",
+            ),
+            (
+                "!nil;",
+                "evaluation error[R005]: operator `!` requires a boolean, got nil at line 1 column 1",
+            ),
+        ];
+
+        for (scenario, expected) in scenarios {
+            let scanner = Scanner::new(scenario).unwrap();
+            let statements = Parser::new(scanner.tokens, true).parse().unwrap();
+            let error = match statements.last().unwrap() {
+                crate::Statement::Expression(expr) => {
+                    expr.evaluate(&mut Environment::new()).unwrap_err()
+                }
+                statement => panic!("unexpected statement {:?}", statement),
+            };
+
+            assert_eq!(error.to_string(), expected, "{}", scenario);
+        }
+    }
+
+    #[test]
+    fn nested_unaries_are_evaluated() {
+        let scenarios = [
+            ("--5;", "5"),
+            ("-(-5);", "5"),
+            ("!!true;", "true"),
+            ("!(1 < -2);", "true"),
+            ("-~5;", "6"),
+            ("~-5;", "4"),
+        ];
+
+        for (scenario, expected) in scenarios {
+            assert_eq!(evaluate_statement(scenario), expected, "{}", scenario);
+        }
+    }
+
+    #[test]
+    fn integers_stay_exact() {
+        let scenarios = [
+            ("9007199254740993;", "9007199254740993"),
+            ("9007199254740993 + 2;", "9007199254740995"),
+            ("3 * -4 - 1;", "-13"),
+            ("-9223372036854775807 - 1;", "-9223372036854775808"),
+            ("6 / 3;", "2"),
+            ("-6 / 4;", "-1.5"),
+            ("5 & 3;", "1"),
+            ("~0;", "-1"),
+            ("1 << 62;", "4611686018427387904"),
+        ];
+
+        for (scenario, expected) in scenarios {
+            assert_eq!(evaluate_statement(scenario), expected, "{}", scenario);
+        }
+    }
+
+    #[test]
+    fn integers_fall_back_to_floats() {
+        let scenarios = [
+            ("1.5 * 2;", "3"),
+            ("0.5 + 1;", "1.5"),
+            ("7 / 2;", "3.5"),
+            ("1 / 0;", "inf"),
+            ("0 / 0;", "nan"),
+            ("9223372036854775807 + 1;", "9223372000000000000"),
+            ("-(-9223372036854775807 - 1);", "9223372000000000000"),
+            ("99999999999999999999;", "100000000000000000000"),
+        ];
+
+        for (scenario, expected) in scenarios {
+            assert_eq!(evaluate_statement(scenario), expected, "{}", scenario);
+        }
+    }
+
+    #[test]
+    fn integers_and_floats_compare_by_value() {
+        let scenarios = [
+            ("1 == 1.0;", "true"),
+            ("1 != 1.0;", "false"),
+            ("2.0 >= 2;", "true"),
+            ("1 < 1.5;", "true"),
+            ("-1 > -1.5;", "true"),
+            ("9007199254740993 == 9007199254740992;", "false"),
+            ("9007199254740993 > 9007199254740992.0;", "true"),
+            ("16777217 == 16777216.0;", "false"),
+            ("16777216 == 16777216.0;", "true"),
+            ("9223372036854775807 < 9223372036854775807.0;", "true"),
+            ("0 / 0 == 0;", "false"),
+            ("0 / 0 != 0;", "true"),
+        ];
+
+        for (scenario, expected) in scenarios {
+            assert_eq!(evaluate_statement(scenario), expected, "{}", scenario);
+        }
+    }
+
+    #[test]
+    fn values_of_any_type_compare_for_equality() {
+        let scenarios = [
+            ("\"abc\" == \"ab\";", "false"),
+            ("\"ab\" == \"abc\";", "false"),
+            ("\"ab\" == \"ab\";", "true"),
+            ("\"1\" == 1;", "false"),
+            ("1 != \"1\";", "true"),
+            ("true == 1;", "false"),
+            ("nil == false;", "false"),
+        ];
+
+        for (scenario, expected) in scenarios {
+            assert_eq!(evaluate_statement(scenario), expected, "{}", scenario);
+        }
+    }
+
+    #[test]
+    fn reads_of_globals_from_nested_blocks_hit_the_cache() {
+        let mut env = Environment::new();
+        crate::stdlib::install(&mut env);
+        env.define("a".into(), Literal::Integer(1));
+        for depth in 0..10 {
+            env.enter_block();
+            env.define(format!("local{}", depth), Literal::Nil);
+        }
+        let scanner = Scanner::new("a + a;").unwrap();
+        let statement = Parser::new(scanner.tokens, true).parse().unwrap().remove(0);
+        let Statement::Expression(expr) = &statement else {
+            panic!("expected an expression statement");
+        };
+
+        env.reset_counters();
+        for _ in 0..1000 {
+            assert!(matches!(expr.evaluate(&mut env), Ok(Literal::Integer(2))));
+        }
+        // Only the first read searches the scopes
+        let counters = env.counters();
+        assert_eq!((counters.lookups, counters.cached_lookups), (2000, 1999));
+
+        // Declaring a variable may shadow the global, so the next read searches
+        env.define("b".into(), Literal::Nil);
+        assert!(expr.evaluate(&mut env).is_ok());
+        assert_eq!(env.counters().cached_lookups, 2000);
+    }
 }
@@ -1,20 +1,195 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::Environment;
+
 #[derive(Clone, Debug)]
 /// Literal value in the lox interpreter environment
+///
+/// Numbers are either integers or floats, both have the type `number` in lox.
+/// Number literals without a decimal point are integers unless they are too
+/// large for an `i64`. Integers stay exact as long as the result of an
+/// operation is an integer:
+/// - `+`, `-`, `*` and negation on integers give integers, unless the result
+///   overflows in which case it's computed as floats
+/// - `/` on integers gives an integer when the left side is a multiple of the
+///   right side, and floats otherwise, so `6 / 3` is `2` and `7 / 2` is `3.5`
+/// - operations involving a float convert the integer to a float
+/// - bitwise operators always give integers
+///
+/// Comparisons and equality use the exact values regardless of the
+/// representation, so `1 == 1.0` is true.
+///
+/// Floats follow IEEE 754 semantics: `0.0 / 0` is NaN which is unequal to
+/// everything including itself, and division by zero results in infinity.
+/// NaN is displayed as `nan`, infinities as `inf`/`-inf` and negative zero as
+/// `0`.
 pub enum Literal {
     Number(f32),
+    Integer(i64),
     String(String),
     Boolean(bool),
-    Variable(String),
+    Nil,
+    Native(Native),
     Assignment(String, Box<Literal>),
 }
 
+impl Literal {
+    /// Name of the value's type used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Literal::Number(_) | Literal::Integer(_) => "number",
+            Literal::String(_) => "string",
+            Literal::Boolean(_) => "boolean",
+            Literal::Nil => "nil",
+            Literal::Native(_) => "function",
+            Literal::Assignment(_, _) => "assignment",
+        }
+    }
+
+    /// Returns false for `nil` and `false`, and true for every other value
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Literal::Nil | Literal::Boolean(false))
+    }
+
+    /// Value of a number as a float, `None` for other types
+    pub fn as_float(&self) -> Option<f32> {
+        match self {
+            Literal::Number(value) => Some(*value),
+            Literal::Integer(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+
+    /// Compares two numbers by their exact values, `None` if either one is
+    /// NaN or not a number
+    pub fn compare_numbers(&self, other: &Literal) -> Option<Ordering> {
+        match (self, other) {
+            (Literal::Integer(left), Literal::Integer(right)) => Some(left.cmp(right)),
+            (Literal::Number(left), Literal::Number(right)) => left.partial_cmp(right),
+            (Literal::Integer(left), Literal::Number(right)) => compare_mixed(*left, *right),
+            (Literal::Number(left), Literal::Integer(right)) => {
+                compare_mixed(*right, *left).map(Ordering::reverse)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Values are equal when they have the same type and value
+///
+/// Numbers are compared by their exact values regardless of the
+/// representation and NaN is unequal to everything, which is why literals
+/// aren't `Eq`. Natives are equal when they have the same name.
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::String(left), Literal::String(right)) => left == right,
+            (Literal::Boolean(left), Literal::Boolean(right)) => left == right,
+            (Literal::Nil, Literal::Nil) => true,
+            (Literal::Native(left), Literal::Native(right)) => left.name == right.name,
+            (Literal::Assignment(name, value), Literal::Assignment(other_name, other_value)) => {
+                name == other_name && value == other_value
+            }
+            _ => self.compare_numbers(other) == Some(Ordering::Equal),
+        }
+    }
+}
+
+/// Equal values have the same hash, so integers and floats with the same
+/// value hash alike
+impl Hash for Literal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Literal::Integer(value) => {
+                state.write_u8(0);
+                value.hash(state);
+            }
+            Literal::Number(value) => {
+                let whole = *value as i64;
+                if whole as f32 == *value && whole != i64::MAX {
+                    // Also maps negative zero to zero
+                    state.write_u8(0);
+                    whole.hash(state);
+                } else {
+                    state.write_u8(1);
+                    value.to_bits().hash(state);
+                }
+            }
+            Literal::String(value) => {
+                state.write_u8(2);
+                value.hash(state);
+            }
+            Literal::Boolean(value) => {
+                state.write_u8(3);
+                value.hash(state);
+            }
+            Literal::Nil => state.write_u8(4),
+            Literal::Native(native) => {
+                state.write_u8(5);
+                native.name.hash(state);
+            }
+            Literal::Assignment(name, value) => {
+                state.write_u8(6);
+                name.hash(state);
+                value.hash(state);
+            }
+        }
+    }
+}
+
+/// Compares an integer with a float without rounding either of them
+fn compare_mixed(integer: i64, float: f32) -> Option<Ordering> {
+    if float.is_nan() {
+        return None;
+    }
+    // Every f32 fits exactly in an f64, and 2^63 is a power of two
+    let float = float as f64;
+    if float >= 9_223_372_036_854_775_808.0 {
+        return Some(Ordering::Less);
+    }
+    if float < -9_223_372_036_854_775_808.0 {
+        return Some(Ordering::Greater);
+    }
+    let whole = float.trunc();
+    Some(
+        integer
+            .cmp(&(whole as i64))
+            .then(0.0.partial_cmp(&(float - whole)).unwrap()),
+    )
+}
+
+/// Function implemented in rust that can be called from lox
+///
+/// The function receives exactly `arity` arguments and returns a message
+/// naming itself and the offending parameter on failure, the interpreter adds
+/// the location of the call.
+#[derive(Clone)]
+pub struct Native {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: fn(&mut Environment, &[Literal]) -> Result<Literal, String>,
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
 impl From<Literal> for String {
     fn from(value: Literal) -> Self {
         match value {
             Literal::String(val) => val,
+            Literal::Number(val) if val.is_nan() => "nan".into(),
+            // Matches negative zero as well
+            Literal::Number(0.0) => "0".into(),
             Literal::Number(val) => format!("{}", val),
+            Literal::Integer(val) => format!("{}", val),
             Literal::Boolean(val) => format!("{}", val),
-            Literal::Variable(val) => val,
+            Literal::Nil => "nil".into(),
+            Literal::Native(native) => format!("{:?}", native),
             Literal::Assignment(name, literal) => {
                 let literal = *literal;
                 let literal: String = literal.into();
@@ -24,3 +199,89 @@ impl From<Literal> for String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::*;
+
+    fn hash(value: &Literal) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn equality_is_symmetric_across_every_pair_of_values() {
+        let values = [
+            Literal::Integer(1),
+            Literal::Number(1.0),
+            Literal::Number(1.5),
+            Literal::Number(f32::NAN),
+            Literal::Integer(0),
+            Literal::Number(-0.0),
+            Literal::String("abc".into()),
+            Literal::String("ab".into()),
+            Literal::String("".into()),
+            Literal::String("1".into()),
+            Literal::Boolean(true),
+            Literal::Boolean(false),
+            Literal::Nil,
+        ];
+        // Indexes of the values equal to each other besides themselves
+        let same_value = [(0, 1), (4, 5)];
+
+        for (i, left) in values.iter().enumerate() {
+            for (j, right) in values.iter().enumerate() {
+                let nan = matches!(left, Literal::Number(value) if value.is_nan());
+                let expected = (i == j && !nan)
+                    || same_value.contains(&(i, j))
+                    || same_value.contains(&(j, i));
+
+                assert_eq!(left == right, expected, "{:?} == {:?}", left, right);
+                if expected {
+                    assert_eq!(hash(left), hash(right), "{:?} and {:?}", left, right);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn strings_with_a_common_prefix_are_unequal() {
+        let (long, short) = (Literal::String("abc".into()), Literal::String("ab".into()));
+
+        assert_ne!(long, short);
+        assert_ne!(short, long);
+    }
+
+    #[test]
+    fn large_numbers_hash_by_value() {
+        let huge = 9_223_372_036_854_775_808.0;
+
+        assert_ne!(Literal::Number(huge), Literal::Integer(i64::MAX));
+        assert_eq!(Literal::Number(-huge), Literal::Integer(i64::MIN));
+        assert_eq!(
+            hash(&Literal::Number(-huge)),
+            hash(&Literal::Integer(i64::MIN))
+        );
+        assert_eq!(
+            hash(&Literal::Integer(16_777_216)),
+            hash(&Literal::Number(16_777_216.0))
+        );
+    }
+
+    #[test]
+    fn only_nil_and_false_are_falsy() {
+        let truthy = [
+            Literal::Boolean(true),
+            Literal::Integer(0),
+            Literal::Number(0.0),
+            Literal::String("".into()),
+        ];
+
+        assert!(truthy.iter().all(Literal::is_truthy));
+        assert!(!Literal::Nil.is_truthy());
+        assert!(!Literal::Boolean(false).is_truthy());
+    }
+}
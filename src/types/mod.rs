@@ -1,9 +1,10 @@
 pub mod expression;
 pub mod literal;
+mod printer;
 pub mod statement;
 pub mod token;
 
 pub use expression::Expression;
-pub use literal::Literal;
+pub use literal::{Literal, Native};
 pub use statement::Statement;
-pub use token::{Token, TokenType};
+pub use token::{write_tokens, LocationInfo, Span, Token, TokenDumpFormat, TokenStream, TokenType};
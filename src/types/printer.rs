@@ -0,0 +1,237 @@
+//! S-expression printer for statements and expressions
+//!
+//! Every node other than literals and variables is wrapped in parentheses so
+//! the printed text shows the shape of the tree, `1 + 2 * 3` is printed as
+//! `(1 + (2 * 3))`. Binary operators, `&&` and `||` included, and
+//! assignments stay between their operands, everything else is printed as
+//! the name of the node followed by its children:
+//! - `-a` is `(- a)`
+//! - `(a)` is `(group a)`
+//! - `f(a, b)` is `(call f a b)`
+//! - `"x ${a}"` is `(interpolate "x " a "")`
+//! - `let a = 1;` is `(let a 1)`, `{ a; }` is `(block a)`, `exit 1;` is
+//!   `(exit 1)` and `assert a, "m";` is `(assert a "m")`
+//!
+//! Expression statements are printed as their expression. Tools such as
+//! `:parse` at the prompt and assertion messages rely on this format.
+//!
+//! Nodes are printed from a heap allocated stack rather than by recursion so
+//! deeply nested trees, i.e long operator chains, don't overflow the stack.
+use std::fmt::Write;
+
+use crate::{Expression, Statement, TokenType};
+
+enum Item<'a> {
+    Statement(&'a Statement),
+    Expression(&'a Expression),
+    Text(&'a str),
+    /// String segment of an interpolation, printed quoted
+    Segment(&'a str),
+}
+
+pub(crate) fn statement(statement: &Statement) -> String {
+    print(Item::Statement(statement))
+}
+
+pub(crate) fn expression(expression: &Expression) -> String {
+    print(Item::Expression(expression))
+}
+
+fn print(root: Item) -> String {
+    let mut output = String::new();
+    let mut stack = vec![root];
+
+    while let Some(item) = stack.pop() {
+        // Items are pushed in reverse so they're printed in order
+        let mut items = Vec::new();
+        match item {
+            Item::Text(text) => output.push_str(text),
+            Item::Segment(text) => write!(output, "{:?}", text).unwrap(),
+            Item::Statement(statement) => match statement {
+                Statement::Expression(expr) | Statement::Variable(expr) => {
+                    items.push(Item::Expression(expr))
+                }
+                Statement::Assign(name, expr) => items.extend([
+                    Item::Text("(let "),
+                    Item::Text(&name.lexeme),
+                    Item::Text(" "),
+                    Item::Expression(expr),
+                    Item::Text(")"),
+                ]),
+                Statement::Block(_, statements) => {
+                    items.push(Item::Text("(block"));
+                    for statement in statements {
+                        items.extend([Item::Text(" "), Item::Statement(statement)]);
+                    }
+                    items.push(Item::Text(")"));
+                }
+                Statement::Assert(_, condition, message) => {
+                    items.extend([Item::Text("(assert "), Item::Expression(condition)]);
+                    if let Some(message) = message {
+                        items.extend([Item::Text(" "), Item::Expression(message)]);
+                    }
+                    items.push(Item::Text(")"));
+                }
+                Statement::Exit(_, expr) => items.extend([
+                    Item::Text("(exit "),
+                    Item::Expression(expr),
+                    Item::Text(")"),
+                ]),
+                Statement::Attempt(body, name, handler) => items.extend([
+                    Item::Text("(attempt "),
+                    Item::Statement(body),
+                    Item::Text(" handle "),
+                    Item::Text(&name.lexeme),
+                    Item::Text(" "),
+                    Item::Statement(handler),
+                    Item::Text(")"),
+                ]),
+                Statement::Print(keyword, expr) => items.extend([
+                    Item::Text("("),
+                    Item::Text(&keyword.lexeme),
+                    Item::Text(" "),
+                    Item::Expression(expr),
+                    Item::Text(")"),
+                ]),
+                Statement::Empty(_) => items.push(Item::Text("(empty)")),
+            },
+            Item::Expression(expr) => match expr {
+                Expression::Literal(token)
+                | Expression::Variable(token)
+                | Expression::This(token) => output.push_str(&token.lexeme),
+                Expression::Super(keyword, method) => {
+                    output.push_str(&format!("{}.{}", keyword.lexeme, method.lexeme))
+                }
+                Expression::Unary(operator, expr) => items.extend([
+                    Item::Text("("),
+                    Item::Text(&operator.lexeme),
+                    Item::Text(" "),
+                    Item::Expression(expr),
+                    Item::Text(")"),
+                ]),
+                Expression::Binary(left, operator, right) => items.extend([
+                    Item::Text("("),
+                    Item::Expression(left),
+                    Item::Text(" "),
+                    Item::Text(&operator.lexeme),
+                    Item::Text(" "),
+                    Item::Expression(right),
+                    Item::Text(")"),
+                ]),
+                Expression::Grouping(expr) => items.extend([
+                    Item::Text("(group "),
+                    Item::Expression(expr),
+                    Item::Text(")"),
+                ]),
+                Expression::Assignment(name, expr) => items.extend([
+                    Item::Text("("),
+                    Item::Text(&name.lexeme),
+                    Item::Text(" = "),
+                    Item::Expression(expr),
+                    Item::Text(")"),
+                ]),
+                Expression::Interpolation(parts) => {
+                    items.push(Item::Text("(interpolate"));
+                    for part in parts {
+                        items.push(Item::Text(" "));
+                        items.push(match part {
+                            Expression::Literal(token)
+                                if matches!(
+                                    token._type,
+                                    TokenType::StringHead
+                                        | TokenType::StringMiddle
+                                        | TokenType::StringTail
+                                ) =>
+                            {
+                                Item::Segment(&token.lexeme)
+                            }
+                            part => Item::Expression(part),
+                        });
+                    }
+                    items.push(Item::Text(")"));
+                }
+                Expression::Call(callee, _, arguments) => {
+                    items.extend([Item::Text("(call "), Item::Expression(callee)]);
+                    for argument in arguments {
+                        items.extend([Item::Text(" "), Item::Expression(argument)]);
+                    }
+                    items.push(Item::Text(")"));
+                }
+            },
+        }
+        stack.extend(items.into_iter().rev());
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, Scanner};
+
+    #[test]
+    fn prints_deeply_nested_expressions() {
+        let source = format!("{}1;", "1 + ".repeat(30_000));
+        let tokens = Scanner::new(&source).unwrap().tokens;
+        let statements = Parser::new(tokens, true).parse().unwrap();
+
+        let printed = statement(&statements[0]);
+        assert!(printed.starts_with(&format!("{}1 + 1)", "(".repeat(30_000))));
+        assert!(printed.ends_with(" + 1) + 1)"));
+        assert_eq!(printed.len(), source.len() - 1 + 2 * 30_000);
+    }
+
+    fn printed(source: &str) -> String {
+        let tokens = Scanner::new(source).unwrap().tokens;
+        statement(&Parser::new(tokens, true).parse().unwrap()[0])
+    }
+
+    #[test]
+    fn prints_the_precedence_of_every_level() {
+        let scenarios = [
+            // Loosest to tightest: assignment, equality and logical,
+            // bitwise or, xor and and, shift, comparison, term, factor,
+            // unary and call
+            ("a = b = 1 + 2;", "(a = (b = (1 + 2)))"),
+            ("1 + 2 * 3 == 7 && true;", "(((1 + (2 * 3)) == 7) && true)"),
+            ("a || b && c == d;", "(((a || b) && c) == d)"),
+            ("1 | 2 ^ 3 & 4 == 5;", "((1 | (2 ^ (3 & 4))) == 5)"),
+            ("1 & 2 << 3;", "(1 & (2 << 3))"),
+            ("1 << 2 < 3;", "(1 << (2 < 3))"),
+            ("1 < 2 + 3 >= 4;", "((1 < (2 + 3)) >= 4)"),
+            ("1 - 2 - 3 / 4 / 5;", "((1 - 2) - ((3 / 4) / 5))"),
+            ("-a * ~b;", "((- a) * (~ b))"),
+            ("!-f(1)(2);", "(! (- (call (call f 1) 2)))"),
+            ("(1 + 2) * 3;", "((group (1 + 2)) * 3)"),
+            (
+                "-(1 - 2) == !(a != b);",
+                "((- (group (1 - 2))) == (! (group (a != b))))",
+            ),
+        ];
+
+        for (source, expected) in scenarios {
+            assert_eq!(printed(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn prints_every_kind_of_node() {
+        let source = "let a = -(1);\n{ a = f(a, \"x ${a} y\"); exit 0; }";
+        let tokens = Scanner::new(source).unwrap().tokens;
+        let printed: Vec<String> = Parser::new(tokens, true)
+            .parse()
+            .unwrap()
+            .iter()
+            .map(statement)
+            .collect();
+
+        assert_eq!(
+            printed,
+            vec![
+                "(let a (- (group 1)))",
+                "(block (a = (call f a (interpolate \"x \" a \" y\"))) (exit 0))",
+            ]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use super::printer;
 use crate::{Expression, Token};
 
 #[derive(Debug, Clone)]
@@ -7,5 +8,71 @@ pub enum Statement {
     Expression(Expression),
     Variable(Expression),
     Assign(Token, Expression),
-    Block(Vec<Statement>),
+    /// Statements between braces, the token is the opening brace
+    Block(Token, Vec<Statement>),
+    /// Stops the program with the exit code the expression evaluates to
+    Exit(Token, Expression),
+    /// Fails unless the condition is truthy, with the message if any
+    Assert(Token, Expression, Option<Expression>),
+    /// Writes the value to the output, followed by a newline when the
+    /// keyword is `print` and not when it is `write`
+    Print(Token, Expression),
+    /// Runs the first block and, if it fails with an evaluation error, the
+    /// second one with the error message bound to the name
+    Attempt(Box<Statement>, Token, Box<Statement>),
+    /// A lone `;`, which does nothing
+    Empty(Token),
+}
+
+impl Statement {
+    /// Returns the token errors about the whole statement point at, its
+    /// keyword, the name of a declaration or else its first token
+    pub fn leftmost_token(&self) -> &Token {
+        match self {
+            Statement::Expression(expr) | Statement::Variable(expr) => expr.leftmost_token(),
+            Statement::Assign(token, _)
+            | Statement::Block(token, _)
+            | Statement::Exit(token, _)
+            | Statement::Assert(token, _, _)
+            | Statement::Print(token, _)
+            | Statement::Empty(token) => token,
+            Statement::Attempt(body, _, _) => body.leftmost_token(),
+        }
+    }
+}
+
+/// Statements are equal when they have the same structure, see
+/// [Expression]'s `PartialEq` for how expressions are compared
+impl PartialEq for Statement {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Statement::Expression(expr), Statement::Expression(other_expr))
+            | (Statement::Variable(expr), Statement::Variable(other_expr))
+            | (Statement::Exit(_, expr), Statement::Exit(_, other_expr)) => expr == other_expr,
+            (Statement::Assign(name, expr), Statement::Assign(other_name, other_expr)) => {
+                name.is_equivalent(other_name) && expr == other_expr
+            }
+            (Statement::Block(_, stmts), Statement::Block(_, other_stmts)) => stmts == other_stmts,
+            (
+                Statement::Assert(_, condition, message),
+                Statement::Assert(_, other_condition, other_message),
+            ) => condition == other_condition && message == other_message,
+            (Statement::Print(keyword, expr), Statement::Print(other_keyword, other_expr)) => {
+                keyword._type == other_keyword._type && expr == other_expr
+            }
+            (
+                Statement::Attempt(body, name, handler),
+                Statement::Attempt(other_body, other_name, other_handler),
+            ) => body == other_body && name.is_equivalent(other_name) && handler == other_handler,
+            (Statement::Empty(_), Statement::Empty(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Prints the statement as an s-expression, such as `(let a (1 + 2))`
+impl From<Statement> for String {
+    fn from(val: Statement) -> String {
+        printer::statement(&val)
+    }
 }
@@ -1,4 +1,7 @@
 use std::fmt;
+use std::io;
+
+use crate::errors::{codes::ErrorCode, ParserError};
 
 /// Token identified during lexical analysis
 #[derive(Debug, Clone)]
@@ -7,6 +10,8 @@ pub struct Token {
     pub lexeme: String,
     pub line: usize,
     pub column: usize,
+    /// Byte offset of the start of the token in the source
+    pub offset: usize,
 }
 
 impl Token {
@@ -16,8 +21,376 @@ impl Token {
             line,
             column,
             _type,
+            offset: 0,
+        }
+    }
+
+    /// Creates a token for code generated by the parser rather than read
+    /// from the source
+    ///
+    /// Synthetic tokens use line `0` as a sentinel location, which error
+    /// reporting renders as "generated code".
+    pub fn synthetic(_type: TokenType, lexeme: &str) -> Self {
+        Self::new(lexeme, 0, 0, _type)
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        self.line == 0
+    }
+
+    /// Returns true if both tokens have the same type and value, ignoring
+    /// where they are located and how numbers are spelled, i.e `1.0` and `1`
+    pub fn is_equivalent(&self, other: &Token) -> bool {
+        if self._type != other._type {
+            return false;
+        }
+        match (self.lexeme.parse::<f32>(), other.lexeme.parse::<f32>()) {
+            (Ok(left), Ok(right)) if self._type == TokenType::Number => left == right,
+            _ => self.lexeme == other.lexeme,
+        }
+    }
+
+    /// Where the token is in the source, see [LocationInfo]
+    pub fn location(&self) -> LocationInfo {
+        LocationInfo::new(self.line, self.column, self.lexeme.chars().count())
+    }
+
+    /// Byte range of the token in the source, including the quotes and
+    /// braces around the lexeme of string tokens
+    pub fn span(&self) -> Span {
+        let (opening, closing) = delimiters(self);
+        Span {
+            offset: self.offset,
+            len: opening + self.lexeme.len() + closing,
+        }
+    }
+}
+
+/// Cursor over the tokens of a source with lookahead
+///
+/// [TokenType::Comment] tokens are skipped and input ends at the first
+/// [TokenType::Eof] or after the last token, whichever comes first, tokens
+/// after an Eof are kept aside as [trailing](TokenStream::trailing). Reading
+/// past the end never panics, an Eof token located right after the last
+/// token is returned instead.
+///
+/// ## Examples
+/// ```rust
+/// use lox::errors::codes;
+/// use lox::{Scanner, TokenStream, TokenType};
+///
+/// let mut tokens = TokenStream::new(Scanner::new("let a = 1;").unwrap().tokens);
+///
+/// assert!(tokens.check(&TokenType::Let));
+/// assert_eq!(tokens.peek_n(1).lexeme, "a");
+/// assert!(tokens.match_any(&[TokenType::Let, TokenType::Exit]).is_some());
+/// assert_eq!(tokens.advance().lexeme, "a");
+/// let error = tokens
+///     .expect(TokenType::SemiColon, codes::EXPECTED_TOKEN, &[&";"])
+///     .unwrap_err();
+/// assert_eq!(error.code(), codes::EXPECTED_TOKEN);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TokenStream {
+    tokens: Vec<Token>,
+    current: usize,
+    /// Returned when reading past either end of the tokens
+    end: Token,
+    /// First token after the end of input
+    trailing: Option<Token>,
+}
+
+impl TokenStream {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        let mut tokens = tokens
+            .into_iter()
+            .filter(|token| token._type != TokenType::Comment);
+        let input: Vec<Token> = tokens
+            .by_ref()
+            .take_while(|token| token._type != TokenType::Eof)
+            .collect();
+        let trailing = tokens.find(|token| token._type != TokenType::Eof);
+        let tokens = input;
+
+        let end = match tokens.last() {
+            Some(last) => end_of(last),
+            None => Token::new("", 1, 1, TokenType::Eof),
+        };
+
+        Self {
+            tokens,
+            current: 0,
+            end,
+            trailing,
+        }
+    }
+
+    /// Token under the cursor
+    pub fn peek(&self) -> &Token {
+        self.peek_n(0)
+    }
+
+    /// Token `k` positions after the cursor
+    pub fn peek_n(&self, k: usize) -> &Token {
+        self.tokens.get(self.current + k).unwrap_or(&self.end)
+    }
+
+    /// Last consumed token, or the end of input if nothing has been
+    /// consumed yet
+    pub fn previous(&self) -> &Token {
+        match self.current.checked_sub(1) {
+            Some(i) => &self.tokens[i],
+            None => &self.end,
+        }
+    }
+
+    /// Consumes the token under the cursor, the cursor stays put at the end
+    /// of input
+    pub fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        token
+    }
+
+    pub fn check(&self, token_type: &TokenType) -> bool {
+        &self.peek()._type == token_type
+    }
+
+    /// Consumes the token under the cursor if it has one of the types
+    pub fn match_any(&mut self, token_types: &[TokenType]) -> Option<Token> {
+        if token_types.contains(&self.peek()._type) {
+            Some(self.advance())
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the token under the cursor if it has the type, otherwise
+    /// fails with the error `code` at the token's location
+    pub fn expect(
+        &mut self,
+        token_type: TokenType,
+        code: ErrorCode,
+        args: &[&dyn fmt::Display],
+    ) -> Result<Token, ParserError> {
+        if self.check(&token_type) {
+            Ok(self.advance())
+        } else {
+            Err(ParserError::new(code, args, self.peek()))
+        }
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.current >= self.tokens.len()
+    }
+
+    /// First token other than an Eof found after the end of input, such
+    /// tokens are never read
+    pub fn trailing(&self) -> Option<&Token> {
+        self.trailing.as_ref()
+    }
+
+    /// Number of tokens consumed so far
+    pub fn position(&self) -> usize {
+        self.current
+    }
+
+    /// Token that was next when the stream was at the
+    /// [position](TokenStream::position)
+    pub fn at(&self, position: usize) -> &Token {
+        self.tokens.get(position).unwrap_or(&self.end)
+    }
+}
+
+/// Eof token located right after the last character of the token
+fn end_of(token: &Token) -> Token {
+    let (opening, closing) = delimiters(token);
+    let (line, column) = match token.lexeme.rsplit_once('\n') {
+        Some((_, last_line)) => (
+            token.line + token.lexeme.matches('\n').count(),
+            last_line.chars().count() + closing + 1,
+        ),
+        None => (
+            token.line,
+            token.column + opening + token.lexeme.chars().count() + closing,
+        ),
+    };
+    Token {
+        offset: token.offset + opening + token.lexeme.len() + closing,
+        ..Token::new("", line, column, TokenType::Eof)
+    }
+}
+
+/// Bytes of the token before and after its lexeme, the quotes of a string
+/// and the `}` and `${` around the segments of an interpolated one
+fn delimiters(token: &Token) -> (usize, usize) {
+    // Only triple quoted strings span several lines
+    let quote = if token.lexeme.contains('\n') { 3 } else { 1 };
+    match token._type {
+        TokenType::String => (quote, quote),
+        TokenType::StringHead => (quote, 2),
+        TokenType::StringMiddle => (1, 2),
+        TokenType::StringTail => (1, quote),
+        _ => (0, 0),
+    }
+}
+
+/// Byte range of a construct in the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn contains(&self, offset: usize) -> bool {
+        self.offset <= offset && offset < self.offset + self.len
+    }
+}
+
+/// Line and column a token or error points at, and how many characters it
+/// covers
+///
+/// Lines and columns are 1-based and columns count characters rather than
+/// bytes, so the first character of the source is at `1:1`. Line `0` marks
+/// code generated rather than read from the source, see
+/// [Token::synthetic]. The length is `0` where only the position is known.
+///
+/// Locations are displayed as `line:column` and are ordered by line, then
+/// by column, the order diagnostics are reported in.
+///
+/// ## Examples
+/// ```rust
+/// use lox::{LocationInfo, Scanner};
+///
+/// let tokens = Scanner::new("let café = 1;\nprint café;").unwrap().tokens;
+/// let location = tokens[6].location();
+/// assert_eq!((location.line(), location.column(), location.len()), (2, 7, 4));
+/// assert_eq!(location.to_string(), "2:7");
+///
+/// let mut locations = vec![location, tokens[1].location()];
+/// locations.sort();
+/// assert_eq!(locations, vec![LocationInfo::new(1, 5, 4), location]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LocationInfo {
+    line: usize,
+    column: usize,
+    len: usize,
+}
+
+impl LocationInfo {
+    pub fn new(line: usize, column: usize, len: usize) -> Self {
+        Self { line, column, len }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Characters covered, starting at the column
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl fmt::Display for LocationInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Layout of the tokens written by [write_tokens]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TokenDumpFormat {
+    /// One token per line as `line:column Type "lexeme"`, the lexeme quoted
+    /// and escaped the way Rust writes strings
+    #[default]
+    Human,
+    /// One JSON object per line with the `type`, `lexeme`, `line` and
+    /// `column` of the token, for tools reading the dump
+    JsonLines,
+}
+
+/// Writes the tokens one per line, straight to `out` without building the
+/// text of the whole dump or of each token first
+///
+/// Lexemes are escaped so a token spanning several lines, such as a
+/// multi-line string, still takes a single line of the dump. Small writes
+/// are made for every token, so `out` is best buffered.
+///
+/// ## Examples
+/// ```rust
+/// use lox::{write_tokens, Scanner, TokenDumpFormat};
+///
+/// let tokens = Scanner::new("print \"a\tb\";").unwrap().tokens;
+/// let mut out = Vec::new();
+/// write_tokens(&tokens[1..2], &mut out, TokenDumpFormat::JsonLines).unwrap();
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     "{\"type\":\"String\",\"lexeme\":\"a\\tb\",\"line\":1,\"column\":7}\n"
+/// );
+/// ```
+pub fn write_tokens(
+    tokens: &[Token],
+    out: &mut impl io::Write,
+    format: TokenDumpFormat,
+) -> io::Result<()> {
+    for token in tokens {
+        match format {
+            TokenDumpFormat::Human => writeln!(
+                out,
+                "{} {:?} {:?}",
+                token.location(),
+                token._type,
+                token.lexeme
+            )?,
+            TokenDumpFormat::JsonLines => {
+                write!(out, "{{\"type\":\"{:?}\",\"lexeme\":", token._type)?;
+                write_json_string(out, &token.lexeme)?;
+                writeln!(
+                    out,
+                    ",\"line\":{},\"column\":{}}}",
+                    token.line, token.column
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the value as a quoted JSON string, copying the runs of characters
+/// that don't need escaping as they are
+fn write_json_string(out: &mut impl io::Write, value: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        if c != '"' && c != '\\' && c >= ' ' {
+            continue;
+        }
+        out.write_all(&value.as_bytes()[start..i])?;
+        match c {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            '\n' => out.write_all(b"\\n")?,
+            '\r' => out.write_all(b"\\r")?,
+            '\t' => out.write_all(b"\\t")?,
+            c => write!(out, "\\u{:04x}", c as u32)?,
         }
+        start = i + c.len_utf8();
     }
+    out.write_all(&value.as_bytes()[start..])?;
+    out.write_all(b"\"")
 }
 
 /// Type of a token
@@ -35,6 +408,8 @@ pub enum TokenType {
     SemiColon,
     Slash,
     Star,
+    BitXor,
+    BitNot,
 
     // One or two character tokens
     NewLine,
@@ -44,7 +419,14 @@ pub enum TokenType {
     Space,
     Identifier,
     String,
+    /// String segment opening an interpolated string
+    StringHead,
+    /// String segment between two interpolated expressions
+    StringMiddle,
+    /// String segment closing an interpolated string
+    StringTail,
     Number,
+    Comment,
     Not,
     NotEqual,
     Equal,
@@ -55,19 +437,102 @@ pub enum TokenType {
     GreaterEqual,
     And,
     Or,
+    NilCoalesce,
+    BitAnd,
+    BitOr,
+    ShiftLeft,
+    ShiftRight,
 
     // Keywords
+    Assert,
+    Attempt,
     Class,
     Else,
+    Exit,
     False,
     For,
+    Handle,
     If,
     Print,
     Return,
     Super,
+    This,
     True,
     Let,
+    Nil,
     While,
+    Write,
+}
+
+impl TokenType {
+    /// Binding strength of the type as a binary operator or assignment,
+    /// higher binds tighter, None for other types
+    ///
+    /// Binary operators are left associative and assignment is right
+    /// associative. Unary operators bind tighter than any binary operator,
+    /// `-` is reported at its binary strength.
+    pub fn precedence(&self) -> Option<u8> {
+        match self {
+            TokenType::Equal => Some(1),
+            TokenType::NilCoalesce => Some(2),
+            TokenType::EqualEqual | TokenType::NotEqual | TokenType::And | TokenType::Or => Some(3),
+            TokenType::BitOr => Some(4),
+            TokenType::BitXor => Some(5),
+            TokenType::BitAnd => Some(6),
+            TokenType::ShiftLeft | TokenType::ShiftRight => Some(7),
+            TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual => Some(8),
+            TokenType::Minus | TokenType::Plus => Some(9),
+            TokenType::Slash | TokenType::Star => Some(10),
+            _ => None,
+        }
+    }
+
+    /// Returns true for words that can't be used as identifiers
+    pub fn is_keyword(&self) -> bool {
+        self.keyword_lexeme().is_some()
+    }
+
+    /// Spelling of the keyword, or None if the type isn't a keyword
+    pub fn keyword_lexeme(&self) -> Option<&'static str> {
+        let lexeme = match self {
+            TokenType::Assert => "assert",
+            TokenType::Attempt => "attempt",
+            TokenType::Class => "class",
+            TokenType::Else => "else",
+            TokenType::Exit => "exit",
+            TokenType::False => "false",
+            TokenType::For => "for",
+            TokenType::Handle => "handle",
+            TokenType::If => "if",
+            TokenType::Print => "print",
+            TokenType::Return => "return",
+            TokenType::Super => "super",
+            TokenType::This => "this",
+            TokenType::True => "true",
+            TokenType::Let => "let",
+            TokenType::Nil => "nil",
+            TokenType::While => "while",
+            TokenType::Write => "write",
+            _ => return None,
+        };
+        Some(lexeme)
+    }
+
+    /// Returns true for operators that work on the integer representation of
+    /// their operands
+    pub fn is_bitwise(&self) -> bool {
+        matches!(
+            self,
+            TokenType::BitAnd
+                | TokenType::BitOr
+                | TokenType::BitXor
+                | TokenType::ShiftLeft
+                | TokenType::ShiftRight
+        )
+    }
 }
 
 impl fmt::Display for TokenType {
@@ -84,6 +549,8 @@ impl fmt::Display for TokenType {
             TokenType::SemiColon => ";",
             TokenType::Slash => "/",
             TokenType::Star => "*",
+            TokenType::BitXor => "^",
+            TokenType::BitNot => "~",
             TokenType::NewLine => "new line",
             TokenType::Eof => "end of file",
             TokenType::Tab => "tab",
@@ -91,7 +558,11 @@ impl fmt::Display for TokenType {
             TokenType::Space => "space",
             TokenType::Identifier => "identifier",
             TokenType::String => "string",
+            TokenType::StringHead => "string",
+            TokenType::StringMiddle => "string",
+            TokenType::StringTail => "string",
             TokenType::Number => "number",
+            TokenType::Comment => "comment",
             TokenType::Not => "!",
             TokenType::NotEqual => "!=",
             TokenType::Equal => "=",
@@ -101,18 +572,30 @@ impl fmt::Display for TokenType {
             TokenType::Greater => ">",
             TokenType::GreaterEqual => ">=",
             TokenType::And => "&&",
+            TokenType::BitAnd => "&",
+            TokenType::BitOr => "|",
+            TokenType::ShiftLeft => "<<",
+            TokenType::ShiftRight => ">>",
+            TokenType::Assert => "assert",
+            TokenType::Attempt => "attempt",
             TokenType::Class => "class",
             TokenType::Else => "else",
+            TokenType::Exit => "exit",
             TokenType::False => "false",
             TokenType::For => "for",
+            TokenType::Handle => "handle",
             TokenType::If => "if",
             TokenType::Or => "||",
+            TokenType::NilCoalesce => "??",
+            TokenType::Nil => "nil",
             TokenType::Print => "print",
             TokenType::Return => "return",
             TokenType::Super => "super",
+            TokenType::This => "this",
             TokenType::True => "true",
             TokenType::Let => "let",
             TokenType::While => "while",
+            TokenType::Write => "write",
         };
 
         write!(f, "{}", str_rep)
@@ -135,6 +618,8 @@ impl TryFrom<char> for TokenType {
             ';' => Ok(TokenType::SemiColon),
             '/' => Ok(TokenType::Slash),
             '*' => Ok(TokenType::Star),
+            '^' => Ok(TokenType::BitXor),
+            '~' => Ok(TokenType::BitNot),
             '<' => Ok(TokenType::Less),
             '>' => Ok(TokenType::Greater),
             '!' => Ok(TokenType::Not),
@@ -147,3 +632,215 @@ impl TryFrom<char> for TokenType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::codes;
+
+    #[test]
+    fn new_tokens_span_their_whole_lexeme() {
+        let token = Token::new("while", 3, 7, TokenType::While);
+
+        assert_eq!(token.lexeme, "while");
+        assert_eq!((token.line, token.column), (3, 7));
+        assert_eq!(token.span().len, 5);
+        assert!(!token.is_synthetic());
+    }
+
+    #[test]
+    fn string_tokens_span_their_quotes() {
+        let source = "print \"ab\" + 'c ${x} d ${y}!' + \"\"\"two\nlines\"\"\";";
+        let spanned: Vec<&str> = crate::Scanner::new(source)
+            .unwrap()
+            .tokens
+            .iter()
+            .filter(|token| token.lexeme.len() != token.span().len)
+            .map(|token| &source[token.span().offset..][..token.span().len])
+            .collect();
+
+        assert_eq!(
+            spanned,
+            ["\"ab\"", "'c ${", "} d ${", "}!'", "\"\"\"two\nlines\"\"\""]
+        );
+    }
+
+    #[test]
+    fn keywords_know_their_spelling() {
+        assert_eq!(TokenType::While.keyword_lexeme(), Some("while"));
+        assert!(TokenType::Nil.is_keyword());
+        assert!(!TokenType::Identifier.is_keyword());
+        assert_eq!(TokenType::Plus.keyword_lexeme(), None);
+    }
+
+    #[test]
+    fn synthetic_tokens_use_the_sentinel_location() {
+        let token = Token::synthetic(TokenType::SemiColon, ";");
+
+        assert_eq!(token._type, TokenType::SemiColon);
+        assert_eq!(token.lexeme, ";");
+        assert_eq!(token.span().len, 1);
+        assert!(token.is_synthetic());
+    }
+
+    fn stream(source: &str) -> TokenStream {
+        TokenStream::new(crate::Scanner::new(source).unwrap().tokens)
+    }
+
+    #[test]
+    fn empty_streams_return_the_end_of_input() {
+        let mut tokens = TokenStream::new(Vec::new());
+
+        assert!(tokens.is_at_end());
+        assert_eq!(tokens.peek()._type, TokenType::Eof);
+        assert_eq!(tokens.previous()._type, TokenType::Eof);
+        assert_eq!(tokens.advance()._type, TokenType::Eof);
+        assert!(tokens.match_any(&[TokenType::Eof]).is_some());
+        assert_eq!(tokens.position(), 0);
+        assert_eq!(
+            tokens
+                .expect(TokenType::SemiColon, codes::EXPECTED_TOKEN, &[&";"])
+                .unwrap_err()
+                .to_string(),
+            "runtime exception[P001]: expected `;` at line 1 column 1"
+        );
+    }
+
+    #[test]
+    fn streams_end_after_the_last_token_without_an_eof() {
+        let mut tokens = TokenStream::new(vec![
+            Token::new("a", 2, 3, TokenType::Identifier),
+            Token::new("// note", 2, 5, TokenType::Comment),
+        ]);
+
+        assert_eq!(tokens.advance().lexeme, "a");
+        assert!(tokens.is_at_end());
+        let end = tokens.advance();
+        assert_eq!((end._type, end.line, end.column), (TokenType::Eof, 2, 4));
+        assert_eq!(tokens.previous().lexeme, "a");
+        assert!(tokens.trailing().is_none());
+    }
+
+    #[test]
+    fn tokens_after_an_eof_are_trailing() {
+        let mut tokens = TokenStream::new(vec![
+            Token::new("a", 1, 1, TokenType::Identifier),
+            Token::new("", 1, 2, TokenType::Eof),
+            Token::new("", 1, 2, TokenType::Eof),
+            Token::new("// c", 1, 3, TokenType::Comment),
+            Token::new("b", 2, 1, TokenType::Identifier),
+        ]);
+
+        assert_eq!(tokens.advance().lexeme, "a");
+        assert!(tokens.is_at_end());
+        assert_eq!(tokens.trailing().map(|token| token.line), Some(2));
+    }
+
+    #[test]
+    fn looks_ahead_without_consuming() {
+        let mut tokens = stream("a = b + 1; // c\n2");
+
+        assert_eq!(tokens.peek_n(2).lexeme, "b");
+        assert_eq!(tokens.peek_n(6).lexeme, "2");
+        assert_eq!(tokens.peek_n(7)._type, TokenType::Eof);
+        assert_eq!(tokens.peek().lexeme, "a");
+
+        assert!(tokens.match_any(&[TokenType::Number]).is_none());
+        assert!(tokens
+            .expect(TokenType::Identifier, codes::EXPECTED_IDENTIFIER, &[])
+            .is_ok());
+        assert!(tokens.check(&TokenType::Equal));
+        assert_eq!(tokens.previous().lexeme, "a");
+        assert_eq!(tokens.position(), 1);
+    }
+
+    #[test]
+    fn locations_order_by_line_then_column() {
+        let mut locations = [
+            LocationInfo::new(2, 1, 3),
+            LocationInfo::new(1, 10, 1),
+            LocationInfo::new(1, 2, 5),
+        ];
+        locations.sort();
+        let rendered: Vec<String> = locations.iter().map(ToString::to_string).collect();
+        assert_eq!(rendered, vec!["1:2", "1:10", "2:1"]);
+        assert!(LocationInfo::new(3, 4, 0).is_empty());
+    }
+
+    fn dump(source: &str, format: TokenDumpFormat) -> String {
+        let tokens = crate::Scanner::new(source).unwrap().tokens;
+        let mut out = Vec::new();
+        write_tokens(&tokens, &mut out, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn dumps_escape_lexemes_to_keep_one_token_per_line() {
+        let source = "write \"\"\"say \"hi\"\n\tto café\\\"\"\";\nlet π = 3;";
+
+        assert_eq!(
+            dump(source, TokenDumpFormat::Human),
+            "1:1 Write \"write\"\n\
+             1:7 String \"say \\\"hi\\\"\\n\\tto café\\\\\"\n\
+             2:13 SemiColon \";\"\n\
+             3:1 Let \"let\"\n\
+             3:5 Identifier \"π\"\n\
+             3:7 Equal \"=\"\n\
+             3:9 Number \"3\"\n\
+             3:10 SemiColon \";\"\n"
+        );
+
+        let lines = dump(source, TokenDumpFormat::JsonLines);
+        let lines: Vec<&str> = lines.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(
+            lines[1],
+            r#"{"type":"String","lexeme":"say \"hi\"\n\tto café\\","line":1,"column":7}"#
+        );
+        assert_eq!(
+            lines[4],
+            r#"{"type":"Identifier","lexeme":"π","line":3,"column":5}"#
+        );
+
+        let mut out = Vec::new();
+        let token = Token::new("a\u{1}b", 1, 1, TokenType::String);
+        write_tokens(&[token], &mut out, TokenDumpFormat::JsonLines).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains(r#""a\u0001b""#));
+    }
+
+    /// Sink counting what is written to it without keeping it
+    #[derive(Default)]
+    struct CountingSink {
+        bytes: usize,
+        largest_write: usize,
+    }
+
+    impl io::Write for CountingSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.bytes += buf.len();
+            self.largest_write = self.largest_write.max(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dumps_stream_tokens_without_collecting_them() {
+        let tokens = crate::Scanner::new(&"a + 1;\n".repeat(250_000))
+            .unwrap()
+            .tokens;
+        assert_eq!(tokens.len(), 1_000_000);
+
+        for format in [TokenDumpFormat::Human, TokenDumpFormat::JsonLines] {
+            let mut sink = CountingSink::default();
+            write_tokens(&tokens, &mut sink, format).unwrap();
+            let first_line = dump("a", format).len();
+            assert!(sink.bytes > 1_000_000 * first_line / 2, "{:?}", format);
+            // The dump of a token is written in pieces as it is formatted
+            assert!(sink.largest_write <= first_line, "{:?}", format);
+        }
+    }
+}
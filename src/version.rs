@@ -0,0 +1,73 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Edition of the language a program is written against
+///
+/// Extensions can break programs written for the core language, i.e `${`
+/// starts an interpolation in strings, so scripts can ask for the core
+/// language to keep their meaning. Programs can read the version they run
+/// under from the `LOX_VERSION` global, `1` or `2`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LanguageVersion {
+    /// The core language without any of the extensions below
+    V1,
+    /// Core language with string interpolation, bitwise operators, `??` and
+    /// the `exit`, `assert`, `write` and `attempt` statements
+    #[default]
+    V2,
+}
+
+impl LanguageVersion {
+    pub fn number(self) -> i64 {
+        match self {
+            LanguageVersion::V1 => 1,
+            LanguageVersion::V2 => 2,
+        }
+    }
+
+    /// Returns true if the extensions to the core language are available
+    pub fn has_extensions(self) -> bool {
+        self >= LanguageVersion::V2
+    }
+}
+
+impl fmt::Display for LanguageVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.number())
+    }
+}
+
+/// Parses `v1` or `v2`, the `v` is optional
+impl FromStr for LanguageVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('v').unwrap_or(s) {
+            "1" => Ok(LanguageVersion::V1),
+            "2" => Ok(LanguageVersion::V2),
+            _ => Err(format!(
+                "unknown language version `{}`, expected v1 or v2",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_round_trip_through_strings() {
+        for version in [LanguageVersion::V1, LanguageVersion::V2] {
+            assert_eq!(version.to_string().parse(), Ok(version));
+        }
+        assert_eq!("2".parse(), Ok(LanguageVersion::V2));
+        assert_eq!(
+            "v3".parse::<LanguageVersion>(),
+            Err("unknown language version `v3`, expected v1 or v2".into())
+        );
+        assert!("".parse::<LanguageVersion>().is_err());
+        assert!("V1".parse::<LanguageVersion>().is_err());
+    }
+}
@@ -0,0 +1,593 @@
+#![cfg(feature = "cli")]
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::{env, fs};
+
+/// Writes `source` to a script unique to the test and runs it with `args`
+fn run_script(name: &str, source: &str, flags: &[&str], args: &[&str]) -> Output {
+    let path = write_script(name, source);
+    let output = lox().args(flags).arg(&path).args(args).output().unwrap();
+    fs::remove_file(path).unwrap();
+    output
+}
+
+fn lox() -> Command {
+    Command::new(PathBuf::from(env!("CARGO_BIN_EXE_lox")))
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn runs_scripts_without_arguments() {
+    let output = run_script("plain", "let a = 2; a * 3; len(args());", &[], &[]);
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "6\n0\n");
+}
+
+#[test]
+fn passes_trailing_arguments_to_the_script() {
+    let source = "args(); env(\"LOX_CLI_TEST\");";
+    let output = lox()
+        .env("LOX_CLI_TEST", "set")
+        .arg("--no-fs")
+        .arg(write_script("args", source))
+        .args(["one", "--time", "two"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&output), "one --time two\nset\n");
+    // The flag after the script belongs to the script
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn sources_given_with_e_run_in_order() {
+    let output = lox()
+        .args(["-e", "let a = 2;", "-e", "print a + 1;", "-e", "a * 5"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&output), "3\n10\n");
+
+    let output = lox().args(["-e", "args()", "one", "two"]).output().unwrap();
+    assert_eq!(stdout(&output), "one two\n");
+
+    // Programs are strict, a missing `;` before the end is an error
+    let output = lox()
+        .args(["-e", "print 1 print 2;", "-e", "print 3;"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "runtime exception[P001]: expected `;` at line 1 column 9\n"
+    );
+
+    let output = lox()
+        .args(["-e", "write \"a\"; exit 3;", "-e", "print 4;"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout(&output), "a");
+}
+
+#[test]
+fn flags_before_the_script_are_still_read() {
+    let output = run_script("flags", "fileExists(\".\");", &["--no-fs"], &["--no-fs"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("file I/O is disabled"));
+}
+
+#[test]
+fn unknown_flags_print_the_usage() {
+    let output = lox().arg("--verbose").output().unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("USAGE"));
+}
+
+#[test]
+fn error_codes_are_explained() {
+    let output = lox().args(["--explain", "p001"]).output().unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("P001: expected `{}`\n\n"));
+
+    let output = lox().args(["--explain", "X999"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: unknown error code or lint `X999`\n"
+    );
+}
+
+#[test]
+fn lints_are_explained_by_name() {
+    let output = lox().args(["--explain", "shadowing"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("shadowing: warn by default\n\nW007: "));
+
+    let output = lox().args(["--explain", "W006"]).output().unwrap();
+    assert!(stdout(&output).ends_with("\nReported by the `empty-block` lint, warn by default.\n"));
+}
+
+#[test]
+fn lint_levels_are_set_by_flags_and_comments() {
+    let source = "let a = 1;\n{ let a = 2; print a; }\n{}";
+    let output = run_script("lint-flags", source, &["--allow=empty-block"], &[]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "warning[W007]: `a` shadows the variable declared at line 1 column 5 at line 2 column 7\n"
+    );
+
+    let output = run_script(
+        "lint-deny",
+        source,
+        &["--deny=shadowing", "--allow=empty-block"],
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error[W007]: `a` shadows the variable declared at line 1 column 5 at line 2 column 7\n\
+         aborting due to 1 warning\n"
+    );
+
+    // The comment wins over the flags
+    let source = format!("// lox-lint: allow(shadowing, empty-block)\n{}", source);
+    let output = run_script("lint-comment", &source, &["--deny=shadowing"], &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&output), "2\n");
+    assert!(output.stderr.is_empty());
+
+    let output = lox().arg("--warn=shadow").output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).starts_with("error: unknown lint `shadow`\n"));
+}
+
+fn write_script(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("lox-cli-{}-{}.lx", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+#[test]
+fn errors_are_printed_with_the_enclosing_blocks() {
+    let output = run_script("trace", "{\n  { let _a = 1 & true; }\n}", &[], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "evaluation error[R007]: operand true of `&` is not a number at line 2 column 16
+    in block started at line 2 column 3
+    in block started at line 1 column 1\n"
+    );
+}
+
+#[test]
+fn warnings_are_printed_before_running() {
+    let source = "{ let unused = 1; }\n2;";
+    let output = run_script("warn", source, &[], &[]);
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "2\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "warning[W001]: unused variable `unused` at line 1 column 7\n"
+    );
+
+    let output = run_script("deny", source, &["--deny-warnings"], &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "warning[W001]: unused variable `unused` at line 1 column 7\naborting due to 1 warning\n"
+    );
+}
+
+#[test]
+fn discarded_comparisons_are_only_reported_in_scripts() {
+    let source = "let a = 1;\n{ a == 5; }\n";
+    let output = run_script("discarded", source, &[], &[]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "warning[W002]: expression result is unused; did you mean `=`? at line 2 column 5\n"
+    );
+
+    let mut prompt = lox()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    prompt
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+    let output = prompt.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn prompt_reports_errors_and_keeps_going() {
+    let mut prompt = lox()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    prompt
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"1 & true\n(2 +\n3)\n")
+        .unwrap();
+    let output = prompt.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "> > ... 5\n> ");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "evaluation error[R007]: operand true of `&` is not a number at line 1 column 3\n"
+    );
+}
+
+#[test]
+fn pasted_programs_run_once_and_blank_lines_keep_the_prompt_open() {
+    let mut prompt = lox()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    prompt
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b":paste\nlet a =\n\n  2;\nprint a;\n:end\n\na * 3\n:quit\nprint 4;\n")
+        .unwrap();
+    let output = prompt.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "> ... ... ... ... ... 2\n> > 6\n> ");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn diagnostics_are_colored_on_request() {
+    let source = "{ let unused = 1; }\n\"out\";\n1 & true;";
+
+    let output = run_script("color", source, &["--color=always"], &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "out\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "\x1b[1;33mwarning[W001]\x1b[0m: unused variable `unused` \x1b[1mat line 1 column 7\x1b[0m
+\x1b[1;31mevaluation error[R007]\x1b[0m: operand true of `&` is not a number \x1b[1mat line 3 column 3\x1b[0m\n"
+    );
+
+    // Not a terminal, so auto and never both leave it plain
+    for flags in [&["--color=never"][..], &[]] {
+        let output = run_script("no-color", source, flags, &[]);
+        assert!(!String::from_utf8_lossy(&output.stderr).contains('\x1b'));
+    }
+
+    let output = lox().arg("--color=sometimes").output().unwrap();
+    assert!(stdout(&output).starts_with("error: unknown color choice `sometimes`"));
+}
+
+#[test]
+fn large_scripts_are_refused() {
+    let path = write_script("large", "1 + 1;");
+
+    let output = lox()
+        .arg("--max-source-size=4")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!("{} is larger than the limit of 4 bytes\n", path.display())
+    );
+
+    let output = lox().arg(&path).output().unwrap();
+    assert_eq!(stdout(&output), "2\n");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn language_version_is_selected_with_a_flag() {
+    let source = "LOX_VERSION; \"${1 + 1}\";";
+
+    let output = run_script("v2", source, &[], &[]);
+    assert_eq!(stdout(&output), "2\n2\n");
+
+    let output = run_script("v1", source, &["--lang=v1"], &[]);
+    assert_eq!(stdout(&output), "1\n${1 + 1}\n");
+
+    let output = run_script("v9", source, &["--lang=v9"], &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).starts_with("error: unknown language version `v9`"));
+}
+
+#[test]
+fn directories_run_every_script_and_report_a_summary() {
+    let dir = env::temp_dir().join(format!("lox-cli-suite-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::write(dir.join("a.lox"), "1 + 1;").unwrap();
+    fs::write(dir.join("b.lox"), "let = 1;").unwrap();
+    fs::write(dir.join("c.lx"), "\"before\";\n1 & true;").unwrap();
+    fs::write(dir.join("notes.txt"), "not a script").unwrap();
+    fs::write(dir.join("nested").join("d.lox"), "3;").unwrap();
+    let name = |file: &str| dir.join(file).display().to_string();
+
+    let output = lox().arg(&dir).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "== {a} ==\n2\n== {b} ==\n== {c} ==\nbefore\n
+{a}: ok\n{b}: parse error\n{c}: runtime error\n1 passed, 2 failed\n",
+            a = name("a.lox"),
+            b = name("b.lox"),
+            c = name("c.lx"),
+        )
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "{}: runtime exception[P002]: expected an identifier",
+            name("b.lox")
+        )),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(&format!("{}: evaluation error", name("c.lx"))),
+        "{}",
+        stderr
+    );
+
+    let output = lox().arg("--recursive").arg(&dir).output().unwrap();
+    assert!(stdout(&output).contains(&format!("== {} ==\n3\n", name("nested/d.lox"))));
+    assert!(stdout(&output).ends_with("2 passed, 2 failed\n"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn deterministic_runs_give_identical_output() {
+    let dir = env::temp_dir().join(format!("lox-cli-deterministic-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let source = "random(); randomInt(1, 100); clock(); clock(); env(\"HOME\");\nargs();";
+    fs::write(dir.join("a.lox"), source).unwrap();
+    fs::write(dir.join("b.lox"), "1 & true;").unwrap();
+
+    let run = || {
+        lox()
+            .current_dir(&dir)
+            .arg("--deterministic")
+            .arg(&dir)
+            .output()
+            .unwrap()
+    };
+    let (first, second) = (run(), run());
+    assert_eq!(first.stdout, second.stdout);
+    assert_eq!(first.stderr, second.stderr);
+    let printed = stdout(&first);
+    assert!(printed.starts_with("== a.lox ==\n"), "{}", printed);
+    assert!(printed.contains("\n0\n1\nnil\n\n"), "{}", printed);
+    assert!(printed.contains("b.lox: runtime error"), "{}", printed);
+    let stderr = String::from_utf8_lossy(&first.stderr);
+    assert!(stderr.starts_with("b.lox: evaluation error"), "{}", stderr);
+
+    let script = dir.join("a.lox");
+    let output = lox()
+        .arg("--deterministic")
+        .arg(&script)
+        .args(["x"])
+        .output()
+        .unwrap();
+    assert!(stdout(&output).ends_with("nil\nx\n"), "{:?}", output);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn check_mode_reports_errors_without_running_scripts() {
+    let output = run_script("check-ok", "let a = 1;\na + 1;", &["--check"], &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        (stdout(&output).as_str(), output.stderr.as_slice()),
+        ("", &b""[..])
+    );
+
+    let path = write_script("check-error", "let = 1;\nprint 2;");
+    let output = lox().arg("--check").arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!(
+            "{}: runtime exception[P002]: expected an identifier at line 1 column 5\n",
+            path.display()
+        )
+    );
+    fs::remove_file(path).unwrap();
+
+    let source = "print 1;\nprint \"two\";\nwrite(3);\nexit 4;";
+    let output = run_script("check-print", source, &["--check"], &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn check_mode_accepts_several_scripts_and_directories() {
+    let dir = env::temp_dir().join(format!("lox-cli-check-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.lox"), "{ let unused = 1; }").unwrap();
+    fs::write(dir.join("b.lx"), "1 & true;").unwrap();
+    let single = write_script("check-single", "2;");
+
+    let output = lox()
+        .arg("--check")
+        .arg(&single)
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!(
+            "{}: warning[W001]: unused variable `unused` at line 1 column 7\n",
+            dir.join("a.lox").display()
+        )
+    );
+
+    let output = lox()
+        .args(["--check", "--deny-warnings"])
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(65));
+
+    fs::remove_file(single).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn scripts_reject_redeclaring_variables_in_blocks() {
+    let source = "{ let _a = 1; let _a = 2; } \"done\";";
+    let output = run_script("redeclare", source, &[], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "evaluation error[R019]: `_a` is already declared in this scope, first at line 1 column 7 at line 1 column 19
+    in block started at line 1 column 1\n"
+    );
+
+    let output = run_script("redeclare-allow", source, &["--redeclare=allow"], &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&output), "done\n");
+}
+
+#[test]
+fn written_output_comes_before_errors_on_a_shared_terminal() {
+    let script = write_script("write", "write \"partial\";\nwrite 1 & true;");
+    let log = env::temp_dir().join(format!("lox-cli-write-{}.log", std::process::id()));
+    let file = fs::File::create(&log).unwrap();
+    let status = lox()
+        .arg("--color=never")
+        .arg(&script)
+        .stdout(file.try_clone().unwrap())
+        .stderr(file)
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(1));
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        "partialevaluation error[R007]: operand true of `&` is not a number at line 2 column 9\n"
+    );
+    fs::remove_file(script).unwrap();
+    fs::remove_file(log).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_interrupts_the_running_script() {
+    use std::io::{BufRead, BufReader, Read};
+
+    // Upper casing a string of 8M characters over and over takes long
+    // enough for the interrupt to arrive while the script runs
+    let source = format!(
+        "let s = \"xxxxxxxx\";\n{}print \"started\";\n{}",
+        "let s = \"${s}${s}\";\n".repeat(20),
+        "let t = toUpper(s);\n".repeat(10_000)
+    );
+    let script = write_script("interrupt", &source);
+    let mut child = lox()
+        .arg("--color=never")
+        .arg(&script)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert_eq!(line, "started\n");
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+
+    let status = child.wait().unwrap();
+    let mut stderr = String::new();
+    child.stderr.unwrap().read_to_string(&mut stderr).unwrap();
+    fs::remove_file(script).unwrap();
+
+    assert_eq!(status.code(), Some(130), "{}", stderr);
+    assert!(
+        stderr.starts_with("^C interrupting...\nevaluation error[R013]: interrupted at line "),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn fmt_mode_prints_the_formatted_script() {
+    let source = "// sum\nlet a=((1)+2)*(3);\n\n\nprint a ;";
+    let output = run_script("fmt", source, &["--fmt"], &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        stdout(&output),
+        "// sum\nlet a = ((1) + 2) * (3);\n\nprint a;\n"
+    );
+
+    let output = run_script("fmt-strip", source, &["--fmt", "--fmt-strip-parens"], &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        stdout(&output),
+        "// sum\nlet a = (1 + 2) * 3;\n\nprint a;\n"
+    );
+}
+
+#[test]
+fn reconstruct_mode_prints_the_source_rebuilt_from_tokens() {
+    let source = "// sum\nlet a = 01 + 2.50;\nprint 'a is ${a}';";
+    let output = run_script("reconstruct", source, &["--reconstruct"], &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&output), "// sum\nlet a=1+2.5;print\"a is ${a}\";\n");
+
+    let output = run_script(
+        "reconstruct-error",
+        "let a = \"open;",
+        &["--reconstruct"],
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+}
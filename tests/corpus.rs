@@ -0,0 +1,110 @@
+//! Replays every program in `tests/corpus` and compares what scanning,
+//! parsing and running it produced with the `.expected` file next to it
+//!
+//! Adding a case only takes dropping a `.lox` file in the directory and
+//! running the tests with `UPDATE_EXPECT=1` to write its expectation, which
+//! is then reviewed and checked in along with it. Programs run with a
+//! statement budget so a case can't hang the test suite, and with a
+//! [TestConfig] so their output is the same on every machine.
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::{env, fs, thread};
+
+use lox::{Interpreter, Parser, Scanner, TestConfig};
+
+const MAX_STATEMENTS: usize = 10_000;
+/// Cases run with the stack the main thread of the `lox` binary has rather
+/// than the smaller one of test threads
+const STACK_SIZE: usize = 8 << 20;
+
+/// What scanning, parsing and running the source produced
+fn replay(source: &str) -> String {
+    let mut report = String::new();
+
+    let tokens = match Scanner::new(source) {
+        Ok(scanner) => scanner.tokens,
+        Err(e) => {
+            writeln!(report, "scan: {}", e).unwrap();
+            return report;
+        }
+    };
+    writeln!(report, "scan: ok\ntokens: {}", tokens.len()).unwrap();
+
+    let (_, errors) = Parser::new(tokens, true).parse_recovering();
+    if !errors.is_empty() {
+        for error in errors {
+            writeln!(report, "parse: {}", error).unwrap();
+        }
+        return report;
+    }
+    writeln!(report, "parse: ok").unwrap();
+
+    let mut interpreter = Interpreter::with_output(source.into(), Vec::new());
+    interpreter.set_max_statements(Some(MAX_STATEMENTS));
+    interpreter.set_test_config(&TestConfig::default());
+    let result = interpreter.interpret(true);
+    for line in String::from_utf8_lossy(interpreter.output()).split_inclusive('\n') {
+        writeln!(report, "output: {:?}", line).unwrap();
+    }
+    match (result, interpreter.exit_code()) {
+        (Err(e), _) => writeln!(report, "run: {}", e).unwrap(),
+        (Ok(()), Some(code)) => writeln!(report, "run: exit {}", code).unwrap(),
+        (Ok(()), None) => writeln!(report, "run: ok").unwrap(),
+    }
+    report
+}
+
+fn cases() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut cases: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect();
+    cases.sort();
+    cases
+}
+
+#[test]
+fn corpus_matches_expectations() {
+    let update = env::var_os("UPDATE_EXPECT").is_some_and(|value| value == "1");
+    let cases = cases();
+    assert!(!cases.is_empty(), "no cases in tests/corpus");
+
+    let mut failures = Vec::new();
+    for case in cases {
+        let name = case.file_name().unwrap().to_string_lossy().into_owned();
+        let source = fs::read_to_string(&case).unwrap();
+        let replayed = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || replay(&source))
+            .unwrap()
+            .join();
+        let actual = match replayed {
+            Ok(actual) => actual,
+            Err(_) => {
+                failures.push(format!("{} panicked", name));
+                continue;
+            }
+        };
+
+        let expected_path = case.with_extension("expected");
+        if update {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{} changed\n--- expected\n{}--- actual\n{}",
+                name, expected, actual
+            )),
+            Err(_) => failures.push(format!(
+                "{} has no expectation, run with UPDATE_EXPECT=1 to write it\n{}",
+                name, actual
+            )),
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,126 @@
+//! Runs every example with fixed input so they keep working as the library
+//! changes
+//!
+//! `cargo test` builds the examples next to the test binaries, they are
+//! found relative to this test rather than rebuilt with `cargo run`.
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::{env, fs};
+
+fn example(name: &str) -> Command {
+    // Tests are in target/<profile>/deps, examples in target/<profile>/examples
+    let mut path = env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push("examples");
+    path.push(format!("{}{}", name, env::consts::EXE_SUFFIX));
+    assert!(path.exists(), "{} is not built", path.display());
+    Command::new(path)
+}
+
+fn run_with_input(mut command: Command, input: &str) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn calculator_evaluates_every_line() {
+    let output = run_with_input(
+        example("calculator"),
+        "2 * 21\n_ / 4\n\nmax(_, 11)\n1 +\nlen(nope)\n",
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "42\n10.5\n11\n\
+         runtime exception[P003]: unexpected end of input, expected an expression at line 1 column 4\n\
+         evaluation error[R001]: undefined variable `nope` at line 1 column 5\n"
+    );
+}
+
+#[test]
+fn embed_host_vars_reads_back_results() {
+    let output = example("embed_host_vars").output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "12 x pencil for 6\n  subtotal = 6\n  discount = 0\n  total = 6\n\
+         5 x notebook for 18\n  subtotal = 20\n  discount = 2\n  total = 18\n"
+    );
+}
+
+#[test]
+fn pretty_print_indents_blocks() {
+    let source = "let a = 1 + 2 * 3;\n{ print a; { a = -a; } }\n\
+                  attempt { exit 1; } handle (e) { write e; }";
+    let output = run_with_input(example("pretty_print"), source);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "(let a (1 + (2 * 3)))\nblock\n  (print a)\n  block\n    (a = (- a))\n\
+         attempt\n  block\n    (exit 1)\nhandle e\n  block\n    (write e)\n"
+    );
+
+    let path: PathBuf = env::temp_dir().join("lox-pretty-print-smoke.lox");
+    fs::write(&path, "let = 1;").unwrap();
+    let output = example("pretty_print").arg(&path).output().unwrap();
+    fs::remove_file(path).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "runtime exception[P002]: expected an identifier at line 1 column 5\n"
+    );
+}
+
+#[test]
+fn sandbox_reports_why_snippets_stop() {
+    let cases = [
+        ("print len(\"abc\");", "3\nfinished after 1 statements\n"),
+        ("exit 3;", "exited with code 3 after 1 statements\n"),
+        (
+            "let a = \"${toUpper(\"ab\")}\";\nprint env(\"HOME\");",
+            "stopped after 2 statements: evaluation error[R011]: env: environment access is \
+             disabled at line 2 column 17\n",
+        ),
+        (
+            "let = 1;",
+            "rejected: runtime exception[P002]: expected an identifier at line 1 column 5\n",
+        ),
+    ];
+    for (snippet, expected) in cases {
+        let output = run_with_input(example("sandbox"), snippet);
+
+        assert!(output.status.success(), "{}", snippet);
+        assert_eq!(stdout(&output), expected, "{}", snippet);
+    }
+
+    let mut snippet = String::from("let a = \"abcd\";\n");
+    for _ in 0..8 {
+        snippet.push_str("a = \"${a}${a}${a}${a}\";\n");
+    }
+    let output = stdout(&run_with_input(example("sandbox"), &snippet));
+    assert!(
+        output.ends_with("stopped after 7 statements: evaluation error[R016]: string length limit of 10000 exceeded at line 7 column 5\n"),
+        "{}",
+        output
+    );
+}
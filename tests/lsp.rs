@@ -0,0 +1,60 @@
+#![cfg(feature = "lsp")]
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn frame(message: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", message.len(), message)
+}
+
+#[test]
+fn server_publishes_diagnostics_over_stdio() {
+    let mut server = Command::new(PathBuf::from(env!("CARGO_BIN_EXE_lox-ls")))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.lox","languageId":"lox","version":1,"text":"let = 1;"}}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ]
+    .map(frame)
+    .concat();
+    server
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    let output = server.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.starts_with("Content-Length: "));
+    assert!(stdout.contains(r#""documentSymbolProvider":true"#));
+    assert!(stdout.contains(r#""range":{"start":{"line":0,"character":4},"end":{"line":0,"character":5}},"severity":1,"code":"P002","source":"lox","message":"expected an identifier""#));
+    assert!(stdout.ends_with(&frame(r#"{"jsonrpc":"2.0","id":2,"result":null}"#)));
+}
+
+#[test]
+fn exiting_without_shutdown_fails() {
+    let output = Command::new(PathBuf::from(env!("CARGO_BIN_EXE_lox-ls")))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .and_then(|mut server| {
+            server
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(frame(r#"{"jsonrpc":"2.0","method":"exit"}"#).as_bytes())?;
+            server.wait_with_output()
+        })
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+}
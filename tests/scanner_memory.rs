@@ -0,0 +1,137 @@
+//! Scanning borrows the source and only allocates for tokens, and dropping
+//! the parsed program frees it without allocating for every node, measured
+//! with an allocator counting the bytes in use and the allocations made. This is a
+//! separate test binary so the counts aren't mixed with other tests running
+//! in parallel.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use lox::{Pipeline, Scanner};
+
+struct Counting;
+
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let in_use = IN_USE.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(in_use, Ordering::SeqCst);
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        IN_USE.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Tests run one at a time, the counts would be mixed otherwise
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[test]
+fn scanning_does_not_copy_the_source() {
+    let _serial = SERIAL.lock().unwrap();
+    let line = format!(
+        "let value = \"{}\"; // {}\n",
+        "text ".repeat(20),
+        "comment ".repeat(12)
+    );
+    let source = line.repeat(10 * 1024 * 1024 / line.len());
+
+    let before = IN_USE.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let scanned = Scanner::new(&source).unwrap().tokens;
+    let tokens = IN_USE.load(Ordering::SeqCst) - before;
+    let peak = PEAK.load(Ordering::SeqCst) - before;
+
+    // Apart from the tokens kept, scanning only needs room for the token
+    // list to grow, a copy of the source would take up to 4 times its size
+    assert!(
+        peak - tokens < source.len(),
+        "scanning {} bytes used {} bytes on top of {} bytes of tokens",
+        source.len(),
+        peak - tokens,
+        tokens
+    );
+    assert_eq!(scanned.len(), source.len() / line.len() * 5);
+}
+
+#[test]
+fn whitespace_and_comments_are_skipped_without_allocating() {
+    let _serial = SERIAL.lock().unwrap();
+    let block = format!(
+        "{{\n{0}let total = 1;\n{0}\t// {1}\r\n{0}total = total + 2;\n}}\n",
+        " ".repeat(48),
+        "note ".repeat(8)
+    );
+    let source = block.repeat(1024 * 1024 / block.len());
+
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    let start = Instant::now();
+    let scanned = Scanner::new(&source).unwrap().tokens;
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::SeqCst) - before;
+
+    let tokens = source.len() / block.len() * 13;
+    assert_eq!(scanned.len(), tokens);
+    // Only the lexeme growing as it's read and the string it becomes for
+    // every token, plus the token list growing, while the source is mostly
+    // indentation. Allocating for every whitespace character took 15 times
+    // the tokens.
+    assert!(
+        allocations <= tokens * 3 + 64,
+        "scanning {} tokens made {} allocations",
+        tokens,
+        allocations
+    );
+    println!(
+        "scanned {} bytes in {:?}, {:.1} MB/s",
+        source.len(),
+        elapsed,
+        source.len() as f64 / elapsed.as_secs_f64() / 1e6
+    );
+}
+
+#[test]
+fn telling_keywords_from_names_does_not_allocate() {
+    let _serial = SERIAL.lock().unwrap();
+    let allocations = |source: String| {
+        let before = ALLOCATIONS.load(Ordering::SeqCst);
+        let scanned = Scanner::new(&source).unwrap().tokens;
+        assert_eq!(scanned.len(), 100_000);
+        ALLOCATIONS.load(Ordering::SeqCst) - before
+    };
+
+    // Numbers of the same lengths never go through the keyword lookup
+    let numbers = allocations("12345 123 123 123 12 ".repeat(20_000));
+    assert_eq!(allocations("while let and nil or ".repeat(20_000)), numbers);
+    assert_eq!(allocations("whilx lex anx nix ox ".repeat(20_000)), numbers);
+}
+
+#[test]
+fn dropping_expressions_does_not_allocate_for_every_node() {
+    let _serial = SERIAL.lock().unwrap();
+    let source = format!("let a = 1{};", " + -(a * 2)".repeat(20_000));
+    let pipeline = Pipeline::default();
+    let statements = pipeline.parse(pipeline.scan(&source).unwrap()).unwrap();
+
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    drop(statements);
+    let allocations = ALLOCATIONS.load(Ordering::SeqCst) - before;
+
+    // Only the stack of the nodes left to drop grows, a placeholder
+    // allocated for every one of the 80 000 nodes would show up here
+    assert!(
+        allocations < 64,
+        "dropping made {} allocations",
+        allocations
+    );
+}
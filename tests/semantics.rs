@@ -0,0 +1,410 @@
+//! Conformance table of the rules values follow: truthiness, equality across
+//! types, nil, logical operators, numbers and how values are printed
+//!
+//! Every case is run as a statement of its own and must print the expected
+//! line or fail with an error containing the expected text. The same table is
+//! rendered to `semantics.md` in the target directory whenever the tests run,
+//! so the document always describes what the interpreter does.
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use lox::Interpreter;
+
+enum Expect {
+    /// Output of the statement without the trailing newline, empty for
+    /// statements that print nothing
+    Prints(&'static str),
+    /// Text the error message contains
+    Fails(&'static str),
+}
+
+use Expect::{Fails, Prints};
+
+struct Section {
+    title: &'static str,
+    summary: &'static str,
+    cases: &'static [(&'static str, Expect)],
+}
+
+const SECTIONS: &[Section] = &[
+    Section {
+        title: "Truthiness",
+        summary: "`nil` and `false` are falsey, every other value is truthy, including `0` \
+                  and the empty string. Truthiness only applies to conditions such as \
+                  `assert`, `!` requires a boolean.",
+        cases: &[
+            ("assert true", Prints("")),
+            ("assert 0", Prints("")),
+            ("assert 0.0", Prints("")),
+            ("assert -1", Prints("")),
+            ("assert \"\"", Prints("")),
+            ("assert \"false\"", Prints("")),
+            ("assert len", Prints("")),
+            ("assert false", Fails("assertion failed: false")),
+            ("assert nil", Fails("assertion failed: nil")),
+            (
+                "assert nil, \"was nil\"",
+                Fails("assertion failed: nil: was nil"),
+            ),
+            ("assert 1 == 2", Fails("assertion failed: (1 == 2)")),
+            ("!true", Prints("false")),
+            ("!false", Prints("true")),
+            ("!(1 == 2)", Prints("true")),
+            ("!nil", Fails("operator `!` requires a boolean, got nil")),
+            ("!0", Fails("operator `!` requires a boolean, got number 0")),
+            (
+                "!\"\"",
+                Fails("operator `!` requires a boolean, got string"),
+            ),
+        ],
+    },
+    Section {
+        title: "Logical operators",
+        summary: "`&&` and `||` take booleans and give a boolean. The right side is only \
+                  evaluated when it decides the result, so `false && ...` and `true || ...` \
+                  don't look at it.",
+        cases: &[
+            ("true && true", Prints("true")),
+            ("true && false", Prints("false")),
+            ("false && true", Prints("false")),
+            ("false && false", Prints("false")),
+            ("true || false", Prints("true")),
+            ("false || true", Prints("true")),
+            ("false || false", Prints("false")),
+            ("true && false || true", Prints("true")),
+            ("false && missing", Prints("false")),
+            ("true || missing", Prints("true")),
+            ("false && 1", Prints("false")),
+            ("true || nil", Prints("true")),
+            ("true && missing", Fails("undefined variable `missing`")),
+            (
+                "true && 1",
+                Fails("operator `&&` is not supported for boolean and number"),
+            ),
+            (
+                "false || 1",
+                Fails("operator `||` is not supported for boolean and number"),
+            ),
+            (
+                "1 && true",
+                Fails("operator `&&` is not supported for number and boolean"),
+            ),
+            (
+                "nil || true",
+                Fails("operator `||` is not supported for nil and boolean"),
+            ),
+            (
+                "nil && false",
+                Fails("operator `&&` is not supported for nil and boolean"),
+            ),
+        ],
+    },
+    Section {
+        title: "Equality",
+        summary: "Values of any types can be compared with `==` and `!=`. Values of \
+                  different types are never equal, numbers are equal when their exact \
+                  values are, whether they are integers or floats.",
+        cases: &[
+            ("1 == 1", Prints("true")),
+            ("1 == 2", Prints("false")),
+            ("1 == 1.0", Prints("true")),
+            ("1.0 == 1", Prints("true")),
+            ("0 == -0", Prints("true")),
+            ("0.0 == -0.0", Prints("true")),
+            ("0.5 == 1 / 2", Prints("true")),
+            ("0.1 + 0.2 == 0.3", Prints("true")),
+            ("1 / 0 == 1 / 0", Prints("true")),
+            ("0 / 0 == 0 / 0", Prints("false")),
+            ("0 / 0 != 0 / 0", Prints("true")),
+            ("true == true", Prints("true")),
+            ("false == false", Prints("true")),
+            ("true != false", Prints("true")),
+            ("true == 1", Prints("false")),
+            ("1 == true", Prints("false")),
+            ("\"1\" == 1", Prints("false")),
+            ("1 != \"1\"", Prints("true")),
+            ("\"0\" == 0", Prints("false")),
+            ("\"\" == 0", Prints("false")),
+            ("len == len", Prints("true")),
+            ("len == toUpper", Prints("false")),
+            ("1 == 1 == true", Prints("true")),
+            ("(1 < 2) == true", Prints("true")),
+            ("missing == 1", Fails("undefined variable `missing`")),
+        ],
+    },
+    Section {
+        title: "Strings",
+        summary: "Strings are equal when they have the same characters, compared exactly. \
+                  They support no other operators, text is built with interpolation.",
+        cases: &[
+            ("\"abc\" == \"abc\"", Prints("true")),
+            ("\"abc\" == \"ABC\"", Prints("false")),
+            ("\"a\" != \"a\"", Prints("false")),
+            ("\"\" == \"\"", Prints("true")),
+            ("\"é\" == \"é\"", Prints("true")),
+            ("\"text\"", Prints("text")),
+            ("\"tab\\tx\"", Prints("tab\\tx")),
+            (
+                "\"a\" + \"b\"",
+                Fails("operator `+` is not supported for string and string"),
+            ),
+            (
+                "\"a\" + 1",
+                Fails("operator `+` is not supported for string and number"),
+            ),
+            (
+                "\"a\" < \"b\"",
+                Fails("operator `<` is not supported for string and string"),
+            ),
+            (
+                "-\"a\"",
+                Fails("operator `-` requires a number, got string \"a\""),
+            ),
+            ("len(\"héllo\")", Prints("5")),
+            ("len(\"\")", Prints("0")),
+            ("toUpper(\"ab\")", Prints("AB")),
+        ],
+    },
+    Section {
+        title: "Nil",
+        summary: "`nil` is only equal to itself and supports no other operator. `??` \
+                  falls back to its right side for `nil` only, other falsey values are \
+                  kept.",
+        cases: &[
+            ("nil", Prints("nil")),
+            ("nil == nil", Prints("true")),
+            ("nil != nil", Prints("false")),
+            ("nil == false", Prints("false")),
+            ("nil != false", Prints("true")),
+            ("nil == 0", Prints("false")),
+            ("nil == \"\"", Prints("false")),
+            ("nil ?? 1", Prints("1")),
+            ("nil ?? nil", Prints("nil")),
+            ("false ?? 1", Prints("false")),
+            ("0 ?? 1", Prints("0")),
+            ("\"[${\"\" ?? 1}]\"", Prints("[]")),
+            ("1 ?? missing", Prints("1")),
+            ("missing ?? 1", Fails("undefined variable `missing`")),
+            (
+                "nil + 1",
+                Fails("operator `+` is not supported for nil and number"),
+            ),
+            (
+                "nil * nil",
+                Fails("operator `*` is not supported for nil and nil"),
+            ),
+            (
+                "nil < 1",
+                Fails("operator `<` is not supported for nil and number"),
+            ),
+            ("-nil", Fails("operator `-` requires a number, got nil")),
+        ],
+    },
+    Section {
+        title: "Arithmetic",
+        summary: "Integers stay exact while the result is an integer: `/` gives an \
+                  integer when the division is exact and overflowing results are computed \
+                  as floats. Floats follow IEEE 754, dividing by zero gives an infinity \
+                  or NaN.",
+        cases: &[
+            ("1 + 2", Prints("3")),
+            ("3 - 5", Prints("-2")),
+            ("1 + 2.5", Prints("3.5")),
+            ("2 * 3.0", Prints("6")),
+            ("6 / 3", Prints("2")),
+            ("7 / 2", Prints("3.5")),
+            ("-7 / 2", Prints("-3.5")),
+            ("10 / 4", Prints("2.5")),
+            ("1.25 * 4", Prints("5")),
+            ("-(3)", Prints("-3")),
+            ("1 / 0", Prints("inf")),
+            ("-1 / 0", Prints("-inf")),
+            ("0 / 0", Prints("nan")),
+            ("2147483647 + 1", Prints("2147483648")),
+            ("9223372036854775807 + 1", Prints("9223372000000000000")),
+            ("-9223372036854775807 - 1", Prints("-9223372036854775808")),
+            (
+                "true + true",
+                Fails("operator `+` is not supported for boolean and boolean"),
+            ),
+            (
+                "-true",
+                Fails("operator `-` requires a number, got boolean true"),
+            ),
+        ],
+    },
+    Section {
+        title: "Comparison",
+        summary: "`<`, `<=`, `>` and `>=` only compare numbers, by their exact values. \
+                  Comparisons involving NaN are false.",
+        cases: &[
+            ("1 < 2", Prints("true")),
+            ("1 >= 1", Prints("true")),
+            ("2 <= 1", Prints("false")),
+            ("1 > 1.5", Prints("false")),
+            ("2.5 > 2", Prints("true")),
+            ("0 / 0 < 1", Prints("false")),
+            ("0 / 0 >= 1", Prints("false")),
+            (
+                "1 < \"2\"",
+                Fails("operator `<` is not supported for number and string"),
+            ),
+            (
+                "true < false",
+                Fails("operator `<` is not supported for boolean and boolean"),
+            ),
+        ],
+    },
+    Section {
+        title: "Number formatting",
+        summary: "Whole numbers print without a fraction whether they are integers or \
+                  floats, negative zero prints as `0`. Floats are single precision and \
+                  print the shortest text that reads back as the same value.",
+        cases: &[
+            ("1.0", Prints("1")),
+            ("2.50", Prints("2.5")),
+            ("0.1", Prints("0.1")),
+            ("-0.5", Prints("-0.5")),
+            ("-0", Prints("0")),
+            ("-0.0", Prints("0")),
+            ("3.0 - 3", Prints("0")),
+            ("0.1 + 0.2", Prints("0.3")),
+            ("1000000.5", Prints("1000000.5")),
+            ("16777217.0", Prints("16777216")),
+            ("100000000", Prints("100000000")),
+            ("9223372036854775807", Prints("9223372036854775807")),
+            ("12345678901234567890", Prints("12345679000000000000")),
+        ],
+    },
+    Section {
+        title: "Interpolation",
+        summary: "Interpolated values are printed the way statements print them.",
+        cases: &[
+            ("\"nested ${1 + 1}\"", Prints("nested 2")),
+            ("\"${\"inner\"}\"", Prints("inner")),
+            ("\"${nil}\"", Prints("nil")),
+            ("\"${true}\"", Prints("true")),
+            ("\"${1 == 1}\"", Prints("true")),
+            ("\"${1.5}\"", Prints("1.5")),
+            ("\"${-0.0}\"", Prints("0")),
+            ("\"${0 / 0}\"", Prints("nan")),
+            ("\"${len}\"", Prints("<native fn len>")),
+            ("\"a${nil ?? \"b\"}c\"", Prints("abc")),
+        ],
+    },
+    Section {
+        title: "Bitwise operators",
+        summary: "Operands must be whole numbers within the range of a 64-bit integer, \
+                  the result is always an integer.",
+        cases: &[
+            ("5 & 3", Prints("1")),
+            ("5 | 3", Prints("7")),
+            ("5 ^ 3", Prints("6")),
+            ("~5", Prints("-6")),
+            ("1 << 3", Prints("8")),
+            ("-16 >> 2", Prints("-4")),
+            ("4.0 & 5", Prints("4")),
+            ("1 << 64", Fails("shift amount 64 is out of range")),
+            (
+                "1.5 & 1",
+                Fails("operand 1.5 of `&` is not a whole number within integer range"),
+            ),
+            ("true | 1", Fails("operand true of `|` is not a number")),
+        ],
+    },
+];
+
+/// Output of the case without the trailing newline, or its error message
+fn run(source: &str) -> Result<String, String> {
+    let mut interpreter = Interpreter::with_output(format!("{};", source), Vec::new());
+    interpreter.interpret(true).map_err(|e| e.msg)?;
+    let output = String::from_utf8_lossy(interpreter.output()).into_owned();
+    Ok(output.strip_suffix('\n').unwrap_or(&output).to_string())
+}
+
+#[test]
+fn conformance_table_holds() {
+    let mut failures = String::new();
+    for section in SECTIONS {
+        for (source, expect) in section.cases {
+            let failure = match (expect, run(source)) {
+                (Prints(expected), Ok(output)) if output == *expected => continue,
+                (Fails(expected), Err(msg)) if msg.contains(expected) => continue,
+                (Prints(expected), Ok(output)) => {
+                    format!("printed {:?} instead of {:?}", output, expected)
+                }
+                (Prints(expected), Err(msg)) => {
+                    format!("failed with {:?} instead of printing {:?}", msg, expected)
+                }
+                (Fails(expected), Ok(output)) => {
+                    format!(
+                        "printed {:?} instead of failing with {:?}",
+                        output, expected
+                    )
+                }
+                (Fails(expected), Err(msg)) => {
+                    format!("failed with {:?} instead of {:?}", msg, expected)
+                }
+            };
+            writeln!(failures, "{}: `{}` {}", section.title, source, failure).unwrap();
+        }
+    }
+
+    assert!(failures.is_empty(), "semantics changed\n{}", failures);
+}
+
+/// Renders the table as markdown
+fn document() -> String {
+    let mut doc = String::from(
+        "# Lox semantics\n\n\
+         Generated from the conformance table in `tests/semantics.rs` by \
+         `cargo test --test semantics`, every case is checked against the \
+         interpreter.\n",
+    );
+    for section in SECTIONS {
+        write!(
+            doc,
+            "\n## {}\n\n{}\n\n| Source | Result |\n| --- | --- |\n",
+            section.title, section.summary
+        )
+        .unwrap();
+        for (source, expect) in section.cases {
+            let result = match expect {
+                Prints("") => "prints nothing".to_string(),
+                Prints(output) => format!("`{}`", output),
+                Fails(msg) => format!("error: {}", msg),
+            };
+            // Pipes would end the cell even inside code spans
+            writeln!(
+                doc,
+                "| `{};` | {} |",
+                source.replace('|', "\\|"),
+                result.replace('|', "\\|")
+            )
+            .unwrap();
+        }
+    }
+    doc
+}
+
+fn target_dir() -> PathBuf {
+    env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("target"))
+}
+
+#[test]
+fn semantics_document_is_generated() {
+    let doc = document();
+    let cases: usize = SECTIONS.iter().map(|section| section.cases.len()).sum();
+    assert_eq!(
+        doc.lines().filter(|line| line.starts_with("| `")).count(),
+        cases
+    );
+    assert!(doc.contains("| `5 \\| 3;` | `7` |"));
+
+    let dir = target_dir();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("semantics.md"), doc).unwrap();
+}